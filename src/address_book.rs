@@ -0,0 +1,25 @@
+use std::collections::BTreeMap;
+
+use snap_coin::crypto::keys::Public;
+
+use crate::sanitize;
+
+/// A named recipient in the address book
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contact {
+    pub public: Public,
+    /// Free-form note, empty when there is none
+    pub note: String,
+}
+
+/// Contacts by alias, stored encrypted next to the wallets
+pub type AddressBook = BTreeMap<String, Contact>;
+
+/// Resolve a send recipient: a contact alias, or else a (possibly messily pasted) base36
+/// address. The error explains what is wrong with the address
+pub fn resolve_recipient(book: &AddressBook, recipient: &str) -> Result<Public, String> {
+    match book.get(recipient) {
+        Some(contact) => Ok(contact.public),
+        None => sanitize::parse_public(recipient),
+    }
+}
@@ -0,0 +1,459 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use snap_coin::to_nano;
+
+use crate::i18n::trf;
+
+/// Character between groups of three digits in displayed amounts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grouping {
+    /// `1,234,567.5`
+    Comma,
+    /// `1.234.567,5`, the decimal mark becomes a comma
+    Period,
+    /// `1 234 567.5`, with a thin space
+    Space,
+    /// `1'234'567.5`
+    Apostrophe,
+    /// `1234567.5`
+    None,
+}
+
+impl Grouping {
+    pub const NAMES: &[&str] = &["comma", "period", "space", "apostrophe", "none"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "comma" => Some(Grouping::Comma),
+            "period" => Some(Grouping::Period),
+            "space" => Some(Grouping::Space),
+            "apostrophe" => Some(Grouping::Apostrophe),
+            "none" => Some(Grouping::None),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+
+    fn separator(&self) -> Option<char> {
+        match self {
+            Grouping::Comma => Some(','),
+            Grouping::Period => Some('.'),
+            Grouping::Space => Some('\u{2009}'),
+            Grouping::Apostrophe => Some('\''),
+            Grouping::None => None,
+        }
+    }
+
+    fn decimal_mark(&self) -> char {
+        if *self == Grouping::Period { ',' } else { '.' }
+    }
+}
+
+/// Unit amounts are displayed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Snap,
+    /// The raw integer amount the protocol works with
+    Nano,
+}
+
+impl Unit {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "snap" => Some(Unit::Snap),
+            "nano" => Some(Unit::Nano),
+            _ => None,
+        }
+    }
+
+    /// Lowercase name, as used in settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            Unit::Snap => "snap",
+            Unit::Nano => "nano",
+        }
+    }
+
+    /// Name shown after amounts
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Snap => "SNAP",
+            Unit::Nano => "nano",
+        }
+    }
+}
+
+static GROUPING: AtomicU8 = AtomicU8::new(Grouping::Comma as u8);
+static UNIT: AtomicU8 = AtomicU8::new(Unit::Snap as u8);
+
+/// Set the grouping used by `format_snap`, from the `thousands_separator` setting
+pub fn set_grouping(grouping: Grouping) {
+    GROUPING.store(grouping as u8, Ordering::Relaxed);
+}
+
+/// Set the unit amounts are displayed in, from the `unit` setting
+pub fn set_unit(unit: Unit) {
+    UNIT.store(unit as u8, Ordering::Relaxed);
+}
+
+pub fn unit() -> Unit {
+    if UNIT.load(Ordering::Relaxed) == Unit::Nano as u8 {
+        Unit::Nano
+    } else {
+        Unit::Snap
+    }
+}
+
+/// Switches the display unit until dropped, for a single command's `--nano`
+pub struct UnitOverride {
+    previous: Unit,
+}
+
+pub fn override_unit(unit: Unit) -> UnitOverride {
+    let previous = self::unit();
+    set_unit(unit);
+    UnitOverride { previous }
+}
+
+impl Drop for UnitOverride {
+    fn drop(&mut self) {
+        set_unit(self.previous);
+    }
+}
+
+fn grouping() -> Grouping {
+    match GROUPING.load(Ordering::Relaxed) {
+        1 => Grouping::Period,
+        2 => Grouping::Space,
+        3 => Grouping::Apostrophe,
+        4 => Grouping::None,
+        _ => Grouping::Comma,
+    }
+}
+
+/// Format `amount` smallest units, `per_unit` of which make one whole unit, with grouped
+/// integer digits and every decimal place. Integer arithmetic only, so there is never any
+/// rounding or exponent notation
+fn format_units(amount: u64, per_unit: u64, grouping: Grouping, trim: bool) -> String {
+    let per_unit = per_unit.max(1);
+    let digits = (amount / per_unit).to_string();
+    let mut out = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.extend(grouping.separator());
+        }
+        out.push(digit);
+    }
+
+    let decimals = per_unit.to_string().len() - 1;
+    let fraction = format!("{:0width$}", amount % per_unit, width = decimals);
+    let fraction = if trim {
+        fraction.trim_end_matches('0')
+    } else {
+        &fraction
+    };
+    if !fraction.is_empty() {
+        out.push(grouping.decimal_mark());
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// Smallest units per displayed unit
+fn per_unit(unit: Unit) -> u64 {
+    match unit {
+        Unit::Snap => to_nano(1.0),
+        Unit::Nano => 1,
+    }
+}
+
+/// Format a nano amount in the display unit with every decimal place, so amounts line up
+/// in columns. Every displayed amount goes through here or `format_amount_short`
+pub fn format_amount(amount: u64) -> String {
+    format_units(amount, per_unit(unit()), grouping(), false)
+}
+
+/// Format a nano amount in the display unit without trailing zeros, where space is tight
+pub fn format_amount_short(amount: u64) -> String {
+    format_units(amount, per_unit(unit()), grouping(), true)
+}
+
+/// Format a nano amount in SNAP with every decimal place and no grouping, whatever the
+/// settings, for output read by programs
+pub fn format_snap_plain(amount: u64) -> String {
+    format_units(amount, per_unit(Unit::Snap), Grouping::None, false)
+}
+
+/// Format a nano amount in the display unit, followed by the unit
+pub fn display(amount: u64) -> String {
+    format!("{} {}", format_amount(amount), unit().symbol())
+}
+
+/// Decimal places of SNAP, one nano being the smallest amount there is
+pub const SNAP_DECIMALS: u32 = 8;
+/// Decimal places of a percentage, which are parts per billion of the spendable balance
+const PERCENT_DECIMALS: u32 = 7;
+
+/// Parse a plain decimal number, like `12`, `0.5` or `.25`, exactly into units of
+/// `10^-decimals` without going through floats. Trailing zeros beyond `decimals` are fine,
+/// any other digit there is `TooPrecise`
+fn parse_decimal(text: &str, decimals: u32) -> Result<u64, PaymentError> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+        return Err(PaymentError::Invalid);
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(PaymentError::TooPrecise(decimals));
+    }
+    // Only digits are left, so a whole part that doesn't parse is too big for a u64
+    let whole: u64 = match whole {
+        "" => 0,
+        whole => whole.parse().map_err(|_| PaymentError::TooLarge)?,
+    };
+    let fraction: u64 = match fraction {
+        "" => 0,
+        fraction => format!("{:0<width$}", fraction, width = decimals as usize)
+            .parse()
+            .unwrap(),
+    };
+    whole
+        .checked_mul(10u64.pow(decimals))
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or(PaymentError::TooLarge)
+}
+
+/// Parse an amount typed by the user, exactly: SNAP with up to 8 decimals, or nano with a
+/// `nano` suffix (`1500nano`)
+pub fn parse_amount(text: &str) -> Result<u64, PaymentError> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(nano) = lower.strip_suffix("nano") {
+        return parse_decimal(nano.trim(), 0);
+    }
+    let snap = lower.strip_suffix("snap").unwrap_or(&lower);
+    parse_decimal(snap.trim(), SNAP_DECIMALS)
+}
+
+/// Why a typed amount can't be used or paid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentError {
+    /// Not a plain decimal number
+    Invalid,
+    /// More decimal places than the amount can have, which are given
+    TooPrecise(u32),
+    /// More than the largest amount there can be
+    TooLarge,
+    /// Zero or negative
+    NotPositive,
+    /// A percentage not above 0 or above 100
+    PercentOutOfRange,
+}
+
+impl PaymentError {
+    /// What is wrong with the amount typed as `text`, naming it
+    pub fn message(self, text: &str) -> String {
+        match self {
+            PaymentError::Invalid => trf("invalid_amount", &[&text]),
+            PaymentError::TooPrecise(decimals) => trf("amount.too_precise", &[&text, &decimals]),
+            PaymentError::TooLarge => trf("amount.too_large", &[&text]),
+            PaymentError::NotPositive => trf("send.amount_not_positive", &[&text]),
+            PaymentError::PercentOutOfRange => trf("send.percent_out_of_range", &[&text]),
+        }
+    }
+}
+
+/// Parse an amount to pay to someone. Unlike `parse_amount`, nothing that would make an empty
+/// output passes
+pub fn parse_payment(text: &str) -> Result<u64, PaymentError> {
+    match parse_amount(text) {
+        Ok(0) => Err(PaymentError::NotPositive),
+        Ok(amount) => Ok(amount),
+        Err(PaymentError::Invalid) => match text.trim().strip_prefix('-').map(parse_amount) {
+            Some(Ok(_)) => Err(PaymentError::NotPositive),
+            _ => Err(PaymentError::Invalid),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a typed amount is a percentage of the spendable balance, like `50%`
+pub fn is_percentage(text: &str) -> bool {
+    text.trim().ends_with('%')
+}
+
+/// Parse an amount to pay that may also be a percentage of `spendable`, where `100%` is all of
+/// it. Snap Coin has no fees to hold back, so `100%` empties the spendable outputs exactly
+pub fn parse_payment_of(text: &str, spendable: u64) -> Result<u64, PaymentError> {
+    let Some(percent) = text.trim().strip_suffix('%') else {
+        return parse_payment(text);
+    };
+    let percent = percent.trim();
+    // In parts per billion the share is exact integer math, and 100% is exactly `spendable`
+    let parts = match parse_decimal(percent, PERCENT_DECIMALS) {
+        Ok(parts) => parts,
+        Err(PaymentError::Invalid) => match percent
+            .strip_prefix('-')
+            .map(|percent| parse_decimal(percent, PERCENT_DECIMALS))
+        {
+            Some(Ok(_)) => return Err(PaymentError::PercentOutOfRange),
+            _ => return Err(PaymentError::Invalid),
+        },
+        Err(PaymentError::TooLarge) => return Err(PaymentError::PercentOutOfRange),
+        Err(e) => return Err(e),
+    };
+    if parts == 0 || parts > 1_000_000_000 {
+        return Err(PaymentError::PercentOutOfRange);
+    }
+    match (spendable as u128 * parts as u128 / 1_000_000_000) as u64 {
+        0 => Err(PaymentError::NotPositive),
+        amount => Ok(amount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PER_UNIT: u64 = 100_000_000;
+
+    #[test]
+    fn test_format_units() {
+        let format = |amount, grouping| format_units(amount, PER_UNIT, grouping, false);
+        assert_eq!(format(0, Grouping::Comma), "0.00000000");
+        assert_eq!(format(1, Grouping::Comma), "0.00000001");
+        assert_eq!(format(123_456_789_120_000_000, Grouping::Comma), "1,234,567,891.20000000");
+        assert_eq!(format(123_456_789_120_000_000, Grouping::Period), "1.234.567.891,20000000");
+        assert_eq!(format(100_000 * PER_UNIT, Grouping::Space), "100\u{2009}000.00000000");
+        assert_eq!(format(999 * PER_UNIT, Grouping::Apostrophe), "999.00000000");
+        // The largest amount there can be, far beyond any supply
+        assert_eq!(format(u64::MAX, Grouping::None), "184467440737.09551615");
+    }
+
+    #[test]
+    fn test_format_units_trimmed() {
+        let format = |amount| format_units(amount, PER_UNIT, Grouping::Comma, true);
+        assert_eq!(format(0), "0");
+        assert_eq!(format(1), "0.00000001");
+        assert_eq!(format(1_234 * PER_UNIT + PER_UNIT / 2), "1,234.5");
+        assert_eq!(format(u64::MAX), "184,467,440,737.09551615");
+    }
+
+    #[test]
+    fn test_nano_unit() {
+        assert_eq!(format_units(1_234_567, 1, Grouping::Comma, false), "1,234,567");
+        assert_eq!(format_units(0, 1, Grouping::Comma, true), "0");
+        assert_eq!(parse_amount("1500nano"), Ok(1500));
+        assert_eq!(parse_amount("1500NANO"), Ok(1500));
+        assert_eq!(parse_amount("1.5nano"), Err(PaymentError::TooPrecise(0)));
+        assert_eq!(parse_amount("2"), Ok(to_nano(2.0)));
+        assert_eq!(parse_amount("2snap"), Ok(to_nano(2.0)));
+        assert_eq!(parse_amount("nano"), Err(PaymentError::Invalid));
+    }
+
+    #[test]
+    fn test_parse_payment() {
+        assert_eq!(parse_amount("nan"), Err(PaymentError::Invalid));
+        assert_eq!(parse_amount("inf"), Err(PaymentError::Invalid));
+        assert_eq!(parse_amount("-5"), Err(PaymentError::Invalid));
+        assert_eq!(parse_payment("1.5"), Ok(to_nano(1.5)));
+        assert_eq!(parse_payment("1nano"), Ok(1));
+        assert_eq!(parse_payment("0.000000001"), Err(PaymentError::TooPrecise(8)));
+        for zero in ["0", "0nano", "-0", "0.00000000"] {
+            assert_eq!(parse_payment(zero), Err(PaymentError::NotPositive), "{}", zero);
+        }
+        for negative in ["-5", "-1nano", " -0.5snap"] {
+            assert_eq!(parse_payment(negative), Err(PaymentError::NotPositive), "{}", negative);
+        }
+        for invalid in ["nan", "NaN", "inf", "-inf", "infinity", "five", ""] {
+            assert_eq!(parse_payment(invalid), Err(PaymentError::Invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_amount_exact() {
+        use PaymentError::*;
+        assert_eq!(10u64.pow(SNAP_DECIMALS), to_nano(1.0));
+        let cases: &[(&str, Result<u64, PaymentError>)] = &[
+            ("0", Ok(0)),
+            ("1", Ok(100_000_000)),
+            ("0.1", Ok(10_000_000)),
+            (".25", Ok(25_000_000)),
+            ("7.", Ok(700_000_000)),
+            ("007.50", Ok(750_000_000)),
+            ("0.00000001", Ok(1)),
+            ("1.23456789", Ok(123_456_789)),
+            // Floats would round these to a neighbouring nano amount
+            ("0.29", Ok(29_000_000)),
+            ("12345678.12345678", Ok(1_234_567_812_345_678)),
+            ("90071992.54740993", Ok(9_007_199_254_740_993)),
+            // Zeros past the 8th decimal change nothing, any other digit is refused
+            ("1.000000000000", Ok(100_000_000)),
+            ("0.000000001", Err(TooPrecise(8))),
+            ("1.123456789", Err(TooPrecise(8))),
+            ("0.000000010", Ok(1)),
+            // The largest amount there can be, and one nano more
+            ("184467440737.09551615", Ok(u64::MAX)),
+            ("184467440737.09551616", Err(TooLarge)),
+            ("184467440738", Err(TooLarge)),
+            ("99999999999999999999999", Err(TooLarge)),
+            ("18446744073709551615nano", Ok(u64::MAX)),
+            ("18446744073709551616nano", Err(TooLarge)),
+            ("1 snap", Ok(100_000_000)),
+            ("42 NANO", Ok(42)),
+            ("1.5nano", Err(TooPrecise(0))),
+            ("1.0nano", Ok(1)),
+            ("", Err(Invalid)),
+            (".", Err(Invalid)),
+            ("1.2.3", Err(Invalid)),
+            ("1,5", Err(Invalid)),
+            ("+1", Err(Invalid)),
+            ("1e3", Err(Invalid)),
+            ("0x10", Err(Invalid)),
+            ("1 000", Err(Invalid)),
+            ("-1", Err(Invalid)),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_amount(text), *expected, "{:?}", text);
+        }
+        // Errors name the amount and how many decimals it may have
+        let message = PaymentError::TooPrecise(8).message("0.000000001");
+        assert!(message.contains("0.000000001") && message.contains('8'), "{}", message);
+    }
+
+    #[test]
+    fn test_parse_payment_of() {
+        let spendable = 1_234_567_891;
+        assert!(is_percentage(" 50% "));
+        assert!(!is_percentage("50"));
+        assert_eq!(parse_payment_of("100%", spendable), Ok(spendable));
+        assert_eq!(parse_payment_of("50%", spendable), Ok(617_283_945));
+        assert_eq!(parse_payment_of("12.5 %", 800), Ok(100));
+        assert_eq!(parse_payment_of("33.3333333%", 3_000_000_000), Ok(999_999_999));
+        assert_eq!(parse_payment_of("33.33333333%", 300), Err(PaymentError::TooPrecise(7)));
+        assert_eq!(parse_payment_of("100%", u64::MAX), Ok(u64::MAX));
+        // Plain amounts don't depend on the spendable balance
+        assert_eq!(parse_payment_of("1nano", 0), Ok(1));
+        for out_of_range in ["0%", "-5%", "100.5%", "200%", "99999999999999999999%"] {
+            let result = parse_payment_of(out_of_range, spendable);
+            assert_eq!(result, Err(PaymentError::PercentOutOfRange), "{}", out_of_range);
+        }
+        for invalid in ["%", "half%", "nan%", "inf%", "5%%"] {
+            let result = parse_payment_of(invalid, spendable);
+            assert_eq!(result, Err(PaymentError::Invalid), "{}", invalid);
+        }
+        // A share too small to be one nano
+        assert_eq!(parse_payment_of("1%", 50), Err(PaymentError::NotPositive));
+        assert_eq!(parse_payment_of("50%", 0), Err(PaymentError::NotPositive));
+    }
+
+    #[test]
+    fn test_grouping_names() {
+        for name in Grouping::NAMES {
+            assert_eq!(Grouping::parse(name).map(|grouping| grouping.name()), Some(*name));
+        }
+    }
+}
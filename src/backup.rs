@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use aes_gcm::aead::{OsRng, rand_core::RngCore};
+
+use crate::signing::{WalletKey, Wallets};
+
+/// Unix time each wallet's key was confirmed as backed up, by wallet name, stored encrypted
+/// next to the wallets
+pub type Backups = BTreeMap<String, u64>;
+
+/// Characters of the key `wallet confirm-backup` asks for
+pub const CHALLENGE_LEN: usize = 6;
+
+/// Local wallets whose key was never confirmed as backed up, in name order. External wallets
+/// hold no key to back up
+pub fn unconfirmed<'a>(wallets: &'a Wallets, backups: &Backups) -> Vec<&'a str> {
+    wallets
+        .iter()
+        .filter(|(name, key)| matches!(key, WalletKey::Local(_)) && !backups.contains_key(*name))
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
+/// A random position in a key of `key_len` characters to ask the challenge from
+pub fn challenge_start(key_len: usize) -> usize {
+    let positions = key_len.saturating_sub(CHALLENGE_LEN) + 1;
+    OsRng.next_u32() as usize % positions
+}
+
+/// Whether `answer` is the part of `key` starting at `start`, ignoring case and spaces
+pub fn check_answer(key: &str, start: usize, answer: &str) -> bool {
+    let answer: String = answer.chars().filter(|c| !c.is_whitespace()).collect();
+    key.get(start..start + CHALLENGE_LEN)
+        .is_some_and(|expected| expected.eq_ignore_ascii_case(&answer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::ExternalSigner;
+    use snap_coin::crypto::keys::Private;
+
+    #[test]
+    fn test_unconfirmed() {
+        let mut wallets = Wallets::new();
+        for name in ["savings", "main", "old"] {
+            wallets.insert(name.to_string(), WalletKey::Local(Private::new_random()));
+        }
+        let external = ExternalSigner {
+            public: Private::new_random().to_public(),
+            command: "signer".to_string(),
+        };
+        wallets.insert("ledger".to_string(), WalletKey::External(external));
+        let mut backups = Backups::new();
+        backups.insert("old".to_string(), 1_700_000_000);
+        assert_eq!(unconfirmed(&wallets, &backups), vec!["main", "savings"]);
+    }
+
+    #[test]
+    fn test_check_answer() {
+        let key = "abcdef0123456789";
+        assert!(check_answer(key, 2, "cdef01"));
+        assert!(check_answer(key, 2, " CDE F01 "));
+        assert!(!check_answer(key, 2, "cdef0"));
+        assert!(!check_answer(key, 3, "cdef01"));
+        // A position past the end can't be answered
+        assert!(!check_answer(key, 12, "456789"));
+        for _ in 0..100 {
+            assert!(challenge_start(key.len()) + CHALLENGE_LEN <= key.len());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Error;
+
+use crate::node::NodeApi;
+
+/// Clock difference in seconds beyond which the wallet warns and corrects timestamps.
+/// Generous, because mempool transactions are naturally a little older than the present
+pub const SKEW_THRESHOLD: u64 = 300;
+/// Mempool transactions needed before their timestamps are trusted as network time
+const MIN_SAMPLES: usize = 3;
+
+/// Seconds subtracted from the local clock when timestamping transactions
+static CORRECTION: AtomicI64 = AtomicI64::new(0);
+
+/// How far the local clock is ahead of the network (negative when behind), from the median
+/// timestamp of the given transactions. `None` when there are too few to tell
+pub fn estimate_skew(local_now: u64, timestamps: &[u64]) -> Option<i64> {
+    if timestamps.len() < MIN_SAMPLES {
+        return None;
+    }
+    let mut sorted = timestamps.to_vec();
+    sorted.sort_unstable();
+    let median = sorted[sorted.len() / 2];
+    Some(local_now as i64 - median as i64)
+}
+
+/// Whether a skew is large enough to warn about and correct
+pub fn is_significant(skew: i64) -> bool {
+    skew.unsigned_abs() > SKEW_THRESHOLD
+}
+
+/// The node has no time endpoint, so network time is read off the mempool
+pub async fn detect_skew(client: &dyn NodeApi) -> Result<Option<i64>, Error> {
+    let mempool = client.get_mempool().await?;
+    let timestamps: Vec<u64> = mempool.iter().map(|tx| tx.timestamp).collect();
+    Ok(estimate_skew(local_now()?, &timestamps))
+}
+
+/// Correct transaction timestamps for a detected skew, small ones are left alone
+pub fn set_correction(skew: Option<i64>) {
+    let correction = skew.filter(|skew| is_significant(*skew)).unwrap_or(0);
+    CORRECTION.store(correction, Ordering::Relaxed);
+}
+
+/// Current local time, uncorrected
+pub fn local_now() -> Result<u64, Error> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+/// Current time for transaction timestamps, corrected for a detected clock skew
+pub fn now() -> Result<u64, Error> {
+    let corrected = local_now()? as i64 - CORRECTION.load(Ordering::Relaxed);
+    Ok(corrected.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_skew() {
+        assert_eq!(estimate_skew(1000, &[990, 995]), None);
+        assert_eq!(estimate_skew(1000, &[990, 995, 2000]), Some(5));
+        assert_eq!(estimate_skew(1000, &[4600, 4590, 4595, 10]), Some(-3595));
+        assert!(!is_significant(60));
+        assert!(is_significant(-3600));
+    }
+}
@@ -0,0 +1,784 @@
+/// A subcommand or setting listed under its command in `help`
+pub struct Sub {
+    pub usage: &'static str,
+    /// i18n key of the one line description
+    pub summary: &'static str,
+}
+
+/// What `help`, `help <command>` and tab completion know about a command. All three read
+/// this table, so they can't drift apart. The usage `set` prints for an unknown setting
+/// lists its subcommands too
+pub struct CommandInfo {
+    pub name: &'static str,
+    /// Other words that run the same command
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    /// i18n key of the one line description
+    pub summary: &'static str,
+    pub subcommands: &'static [Sub],
+    /// Argument formats and flags, shown by `help <command>`
+    pub details: &'static [&'static str],
+    pub examples: &'static [&'static str],
+}
+
+const fn sub(usage: &'static str, summary: &'static str) -> Sub {
+    Sub { usage, summary }
+}
+
+const ADDRESS: &str = "<addr> is a base36 address, about 50 lowercase letters and digits. A \
+    contact alias from the address book works wherever an address is asked for.";
+const AMOUNT: &str = "<amt> is in SNAP, e.g. 1.5, or in nano with a `nano` suffix, e.g. 1500nano.";
+
+/// Every command, in `help` order
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "balance",
+        aliases: &[],
+        usage: "balance [wallet]",
+        summary: "help.balance",
+        subcommands: &[],
+        details: &[
+            "Shows the node's balance for the current wallet. When part of it can't be sent \
+             yet, the spendable amount follows with the reasons.",
+            "With a wallet name, shows that wallet's balance instead without switching to it, \
+             external signer wallets included.",
+        ],
+        examples: &["balance", "balance savings", "balance --nano"],
+    },
+    CommandInfo {
+        name: "available",
+        aliases: &[],
+        usage: "available [--sort <key>]",
+        summary: "help.available",
+        subcommands: &[sub("export <path|->", "help.available_export")],
+        details: &["--sort amount|age|txid orders the outputs, amount is the default. \
+            Outputs already spent this session are flagged session-spent."],
+        examples: &["available", "available --sort age", "available export utxos.csv"],
+    },
+    CommandInfo {
+        name: "history",
+        aliases: &[],
+        usage: "history [--limit <n>]",
+        summary: "help.history",
+        subcommands: &[],
+        details: &["--limit <n> shows only the <n> most recent transactions."],
+        examples: &["history", "history --limit 10"],
+    },
+    CommandInfo {
+        name: "report",
+        aliases: &[],
+        usage: "report <year> --out <path> [--wallet <name>]",
+        summary: "help.report",
+        subcommands: &[],
+        details: &["Writes one CSV row per transaction of <year> (UTC dates) with its direction, \
+            amount, fee, counterparty, running balance and note, then the totals of each month. \
+            The running balance is replayed from the whole history, not asked from the node."],
+        examples: &["report 2025 --out 2025.csv", "report 2025 --wallet savings --out s.csv"],
+    },
+    CommandInfo {
+        name: "snapshot",
+        aliases: &[],
+        usage: "snapshot",
+        summary: "help.snapshot",
+        subcommands: &[],
+        details: &["Appends the time and the node's balance of the current wallet to \
+            ~/.snap-coin-balances, which keeps the last 5000 snapshots of all wallets. With \
+            `set encrypt-snapshots on` the balance is sealed with a key of the wallet and the \
+            line doesn't show its address."],
+        examples: &["snapshot"],
+    },
+    CommandInfo {
+        name: "balance-history",
+        aliases: &[],
+        usage: "balance-history [--limit <n>]",
+        summary: "help.balance_history",
+        subcommands: &[],
+        details: &["Shows the recorded snapshots of the current wallet with the change since \
+            the previous one, then a sparkline of them. --limit <n> shows only the <n> most \
+            recent."],
+        examples: &["balance-history", "balance-history --limit 30"],
+    },
+    CommandInfo {
+        name: "tx-info",
+        aliases: &[],
+        usage: "tx-info <txid>",
+        summary: "help.tx_info",
+        subcommands: &[],
+        details: &["<txid> is a base36 transaction id, as shown by history."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "tx-note",
+        aliases: &[],
+        usage: "tx-note <txid> [<text>]",
+        summary: "help.tx_note",
+        subcommands: &[],
+        details: &["Everything after <txid> is the note. Without text the note is removed."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "prune",
+        aliases: &[],
+        usage: "prune [--older-than <d>]",
+        summary: "help.prune",
+        subcommands: &[],
+        details: &["--older-than <d> also removes notes older than <d> days, instead of the \
+            note-max-age setting."],
+        examples: &["prune", "prune --older-than 90"],
+    },
+    CommandInfo {
+        name: "estimate",
+        aliases: &[],
+        usage: "estimate <amt> [n]",
+        summary: "help.estimate",
+        subcommands: &[],
+        details: &[AMOUNT, "[n] is the number of recipients, 1 by default."],
+        examples: &["estimate 25", "estimate 100 4"],
+    },
+    CommandInfo {
+        name: "merge-available",
+        aliases: &[],
+        usage: "merge-available",
+        summary: "help.merge_available",
+        subcommands: &[],
+        details: &["Asks for the PIN, then sends every available output back to the wallet in \
+            as few outputs as transactions allow."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "send",
+        aliases: &[],
+        usage: "send [<addr|alias> <amt>...] [--no-refresh]",
+        summary: "help.send",
+        subcommands: &[],
+        details: &[
+            "Takes one or more <addr> <amt> pairs, all paid by one transaction.",
+            ADDRESS,
+            AMOUNT,
+            "<amt> can also be a share of what can be sent now, e.g. 50%; 100% sends all of it.",
+            "--label <text> after a pair labels that recipient. Labels become the transaction's \
+             note in history and tx-info, and are never broadcast.",
+            "A summary is shown and the PIN asked for before anything is submitted.",
+            "Right before submitting, the inputs are checked against a fresh listing from the \
+             node, and the transaction is built again if one was spent elsewhere meanwhile. \
+             --no-refresh skips that check, saving a node call in tight loops.",
+            "--expect-tx <txid> sends only a transaction with that id, and nothing if it was \
+             built differently, as JSON-RPC confirm_send does with the proposed one.",
+            "Without arguments, send asks for each recipient and amount in turn.",
+        ],
+        examples: &[
+            "send",
+            "send alice 1.5",
+            "send alice 1 bob 250000000nano",
+            "send alice 50%",
+            "send alice 10 --label \"june rent\"",
+        ],
+    },
+    CommandInfo {
+        name: "session",
+        aliases: &[],
+        usage: "session [reset [--all]]",
+        summary: "help.session",
+        subcommands: &[sub("reset", "help.session_reset")],
+        details: &[
+            "Inputs spent this session are left out of new transactions until the node \
+             confirms them. reset is for when a transaction spending them was dropped.",
+            "Each wallet's inputs are kept apart: session lists and reset clears the current \
+             wallet's, reset --all every wallet's.",
+        ],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "status",
+        aliases: &[],
+        usage: "status",
+        summary: "help.status",
+        subcommands: &[],
+        details: &[
+            "Shows the version, wallet and address, wallet file, unsaved changes, session-spent \
+             inputs, whether the node answers, pending transactions and the clock skew.",
+            "Holds no keys or PIN details, so it can be pasted into a bug report as is. It \
+             works with the node down too.",
+        ],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "diagnose",
+        aliases: &[],
+        usage: "diagnose",
+        summary: "help.diagnose",
+        subcommands: &[],
+        details: &[
+            "Checks that the node answers and how fast, that the wallet file decrypts with this \
+             session's key, pending transactions stuck in the mempool, the clock against the \
+             network and that the balance matches the spendable outputs.",
+            "Each check passes, warns or fails, with what to do about it. Nothing is sent or \
+             saved. Exits with an error when a check fails.",
+        ],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "log",
+        aliases: &[],
+        usage: "log path",
+        summary: "help.log",
+        subcommands: &[],
+        details: &[
+            "The operation log records commands by name, node calls with their durations, \
+             connections and errors, for attaching to bug reports. Arguments are never written, \
+             and numbers and long words such as addresses are masked in error messages.",
+            "It is written with --log-file or `set log-file on` and rotated at 1 MiB.",
+        ],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "dashboard",
+        aliases: &[],
+        usage: "dashboard",
+        summary: "help.dashboard",
+        subcommands: &[],
+        details: &["Keys: s send, w next wallet, r refresh, : type a command, q back."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "watch",
+        aliases: &[],
+        usage: "watch <cmd> [seconds]",
+        summary: "help.watch",
+        subcommands: &[],
+        details: &["<cmd> is balance, available or mempool, with their options. [seconds] is \
+            the refresh interval, 5 by default. Ctrl+C stops."],
+        examples: &["watch balance", "watch mempool --mine 30"],
+    },
+    CommandInfo {
+        name: "open",
+        aliases: &[],
+        usage: "open <txid|address|alias>",
+        summary: "help.open",
+        subcommands: &[],
+        details: &["Set explorer_tx_url (with {txid}) and explorer_address_url (with {addr}) in \
+            ~/.snap-coin-config. A transaction the node knows opens its page, anything else is \
+            taken as an address or a contact."],
+        examples: &["open alice"],
+    },
+    CommandInfo {
+        name: "install-uri-handler",
+        aliases: &[],
+        usage: "install-uri-handler",
+        summary: "help.install_uri_handler",
+        subcommands: &[],
+        details: &["Registers this binary for snap: links: a desktop entry on Linux, the user's \
+            registry on Windows. macOS needs an application bundle, the steps are printed. \
+            Clicked links run snap-coin-wallet --handle-uri <link>."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "webhook",
+        aliases: &[],
+        usage: "webhook test",
+        summary: "help.webhook",
+        subcommands: &[],
+        details: &["Set webhook_url in ~/.snap-coin-config to have confirmed sends posted there. \
+            The test payload has event \"test\" and retries like a real one."],
+        examples: &["webhook test"],
+    },
+    CommandInfo {
+        name: "vanity",
+        aliases: &[],
+        usage: "vanity <prefix>",
+        summary: "help.vanity",
+        subcommands: &[],
+        details: &["<prefix> is letters and digits. Each extra character makes the search about \
+            36 times longer."],
+        examples: &["vanity abc"],
+    },
+    CommandInfo {
+        name: "sign",
+        aliases: &["prove-ownership"],
+        usage: "sign <message>",
+        summary: "help.sign",
+        subcommands: &[],
+        details: &["Everything after sign is the message, words joined by single spaces."],
+        examples: &["sign I control this address"],
+    },
+    CommandInfo {
+        name: "verify",
+        aliases: &[],
+        usage: "verify <addr> <msg> <sig>",
+        summary: "help.verify",
+        subcommands: &[],
+        details: &[ADDRESS, "<sig> is the base36 signature printed by sign."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "file-info",
+        aliases: &[],
+        usage: "file-info",
+        summary: "help.file_info",
+        subcommands: &[],
+        details: &[],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "config",
+        aliases: &[],
+        usage: "config <subcmd>",
+        summary: "help.config",
+        subcommands: &[sub("show", "help.config_show")],
+        details: &[],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "mempool",
+        aliases: &[],
+        usage: "mempool [--mine]",
+        summary: "help.mempool",
+        subcommands: &[],
+        details: &["--mine lists only transactions paying or spending from this wallet."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "contact",
+        aliases: &[],
+        usage: "contact <subcmd>",
+        summary: "help.contact",
+        subcommands: &[
+            sub("add <alias> <addr>", "help.contact_add"),
+            sub("remove <alias>", "help.contact_remove"),
+            sub("rename <old> <new>", "help.contact_rename"),
+            sub("note <alias> [text]", "help.contact_note"),
+            sub("list", "help.contact_list"),
+        ],
+        details: &[ADDRESS],
+        examples: &["contact add alice <addr>", "send alice 2"],
+    },
+    CommandInfo {
+        name: "wallet",
+        aliases: &[],
+        usage: "wallet <subcmd> [<wallet>]",
+        summary: "help.wallet",
+        subcommands: &[
+            sub("list [--balances]", "help.wallet_list"),
+            sub("delete [<wallet>]", "help.wallet_delete"),
+            sub("private [<wallet>] [--screen] [--qr] [--checksummed]", "help.wallet_private"),
+            sub("confirm-backup [<wallet>]", "help.wallet_confirm_backup"),
+            sub("public [<wallet>]", "help.wallet_public"),
+            sub("switch [<wallet>]", "help.wallet_switch"),
+            sub("add-external <wallet> <pub> <cmd...> ", "help.wallet_add_external"),
+            sub("import-qr <image> [<wallet>]", "help.wallet_import_qr"),
+            sub("import-json <path> [--dry-run]", "help.wallet_import_json"),
+            sub("merge <source> <destination>", "help.wallet_merge"),
+        ],
+        details: &["<wallet> is a wallet name, the current wallet when left out."],
+        examples: &[
+            "wallet list --balances",
+            "wallet switch savings",
+            "wallet private --screen",
+        ],
+    },
+    CommandInfo {
+        name: "save",
+        aliases: &[],
+        usage: "save",
+        summary: "help.save",
+        subcommands: &[],
+        details: &["Only needed with `set autosave off`, exiting offers to save as well."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "compact",
+        aliases: &[],
+        usage: "compact",
+        summary: "help.compact",
+        subcommands: &[],
+        details: &["Backs the file up to ~/.snap-coin-backups, rewrites it in the latest format \
+            without wallets whose key another wallet holds, and keeps only the newest \
+            backup_retention backups (5 by default). Asks before changing anything."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "change-pin",
+        aliases: &[],
+        usage: "change-pin",
+        summary: "help.change_pin",
+        subcommands: &[],
+        details: &["Asks for the current PIN, then the new one twice. The wallet file is \
+            rewritten for the new PIN at once and the session goes on with it, no login needed."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "set",
+        aliases: &[],
+        usage: "set <setting> <value>",
+        summary: "help.set",
+        subcommands: &[
+            sub("kdf [options]", "help.set_kdf"),
+            sub("prompt-balance on|off", "help.set_prompt_balance"),
+            sub("bell on|off", "help.set_bell"),
+            sub("quiet on|off", "help.set_quiet"),
+            sub("auto-login on|off", "help.set_auto_login"),
+            sub("pager on|off", "help.set_pager"),
+            sub("autocorrect on|off", "help.set_autocorrect"),
+            sub("autosave on|off", "help.set_autosave"),
+            sub("encrypt-snapshots on|off", "help.set_encrypt_snapshots"),
+            sub("log-file on|off", "help.set_log_file"),
+            sub("pin-timeout <seconds>", "help.set_pin_timeout"),
+            sub("note-max-age <days>", "help.set_note_max_age"),
+            sub("thousands-separator <s>", "help.set_thousands_separator"),
+            sub("unit snap|nano", "help.set_unit"),
+            sub("lang en|es|auto", "help.set_lang"),
+            sub("notify off|bell|desktop|both", "help.set_notify"),
+            sub("donation-prompt on|weekly|off", "help.set_donation_prompt"),
+            sub("low-balance <amt>|off", "help.set_low_balance"),
+        ],
+        details: &[
+            "Settings are saved in the config file right away.",
+            "kdf takes --memory <size> (e.g. 64MiB), --iterations <n> and --parallelism <n>, \
+             and shows the current settings without options.",
+        ],
+        examples: &["set unit nano", "set kdf --memory 128MiB"],
+    },
+    CommandInfo {
+        name: "help",
+        aliases: &[],
+        usage: "help [<command>]",
+        summary: "help.help",
+        subcommands: &[],
+        details: &[],
+        examples: &["help", "help send"],
+    },
+    CommandInfo {
+        name: "clear",
+        aliases: &[],
+        usage: "clear",
+        summary: "help.clear",
+        subcommands: &[],
+        details: &["The command history is kept, `clear-history` forgets it."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "history-list",
+        aliases: &[],
+        usage: "history-list",
+        summary: "help.history_list",
+        subcommands: &[],
+        details: &[
+            "`!!` runs the last command again, `!<n>` entry n of this list and `!<prefix>` the \
+             newest command starting with prefix. The expanded command is shown before it runs.",
+        ],
+        examples: &["!!", "!12", "!send"],
+    },
+    CommandInfo {
+        name: "clear-history",
+        aliases: &[],
+        usage: "clear-history",
+        summary: "help.clear_history",
+        subcommands: &[],
+        details: &["Asks first, then forgets every command entered, also in the history file."],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "suspend",
+        aliases: &[],
+        usage: "suspend",
+        summary: "help.suspend",
+        subcommands: &[],
+        details: &[
+            "Saves, then forgets the keys and disconnects from the node. Enter and the PIN resume \
+             where you left off, Ctrl+C quits.",
+        ],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "exit",
+        aliases: &["quit"],
+        usage: "exit, quit",
+        summary: "help.exit",
+        subcommands: &[],
+        details: &[],
+        examples: &[],
+    },
+    CommandInfo {
+        name: "alias",
+        aliases: &[],
+        usage: "alias <subcmd>",
+        summary: "help.alias",
+        subcommands: &[
+            sub("add <name> <cmd...>", "help.alias_add"),
+            sub("remove <name>", "help.alias_remove"),
+            sub("list", "help.alias_list"),
+        ],
+        details: &["Typing <name> runs <cmd...>, followed by anything typed after <name>."],
+        examples: &["alias add b balance", "alias add pay-rent send landlord 500"],
+    },
+];
+
+/// The command called `name`, or one of its aliases
+pub fn find(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS
+        .iter()
+        .find(|info| info.name == name || info.aliases.contains(&name))
+}
+
+/// Every word that runs a command
+pub fn names() -> impl Iterator<Item = &'static str> {
+    COMMANDS
+        .iter()
+        .flat_map(|info| std::iter::once(info.name).chain(info.aliases.iter().copied()))
+}
+
+/// First words of the subcommands of `name`
+pub fn subcommand_names(name: &str) -> Vec<&'static str> {
+    find(name).map_or(vec![], |info| {
+        info.subcommands
+            .iter()
+            .filter_map(|sub| sub.usage.split_whitespace().next())
+            .collect()
+    })
+}
+
+/// How a command in a chained line depends on the ones before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Chain {
+    /// The first command, or one after `;`: runs whatever happened before
+    Always,
+    /// After `&&`: runs only if the command before it succeeded
+    OnSuccess,
+}
+
+/// Split a line into the commands chained with `&&` and `;`. Separators inside single or
+/// double quotes are part of the command, and empty commands are left out
+pub fn split_chain(line: &str) -> Vec<(Chain, String)> {
+    let mut commands = vec![];
+    let mut push = |chain: Chain, command: &str| {
+        if !command.trim().is_empty() {
+            commands.push((chain, command.trim().to_string()));
+        }
+    };
+    let mut chain = Chain::Always;
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            (';', None) => {
+                push(chain, &current);
+                current.clear();
+                chain = Chain::Always;
+                continue;
+            }
+            ('&', None) if chars.peek() == Some(&'&') => {
+                chars.next();
+                push(chain, &current);
+                current.clear();
+                chain = Chain::OnSuccess;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    push(chain, &current);
+    commands
+}
+
+/// Expand the history references in a line: `!!` is the last command, `!<n>` entry `n` of
+/// `history-list` and `!<prefix>` the newest command starting with `prefix`. Only words
+/// starting with `!` outside quotes are references. `Ok(None)` when the line has none
+pub fn expand_history(line: &str, entries: &[String]) -> Result<Option<String>, String> {
+    let mut expanded = String::new();
+    let mut quote: Option<char> = None;
+    let mut word_start = true;
+    let mut found = false;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match (c, quote) {
+            ('!', None) if word_start => {
+                let len = if rest.starts_with('!') {
+                    1
+                } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                    rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())
+                } else {
+                    rest.find(|c: char| c.is_whitespace() || "&;\"'".contains(c))
+                        .unwrap_or(rest.len())
+                };
+                let reference = &rest[..len];
+                if !reference.is_empty() {
+                    let entry = match reference {
+                        "!" => entries.last(),
+                        _ if reference.starts_with(|c: char| c.is_ascii_digit()) => reference
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|n| entries.get(n.checked_sub(1)?)),
+                        _ => entries.iter().rev().find(|entry| entry.starts_with(reference)),
+                    };
+                    let entry = entry.ok_or_else(|| format!("!{}: event not found", reference))?;
+                    expanded.push_str(entry);
+                    rest = &rest[len..];
+                    found = true;
+                    word_start = false;
+                    continue;
+                }
+            }
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            _ => {}
+        }
+        expanded.push(c);
+        word_start = quote.is_none() && (c.is_whitespace() || c == ';' || c == '&');
+    }
+    Ok(found.then_some(expanded))
+}
+
+/// Number of single character edits between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + (ca != *cb) as usize;
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(previous + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The candidate closest to a mistyped `name` with its distance, if any is close enough to be
+/// what was meant. Ties go to the earlier candidate
+pub fn closest_among<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<(&'a str, usize)> {
+    let name = name.to_lowercase();
+    candidates
+        .into_iter()
+        .map(|candidate| (candidate, edit_distance(&name, candidate)))
+        .filter(|(candidate, distance)| *distance <= 2 && *distance < candidate.len())
+        .min_by_key(|(_, distance)| *distance)
+}
+
+/// The command name closest to a mistyped `name`, if any is close enough to be what was meant
+pub fn closest(name: &str) -> Option<&'static str> {
+    closest_among(name, names()).map(|(candidate, _)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::tr;
+
+    #[test]
+    fn test_table() {
+        let mut seen = std::collections::HashSet::new();
+        for name in names() {
+            assert!(seen.insert(name), "'{}' is listed twice", name);
+        }
+        for info in COMMANDS {
+            assert_ne!(tr(info.summary), info.summary, "no message for {}", info.summary);
+            for sub in info.subcommands {
+                assert_ne!(tr(sub.summary), sub.summary, "no message for {}", sub.summary);
+            }
+        }
+        assert_eq!(find("quit").map(|info| info.name), Some("exit"));
+        assert!(subcommand_names("wallet").contains(&"add-external"));
+        assert!(subcommand_names("set").contains(&"low-balance"));
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(closest("snd"), Some("send"));
+        assert_eq!(closest("balnce"), Some("balance"));
+        assert_eq!(closest("Wallte"), Some("wallet"));
+        assert_eq!(closest("xyzzy"), None);
+    }
+
+    #[test]
+    fn test_common_typos() {
+        let typos = [
+            ("blance", "balance", 1),
+            ("balnace", "balance", 2),
+            ("sned", "send", 2),
+            ("sendd", "send", 1),
+            ("hlep", "help", 2),
+            ("hisotry", "history", 2),
+            ("histroy", "history", 2),
+            ("stauts", "status", 2),
+            ("contcat", "contact", 2),
+            ("wallt", "wallet", 1),
+            ("exti", "exit", 2),
+            ("clera", "clear", 2),
+            ("mempol", "mempool", 1),
+            ("verfy", "verify", 1),
+        ];
+        for (typo, meant, distance) in typos {
+            assert_eq!(closest_among(typo, names()), Some((meant, distance)), "{}", typo);
+        }
+        // Single letters are too short to guess from
+        assert_eq!(closest_among("x", names()), None);
+        let wallet = subcommand_names("wallet");
+        assert_eq!(closest_among("swich", wallet.clone()), Some(("switch", 1)));
+        assert_eq!(closest_among("lsit", wallet), Some(("list", 2)));
+        assert_eq!(closest_among("pay-rnt", ["pay-rent"]), Some(("pay-rent", 1)));
+    }
+
+    #[test]
+    fn test_split_chain() {
+        let owned = |commands: &[(Chain, &str)]| -> Vec<(Chain, String)> {
+            commands.iter().map(|(chain, command)| (*chain, command.to_string())).collect()
+        };
+        assert_eq!(split_chain("balance"), owned(&[(Chain::Always, "balance")]));
+        assert_eq!(
+            split_chain("balance && available; history"),
+            owned(&[
+                (Chain::Always, "balance"),
+                (Chain::OnSuccess, "available"),
+                (Chain::Always, "history"),
+            ])
+        );
+        // Quoted separators stay in the command, quotes included
+        assert_eq!(
+            split_chain("tx-note abc \"rent; paid && done\" && balance"),
+            owned(&[
+                (Chain::Always, "tx-note abc \"rent; paid && done\""),
+                (Chain::OnSuccess, "balance"),
+            ])
+        );
+        assert_eq!(
+            split_chain("alias add b 'balance && available'"),
+            owned(&[(Chain::Always, "alias add b 'balance && available'")])
+        );
+        // A single & and empty commands
+        assert_eq!(split_chain("a & b;;"), owned(&[(Chain::Always, "a & b")]));
+        assert_eq!(split_chain(" ; && "), vec![]);
+    }
+
+    #[test]
+    fn test_expand_history() {
+        let entries: Vec<String> = ["balance", "send bob 5", "available --sort age"]
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        let expand = |line: &str| expand_history(line, &entries);
+        assert_eq!(expand("!!"), Ok(Some("available --sort age".to_string())));
+        assert_eq!(expand("!1"), Ok(Some("balance".to_string())));
+        assert_eq!(expand("!se"), Ok(Some("send bob 5".to_string())));
+        assert_eq!(expand("!1 && !!"), Ok(Some("balance && available --sort age".to_string())));
+        assert_eq!(expand("!1;!2"), Ok(Some("balance;send bob 5".to_string())));
+        // Only words starting with ! outside quotes are references
+        assert_eq!(expand("balance"), Ok(None));
+        assert_eq!(expand("tx-note abc \"!! wow\""), Ok(None));
+        assert_eq!(expand("tx-note abc wow!!"), Ok(None));
+        assert_eq!(expand("!"), Ok(None));
+        assert!(expand("!4").is_err());
+        assert!(expand("!0").is_err());
+        assert!(expand("!history").is_err());
+        assert!(expand_history("!!", &[]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Error;
+
+use crate::{
+    backup::Backups,
+    export,
+    signing::{WalletKey, Wallets},
+};
+
+/// Backups of the wallet file are named `wallet-<unix time>.bak`
+const BACKUP_PREFIX: &str = "wallet-";
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// What `compact` removes from the wallet file
+#[derive(Debug, Default, PartialEq)]
+pub struct Plan {
+    /// Wallets whose key another wallet already holds, each with the wallet kept for it
+    pub duplicates: Vec<(String, String)>,
+    /// Backup confirmations of wallets that no longer exist
+    pub stale_confirmations: Vec<String>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty() && self.stale_confirmations.is_empty()
+    }
+}
+
+/// Whether `kept` makes `other` redundant: it signs for the same address at least as well.
+/// A local key beats an external signer, and two external signers only match when they run
+/// the same command
+fn covers(kept: &WalletKey, other: &WalletKey) -> bool {
+    match (kept, other) {
+        (WalletKey::Local(kept), WalletKey::Local(other)) => kept.to_public() == other.to_public(),
+        (WalletKey::Local(kept), WalletKey::External(other)) => kept.to_public() == other.public,
+        (WalletKey::External(kept), WalletKey::External(other)) => {
+            kept.public == other.public && kept.command == other.command
+        }
+        (WalletKey::External(_), WalletKey::Local(_)) => false,
+    }
+}
+
+/// Find what can go without losing a key or a way to sign. Of equal wallets, the name that
+/// sorts first is kept
+pub fn plan(wallets: &Wallets, backups: &Backups) -> Plan {
+    let mut names: Vec<&String> = wallets.keys().collect();
+    // Local keys first, so an external signer is dropped for the key rather than the reverse
+    names.sort_by_key(|name| !matches!(wallets[*name], WalletKey::Local(_)));
+
+    let mut plan = Plan::default();
+    let mut kept: Vec<&String> = vec![];
+    for name in names {
+        let wallet = &wallets[name];
+        match kept.iter().find(|kept| covers(&wallets[**kept], wallet)) {
+            Some(kept) => plan.duplicates.push((name.clone(), kept.to_string())),
+            None => kept.push(name),
+        }
+    }
+    plan.duplicates.sort();
+    plan.stale_confirmations = backups
+        .keys()
+        .filter(|name| !wallets.contains_key(*name))
+        .cloned()
+        .collect();
+    plan
+}
+
+/// Remove what `plan` found. A removed wallet's backup confirmation moves to the wallet kept
+/// for it, as that holds the same key
+pub fn apply(plan: &Plan, wallets: &mut Wallets, backups: &mut Backups) {
+    for (removed, kept) in &plan.duplicates {
+        wallets.remove(removed);
+        if let Some(confirmed) = backups.remove(removed) {
+            let entry = backups.entry(kept.clone()).or_insert(confirmed);
+            *entry = (*entry).min(confirmed);
+        }
+    }
+    for name in &plan.stale_confirmations {
+        backups.remove(name);
+    }
+}
+
+/// Backups in `dir`, oldest first
+fn list_backups(dir: &Path) -> Result<Vec<(u64, PathBuf)>, Error> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut backups = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let time = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| {
+                name.strip_prefix(BACKUP_PREFIX)?
+                    .strip_suffix(BACKUP_SUFFIX)
+            })
+            .and_then(|time| time.parse::<u64>().ok());
+        if let Some(time) = time {
+            backups.push((time, path));
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Copy the wallet file as it is on disk, still encrypted, into `dir`
+pub fn back_up(wallet_file: &Path, dir: &Path, now: u64) -> Result<PathBuf, Error> {
+    let contents = fs::read(wallet_file)?;
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}{}{}", BACKUP_PREFIX, now, BACKUP_SUFFIX));
+    export::write_atomic(&path, &contents)?;
+    Ok(path)
+}
+
+/// Backups in `dir` beyond the newest `keep`, oldest first
+pub fn old_backups(dir: &Path, keep: usize) -> Result<Vec<PathBuf>, Error> {
+    let backups = list_backups(dir)?;
+    let excess = backups.len().saturating_sub(keep);
+    Ok(backups
+        .into_iter()
+        .take(excess)
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// Size of a file in bytes, 0 when it can't be read
+pub fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::ExternalSigner;
+    use snap_coin::crypto::keys::Private;
+
+    #[test]
+    fn test_plan() {
+        let key = Private::new_random();
+        let signer = |command: &str| {
+            WalletKey::External(ExternalSigner {
+                public: key.to_public(),
+                command: command.to_string(),
+            })
+        };
+        let mut wallets = Wallets::new();
+        wallets.insert("b-main".to_string(), WalletKey::Local(key));
+        wallets.insert("a-copy".to_string(), WalletKey::Local(key));
+        wallets.insert("watch".to_string(), signer("hsm"));
+        wallets.insert("other".to_string(), WalletKey::Local(Private::new_random()));
+        let mut backups = Backups::new();
+        backups.insert("b-main".to_string(), 20);
+        backups.insert("deleted".to_string(), 10);
+
+        let plan = plan(&wallets, &backups);
+        assert_eq!(
+            plan.duplicates,
+            vec![
+                ("b-main".to_string(), "a-copy".to_string()),
+                ("watch".to_string(), "a-copy".to_string()),
+            ]
+        );
+        assert_eq!(plan.stale_confirmations, vec!["deleted".to_string()]);
+
+        apply(&plan, &mut wallets, &mut backups);
+        assert_eq!(wallets.len(), 2);
+        assert_eq!(
+            backups.into_iter().collect::<Vec<_>>(),
+            vec![("a-copy".to_string(), 20)]
+        );
+
+        // Signers running different commands are different ways to sign
+        let mut wallets = Wallets::new();
+        wallets.insert("usb".to_string(), signer("usb-signer"));
+        wallets.insert("hsm".to_string(), signer("hsm"));
+        assert!(super::plan(&wallets, &Backups::new()).is_empty());
+    }
+
+    #[test]
+    fn test_backups() {
+        let dir = std::env::temp_dir().join(format!("snap-compact-{}", std::process::id()));
+        let wallet_file = dir.join("wallet");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&wallet_file, b"encrypted").unwrap();
+        let backup_dir = dir.join("backups");
+        for now in [300, 100, 200] {
+            back_up(&wallet_file, &backup_dir, now).unwrap();
+        }
+        fs::write(backup_dir.join("notes.txt"), b"not a backup").unwrap();
+        assert_eq!(
+            old_backups(&backup_dir, 1).unwrap(),
+            vec![
+                backup_dir.join("wallet-100.bak"),
+                backup_dir.join("wallet-200.bak")
+            ]
+        );
+        assert_eq!(
+            fs::read(backup_dir.join("wallet-300.bak")).unwrap(),
+            b"encrypted"
+        );
+        assert!(old_backups(&backup_dir, 5).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
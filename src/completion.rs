@@ -0,0 +1,181 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rustyline::{
+    Context, Helper, completion::Completer, highlight::Highlighter, hint::Hinter,
+    validate::Validator,
+};
+
+use crate::commands;
+
+/// Command words that are handled outside `commands::COMMANDS`, and can't be aliased either
+pub const SHORT_COMMANDS: &[&str] = &["e", "q", "cls"];
+
+/// Names the completer offers beyond the fixed command set
+#[derive(Default)]
+pub struct CompletionData {
+    pub wallet_names: Vec<String>,
+    pub contact_aliases: Vec<String>,
+    /// Base36 addresses sent to this session, most recent first
+    pub recent_addresses: Vec<String>,
+    pub command_aliases: Vec<String>,
+}
+
+/// Completion data shared between the REPL loop and the completer
+pub type SharedCompletionData = Rc<RefCell<CompletionData>>;
+
+/// Rustyline helper completing commands, subcommands, wallet names and send recipients
+pub struct WalletHelper {
+    pub data: SharedCompletionData,
+}
+
+impl WalletHelper {
+    pub fn new(data: SharedCompletionData) -> Self {
+        WalletHelper { data }
+    }
+}
+
+/// Candidates from `options` starting with `prefix`, ignoring case
+fn matching(options: impl IntoIterator<Item = String>, prefix: &str) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    let mut candidates: Vec<String> = options
+        .into_iter()
+        .filter(|option| option.to_lowercase().starts_with(&prefix))
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// Complete the word ending at `pos`. Returns the start of that word and its candidates
+pub fn complete_line(line: &str, pos: usize, data: &CompletionData) -> (usize, Vec<String>) {
+    let line = &line[..pos];
+    let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let word = &line[start..];
+    let previous: Vec<&str> = line[..start].split_whitespace().collect();
+
+    let owned = |list: Vec<&str>| list.into_iter().map(str::to_string).collect::<Vec<_>>();
+    let candidates = match previous.as_slice() {
+        [] => matching(
+            commands::names()
+                .map(str::to_string)
+                .chain(data.command_aliases.iter().cloned()),
+            word,
+        ),
+        ["help"] => matching(commands::names().map(str::to_string), word),
+        ["open"] => matching(data.contact_aliases.iter().cloned(), word),
+        ["wallet", "delete" | "private" | "confirm-backup" | "public" | "switch" | "merge"]
+        | ["wallet", "merge", _] => matching(data.wallet_names.iter().cloned(), word),
+        // send <address> <amount> [<address> <amount>...]: only addresses complete.
+        // Aliases are inserted as typed, recent addresses keep their recency order
+        ["send", rest @ ..] if rest.len().is_multiple_of(2) => {
+            let aliases = matching(data.contact_aliases.iter().cloned(), word);
+            let recent = data
+                .recent_addresses
+                .iter()
+                .filter(|address| address.to_lowercase().starts_with(&word.to_lowercase()))
+                .filter(|address| !aliases.contains(address))
+                .cloned();
+            aliases.iter().cloned().chain(recent).collect()
+        }
+        ["contact", "remove" | "rename" | "note"] => {
+            matching(data.contact_aliases.iter().cloned(), word)
+        }
+        ["alias", "remove"] => matching(data.command_aliases.iter().cloned(), word),
+        [command] => matching(owned(commands::subcommand_names(command)), word),
+        _ => vec![],
+    };
+    (start, candidates)
+}
+
+impl Completer for WalletHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete_line(line, pos, &self.data.borrow()))
+    }
+}
+
+impl Hinter for WalletHelper {
+    type Hint = String;
+}
+
+impl Highlighter for WalletHelper {}
+
+impl Validator for WalletHelper {}
+
+impl Helper for WalletHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> CompletionData {
+        CompletionData {
+            wallet_names: vec!["Savings".to_string(), "spending".to_string(), "main".to_string()],
+            contact_aliases: vec!["alice".to_string(), "albert".to_string()],
+            recent_addresses: vec!["al3xaddr".to_string(), "bob9addr".to_string()],
+            command_aliases: vec!["b".to_string(), "pay-rent".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_complete_commands() {
+        assert_eq!(complete_line("bal", 3, &names()), (0, vec!["balance".to_string()]));
+        assert_eq!(
+            complete_line("SE", 2, &names()),
+            (0, vec!["send".to_string(), "session".to_string(), "set".to_string()])
+        );
+        // Subcommands and help topics come from the command table
+        assert_eq!(complete_line("set low", 7, &names()), (4, vec!["low-balance".to_string()]));
+        assert_eq!(
+            complete_line("help pr", 7, &names()),
+            (5, vec!["prove-ownership".to_string(), "prune".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_complete_wallet_names() {
+        assert_eq!(
+            complete_line("wallet sw", 9, &names()),
+            (7, vec!["switch".to_string()])
+        );
+        assert_eq!(
+            complete_line("wallet switch s", 15, &names()),
+            (14, vec!["Savings".to_string(), "spending".to_string()])
+        );
+        assert_eq!(complete_line("wallet add-external s", 21, &names()).1.len(), 0);
+    }
+
+    #[test]
+    fn test_complete_command_aliases() {
+        assert_eq!(
+            complete_line("b", 1, &names()),
+            (0, vec!["b".to_string(), "balance".to_string()])
+        );
+        assert_eq!(
+            complete_line("alias remove p", 14, &names()),
+            (13, vec!["pay-rent".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_complete_send_recipients() {
+        assert_eq!(
+            complete_line("send al", 7, &names()),
+            (
+                5,
+                vec!["albert".to_string(), "alice".to_string(), "al3xaddr".to_string()]
+            )
+        );
+        // Amount positions get no candidates
+        assert_eq!(complete_line("send alice a", 12, &names()).1.len(), 0);
+        assert_eq!(
+            complete_line("send alice 5 b", 14, &names()),
+            (13, vec!["bob9addr".to_string()])
+        );
+    }
+}
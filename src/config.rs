@@ -0,0 +1,586 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{Read, Write},
+};
+
+use anyhow::Error;
+
+use crate::{
+    amount::{Grouping, Unit, parse_amount},
+    explorer::{self, ADDRESS_PLACEHOLDER, TX_PLACEHOLDER},
+    i18n::Lang,
+    notify::NotifyMode,
+    webhook::{self, Endpoint},
+};
+
+/// How many times an alias may expand into another alias
+const MAX_ALIAS_DEPTH: usize = 16;
+
+/// Where the effective value of a setting came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Default,
+    File,
+    Environment,
+    Flag,
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Default => "default",
+            Source::File => "file",
+            Source::Environment => "environment",
+            Source::Flag => "flag",
+        }
+    }
+}
+
+/// How often the donation line is shown at startup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DonationPrompt {
+    Always,
+    /// Once in `WEEK` seconds
+    Weekly,
+    Off,
+}
+
+impl DonationPrompt {
+    pub const NAMES: &[&str] = &["on", "weekly", "off"];
+    const WEEK: u64 = 7 * 86_400;
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "on" => Some(DonationPrompt::Always),
+            "weekly" => Some(DonationPrompt::Weekly),
+            "off" => Some(DonationPrompt::Off),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+
+    /// Whether the line is due at `now`, when it was last shown at `last_shown`
+    pub fn due(&self, last_shown: Option<u64>, now: u64) -> bool {
+        match self {
+            DonationPrompt::Always => true,
+            DonationPrompt::Weekly => {
+                last_shown.is_none_or(|shown| now.saturating_sub(shown) >= Self::WEEK)
+            }
+            DonationPrompt::Off => false,
+        }
+    }
+}
+
+/// A setting with its effective value, as listed by `config show`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+    pub source: Source,
+}
+
+impl Setting {
+    pub fn new(name: &str, value: impl ToString, source: Source) -> Self {
+        Setting {
+            name: name.to_string(),
+            value: value.to_string(),
+            source,
+        }
+    }
+}
+
+/// User settings, stored unencrypted as `key = value` lines. Never holds secrets
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Command aliases, `alias.<name> = <command>`
+    pub aliases: BTreeMap<String, String>,
+    /// Show the wallet balance in the prompt, `prompt_balance = on|off`
+    pub prompt_balance: bool,
+    /// Ring the terminal bell when a send completes or fails, `bell = on|off`
+    pub bell: bool,
+    /// Silence every audible cue, whatever other settings say, `quiet = on|off`
+    pub quiet: bool,
+    /// Seconds a PIN prompt waits for input, 0 for no limit, `pin_timeout = <seconds>`
+    pub pin_timeout: u64,
+    /// Age in days after which `prune` removes transaction notes, 0 keeps them,
+    /// `note_max_age_days = <days>`
+    pub note_max_age_days: u64,
+    /// Log in to the last used wallet without showing the wallet menu, the PIN is still
+    /// asked for, `auto_login_default = on|off`
+    pub auto_login_default: bool,
+    /// Digit grouping in displayed amounts, `thousands_separator = comma|period|space|...`
+    pub thousands_separator: Grouping,
+    /// Unit amounts are displayed in, `unit = snap|nano`
+    pub unit: Unit,
+    /// Show output too long for the terminal through `$PAGER`, `pager = on|off`
+    pub pager: bool,
+    /// Offer to run the command a one-letter typo was meant to be, `autocorrect = on|off`
+    pub autocorrect: bool,
+    /// Commands kept in the history file, oldest dropped first, `history_size = <entries>`
+    pub history_size: usize,
+    /// Wallets one wallet file may hold, guarding against imports run away,
+    /// `max_wallets = <count>`
+    pub max_wallets: usize,
+    /// Wallet file backups `compact` keeps, the oldest beyond it are removed,
+    /// `backup_retention = <count>`
+    pub backup_retention: usize,
+    /// Write changes to the wallet file as they are made, otherwise on `save` or exit,
+    /// `autosave = on|off`
+    pub autosave: bool,
+    /// Seal the balances `snapshot` records with a key of the wallet, `encrypt_snapshots = on|off`
+    pub encrypt_snapshots: bool,
+    /// Write the troubleshooting log, `log_file = on|off`
+    pub log_file: bool,
+    /// Language of messages unless `--lang` is given, `None` to follow the environment,
+    /// `lang = auto|en|es`
+    pub lang: Option<Lang>,
+    /// How incoming payments are announced, `notify = off|bell|desktop|both`
+    pub notify: NotifyMode,
+    /// How often the donation line is shown, `donation_prompt = on|weekly|off`
+    pub donation_prompt: DonationPrompt,
+    /// Notify when the balance drops below this many nano, `low_balance = off|<amount>`
+    pub low_balance: Option<u64>,
+    /// Where confirmed sends are posted, `webhook_url = off|<url>`
+    pub webhook_url: Option<String>,
+    /// Header the webhook secret is sent in, the secret itself comes from the environment,
+    /// `webhook_secret_header = off|<name>`
+    pub webhook_secret_header: Option<String>,
+    /// Block explorer page of a transaction, `explorer_tx_url = off|<url with {txid}>`
+    pub explorer_tx_url: Option<String>,
+    /// Block explorer page of an address, `explorer_address_url = off|<url with {addr}>`
+    pub explorer_address_url: Option<String>,
+    /// File holding the `serve` token, which the config file itself never holds,
+    /// `rpc_token_file = off|<path>`
+    pub rpc_token_file: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            aliases: BTreeMap::new(),
+            prompt_balance: true,
+            bell: false,
+            quiet: false,
+            pin_timeout: 60,
+            note_max_age_days: 0,
+            auto_login_default: false,
+            thousands_separator: Grouping::Comma,
+            unit: Unit::Snap,
+            pager: true,
+            autocorrect: false,
+            autosave: true,
+            encrypt_snapshots: false,
+            log_file: false,
+            lang: None,
+            history_size: 1000,
+            max_wallets: 1000,
+            backup_retention: 5,
+            notify: NotifyMode::Off,
+            donation_prompt: DonationPrompt::Always,
+            low_balance: None,
+            webhook_url: None,
+            webhook_secret_header: None,
+            explorer_tx_url: None,
+            explorer_address_url: None,
+            rpc_token_file: None,
+        }
+    }
+}
+
+/// Parse an `on`/`off` setting value
+pub fn parse_switch(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" => Some(true),
+        "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn switch(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+impl Config {
+    /// Parse a config file. Blank lines and `#` comments are skipped, unknown keys are ignored
+    pub fn parse(text: &str) -> Self {
+        Self::parse_with_keys(text).0
+    }
+
+    /// Parse a config file, also returning the keys it set to a valid value
+    pub fn parse_with_keys(text: &str) -> (Self, HashSet<String>) {
+        let mut config = Config::default();
+        let mut keys = HashSet::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            if config.set_key(key, value) {
+                keys.insert(key.to_string());
+            }
+        }
+        (config, keys)
+    }
+
+    /// Apply one `key = value` line, returning whether the key is known and the value valid
+    fn set_key(&mut self, key: &str, value: &str) -> bool {
+        if let Some(name) = key.strip_prefix("alias.") {
+            self.aliases.insert(name.to_string(), value.to_string());
+            return true;
+        }
+        match key {
+            "thousands_separator" => Grouping::parse(value)
+                .map(|grouping| self.thousands_separator = grouping)
+                .is_some(),
+            "unit" => Unit::parse(value).map(|unit| self.unit = unit).is_some(),
+            "lang" if value.eq_ignore_ascii_case("auto") => {
+                self.lang = None;
+                true
+            }
+            "lang" => Lang::from_code(value)
+                .map(|lang| self.lang = Some(lang))
+                .is_some(),
+            "low_balance" if value.eq_ignore_ascii_case("off") => {
+                self.low_balance = None;
+                true
+            }
+            "low_balance" => parse_amount(value)
+                .ok()
+                .map(|threshold| self.low_balance = Some(threshold).filter(|t| *t > 0))
+                .is_some(),
+            "donation_prompt" => DonationPrompt::parse(value)
+                .map(|prompt| self.donation_prompt = prompt)
+                .is_some(),
+            "notify" => NotifyMode::parse(value)
+                .map(|mode| self.notify = mode)
+                .is_some(),
+            "webhook_url" | "webhook_secret_header" if value.eq_ignore_ascii_case("off") => {
+                match key {
+                    "webhook_url" => self.webhook_url = None,
+                    _ => self.webhook_secret_header = None,
+                }
+                true
+            }
+            "webhook_url" if webhook::is_valid_url(value) => {
+                self.webhook_url = Some(value.to_string());
+                true
+            }
+            // A header name is a single token, so the line can't smuggle in another header
+            "webhook_secret_header"
+                if !value.is_empty() && value.bytes().all(|b| b.is_ascii_graphic() && b != b':') =>
+            {
+                self.webhook_secret_header = Some(value.to_string());
+                true
+            }
+            "explorer_tx_url" | "explorer_address_url" if value.eq_ignore_ascii_case("off") => {
+                match key {
+                    "explorer_tx_url" => self.explorer_tx_url = None,
+                    _ => self.explorer_address_url = None,
+                }
+                true
+            }
+            "explorer_tx_url" if explorer::is_valid_template(value, TX_PLACEHOLDER) => {
+                self.explorer_tx_url = Some(value.to_string());
+                true
+            }
+            "explorer_address_url" if explorer::is_valid_template(value, ADDRESS_PLACEHOLDER) => {
+                self.explorer_address_url = Some(value.to_string());
+                true
+            }
+            "rpc_token_file" if value.eq_ignore_ascii_case("off") => {
+                self.rpc_token_file = None;
+                true
+            }
+            "rpc_token_file" => {
+                self.rpc_token_file = Some(value.to_string());
+                true
+            }
+            "pin_timeout" => value.parse().map(|value| self.pin_timeout = value).is_ok(),
+            "history_size" => value.parse().map(|value| self.history_size = value).is_ok(),
+            // At least one, or no wallet could ever be created
+            "max_wallets" => value
+                .parse()
+                .ok()
+                .filter(|max| *max > 0)
+                .map(|max| self.max_wallets = max)
+                .is_some(),
+            // At least one, so the backup `compact` makes first is never removed with the rest
+            "backup_retention" => value
+                .parse()
+                .ok()
+                .filter(|keep| *keep > 0)
+                .map(|keep| self.backup_retention = keep)
+                .is_some(),
+            "note_max_age_days" => value
+                .parse()
+                .map(|value| self.note_max_age_days = value)
+                .is_ok(),
+            _ => {
+                let field = match key {
+                    "prompt_balance" => &mut self.prompt_balance,
+                    "bell" => &mut self.bell,
+                    "quiet" => &mut self.quiet,
+                    "auto_login_default" => &mut self.auto_login_default,
+                    "pager" => &mut self.pager,
+                    "autocorrect" => &mut self.autocorrect,
+                    "autosave" => &mut self.autosave,
+                    "encrypt_snapshots" => &mut self.encrypt_snapshots,
+                    "log_file" => &mut self.log_file,
+                    _ => return false,
+                };
+                parse_switch(value).map(|value| *field = value).is_some()
+            }
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!("prompt_balance = {}\n", switch(self.prompt_balance));
+        out.push_str(&format!("bell = {}\n", switch(self.bell)));
+        out.push_str(&format!("quiet = {}\n", switch(self.quiet)));
+        out.push_str(&format!("pin_timeout = {}\n", self.pin_timeout));
+        out.push_str(&format!("note_max_age_days = {}\n", self.note_max_age_days));
+        out.push_str(&format!(
+            "auto_login_default = {}\n",
+            switch(self.auto_login_default)
+        ));
+        out.push_str(&format!(
+            "thousands_separator = {}\n",
+            self.thousands_separator.name()
+        ));
+        out.push_str(&format!("unit = {}\n", self.unit.name()));
+        out.push_str(&format!("pager = {}\n", switch(self.pager)));
+        out.push_str(&format!("autocorrect = {}\n", switch(self.autocorrect)));
+        out.push_str(&format!("autosave = {}\n", switch(self.autosave)));
+        out.push_str(&format!(
+            "encrypt_snapshots = {}\n",
+            switch(self.encrypt_snapshots)
+        ));
+        out.push_str(&format!("log_file = {}\n", switch(self.log_file)));
+        let lang = self.lang.map_or("auto", |lang| lang.code());
+        out.push_str(&format!("lang = {}\n", lang));
+        out.push_str(&format!("history_size = {}\n", self.history_size));
+        out.push_str(&format!("max_wallets = {}\n", self.max_wallets));
+        out.push_str(&format!("backup_retention = {}\n", self.backup_retention));
+        out.push_str(&format!("notify = {}\n", self.notify.name()));
+        out.push_str(&format!(
+            "donation_prompt = {}\n",
+            self.donation_prompt.name()
+        ));
+        // In nano, so the value survives any unit and grouping setting exactly
+        match self.low_balance {
+            Some(threshold) => out.push_str(&format!("low_balance = {}nano\n", threshold)),
+            None => out.push_str("low_balance = off\n"),
+        }
+        let off = |value: &Option<String>| value.clone().unwrap_or_else(|| "off".to_string());
+        out.push_str(&format!("webhook_url = {}\n", off(&self.webhook_url)));
+        out.push_str(&format!(
+            "webhook_secret_header = {}\n",
+            off(&self.webhook_secret_header)
+        ));
+        out.push_str(&format!("explorer_tx_url = {}\n", off(&self.explorer_tx_url)));
+        out.push_str(&format!(
+            "explorer_address_url = {}\n",
+            off(&self.explorer_address_url)
+        ));
+        out.push_str(&format!("rpc_token_file = {}\n", off(&self.rpc_token_file)));
+        for (name, command) in &self.aliases {
+            out.push_str(&format!("alias.{} = {}\n", name, command));
+        }
+        out
+    }
+
+    /// Load the config file, or the defaults if there is none
+    pub fn load() -> Result<Self, Error> {
+        Ok(Self::load_with_keys()?.0)
+    }
+
+    /// Load the config file, also returning the keys it sets
+    pub fn load_with_keys() -> Result<(Self, HashSet<String>), Error> {
+        let path = crate::config_path()?;
+        if !path.exists() {
+            return Ok((Config::default(), HashSet::new()));
+        }
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        Ok(Config::parse_with_keys(&text))
+    }
+
+    /// Every setting with its effective value, marked as coming from the file when `file_keys`
+    /// holds it. Listed in file order, from `serialize`, so no setting can be left out
+    pub fn settings(&self, file_keys: &HashSet<String>) -> Vec<Setting> {
+        self.serialize()
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(key, value)| {
+                let source = if file_keys.contains(key) {
+                    Source::File
+                } else {
+                    Source::Default
+                };
+                Setting::new(key, value, source)
+            })
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let path = crate::config_path()?;
+        let mut file = File::create(path)?;
+        file.write_all(self.serialize().as_bytes())?;
+        Ok(())
+    }
+
+    /// Where to post confirmed sends, if anywhere
+    pub fn webhook(&self) -> Option<Endpoint> {
+        self.webhook_url.as_ref().map(|url| Endpoint {
+            url: url.clone(),
+            secret_header: self.webhook_secret_header.clone(),
+        })
+    }
+
+    /// Block explorer link of a transaction, if a template is set
+    pub fn tx_link(&self, tx_id: &str) -> Option<String> {
+        let template = self.explorer_tx_url.as_ref()?;
+        Some(explorer::link(template, TX_PLACEHOLDER, tx_id))
+    }
+
+    /// Block explorer link of an address, if a template is set
+    pub fn address_link(&self, address: &str) -> Option<String> {
+        let template = self.explorer_address_url.as_ref()?;
+        Some(explorer::link(template, ADDRESS_PLACEHOLDER, address))
+    }
+
+    /// Whether the bell should ring, `quiet` wins over `bell`
+    pub fn bell_enabled(&self) -> bool {
+        self.bell && !self.quiet
+    }
+
+    /// Expand a leading alias, repeatedly, keeping any arguments typed after it
+    pub fn expand_alias(&self, command: &str) -> Result<String, String> {
+        let mut command = command.trim().to_string();
+        let mut seen = HashSet::new();
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let (name, rest) = match command.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, rest.trim()),
+                None => (command.as_str(), ""),
+            };
+            let Some(expansion) = self.aliases.get(name) else {
+                return Ok(command);
+            };
+            if !seen.insert(name.to_string()) {
+                return Err(format!("Alias '{}' expands into itself", name));
+            }
+            command = if rest.is_empty() {
+                expansion.clone()
+            } else {
+                format!("{} {}", expansion, rest)
+            };
+        }
+        Err(format!("Aliases nest deeper than {} levels", MAX_ALIAS_DEPTH))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(aliases: &[(&str, &str)]) -> Config {
+        Config {
+            aliases: aliases
+                .iter()
+                .map(|(name, command)| (name.to_string(), command.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let mut original = config(&[("b", "balance"), ("pay-rent", "send abc 12")]);
+        original.prompt_balance = false;
+        original.bell = true;
+        original.pin_timeout = 0;
+        original.note_max_age_days = 30;
+        original.auto_login_default = true;
+        original.thousands_separator = Grouping::Period;
+        original.unit = Unit::Nano;
+        original.pager = false;
+        original.autocorrect = true;
+        original.autosave = false;
+        original.encrypt_snapshots = true;
+        original.log_file = true;
+        original.lang = Some(Lang::Es);
+        original.history_size = 50;
+        original.max_wallets = 5;
+        original.backup_retention = 2;
+        original.notify = NotifyMode::Both;
+        original.donation_prompt = DonationPrompt::Weekly;
+        original.low_balance = Some(123_456_789);
+        original.webhook_url = Some("https://example.com/hook?a=1".to_string());
+        original.webhook_secret_header = Some("X-Webhook-Secret".to_string());
+        original.explorer_tx_url = Some("https://explorer.example/tx/{txid}".to_string());
+        original.explorer_address_url = Some("https://explorer.example/a/{addr}".to_string());
+        original.rpc_token_file = Some("/home/user/.snap-rpc-token".to_string());
+        assert_eq!(Config::parse(&original.serialize()), original);
+        assert_eq!(
+            Config::parse("# comment\n\nnonsense\nalias.b=balance\nunknown = 1\n"),
+            config(&[("b", "balance")])
+        );
+    }
+
+    #[test]
+    fn test_settings_sources() {
+        let text = "bell = on\nunit = bogus\npin_timeout = 30\nalias.b = balance\n";
+        let (config, keys) = Config::parse_with_keys(text);
+        let settings = config.settings(&keys);
+        let source = |name: &str| {
+            settings
+                .iter()
+                .find(|setting| setting.name == name)
+                .map(|setting| (setting.value.as_str(), setting.source))
+        };
+        assert_eq!(source("bell"), Some(("on", Source::File)));
+        assert_eq!(source("pin_timeout"), Some(("30", Source::File)));
+        // Invalid values are ignored, so the default stays in effect
+        assert_eq!(source("unit"), Some(("snap", Source::Default)));
+        assert_eq!(source("quiet"), Some(("off", Source::Default)));
+        assert!(!Config::parse_with_keys("max_wallets = 0").1.contains("max_wallets"));
+        let header = "webhook_secret_header = X-A: b\nwebhook_url = example.com";
+        assert_eq!(Config::parse_with_keys(header).1, HashSet::new());
+        // A template without its placeholder would link every value to the same page
+        let explorer = "explorer_tx_url = https://explorer.example/tx/";
+        assert!(Config::parse_with_keys(explorer).1.is_empty());
+        assert_eq!(source("alias.b"), Some(("balance", Source::File)));
+    }
+
+    #[test]
+    fn test_donation_prompt_due() {
+        let now = 1_000_000;
+        assert!(DonationPrompt::Always.due(Some(now), now));
+        assert!(!DonationPrompt::Off.due(None, now));
+        assert!(DonationPrompt::Weekly.due(None, now));
+        assert!(!DonationPrompt::Weekly.due(Some(now - 86_400), now));
+        assert!(DonationPrompt::Weekly.due(Some(now - 7 * 86_400), now));
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let config = config(&[("b", "balance"), ("rent", "send abc 12"), ("r", "rent")]);
+        assert_eq!(config.expand_alias("b"), Ok("balance".to_string()));
+        assert_eq!(config.expand_alias("r def 3"), Ok("send abc 12 def 3".to_string()));
+        assert_eq!(config.expand_alias("history"), Ok("history".to_string()));
+    }
+
+    #[test]
+    fn test_recursive_alias_rejected() {
+        let config = config(&[("a", "b"), ("b", "a x")]);
+        assert!(config.expand_alias("a").is_err());
+    }
+}
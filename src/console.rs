@@ -0,0 +1,56 @@
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether stdout is kept for machine-readable output, with messages going to stderr.
+/// Set by `--print-address-on-start` until the address is printed, and by `--json` while its
+/// command runs
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+pub fn reserve_stdout(reserved: bool) {
+    STDOUT_RESERVED.store(reserved, Ordering::Relaxed);
+}
+
+pub fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+/// Keeps stdout reserved until dropped, for a single command's `--json`
+pub struct Reserved {
+    previous: bool,
+}
+
+pub fn reserve_until_dropped() -> Reserved {
+    let previous = stdout_reserved();
+    reserve_stdout(true);
+    Reserved { previous }
+}
+
+impl Drop for Reserved {
+    fn drop(&mut self) {
+        reserve_stdout(self.previous);
+    }
+}
+
+/// Where messages and prompts are written: stdout, or stderr while stdout is reserved
+pub fn messages() -> Box<dyn Write> {
+    if stdout_reserved() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// `println!` for messages that must stay off a reserved stdout
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::console::stdout_reserved() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+pub(crate) use say;
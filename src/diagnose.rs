@@ -0,0 +1,310 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use snap_coin::{core::transaction::TransactionInput, crypto::keys::Public};
+
+use crate::{
+    amount, clock, encryption::SessionKeys, history::TxEffect, node::NodeApi, spendable, style,
+};
+
+/// Time the node gets to answer before it counts as unreachable
+const NODE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Answers slower than this are flagged
+const SLOW_ANSWER: Duration = Duration::from_secs(1);
+/// Seconds after which a transaction still in the mempool counts as stuck
+pub const STUCK_AFTER: u64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// Couldn't be checked, such as everything needing the node when it is down
+    Skip,
+}
+
+impl Status {
+    pub fn tag(self) -> String {
+        match self {
+            Status::Pass => style::incoming("[PASS]"),
+            Status::Warn => style::warning("[WARN]"),
+            Status::Fail => style::error("[FAIL]"),
+            Status::Skip => style::dim("[SKIP]"),
+        }
+    }
+}
+
+/// The outcome of one check, with what to do about it when it didn't pass
+#[derive(Debug)]
+pub struct Finding {
+    pub status: Status,
+    pub check: &'static str,
+    pub detail: String,
+    pub hint: Option<&'static str>,
+}
+
+impl Finding {
+    fn new(status: Status, check: &'static str, detail: impl Into<String>) -> Self {
+        Finding {
+            status,
+            check,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: &'static str) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+}
+
+/// Whether the wallet file on disk is the one this session would write: it must decrypt with
+/// the session's keys
+pub fn wallet_file(path: &Path, keys: &SessionKeys) -> Finding {
+    let check = "Wallet file";
+    match fs::read(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Finding::new(
+            Status::Warn,
+            check,
+            format!("{} doesn't exist yet", path.display()),
+        )
+        .hint("Run `save` to write the wallets to disk."),
+        Err(e) => Finding::new(Status::Fail, check, format!("can't read it: {}", e))
+            .hint("Check the permissions of the file and its directory."),
+        Ok(data) if keys.opens(&data) => Finding::new(Status::Pass, check, "decrypts"),
+        Ok(_) => Finding::new(
+            Status::Fail,
+            check,
+            "doesn't decrypt with this session's key",
+        )
+        .hint(
+            "It was rewritten since login, by another wallet process or in the legacy format, or \
+             is damaged. Restore a copy from ~/.snap-coin-backups, or `save` to write this \
+             session's wallets over it.",
+        ),
+    }
+}
+
+/// Transactions of the wallet in the mempool, and how many of them are older than
+/// `STUCK_AFTER` at `now`
+pub fn pending(timestamps: &[u64], now: u64) -> Finding {
+    let check = "Pending transactions";
+    let stuck = timestamps
+        .iter()
+        .filter(|timestamp| now.saturating_sub(**timestamp) > STUCK_AFTER)
+        .count();
+    match (timestamps.len(), stuck) {
+        (0, _) => Finding::new(Status::Pass, check, "none in the mempool"),
+        (count, 0) => Finding::new(
+            Status::Pass,
+            check,
+            format!("{} waiting, none stuck", count),
+        ),
+        (count, stuck) => Finding::new(
+            Status::Warn,
+            check,
+            format!(
+                "{} of {} waiting for over {} minutes",
+                stuck,
+                count,
+                STUCK_AFTER / 60
+            ),
+        )
+        .hint(
+            "They leave the mempool once mined or dropped; `mempool --mine` lists them. If they \
+             never do, the node may not relay to miners, try another node.",
+        ),
+    }
+}
+
+/// Whether the balance and the spendable outputs, two separate queries, agree
+fn balance(balance: u64, available: u64) -> Finding {
+    let check = "Balance";
+    if available > balance {
+        return Finding::new(
+            Status::Fail,
+            check,
+            format!(
+                "the node offers {} in outputs but reports a balance of {}",
+                amount::display(available),
+                amount::display(balance)
+            ),
+        )
+        .hint("The node's indexes disagree, restart it or let it resync.");
+    }
+    if available < balance {
+        return Finding::new(
+            Status::Warn,
+            check,
+            spendable::unavailable_reason(balance - available),
+        )
+        .hint("Nothing to fix if a mining reward is maturing; `available` lists what is usable.");
+    }
+    Finding::new(
+        Status::Pass,
+        check,
+        format!(
+            "{}, all of it in spendable outputs",
+            amount::display(balance)
+        ),
+    )
+}
+
+fn clock(skew: Option<i64>) -> Finding {
+    let check = "Clock";
+    match skew {
+        Some(skew) if clock::is_significant(skew) => Finding::new(
+            Status::Warn,
+            check,
+            format!(
+                "{} seconds {} the network",
+                skew.unsigned_abs(),
+                if skew > 0 { "ahead of" } else { "behind" }
+            ),
+        )
+        .hint("Transaction timestamps are corrected, but turn on network time sync (NTP)."),
+        Some(skew) => Finding::new(Status::Pass, check, format!("in sync ({:+} s)", skew)),
+        None => Finding::new(
+            Status::Skip,
+            check,
+            "too few mempool transactions to compare with",
+        ),
+    }
+}
+
+/// Run every check. Only reads: nothing is sent or saved. Also gives the clock skew when the
+/// mempool allowed estimating it, for the caller to apply like `status` does
+pub async fn run(
+    client: &dyn NodeApi,
+    node: impl Display,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+    wallet_path: &Path,
+    keys: &SessionKeys,
+) -> (Vec<Finding>, Option<i64>) {
+    let mut findings = vec![wallet_file(wallet_path, keys)];
+
+    let started = Instant::now();
+    let mempool = match tokio::time::timeout(NODE_TIMEOUT, client.get_mempool()).await {
+        Ok(Ok(mempool)) => mempool,
+        failed => {
+            let detail = match failed {
+                Ok(Err(e)) => format!("{} is unreachable: {}", node, e),
+                _ => format!("{} didn't answer within {} s", node, NODE_TIMEOUT.as_secs()),
+            };
+            findings.push(Finding::new(Status::Fail, "Node", detail).hint(
+                "Check the node is running and that this is its API address, the wallet's \
+                     first argument (default 127.0.0.1:3003).",
+            ));
+            for check in ["Sync", "Pending transactions", "Clock", "Balance"] {
+                findings.push(Finding::new(Status::Skip, check, "needs the node"));
+            }
+            return (findings, None);
+        }
+    };
+    let elapsed = started.elapsed();
+    let answered = format!("{} answered in {} ms", node, elapsed.as_millis());
+    findings.push(match elapsed > SLOW_ANSWER {
+        true => Finding::new(Status::Warn, "Node", answered)
+            .hint("A slow node makes every command slow, prefer a local node."),
+        false => Finding::new(Status::Pass, "Node", answered),
+    });
+    findings.push(
+        Finding::new(
+            Status::Skip,
+            "Sync",
+            "the node API doesn't report its height or sync state",
+        )
+        .hint("Compare the node's block height with a block explorer or another node."),
+    );
+
+    let mine: Vec<u64> = mempool
+        .iter()
+        .filter(|tx| {
+            let effect = TxEffect::of(tx, public);
+            effect.received > 0 || effect.is_outgoing()
+        })
+        .map(|tx| tx.timestamp)
+        .collect();
+    let timestamps: Vec<u64> = mempool.iter().map(|tx| tx.timestamp).collect();
+    let skew = clock::local_now()
+        .ok()
+        .and_then(|now| clock::estimate_skew(now, &timestamps));
+    match clock::now() {
+        Ok(now) => findings.push(pending(&mine, now)),
+        Err(e) => findings.push(Finding::new(
+            Status::Skip,
+            "Pending transactions",
+            e.to_string(),
+        )),
+    }
+    findings.push(clock(skew));
+
+    match spendable::fetch(client, public, used_session_inputs).await {
+        Ok(breakdown) => findings.push(balance(breakdown.balance, breakdown.available)),
+        Err(e) => findings.push(Finding::new(
+            Status::Fail,
+            "Balance",
+            format!("the node failed to answer: {}", e),
+        )),
+    }
+    (findings, skew)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encryption::{KdfAlgorithm, KdfParams},
+        node::mock::MockNode,
+    };
+    use snap_coin::{crypto::keys::Private, to_nano};
+
+    #[test]
+    fn test_pending() {
+        let now = 10_000;
+        assert_eq!(pending(&[], now).status, Status::Pass);
+        assert_eq!(pending(&[now - 60, now], now).status, Status::Pass);
+        let stuck = pending(&[now - 60, now - STUCK_AFTER - 1], now);
+        assert_eq!(stuck.status, Status::Warn);
+        assert!(stuck.detail.starts_with("1 of 2"));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let owner = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(owner, to_nano(2.0));
+        node.locked.push((owner, to_nano(1.0)));
+        let params = KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let keys = SessionKeys::derive("123456", params).unwrap();
+        let missing = std::env::temp_dir().join("snap-diagnose-missing");
+
+        let (findings, skew) = run(&node, "mock", owner, &[], &missing, &keys).await;
+        let status = |check: &str| {
+            findings
+                .iter()
+                .find(|finding| finding.check == check)
+                .map(|finding| finding.status)
+        };
+        assert_eq!(status("Wallet file"), Some(Status::Warn));
+        assert_eq!(status("Node"), Some(Status::Pass));
+        assert_eq!(status("Pending transactions"), Some(Status::Pass));
+        // An empty mempool can't tell the time
+        assert_eq!((status("Clock"), skew), (Some(Status::Skip), None));
+        // The maturing reward is in the balance but no output
+        assert_eq!(status("Balance"), Some(Status::Warn));
+        assert_eq!(balance(5, 5).status, Status::Pass);
+        assert_eq!(balance(5, 6).status, Status::Fail);
+    }
+}
@@ -1,82 +1,747 @@
-use aes_gcm::aead::{Aead, KeyInit, OsRng, rand_core::RngCore};
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload, rand_core::RngCore};
 use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
 use snap_coin::crypto::Hash;
-use snap_coin::crypto::keys::Private;
-use std::collections::HashMap;
+use snap_coin::crypto::keys::{Private, Public};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
 
-/// Compute hash of a PIN (used as encryption key)
-fn compute_pin_hash(pin: &str) -> [u8; 32] {
-    Hash::new(format!("snap-coin-wallet-{}", pin).as_bytes()).dump_buf()
+use crate::{
+    address_book::{AddressBook, Contact},
+    backup::Backups,
+    notes::{TxNote, TxNotes},
+    signing::{ExternalSigner, WalletKey, Wallets},
+};
+
+/// Magic bytes at the start of every headered wallet file
+const MAGIC: &[u8; 4] = b"SNPW";
+/// Current wallet file format version
+/// 1: every entry is a local private key
+/// 2: entries carry a kind byte, allowing external signer wallets
+/// 3: address book contacts are stored as entries
+/// 4: contacts carry a note
+/// 5: transaction notes are stored as entries
+/// 6: confirmed key backups are stored as entries
+const FORMAT_VERSION: u8 = 6;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// magic | version | kdf id | memory | iterations | parallelism | salt
+const HEADER_LEN: usize = 4 + 1 + 1 + 4 + 4 + 4 + SALT_LEN;
+
+/// Loading a file with KDF parameters above these requires confirmation
+pub const MAX_SANE_MEMORY_KIB: u32 = 1024 * 1024; // 1 GiB
+pub const MAX_SANE_ITERATIONS: u32 = 64;
+pub const MAX_SANE_PARALLELISM: u32 = 16;
+
+/// Key derivation algorithm used to turn a PIN into an encryption key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+    /// Single salted hash, used by wallet files written before the header existed
+    Legacy = 0,
+    Argon2id = 1,
+}
+
+impl KdfAlgorithm {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(KdfAlgorithm::Legacy),
+            1 => Some(KdfAlgorithm::Argon2id),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KdfAlgorithm::Legacy => "legacy",
+            KdfAlgorithm::Argon2id => "argon2id",
+        }
+    }
+}
+
+/// KDF parameters, stored in the wallet file header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub algorithm: KdfAlgorithm,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Check that these parameters are accepted by the KDF
+    pub fn validate(&self) -> Result<(), String> {
+        match self.algorithm {
+            KdfAlgorithm::Legacy => Ok(()),
+            KdfAlgorithm::Argon2id => {
+                Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Whether these parameters exceed the sanity ceiling (possible DoS via a malicious file)
+    pub fn exceeds_sanity_ceiling(&self) -> bool {
+        self.memory_kib > MAX_SANE_MEMORY_KIB
+            || self.iterations > MAX_SANE_ITERATIONS
+            || self.parallelism > MAX_SANE_PARALLELISM
+    }
+
+    fn encode(&self, salt: &[u8; SALT_LEN]) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(FORMAT_VERSION);
+        header.push(self.algorithm as u8);
+        header.extend_from_slice(&self.memory_kib.to_le_bytes());
+        header.extend_from_slice(&self.iterations.to_le_bytes());
+        header.extend_from_slice(&self.parallelism.to_le_bytes());
+        header.extend_from_slice(salt);
+        header
+    }
+
+    /// Returns the parameters, salt and format version of a header
+    fn decode(header: &[u8]) -> Option<(Self, [u8; SALT_LEN], u8)> {
+        if header.len() < HEADER_LEN || &header[..4] != MAGIC || !(1..=FORMAT_VERSION).contains(&header[4]) {
+            return None;
+        }
+        let read_u32 = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let params = KdfParams {
+            algorithm: KdfAlgorithm::from_id(header[5])?,
+            memory_kib: read_u32(6),
+            iterations: read_u32(10),
+            parallelism: read_u32(14),
+        };
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&header[18..HEADER_LEN]);
+        Some((params, salt, header[4]))
+    }
+}
+
+/// Parse a memory size like `64MiB`, `1GiB` or `65536KiB` into KiB. A bare number is MiB
+pub fn parse_memory_size(s: &str) -> Option<u32> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u32 = number.parse().ok()?;
+    let multiplier = match unit.to_ascii_lowercase().as_str() {
+        "kib" | "k" => 1,
+        "" | "mib" | "m" => 1024,
+        "gib" | "g" => 1024 * 1024,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+/// Compute hash of a PIN (used as encryption key by legacy wallet files)
+fn compute_pin_hash(pin: &str) -> Zeroizing<[u8; 32]> {
+    Zeroizing::new(Hash::new(format!("snap-coin-wallet-{}", pin).as_bytes()).dump_buf())
+}
+
+/// Derive the Argon2id encryption key of a PIN with the given KDF parameters
+fn derive_key(pin: &str, params: &KdfParams, salt: &[u8]) -> Option<Zeroizing<[u8; 32]>> {
+    let argon_params =
+        Params::new(params.memory_kib, params.iterations, params.parallelism, Some(32)).ok()?;
+    let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params);
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon.hash_password_into(pin.as_bytes(), salt, &mut *key).ok()?;
+    Some(key)
+}
+
+/// Compute the verification hash of an encryption key
+fn compute_verifier(key: &[u8; 32]) -> [u8; 32] {
+    let mut buf = b"snap-coin-wallet-verifier-".to_vec();
+    buf.extend_from_slice(key);
+    let verifier = Hash::new(&buf).dump_buf();
+    buf.zeroize();
+    verifier
+}
+
+/// What an unlocked wallet file holds, with the session keys to save it again
+pub type Unlocked = (Wallets, AddressBook, TxNotes, Backups, SessionKeys);
+
+/// Key material held for the duration of a session, in place of the PIN
+/// The encryption key is needed for saves, the verifier for PIN confirmation prompts
+pub struct SessionKeys {
+    params: KdfParams,
+    salt: [u8; SALT_LEN],
+    key: Zeroizing<[u8; 32]>,
+    verifier: [u8; 32],
+}
+
+impl SessionKeys {
+    /// Derive session keys from a PIN with a fresh salt. The PIN is dropped before returning
+    pub fn derive(pin: impl AsRef<str>, params: KdfParams) -> Option<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive_with_salt(pin.as_ref(), params, salt)
+    }
+
+    fn derive_with_salt(pin: &str, params: KdfParams, salt: [u8; SALT_LEN]) -> Option<Self> {
+        // Session keys are never legacy, files get upgraded on their next save
+        let params = if params.algorithm == KdfAlgorithm::Legacy {
+            KdfParams::default()
+        } else {
+            params
+        };
+        let key = derive_key(pin, &params, &salt)?;
+        let verifier = compute_verifier(&key);
+        Some(SessionKeys {
+            params,
+            salt,
+            key,
+            verifier,
+        })
+    }
+
+    /// Check a freshly entered PIN against this session, in constant time
+    pub fn verify(&self, pin: impl AsRef<str>) -> bool {
+        match derive_key(pin.as_ref(), &self.params, &self.salt) {
+            Some(key) => compute_verifier(&key).ct_eq(&self.verifier).into(),
+            None => false,
+        }
+    }
+
+    /// Wipe the key and the verifier, for a suspended session. No PIN verifies afterwards,
+    /// and nothing may be saved with the keys until they are replaced
+    pub fn forget(&mut self) {
+        self.key.zeroize();
+        self.verifier.zeroize();
+    }
+
+    /// KDF parameters the wallet file is saved with
+    pub fn kdf_params(&self) -> &KdfParams {
+        &self.params
+    }
+
+    /// Whether these keys decrypt a wallet file without the PIN: it must have been saved with
+    /// them, same KDF settings and salt. Legacy files have no salt, so they never are
+    pub fn opens(&self, data: &[u8]) -> bool {
+        let Some((params, salt, _)) = KdfParams::decode(data) else {
+            return false;
+        };
+        let body = &data[HEADER_LEN..];
+        if params != self.params || salt != self.salt || body.len() < NONCE_LEN {
+            return false;
+        }
+        let Ok(cipher) = Aes256Gcm::new_from_slice(&*self.key) else {
+            return false;
+        };
+        let payload = Payload {
+            msg: &body[NONCE_LEN..],
+            aad: &data[..HEADER_LEN],
+        };
+        cipher.decrypt(Nonce::from_slice(&body[..NONCE_LEN]), payload).is_ok()
+    }
+}
+
+/// Read the KDF parameters of an encrypted wallet file without decrypting it
+/// Files without a header are reported as using the legacy KDF
+pub fn read_kdf_params(data: &[u8]) -> Option<KdfParams> {
+    if data.starts_with(MAGIC) {
+        return KdfParams::decode(data).map(|(params, _, _)| params);
+    }
+    Some(KdfParams {
+        algorithm: KdfAlgorithm::Legacy,
+        memory_kib: 0,
+        iterations: 0,
+        parallelism: 0,
+    })
+}
+
+/// Unencrypted facts about a wallet file, readable without the PIN
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileHeader {
+    /// Written before the header existed: nonce | ciphertext, keyed by a PIN hash without salt
+    Legacy,
+    Versioned {
+        version: u8,
+        params: KdfParams,
+        /// The salt bytes are not all zero
+        salt: bool,
+    },
+    /// Starts like a headered file, but the header can't be read, e.g. a version newer than
+    /// this wallet understands or a truncated file
+    Unreadable { version: Option<u8> },
+}
+
+/// Describe the header of a wallet file. Never touches the ciphertext
+pub fn read_file_header(data: &[u8]) -> FileHeader {
+    if !data.starts_with(MAGIC) {
+        return FileHeader::Legacy;
+    }
+    match KdfParams::decode(data) {
+        Some((params, salt, version)) => FileHeader::Versioned {
+            version,
+            params,
+            salt: salt.iter().any(|byte| *byte != 0),
+        },
+        None => FileHeader::Unreadable {
+            version: data.get(MAGIC.len()).copied(),
+        },
+    }
+}
+
+/// The newest wallet file format version this wallet reads and writes
+pub fn format_version() -> u8 {
+    FORMAT_VERSION
 }
 
-/// Encrypt multiple wallets using a PIN
-/// Serialized as: [name_len(u8)|name|private_key(32 bytes)] repeated
-pub fn encrypt_wallets(wallets: &HashMap<String, Private>, pin: &str) -> Option<Vec<u8>> {
-    let mut serialized = Vec::new();
+/// Entry kinds of format version 2 and later
+const ENTRY_LOCAL: u8 = 0;
+const ENTRY_EXTERNAL: u8 = 1;
+/// Format version 3 and later
+const ENTRY_CONTACT: u8 = 2;
+/// Format version 5 and later
+const ENTRY_TX_NOTE: u8 = 3;
+/// Format version 6 and later
+const ENTRY_BACKUP: u8 = 4;
+
+/// Encrypt multiple wallets, the address book, transaction notes and confirmed backups using
+/// the session keys
+/// Serialized as: [kind(u8)|name_len(u8)|name|entry] repeated, where entry is
+/// local: private_key(32 bytes), external: public_key(32 bytes)|command_len(u16)|command,
+/// contact: public_key(32 bytes)|note_len(u16)|note,
+/// transaction note (named by transaction id): created(u64)|text_len(u16)|text,
+/// backup (named by wallet): confirmed(u64)
+/// File layout: header | nonce | ciphertext, with the header authenticated as associated data
+pub fn encrypt_wallets(
+    wallets: &Wallets,
+    book: &AddressBook,
+    notes: &TxNotes,
+    backups: &Backups,
+    keys: &SessionKeys,
+) -> Option<Vec<u8>> {
+    let mut serialized = Zeroizing::new(Vec::new());
     for (name, key) in wallets {
         let name_bytes = name.as_bytes();
-        if name_bytes.len() > 255 { return None; }
+        if name_bytes.len() > 255 {
+            return None;
+        }
+        match key {
+            WalletKey::Local(private) => {
+                serialized.push(ENTRY_LOCAL);
+                serialized.push(name_bytes.len() as u8);
+                serialized.extend_from_slice(name_bytes);
+                serialized.extend_from_slice(private.dump_buf());
+            }
+            WalletKey::External(external) => {
+                let command = external.command.as_bytes();
+                let command_len = u16::try_from(command.len()).ok()?;
+                serialized.push(ENTRY_EXTERNAL);
+                serialized.push(name_bytes.len() as u8);
+                serialized.extend_from_slice(name_bytes);
+                serialized.extend_from_slice(external.public.dump_buf());
+                serialized.extend_from_slice(&command_len.to_le_bytes());
+                serialized.extend_from_slice(command);
+            }
+        }
+    }
+    for (alias, contact) in book {
+        let alias_bytes = alias.as_bytes();
+        if alias_bytes.len() > 255 {
+            return None;
+        }
+        let note = contact.note.as_bytes();
+        let note_len = u16::try_from(note.len()).ok()?;
+        serialized.push(ENTRY_CONTACT);
+        serialized.push(alias_bytes.len() as u8);
+        serialized.extend_from_slice(alias_bytes);
+        serialized.extend_from_slice(contact.public.dump_buf());
+        serialized.extend_from_slice(&note_len.to_le_bytes());
+        serialized.extend_from_slice(note);
+    }
+    for (tx_id, note) in notes {
+        let tx_id_bytes = tx_id.as_bytes();
+        if tx_id_bytes.len() > 255 {
+            return None;
+        }
+        let text = note.text.as_bytes();
+        let text_len = u16::try_from(text.len()).ok()?;
+        serialized.push(ENTRY_TX_NOTE);
+        serialized.push(tx_id_bytes.len() as u8);
+        serialized.extend_from_slice(tx_id_bytes);
+        serialized.extend_from_slice(&note.created.to_le_bytes());
+        serialized.extend_from_slice(&text_len.to_le_bytes());
+        serialized.extend_from_slice(text);
+    }
+    for (name, confirmed) in backups {
+        let name_bytes = name.as_bytes();
+        if name_bytes.len() > 255 {
+            return None;
+        }
+        serialized.push(ENTRY_BACKUP);
         serialized.push(name_bytes.len() as u8);
         serialized.extend_from_slice(name_bytes);
-        serialized.extend_from_slice(key.dump_buf());
+        serialized.extend_from_slice(&confirmed.to_le_bytes());
     }
 
-    let cipher = Aes256Gcm::new_from_slice(&compute_pin_hash(pin)).ok()?;
-    let mut nonce_bytes = [0u8; 12];
+    let header = keys.params.encode(&keys.salt);
+
+    let cipher = Aes256Gcm::new_from_slice(&*keys.key).ok()?;
+    let mut nonce_bytes = [0u8; NONCE_LEN];
     OsRng.fill_bytes(&mut nonce_bytes);
     let nonce = Nonce::from_slice(&nonce_bytes);
 
-    let ciphertext = cipher.encrypt(nonce, serialized.as_ref()).ok()?;
-    let mut out = Vec::with_capacity(12 + ciphertext.len());
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: &serialized,
+                aad: &header,
+            },
+        )
+        .ok()?;
+    let mut out = Vec::with_capacity(HEADER_LEN + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&header);
     out.extend_from_slice(&nonce_bytes);
     out.extend_from_slice(&ciphertext);
     Some(out)
 }
 
-/// Decrypt multiple wallets using a PIN
-pub fn decrypt_wallets(data: &[u8], pin: &str) -> Option<HashMap<String, Private>> {
-    if data.len() < 12 { return None; }
-    let cipher = Aes256Gcm::new_from_slice(&compute_pin_hash(pin)).ok()?;
-    let nonce = Nonce::from_slice(&data[..12]);
-    let ciphertext = &data[12..];
-    let decrypted = cipher.decrypt(nonce, ciphertext.as_ref()).ok()?;
+/// Decrypt multiple wallets, the address book, transaction notes and confirmed backups using
+/// a PIN
+/// Returns them with the session keys to save them again
+/// Legacy files yield keys for the default KDF, so they are upgraded on their next save
+pub fn decrypt_wallets(data: &[u8], pin: impl AsRef<str>) -> Option<Unlocked> {
+    let pin = pin.as_ref();
+    let (decrypted, keys, version) = if data.starts_with(MAGIC) {
+        let (params, salt, version) = KdfParams::decode(data)?;
+        let body = &data[HEADER_LEN..];
+        if body.len() < NONCE_LEN || params.algorithm == KdfAlgorithm::Legacy {
+            return None;
+        }
+        let keys = SessionKeys::derive_with_salt(pin, params, salt)?;
+        let cipher = Aes256Gcm::new_from_slice(&*keys.key).ok()?;
+        let decrypted = cipher
+            .decrypt(
+                Nonce::from_slice(&body[..NONCE_LEN]),
+                Payload {
+                    msg: &body[NONCE_LEN..],
+                    aad: &data[..HEADER_LEN],
+                },
+            )
+            .ok()?;
+        (Zeroizing::new(decrypted), keys, version)
+    } else {
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+        let cipher = Aes256Gcm::new_from_slice(&*compute_pin_hash(pin)).ok()?;
+        let nonce = Nonce::from_slice(&data[..NONCE_LEN]);
+        let decrypted = Zeroizing::new(cipher.decrypt(nonce, &data[NONCE_LEN..]).ok()?);
+        (decrypted, SessionKeys::derive(pin, KdfParams::default())?, 1)
+    };
 
-    let mut wallets = HashMap::new();
+    let mut wallets = Wallets::new();
+    let mut book = AddressBook::new();
+    let mut notes = TxNotes::new();
+    let mut backups = Backups::new();
     let mut i = 0;
     while i < decrypted.len() {
-        let name_len = decrypted[i] as usize;
+        let kind = if version >= 2 {
+            i += 1;
+            decrypted[i - 1]
+        } else {
+            ENTRY_LOCAL
+        };
+        let name_len = *decrypted.get(i)? as usize;
         i += 1;
-        if i + name_len + 32 > decrypted.len() { return None; }
-        let name = String::from_utf8_lossy(&decrypted[i..i + name_len]).to_string();
+        let name = String::from_utf8_lossy(decrypted.get(i..i + name_len)?).to_string();
         i += name_len;
+        if kind == ENTRY_TX_NOTE && version >= 5 {
+            let created = u64::from_le_bytes(decrypted.get(i..i + 8)?.try_into().ok()?);
+            i += 8;
+            let text_len = u16::from_le_bytes(decrypted.get(i..i + 2)?.try_into().ok()?) as usize;
+            i += 2;
+            let text = String::from_utf8_lossy(decrypted.get(i..i + text_len)?).to_string();
+            i += text_len;
+            notes.insert(name, TxNote { text, created });
+            continue;
+        }
+        if kind == ENTRY_BACKUP && version >= 6 {
+            let confirmed = u64::from_le_bytes(decrypted.get(i..i + 8)?.try_into().ok()?);
+            i += 8;
+            backups.insert(name, confirmed);
+            continue;
+        }
+        if i + 32 > decrypted.len() {
+            return None;
+        }
         let mut buf = [0u8; 32];
-        buf.copy_from_slice(&decrypted[i..i+32]);
+        buf.copy_from_slice(&decrypted[i..i + 32]);
         i += 32;
-        wallets.insert(name, Private::new_from_buf(&buf));
+        let key = match kind {
+            ENTRY_CONTACT if version >= 3 => {
+                let note = if version >= 4 {
+                    let note_len =
+                        u16::from_le_bytes(decrypted.get(i..i + 2)?.try_into().ok()?) as usize;
+                    i += 2;
+                    let note = decrypted.get(i..i + note_len)?;
+                    i += note_len;
+                    String::from_utf8_lossy(note).to_string()
+                } else {
+                    String::new()
+                };
+                book.insert(
+                    name,
+                    Contact {
+                        public: Public::new_from_buf(&buf),
+                        note,
+                    },
+                );
+                continue;
+            }
+            ENTRY_LOCAL => WalletKey::Local(Private::new_from_buf(&buf)),
+            ENTRY_EXTERNAL => {
+                let command_len =
+                    u16::from_le_bytes(decrypted.get(i..i + 2)?.try_into().ok()?) as usize;
+                i += 2;
+                let command = decrypted.get(i..i + command_len)?;
+                i += command_len;
+                WalletKey::External(ExternalSigner {
+                    public: Public::new_from_buf(&buf),
+                    command: String::from_utf8_lossy(command).to_string(),
+                })
+            }
+            _ => return None,
+        };
+        wallets.insert(name, key);
     }
-    Some(wallets)
+    Some((wallets, book, notes, backups, keys))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use snap_coin::crypto::keys::Private;
-    use std::collections::HashMap;
+
+    /// Cheap parameters so tests don't spend seconds in the KDF
+    fn test_params() -> KdfParams {
+        KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    fn test_wallets() -> Wallets {
+        let mut wallets = Wallets::new();
+        wallets.insert("alice".to_string(), WalletKey::Local(Private::new_random()));
+        wallets.insert("bob".to_string(), WalletKey::Local(Private::new_random()));
+        wallets.insert(
+            "cold".to_string(),
+            WalletKey::External(ExternalSigner {
+                public: Private::new_random().to_public(),
+                command: "my-signer --device /dev/hsm0".to_string(),
+            }),
+        );
+        wallets
+    }
+
+    /// Encrypt wallets with nothing else in the file
+    fn encrypt_only_wallets(wallets: &Wallets, keys: &SessionKeys) -> Option<Vec<u8>> {
+        let (book, notes) = (AddressBook::new(), TxNotes::new());
+        encrypt_wallets(wallets, &book, &notes, &Backups::new(), keys)
+    }
+
+    fn assert_same_wallets(a: &Wallets, b: &Wallets) {
+        assert_eq!(a.len(), b.len());
+        for (name, key) in a {
+            assert!(b.get(name) == Some(key), "wallet '{}' changed", name);
+        }
+    }
 
     #[test]
     fn test_encrypt_decrypt_multi() {
-        let mut wallets = HashMap::new();
-        wallets.insert("alice".to_string(), Private::new_random());
-        wallets.insert("bob".to_string(), Private::new_random());
+        let wallets = test_wallets();
         let pin = "123456";
 
-        let encrypted = encrypt_wallets(&wallets, pin).expect("encryption failed");
-        let decrypted = decrypt_wallets(&encrypted, pin).expect("decryption failed");
+        let keys = SessionKeys::derive(pin, test_params()).unwrap();
+        let encrypted = encrypt_only_wallets(&wallets, &keys).expect("encryption failed");
+        let (decrypted, _, _, _, _) = decrypt_wallets(&encrypted, pin).expect("decryption failed");
+
+        assert_same_wallets(&wallets, &decrypted);
+        // The session's own keys open the file, keys with another salt don't
+        assert!(keys.opens(&encrypted));
+        let other = SessionKeys::derive(pin, test_params()).unwrap();
+        assert!(!other.opens(&encrypted));
+    }
 
-        assert_eq!(wallets.len(), decrypted.len());
-        for (name, key) in wallets {
-            let dec_key = decrypted.get(&name).unwrap();
-            assert_eq!(key.dump_buf(), dec_key.dump_buf());
+    #[test]
+    fn test_address_book_round_trip() {
+        let wallets = test_wallets();
+        let mut book = AddressBook::new();
+        book.insert(
+            "alice".to_string(),
+            Contact {
+                public: Private::new_random().to_public(),
+                note: String::new(),
+            },
+        );
+        book.insert(
+            "landlord".to_string(),
+            Contact {
+                public: Private::new_random().to_public(),
+                note: "rent, due on the 1st".to_string(),
+            },
+        );
+        let mut notes = TxNotes::new();
+        notes.insert(
+            "alice".to_string(),
+            TxNote {
+                text: "march rent".to_string(),
+                created: 1_700_000_000,
+            },
+        );
+        let mut backups = Backups::new();
+        backups.insert("alice".to_string(), 1_700_000_100);
+        let keys = SessionKeys::derive("123456", test_params()).unwrap();
+        let encrypted =
+            encrypt_wallets(&wallets, &book, &notes, &backups, &keys).expect("encryption failed");
+        let (decrypted, decrypted_book, decrypted_notes, decrypted_backups, _) =
+            decrypt_wallets(&encrypted, "123456").expect("decryption failed");
+
+        // A contact, note or backup sharing a wallet's name stays what it is
+        assert_same_wallets(&wallets, &decrypted);
+        assert_eq!(book, decrypted_book);
+        assert_eq!(notes, decrypted_notes);
+        assert_eq!(backups, decrypted_backups);
+    }
+
+    #[test]
+    fn test_kdf_params_round_trip() {
+        let wallets = test_wallets();
+        let pin = "654321";
+        let combinations = [(8, 1, 1), (64, 2, 1), (256, 3, 2), (1024, 1, 4), (2048, 5, 8)];
+
+        for (memory_kib, iterations, parallelism) in combinations {
+            let params = KdfParams {
+                algorithm: KdfAlgorithm::Argon2id,
+                memory_kib,
+                iterations,
+                parallelism,
+            };
+            let keys = SessionKeys::derive(pin, params).unwrap();
+            let encrypted = encrypt_only_wallets(&wallets, &keys).expect("encryption failed");
+            assert_eq!(read_kdf_params(&encrypted), Some(params));
+
+            let (decrypted, _, _, _, keys) =
+                decrypt_wallets(&encrypted, pin).expect("decryption failed");
+            assert_same_wallets(&wallets, &decrypted);
+            assert_eq!(keys.kdf_params(), &params);
+            assert!(decrypt_wallets(&encrypted, "000000").is_none());
+        }
+    }
+
+    #[test]
+    fn test_tampered_header_fails() {
+        let wallets = test_wallets();
+        let keys = SessionKeys::derive("123456", test_params()).unwrap();
+        let mut encrypted = encrypt_only_wallets(&wallets, &keys).unwrap();
+        // Bump the iteration count without re-encrypting
+        encrypted[10] += 1;
+        assert!(decrypt_wallets(&encrypted, "123456").is_none());
+    }
+
+    #[test]
+    fn test_legacy_file_decrypts() {
+        let mut wallets = test_wallets();
+        wallets.retain(|_, key| matches!(key, WalletKey::Local(_)));
+        let pin = "123456";
+
+        let mut serialized = Vec::new();
+        for (name, key) in &wallets {
+            serialized.push(name.len() as u8);
+            serialized.extend_from_slice(name.as_bytes());
+            if let WalletKey::Local(private) = key {
+                serialized.extend_from_slice(private.dump_buf());
+            }
         }
+        let cipher = Aes256Gcm::new_from_slice(&*compute_pin_hash(pin)).unwrap();
+        let nonce_bytes = [7u8; NONCE_LEN];
+        let mut legacy = nonce_bytes.to_vec();
+        legacy.extend(
+            cipher
+                .encrypt(Nonce::from_slice(&nonce_bytes), serialized.as_ref())
+                .unwrap(),
+        );
+
+        assert_eq!(
+            read_kdf_params(&legacy).map(|p| p.algorithm),
+            Some(KdfAlgorithm::Legacy)
+        );
+        let (decrypted, _, _, _, keys) =
+            decrypt_wallets(&legacy, pin).expect("legacy decryption failed");
+        assert_same_wallets(&wallets, &decrypted);
+        assert_eq!(keys.kdf_params(), &KdfParams::default());
+    }
+
+    #[test]
+    fn test_read_file_header() {
+        let keys = SessionKeys::derive("123456", test_params()).unwrap();
+        let mut encrypted = encrypt_only_wallets(&test_wallets(), &keys).unwrap();
+        assert_eq!(
+            read_file_header(&encrypted),
+            FileHeader::Versioned {
+                version: FORMAT_VERSION,
+                params: test_params(),
+                salt: true,
+            }
+        );
+        assert_eq!(read_file_header(&[7u8; 40]), FileHeader::Legacy);
+
+        encrypted[4] = FORMAT_VERSION + 1;
+        assert_eq!(
+            read_file_header(&encrypted),
+            FileHeader::Unreadable {
+                version: Some(FORMAT_VERSION + 1)
+            }
+        );
+        assert_eq!(read_file_header(MAGIC), FileHeader::Unreadable { version: None });
+    }
+
+    #[test]
+    fn test_session_keys_verify() {
+        let keys = SessionKeys::derive("123456", test_params()).unwrap();
+        assert!(keys.verify("123456"));
+        assert!(!keys.verify("123457"));
+        assert!(!keys.verify(""));
+    }
+
+    #[test]
+    fn test_sanity_ceiling() {
+        assert!(!KdfParams::default().exceeds_sanity_ceiling());
+        let params = KdfParams {
+            memory_kib: MAX_SANE_MEMORY_KIB + 1,
+            ..Default::default()
+        };
+        assert!(params.exceeds_sanity_ceiling());
+        let params = KdfParams {
+            iterations: MAX_SANE_ITERATIONS + 1,
+            ..Default::default()
+        };
+        assert!(params.exceeds_sanity_ceiling());
+    }
+
+    #[test]
+    fn test_parse_memory_size() {
+        assert_eq!(parse_memory_size("64MiB"), Some(64 * 1024));
+        assert_eq!(parse_memory_size("1GiB"), Some(1024 * 1024));
+        assert_eq!(parse_memory_size("512KiB"), Some(512));
+        assert_eq!(parse_memory_size("32"), Some(32 * 1024));
+        assert_eq!(parse_memory_size("lots"), None);
+        assert_eq!(parse_memory_size("64TB"), None);
     }
 }
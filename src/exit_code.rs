@@ -0,0 +1,250 @@
+use std::fmt;
+
+use anyhow::Error;
+
+use crate::{
+    i18n::{tr, trf},
+    input::InputError,
+};
+
+/// What the program exits with, so scripts can tell failures apart. Errors that carry no
+/// code exit with `Failure`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitCode {
+    Failure = 1,
+    /// Invalid command line arguments
+    Usage = 2,
+    /// The wallet file doesn't decrypt with the PIN, or the PINs typed don't match
+    WrongPin = 3,
+    NodeUnreachable = 4,
+    InsufficientFunds = 5,
+    /// The node didn't take a transaction into its mempool
+    Rejected = 6,
+    /// Another running wallet holds the wallet file
+    WalletLocked = 7,
+}
+
+impl ExitCode {
+    /// The code an error ends the program with
+    pub fn of(e: &Error) -> ExitCode {
+        ErrorCode::of(e).exit_code()
+    }
+}
+
+/// Why a command or the program failed, as a stable identifier for scripts to branch on
+/// instead of the wording of a message. Shown after the message, and as `code` in the JSON
+/// error object of a `--json` command. The codes never change meaning:
+///
+/// - `E_FAILED`: anything without a more specific code
+/// - `E_USAGE`: invalid command line arguments or node address
+/// - `E_WRONG_PIN`: the wallet file doesn't decrypt, the PINs typed don't match, or a
+///   command was given a wrong PIN
+/// - `E_NODE_UNREACHABLE`: the node can't be reached, or a call to it failed
+/// - `E_INSUFFICIENT_FUNDS`: the wallet can't cover the payments and fee
+/// - `E_REJECTED`: the node didn't take a transaction into its mempool
+/// - `E_WALLET_LOCKED`: another running wallet holds the wallet file
+/// - `E_INVALID_ADDRESS`: a recipient isn't an address or address book name
+/// - `E_INVALID_AMOUNT`: an amount doesn't parse, isn't positive or is out of range
+/// - `E_CANCELLED`: a prompt was backed out of or timed out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    Failed,
+    Usage,
+    WrongPin,
+    NodeUnreachable,
+    InsufficientFunds,
+    Rejected,
+    WalletLocked,
+    InvalidAddress,
+    InvalidAmount,
+    Cancelled,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 10] = [
+        ErrorCode::Failed,
+        ErrorCode::Usage,
+        ErrorCode::WrongPin,
+        ErrorCode::NodeUnreachable,
+        ErrorCode::InsufficientFunds,
+        ErrorCode::Rejected,
+        ErrorCode::WalletLocked,
+        ErrorCode::InvalidAddress,
+        ErrorCode::InvalidAmount,
+        ErrorCode::Cancelled,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Failed => "E_FAILED",
+            ErrorCode::Usage => "E_USAGE",
+            ErrorCode::WrongPin => "E_WRONG_PIN",
+            ErrorCode::NodeUnreachable => "E_NODE_UNREACHABLE",
+            ErrorCode::InsufficientFunds => "E_INSUFFICIENT_FUNDS",
+            ErrorCode::Rejected => "E_REJECTED",
+            ErrorCode::WalletLocked => "E_WALLET_LOCKED",
+            ErrorCode::InvalidAddress => "E_INVALID_ADDRESS",
+            ErrorCode::InvalidAmount => "E_INVALID_AMOUNT",
+            ErrorCode::Cancelled => "E_CANCELLED",
+        }
+    }
+
+    /// What the program exits with when this ends it
+    pub fn exit_code(self) -> ExitCode {
+        match self {
+            ErrorCode::Usage => ExitCode::Usage,
+            ErrorCode::WrongPin => ExitCode::WrongPin,
+            ErrorCode::NodeUnreachable => ExitCode::NodeUnreachable,
+            ErrorCode::InsufficientFunds => ExitCode::InsufficientFunds,
+            ErrorCode::Rejected => ExitCode::Rejected,
+            ErrorCode::WalletLocked => ExitCode::WalletLocked,
+            ErrorCode::Failed
+            | ErrorCode::InvalidAddress
+            | ErrorCode::InvalidAmount
+            | ErrorCode::Cancelled => ExitCode::Failure,
+        }
+    }
+
+    /// The message for an error that has no more specific one
+    pub fn summary(self) -> String {
+        tr(match self {
+            ErrorCode::Failed => "error_code.failed",
+            ErrorCode::Usage => "error_code.usage",
+            ErrorCode::WrongPin => "error_code.wrong_pin",
+            ErrorCode::NodeUnreachable => "error_code.node_unreachable",
+            ErrorCode::InsufficientFunds => "error_code.insufficient_funds",
+            ErrorCode::Rejected => "error_code.rejected",
+            ErrorCode::WalletLocked => "error_code.wallet_locked",
+            ErrorCode::InvalidAddress => "error_code.invalid_address",
+            ErrorCode::InvalidAmount => "error_code.invalid_amount",
+            ErrorCode::Cancelled => "cancelled",
+        })
+        .to_string()
+    }
+
+    /// An error with this code and the message to show for it
+    pub fn error(self, message: impl Into<String>) -> Error {
+        CodedError {
+            code: self,
+            message: message.into(),
+            shown: false,
+        }
+        .into()
+    }
+
+    /// An error with this code for a problem the user was already told about, which isn't
+    /// shown again
+    pub fn reported(self) -> Error {
+        CodedError {
+            code: self,
+            message: self.summary(),
+            shown: true,
+        }
+        .into()
+    }
+
+    /// The code of an error. A backed out prompt is `Cancelled`, errors that carry no code
+    /// are `Failed`
+    pub fn of(e: &Error) -> ErrorCode {
+        if let Some(coded) = e.downcast_ref::<CodedError>() {
+            return coded.code;
+        }
+        match e.downcast_ref::<InputError>() {
+            Some(InputError::Cancelled | InputError::TimedOut) => ErrorCode::Cancelled,
+            _ => ErrorCode::Failed,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct CodedError {
+    code: ErrorCode,
+    message: String,
+    shown: bool,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+/// Whether an error was already shown where it happened
+pub fn is_reported(e: &Error) -> bool {
+    e.downcast_ref::<CodedError>()
+        .is_some_and(|coded| coded.shown)
+}
+
+/// The same error once it has been shown, keeping its code and message
+pub fn shown(e: Error) -> Error {
+    CodedError {
+        code: ErrorCode::of(&e),
+        message: e.to_string(),
+        shown: true,
+    }
+    .into()
+}
+
+/// The message of an error followed by its code, for people and scripts alike.
+/// `E_FAILED` is left out, as any error without a code has it
+pub fn describe(e: &Error) -> String {
+    match ErrorCode::of(e) {
+        ErrorCode::Failed => e.to_string(),
+        code => trf("error_code.with_code", &[&code, e]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let wrong_pin = ErrorCode::WrongPin.error("Failed to decrypt");
+        assert_eq!(ExitCode::of(&wrong_pin) as i32, 3);
+        assert_eq!(wrong_pin.to_string(), "Failed to decrypt");
+        assert!(!is_reported(&wrong_pin));
+        // Context added on the way up keeps the code
+        let rejected = ErrorCode::Rejected.reported().context("send");
+        assert_eq!(ExitCode::of(&rejected), ExitCode::Rejected);
+        assert!(is_reported(&rejected));
+        assert_eq!(ExitCode::of(&Error::msg("anything")) as i32, 1);
+        let locked = ErrorCode::WalletLocked.error("In use");
+        assert_eq!(ExitCode::of(&locked) as i32, 7);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let codes: Vec<&str> = ErrorCode::ALL.iter().map(|code| code.as_str()).collect();
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+        assert!(codes.iter().all(|code| code.starts_with("E_")));
+
+        let address = ErrorCode::InvalidAddress.error("Invalid address: x");
+        assert_eq!(ErrorCode::of(&address).as_str(), "E_INVALID_ADDRESS");
+        assert_eq!(ExitCode::of(&address), ExitCode::Failure);
+        assert_eq!(
+            ErrorCode::of(&Error::new(InputError::TimedOut)),
+            ErrorCode::Cancelled
+        );
+        assert_eq!(ErrorCode::of(&Error::msg("anything")), ErrorCode::Failed);
+
+        // Shown keeps the code and the message, for the RPC server to pass on
+        let shown = shown(ErrorCode::InsufficientFunds.error("Not enough"));
+        assert!(is_reported(&shown));
+        assert_eq!(ErrorCode::of(&shown), ErrorCode::InsufficientFunds);
+        assert_eq!(shown.to_string(), "Not enough");
+        assert!(describe(&shown).contains("E_INSUFFICIENT_FUNDS"));
+        assert_eq!(describe(&Error::msg("anything")), "anything");
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::Error;
+
+/// Where a transaction id goes in `explorer_tx_url`
+pub const TX_PLACEHOLDER: &str = "{txid}";
+/// Where an address goes in `explorer_address_url`
+pub const ADDRESS_PLACEHOLDER: &str = "{addr}";
+
+/// Explains how to get links, for when no template is set
+pub const SETUP_HINT: &str = "No block explorer is set. Add explorer_tx_url = https://<explorer>/tx/{txid} and explorer_address_url = https://<explorer>/address/{addr} to ~/.snap-coin-config.";
+
+/// Whether a template is a web address with `placeholder` somewhere after the host
+pub fn is_valid_template(template: &str, placeholder: &str) -> bool {
+    ["http://", "https://"].iter().any(|scheme| {
+        template
+            .strip_prefix(scheme)
+            .is_some_and(|rest| !rest.starts_with(placeholder) && rest.contains(placeholder))
+    })
+}
+
+/// The link for `value`: the template with every placeholder replaced
+pub fn link(template: &str, placeholder: &str, value: &str) -> String {
+    template.replace(placeholder, value)
+}
+
+/// Open a link in the default browser, without waiting for it
+pub fn open(url: &str) -> Result<(), Error> {
+    open::that_detached(url).map_err(|e| Error::msg(format!("Can't open {}: {}", url, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates() {
+        let template = "https://explorer.example/tx/{txid}";
+        assert!(is_valid_template(template, TX_PLACEHOLDER));
+        assert!(!is_valid_template(template, ADDRESS_PLACEHOLDER));
+        assert!(!is_valid_template("https://{txid}", TX_PLACEHOLDER));
+        assert!(!is_valid_template(
+            "explorer.example/tx/{txid}",
+            TX_PLACEHOLDER
+        ));
+        assert_eq!(
+            link(template, TX_PLACEHOLDER, "abc"),
+            "https://explorer.example/tx/abc"
+        );
+    }
+}
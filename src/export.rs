@@ -0,0 +1,106 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Error;
+
+use crate::json::Utxo;
+
+/// Header of `available export`
+const UTXO_HEADER: &str = "tx_id,index,amount_nano,amount_snap,session_excluded";
+
+/// Quote a CSV field when it holds a separator, a quote or a line break
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The outputs as CSV, ordered by transaction id and index so the same set always gives the
+/// same file
+pub fn utxo_csv(utxos: &[Utxo]) -> String {
+    let mut sorted: Vec<&Utxo> = utxos.iter().collect();
+    sorted.sort_by(|a, b| (&a.tx_id, a.index).cmp(&(&b.tx_id, b.index)));
+    let mut csv = format!("{}\n", UTXO_HEADER);
+    for utxo in sorted {
+        let fields = [
+            csv_field(&utxo.tx_id),
+            utxo.index.to_string(),
+            utxo.amount.nano.to_string(),
+            utxo.amount.snap.clone(),
+            utxo.session_spent.to_string(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Write a file so that it is either fully there or untouched: the contents go to a
+/// temporary file next to it first, which then replaces it
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::msg(format!("Not a file path: {}", path.display())))?;
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp, path)) {
+        fs::remove_file(&temp).ok();
+        return Err(Error::msg(format!("Can't write {}: {}", path.display(), e)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(tx_id: &str, index: usize, nano: u64, session_spent: bool) -> Utxo {
+        Utxo {
+            tx_id: tx_id.to_string(),
+            index,
+            amount: nano.into(),
+            timestamp: None,
+            session_spent,
+        }
+    }
+
+    #[test]
+    fn test_utxo_csv() {
+        let utxos = [
+            utxo("b", 0, 5, false),
+            utxo("a", 2, 100_000_000, true),
+            utxo("a", 1, 1, false),
+        ];
+        assert_eq!(
+            utxo_csv(&utxos),
+            "tx_id,index,amount_nano,amount_snap,session_excluded\n\
+             a,1,1,0.00000001,false\n\
+             a,2,100000000,1.00000000,true\n\
+             b,0,5,0.00000005,false\n"
+        );
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("snap-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("utxos.csv");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!dir.join("utxos.csv.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,355 +1,3735 @@
-use std::{collections::HashMap, process::exit};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
 
 use snap_coin::{
-    api::client::Client,
-    blockchain_data_provider::BlockchainDataProvider,
-    build_transaction,
-    core::transaction::{MAX_TRANSACTION_IO, TransactionId, TransactionInput, TransactionOutput},
+    core::transaction::{MAX_TRANSACTION_IO, Transaction, TransactionId, TransactionInput},
     crypto::{
-        Hash,
+        Hash, Signature,
         keys::{Private, Public},
     },
-    to_nano, to_snap,
 };
+use zeroize::Zeroizing;
+
+use crate::{
+    address_book::{AddressBook, Contact, resolve_recipient},
+    backup::{self, Backups},
+    amount::{self, Grouping, Unit, format_amount},
+    clock,
+    commands, compact, diagnose,
+    completion::SHORT_COMMANDS,
+    config::{Config, DonationPrompt, parse_switch},
+    console::{self, say},
+    encryption::{self, FileHeader, SessionKeys, parse_memory_size},
+    exit_code::{self, ErrorCode},
+    explorer, export, first_notice,
+    history::{TxEffect, fee_rate},
+    node::{self, NodeApi},
+    i18n::{self, Lang, tr, trf},
+    style,
+    input::{InputError, accept_pin, confirm, confirm_pin, read_input, read_pin, show_secret},
+    interrupt, json, keystore,
+    notes::{TxNote, TxNotes, prune_candidates, send_note, split_labels},
+    notify::{self, NotifyMode},
+    oplog,
+    pager::{self, Output},
+    qr, report,
+    sanitize,
+    save_last_login,
+    session::Session,
+    snapshot::{self, SealKey, Snapshot},
+    spendable::{self, Breakdown},
+    spinner::{Spinner, spin},
+    vanity, watch, webhook,
+    table::{self, Column},
+    uri::{self, Installed},
+    signing::{
+        ExternalSigner, PowCache, WalletKey, Wallets, build_transaction, difficulty_increased,
+        estimate_transaction_size, expected_pow_hashes, sign_message, transaction_size,
+        verify_message,
+    },
+};
+
+/// An error that ends the session, as opposed to failing only the current command
+#[derive(Debug)]
+pub struct FatalError(pub String);
+
+impl std::fmt::Display for FatalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for FatalError {}
+
+/// Print an error that failed only the current command
+/// A cancelled or timed out PIN prompt is the user backing out, not a failure
+pub fn print_command_error(e: &anyhow::Error) {
+    if exit_code::is_reported(e) {
+        return;
+    }
+    match e.downcast_ref::<InputError>() {
+        Some(InputError::Cancelled) => println!("{}", style::warning(tr("cancelled"))),
+        Some(InputError::TimedOut) => println!("{}", style::warning(tr("pin.timed_out"))),
+        _ => println!("{}", style::error(trf("command_error", &[&exit_code::describe(e)]))),
+    }
+}
+
+/// Whether one more wallet fits under the `max_wallets` limit, saying so when it doesn't
+pub fn room_for_wallet(wallets: &Wallets, max_wallets: usize) -> bool {
+    if wallets.len() < max_wallets {
+        return true;
+    }
+    say!("{}", style::error(trf("wallet.limit_reached", &[&wallets.len()])));
+    false
+}
 
-use crate::{input::read_pin, save_last_login};
+/// Compute a built transaction's proof of work, or take it from the cache when this exact
+/// payment was worked out before. Gives the finished transaction and its difficulty
+pub async fn finish_pow(
+    client: &dyn NodeApi,
+    pow_cache: &mut PowCache,
+    mut transaction: Transaction,
+) -> Result<(Transaction, [u8; 32]), anyhow::Error> {
+    if let Some(cached) = pow_cache.get(&transaction) {
+        // A previous attempt at this exact payment never reached the mempool
+        say!("{}", tr("send.pow_reused"));
+        return Ok(cached);
+    }
+    say!("{}", tr("send.computing_pow"));
+    let difficulty = client.get_live_transaction_difficulty().await?;
+    transaction.compute_pow(&difficulty, Some(0.1f64))?;
+    pow_cache.insert(transaction.clone(), difficulty);
+    Ok((transaction, difficulty))
+}
+
+/// Tell how to get block explorer links, once ever, where one would have been shown
+fn explorer_hint() {
+    if first_notice("explorer-links").unwrap_or(false) {
+        say!("{}", style::dim(explorer::SETUP_HINT));
+    }
+}
+
+/// Number of sent-to addresses remembered for completion
+const MAX_RECENT_ADDRESSES: usize = 10;
+/// Help text is never wrapped narrower than this, however narrow the terminal
+const MIN_WRAP_WIDTH: usize = 20;
+/// How long `status` waits for the node before calling it unreachable
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether changes are written to the wallet file as they are made
+static AUTOSAVE: AtomicBool = AtomicBool::new(true);
+/// Whether there are changes only `save` or exiting will write
+static UNSAVED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_autosave(enabled: bool) {
+    AUTOSAVE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether changes were made that aren't in the wallet file yet
+pub fn has_unsaved_changes() -> bool {
+    UNSAVED.load(Ordering::Relaxed)
+}
+
+/// Save a change to wallets, the address book, transaction notes or confirmed backups. Without
+/// autosave it is only marked as unsaved, for `save` or exiting to write
+fn persist(
+    wallets: &Wallets,
+    book: &AddressBook,
+    notes: &TxNotes,
+    backups: &Backups,
+    keys: &SessionKeys,
+) {
+    if AUTOSAVE.load(Ordering::Relaxed) {
+        save(wallets, book, notes, backups, keys);
+    } else {
+        UNSAVED.store(true, Ordering::Relaxed);
+    }
+}
 
-/// Encrypt and save wallets
-fn persist(wallets: &HashMap<String, Private>, pin: &str) {
-    match crate::encryption::encrypt_wallets(wallets, pin) {
+/// Encrypt and save wallets, the address book, transaction notes and confirmed backups now,
+/// whatever the autosave setting. Returns whether they were saved
+pub fn save(
+    wallets: &Wallets,
+    book: &AddressBook,
+    notes: &TxNotes,
+    backups: &Backups,
+    keys: &SessionKeys,
+) -> bool {
+    match crate::encryption::encrypt_wallets(wallets, book, notes, backups, keys) {
         Some(bytes) => match crate::wallet_path() {
             Ok(path) => {
                 if let Err(e) = std::fs::write(path, bytes) {
-                    eprintln!("Failed to save wallets: {}", e);
+                    eprintln!("{}", style::error(trf("save.failed", &[&e])));
+                    return false;
                 }
+                UNSAVED.store(false, Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                eprintln!("{}", style::error(trf("save.no_path", &[&e])));
+                false
             }
-            Err(e) => eprintln!("Could not determine wallet path: {}", e),
         },
-        None => eprintln!("Failed to encrypt wallets — wallets NOT saved!"),
+        None => {
+            eprintln!("{}", style::error(tr("save.not_encrypted")));
+            false
+        }
     }
 }
 
-/// Handle CLI commands
-pub async fn handle_command(
-    client: &Client,
-    wallets: &mut HashMap<String, Private>,
-    current_wallet: &mut String,
-    pin: &str,
-    command: String,
-    used_session_inputs: &mut Vec<TransactionInput>,
+/// Rewrite the wallet file at `path` for the keys of a new PIN or new KDF settings with `write`,
+/// then read it back. Unless it opens with `new_keys`, the file as it was is put back, so the
+/// old keys stay in effect
+fn rewrite_for_pin(
+    path: &Path,
+    wallets: &Wallets,
+    book: &AddressBook,
+    notes: &TxNotes,
+    backups: &Backups,
+    new_keys: &SessionKeys,
+    write: impl Fn(&Path, &[u8]) -> Result<(), anyhow::Error>,
 ) -> Result<(), anyhow::Error> {
-    let mut parts = command.trim().split_whitespace();
+    let bytes = encryption::encrypt_wallets(wallets, book, notes, backups, new_keys)
+        .ok_or_else(|| anyhow::Error::msg(tr("rewrite.encrypt_failed")))?;
+    let previous = match fs::read(path) {
+        Ok(data) => Some(data),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            let message = trf("read_failed", &[&path.display(), &e]);
+            return Err(anyhow::Error::msg(message));
+        }
+    };
+    write(path, &bytes)?;
+    if fs::read(path).is_ok_and(|data| new_keys.opens(&data)) {
+        return Ok(());
+    }
+    let restored = match &previous {
+        Some(previous) => export::write_atomic(path, previous),
+        None => fs::remove_file(path).map_err(anyhow::Error::from),
+    };
+    let mut message = tr("rewrite.not_opening").to_string();
+    if let Err(e) = restored {
+        message.push(' ');
+        message.push_str(&trf("rewrite.restore_failed", &[&e]));
+    }
+    Err(anyhow::Error::msg(message))
+}
+
+/// Balance, and the spendable part when it differs
+pub async fn balance_lines(
+    client: &dyn NodeApi,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+) -> Result<Vec<String>, anyhow::Error> {
+    let breakdown = spendable::fetch(client, public, used_session_inputs).await?;
+    let mut lines = vec![trf("balance", &[&amount::display(breakdown.balance)])];
+    lines.extend(spendable_lines(&breakdown));
+    Ok(lines)
+}
+
+/// Sort order of `available [--sort age|amount|txid]`, `None` for invalid arguments
+fn parse_available_args(args: &[&str]) -> Option<&'static str> {
+    match args {
+        [] | ["--sort", "amount"] => Some("amount"),
+        ["--sort", "age"] => Some("age"),
+        ["--sort", "txid"] => Some("txid"),
+        _ => None,
+    }
+}
+
+/// The spendable outputs, sorted by `sort`
+async fn available_utxos(
+    client: &dyn NodeApi,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+    sort: &str,
+) -> Result<Vec<json::Utxo>, anyhow::Error> {
+    let utxos = node::available_outputs(client, public).await?;
+
+    // Ages come from the creating transactions, each fetched once
+    let mut timestamps: HashMap<Hash, Option<u64>> = HashMap::new();
+    for (tx_hash, _, _) in &utxos {
+        if !timestamps.contains_key(tx_hash) {
+            let tx = client.get_transaction(tx_hash).await?;
+            timestamps.insert(*tx_hash, tx.map(|tx| tx.timestamp));
+        }
+    }
+
+    let mut utxos: Vec<json::Utxo> = utxos
+        .into_iter()
+        .map(|(tx_hash, output, index)| json::Utxo {
+            tx_id: tx_hash.dump_base36(),
+            index,
+            amount: output.amount.into(),
+            timestamp: timestamps.get(&tx_hash).copied().flatten(),
+            session_spent: used_session_inputs.iter().any(|input| {
+                input.transaction_id == tx_hash && input.output_index == index
+            }),
+        })
+        .collect();
+    // Ordered by output first, so ties of the other sorts don't depend on the node's order
+    utxos.sort_by(|a, b| (&a.tx_id, a.index).cmp(&(&b.tx_id, b.index)));
+    match sort {
+        // Newest first, unknown ages last
+        "age" => utxos.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)),
+        "txid" => {}
+        _ => utxos.sort_by(|a, b| b.amount.nano.cmp(&a.amount.nano)),
+    }
+    Ok(utxos)
+}
+
+/// Table of the spendable outputs, sorted by `sort`
+pub async fn available_lines(
+    client: &dyn NodeApi,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+    sort: &str,
+) -> Result<Vec<String>, anyhow::Error> {
+    let utxos = available_utxos(client, public, used_session_inputs, sort).await?;
+
+    let now = clock::now()?;
+    let columns = [
+        Column::shrinking(tr("column.tx_id")),
+        Column::right(tr("column.index")),
+        Column::right(tr("column.amount")),
+        Column::right(tr("column.age")),
+        Column::left(tr("column.flags")),
+    ];
+    let rows: Vec<Vec<String>> = utxos
+        .iter()
+        .map(|utxo| {
+            vec![
+                utxo.tx_id.clone(),
+                utxo.index.to_string(),
+                style::incoming(format_amount(utxo.amount.nano)),
+                utxo.timestamp
+                    .map(|timestamp| table::format_age(now.saturating_sub(timestamp)))
+                    .unwrap_or_else(|| "?".to_string()),
+                if utxo.session_spent {
+                    tr("available.session_spent")
+                } else {
+                    ""
+                }
+                .to_string(),
+            ]
+        })
+        .collect();
+    let mut lines = vec![trf("available.header", &[&rows.len()])];
+    lines.extend(table::render(&columns, &rows));
+    // Outputs that can't be spent yet aren't listed by the node at all, only their value shows
+    // in the balance
+    let listed: u64 = utxos.iter().map(|utxo| utxo.amount.nano).sum();
+    let unavailable = client.get_balance(public).await?.saturating_sub(listed);
+    if unavailable > 0 {
+        lines.push(style::warning(spendable::unavailable_reason(unavailable)));
+    }
+    Ok(lines)
+}
+
+/// The table of `balance-history`: the last `shown` snapshots with the change since the one
+/// before each, then a sparkline of them
+fn balance_history_lines(snapshots: &[Snapshot], shown: usize) -> Vec<String> {
+    let skipped = snapshots.len().saturating_sub(shown);
+    let columns = [
+        Column::left(tr("column.time")),
+        Column::right(tr("column.balance")),
+        Column::right(tr("column.change")),
+    ];
+    let rows: Vec<Vec<String>> = snapshots
+        .iter()
+        .enumerate()
+        .skip(skipped)
+        .map(|(index, taken)| {
+            let previous = index.checked_sub(1).map(|index| snapshots[index].balance);
+            let change = match previous {
+                Some(previous) if taken.balance > previous => {
+                    style::incoming(format!("+{}", format_amount(taken.balance - previous)))
+                }
+                Some(previous) if taken.balance < previous => {
+                    style::outgoing(format!("-{}", format_amount(previous - taken.balance)))
+                }
+                Some(_) => "0".to_string(),
+                None => String::new(),
+            };
+            vec![
+                snapshot::format_time(taken.timestamp),
+                format_amount(taken.balance),
+                change,
+            ]
+        })
+        .collect();
+    let mut lines = Vec::new();
+    if skipped > 0 {
+        let (shown, taken) = (rows.len(), snapshots.len());
+        lines.push(trf("balance_history.showing", &[&shown, &taken]));
+    }
+    lines.extend(table::render(&columns, &rows));
+    let balances: Vec<u64> = snapshots[skipped..].iter().map(|taken| taken.balance).collect();
+    let trend = snapshot::sparkline(&balances);
+    lines.push(trf("balance_history.trend", &[&trend]));
+    lines
+}
+
+/// Whether `mempool [--mine]` shows only transactions involving the wallet, `None` for
+/// invalid arguments
+fn parse_mempool_args(args: &[&str]) -> Option<bool> {
+    match args {
+        [] => Some(false),
+        ["--mine"] => Some(true),
+        _ => None,
+    }
+}
+
+/// Table of the transactions in the mempool, newest first
+pub async fn mempool_lines(
+    client: &dyn NodeApi,
+    public: Public,
+    mine: bool,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut mempool = client.get_mempool().await?;
+    if mine {
+        mempool.retain(|tx| {
+            let effect = TxEffect::of(tx, public);
+            effect.received > 0 || effect.is_outgoing()
+        });
+    }
+    if mempool.is_empty() {
+        let empty = if mine {
+            tr("mempool.none_mine")
+        } else {
+            tr("mempool.empty")
+        };
+        return Ok(vec![empty.to_string()]);
+    }
+    mempool.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    let now = clock::now()?;
+    let columns = [
+        Column::shrinking(tr("column.tx_id")),
+        Column::right(tr("column.inputs")),
+        Column::right(tr("column.outputs")),
+        Column::right(tr("column.amount")),
+        Column::right(tr("column.age")),
+        Column::left(tr("column.flags")),
+    ];
+    let rows: Vec<Vec<String>> = mempool
+        .iter()
+        .map(|tx| {
+            let amount = tx.outputs.iter().map(|output| output.amount).sum();
+            let effect = TxEffect::of(tx, public);
+            let yours = effect.received > 0 || effect.is_outgoing();
+            vec![
+                tx.transaction_id
+                    .map(|tx_id| tx_id.dump_base36())
+                    .unwrap_or_else(|| "?".to_string()),
+                tx.inputs.len().to_string(),
+                tx.outputs.len().to_string(),
+                format_amount(amount),
+                table::format_age(now.saturating_sub(tx.timestamp)),
+                if yours { tr("mempool.yours") } else { "" }.to_string(),
+            ]
+        })
+        .collect();
+    let mut lines = vec![trf("mempool.header", &[&rows.len()])];
+    lines.extend(table::render(&columns, &rows));
+    Ok(lines)
+}
+
+/// What part of the balance can be sent now, and why the rest can't, when they differ
+fn spendable_lines(breakdown: &Breakdown) -> Vec<String> {
+    if !breakdown.differs() {
+        return vec![];
+    }
+    let mut lines = vec![trf("spendable", &[&amount::display(breakdown.spendable())])];
+    for reason in breakdown.reasons() {
+        lines.push(format!("  - {}", style::dim(reason)));
+    }
+    lines
+}
+
+/// Ask for the payments of a `send` typed without arguments, one recipient and amount at a
+/// time. Returns them as `send` arguments, so they go through the one-line form's checks, or
+/// `None` when the user backs out. An empty answer goes back a step, Ctrl+C cancels
+async fn send_wizard(
+    client: &dyn NodeApi,
+    book: &AddressBook,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+) -> Result<Option<Vec<String>>, anyhow::Error> {
+    let spendable = spendable::fetch(client, public, used_session_inputs)
+        .await?
+        .spendable();
+    say!("{}", style::dim(tr("send.wizard_intro")));
+    let mut payments: Vec<(String, String, u64)> = Vec::new();
+    let mut recipient: Option<String> = None;
+    loop {
+        let Some(to) = recipient.clone() else {
+            let answer = read_input(tr("send.wizard_recipient"))?;
+            if answer.is_empty() {
+                // Back to the amount of the previous recipient, or out
+                match payments.pop() {
+                    Some((to, _, _)) => recipient = Some(to),
+                    None => return Ok(None),
+                }
+                continue;
+            }
+            match resolve_recipient(book, &answer) {
+                Ok(_) => recipient = Some(answer),
+                Err(reason) => {
+                    let address = format!("{} ({})", answer, reason);
+                    say!("{}", style::error(trf("invalid_address", &[&address])));
+                }
+            }
+            continue;
+        };
+
+        let total: u64 = payments.iter().map(|(_, _, amount)| amount).sum();
+        let left = spendable.saturating_sub(total);
+        let answer = read_input(&trf("send.wizard_amount", &[&to, &amount::display(left)]))?;
+        if answer.is_empty() {
+            recipient = None;
+            continue;
+        }
+        match amount::parse_payment_of(&answer, spendable) {
+            Ok(amount) if amount > left => say!(
+                "{}",
+                style::error(trf("send.wizard_exceeds", &[&amount::display(left)]))
+            ),
+            Ok(amount) => {
+                payments.push((to, answer, amount));
+                recipient = None;
+                if !confirm(tr("send.wizard_another"), false) {
+                    break;
+                }
+            }
+            Err(e) => say!("{}", style::error(e.message(&answer))),
+        }
+    }
+    Ok(Some(
+        payments
+            .into_iter()
+            .flat_map(|(to, amount, _)| [to, amount])
+            .collect(),
+    ))
+}
+
+/// The payments of a `send`, with what its summary and note show for each
+#[derive(Default)]
+pub struct Payments<'a> {
+    pub payments: Vec<(Public, u64)>,
+    /// Recipients keep the text they were given as, so aliases stay readable in the summary
+    pub recipients: Vec<&'a str>,
+    /// How a percentage was worked out, ` (<percent> of <spendable>)`
+    pub shares: Vec<Option<String>>,
+    pub labels: Vec<Option<String>>,
+}
+
+/// Show who a `send` pays and how much, before it is confirmed
+fn print_send_summary(
+    book: &AddressBook,
+    recipients: &[&str],
+    payments: &[(Public, u64)],
+    shares: &[Option<String>],
+    labels: &[Option<String>],
+) {
+    say!("{}", tr("send.summary"));
+    for (((recipient, (public, amount)), share), label) in
+        recipients.iter().zip(payments).zip(shares).zip(labels)
+    {
+        let share = share.as_deref().unwrap_or_default();
+        if let Some(contact) = book.get(*recipient) {
+            say!(
+                "  {} ({}): {}{}",
+                recipient,
+                style::dim(public.dump_base36()),
+                style::outgoing(amount::display(*amount)),
+                share
+            );
+            if !contact.note.is_empty() {
+                say!("    {}", trf("send.contact_note", &[&contact.note]));
+            }
+        } else {
+            say!(
+                "  {}: {}{}",
+                public.dump_base36(),
+                style::outgoing(amount::display(*amount)),
+                share
+            );
+        }
+        if let Some(label) = label {
+            say!("    {}", trf("send.label", &[label]));
+        }
+    }
+}
+
+/// Check every recipient and amount of a `send` before anything is built. Either all of them
+/// are valid, or every problem found is returned and nothing may be sent: a transaction paying
+/// only the valid recipients would look like a success
+pub fn check_payments<'a>(
+    args: &[&'a str],
+    labels: Vec<Option<String>>,
+    book: &AddressBook,
+    spendable: u64,
+) -> Result<Payments<'a>, Vec<anyhow::Error>> {
+    let mut checked = Payments::default();
+    let mut problems = Vec::new();
+    for (pair, label) in args.chunks(2).zip(labels) {
+        let (receiver, amount_str) = (pair[0], pair[1]);
+        let amount = match amount::parse_payment_of(amount_str, spendable) {
+            Ok(amount) => Some(amount),
+            Err(e) => {
+                problems.push(ErrorCode::InvalidAmount.error(e.message(amount_str)));
+                None
+            }
+        };
+        let public = match resolve_recipient(book, receiver) {
+            Ok(public) => Some(public),
+            Err(reason) => {
+                let address = format!("{} ({})", receiver, reason);
+                problems.push(ErrorCode::InvalidAddress.error(trf("invalid_address", &[&address])));
+                None
+            }
+        };
+        let (Some(amount), Some(public)) = (amount, public) else {
+            continue;
+        };
+        checked.payments.push((public, amount));
+        checked.recipients.push(receiver);
+        checked.labels.push(label);
+        checked.shares.push(amount::is_percentage(amount_str).then(|| {
+            let spendable = amount::display(spendable);
+            format!(" ({})", trf("send.share", &[amount_str, &spendable]))
+        }));
+    }
+    match problems.is_empty() {
+        true => Ok(checked),
+        false => Err(problems),
+    }
+}
+
+/// Check the command word, and the subcommand after `wallet`, for typos before the line runs.
+/// A typo close to a known word gets a suggestion. With `autocorrect` on, a one-letter slip
+/// runs as the suggestion once confirmed. Returns the line to run, `None` when there is none
+pub fn correct_typos(command: &str, config: &Config) -> Option<String> {
+    let mut words: Vec<&str> = command.split_whitespace().collect();
+    let wallet_subcommands = commands::subcommand_names("wallet");
+    let (position, suggestion, distance) = match words.as_slice() {
+        [first, ..]
+            if commands::find(first).is_none()
+                && !SHORT_COMMANDS.contains(first)
+                && !config.aliases.contains_key(*first) =>
+        {
+            let aliases = config.aliases.keys().map(String::as_str);
+            let Some((suggestion, distance)) =
+                commands::closest_among(first, commands::names().chain(aliases))
+            else {
+                println!("{}", style::error(trf("unknown_command", &[first])));
+                return None;
+            };
+            let message = trf("unknown_command_suggest", &[first, &suggestion]);
+            println!("{}", style::error(message));
+            (0, suggestion, distance)
+        }
+        ["wallet", subcommand, ..] if !wallet_subcommands.contains(subcommand) => {
+            let closest = commands::closest_among(subcommand, wallet_subcommands.iter().copied());
+            let Some((suggestion, distance)) = closest else {
+                // Left to `wallet` itself to report
+                return Some(command.to_string());
+            };
+            let message = trf("unknown_subcommand_suggest", &[subcommand, &suggestion]);
+            println!("{}", style::error(message));
+            (1, suggestion, distance)
+        }
+        _ => return Some(command.to_string()),
+    };
+    let prompt = trf("autocorrect_confirm", &[&suggestion]);
+    if !config.autocorrect || distance > 1 || !confirm(&prompt, true) {
+        return None;
+    }
+    words[position] = suggestion;
+    Some(words.join(" "))
+}
+
+/// Print `text` after `prefix`, wrapped at the terminal width, continuation lines lined up
+/// under the first
+fn print_wrapped(prefix: &str, text: &str) {
+    let indent = prefix.chars().count();
+    let width = table::terminal_width()
+        .map_or(usize::MAX, |width| width.saturating_sub(indent).max(MIN_WRAP_WIDTH));
+    for (i, line) in table::wrap(text, width).iter().enumerate() {
+        if i == 0 {
+            println!("{}{}", prefix, line);
+        } else {
+            println!("{:indent$}{}", "", line, indent = indent);
+        }
+    }
+}
+
+/// Detailed `help <command>`: usage, argument formats, subcommands and examples
+fn print_command_help(name: &str) {
+    let Some(info) = commands::find(name) else {
+        println!("{}", style::error(trf("unknown_command", &[&name])));
+        if let Some(suggestion) = commands::closest(name) {
+            println!("{}", trf("did_you_mean", &[&format!("help {}", suggestion)]));
+        }
+        return;
+    };
+    println!("{} {}", tr("help.usage"), info.usage);
+    print_wrapped("  ", tr(info.summary));
+    if !info.aliases.is_empty() {
+        println!("  {} {}", tr("help.also"), info.aliases.join(", "));
+    }
+    for detail in info.details {
+        println!();
+        print_wrapped("", detail);
+    }
+    if !info.subcommands.is_empty() {
+        println!();
+        println!("{}", tr("help.subcommands"));
+        for sub in info.subcommands {
+            let prefix = format!("  {} {:<27}- ", info.name, sub.usage);
+            print_wrapped(&prefix, tr(sub.summary));
+        }
+    }
+    if !info.examples.is_empty() {
+        println!();
+        println!("{}", tr("help.examples"));
+        for example in info.examples {
+            println!("  {}", example);
+        }
+    }
+}
+
+/// Handle CLI commands
+/// Errors fail only the current command, unless they are a `FatalError`
+pub async fn handle_command(session: &mut Session, command: String) -> Result<(), anyhow::Error> {
+    // The log gets names from the command list only, what was typed may hold anything
+    let name = command.split_whitespace().next().map(|word| match commands::find(word) {
+        Some(info) => info.name,
+        None if session.config.aliases.contains_key(word) => "<alias>",
+        None => "<unknown>",
+    });
+    let started = Instant::now();
+    let result = run_command(session, command).await;
+    if let Some(name) = name {
+        oplog::command(name, started.elapsed(), result.as_ref().err());
+    }
+    // A --json command that failed before printing its object prints the error as one, for
+    // scripts to branch on its code
+    let awaited = json::take_awaited();
+    match result {
+        Err(e) if awaited && e.downcast_ref::<FatalError>().is_none() => {
+            json::print(&json::ErrorOutput::new(&e))?;
+            Err(exit_code::shown(e))
+        }
+        result => result,
+    }
+}
+
+async fn run_command(session: &mut Session, command: String) -> Result<(), anyhow::Error> {
+    let Session {
+        client,
+        wallets,
+        book,
+        notes,
+        backups,
+        current_wallet,
+        keys,
+        used_session_inputs,
+        recent_addresses,
+        pow_cache,
+        node,
+        clock_skew,
+        config,
+        startup_settings,
+    } = session;
+    let client: &dyn NodeApi = &**client;
+    let command = match config.expand_alias(&command) {
+        Ok(command) => command,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    let mut parts = command.split_whitespace();
     let cmd = match parts.next() {
         Some(c) => c,
         None => return Ok(()),
     };
-    let args: Vec<&str> = parts.collect();
+    let mut args: Vec<&str> = parts.collect();
+    // --nano shows this command's amounts in nano, whatever the unit setting
+    let _unit = args.contains(&"--nano").then(|| amount::override_unit(Unit::Nano));
+    // --full prints ids and addresses untruncated, for copying, whatever the terminal width
+    let _full = args.contains(&"--full").then(table::show_full);
+    // --json prints one JSON object on stdout for scripts, with everything else on stderr
+    let json = args.contains(&"--json");
+    let _stdout = json.then(console::reserve_until_dropped);
+    if json && !json::COMMANDS.contains(&cmd) {
+        say!("{}", style::error(trf("json.unsupported", &[&cmd])));
+        return Ok(());
+    }
+    args.retain(|arg| *arg != "--nano" && *arg != "--full" && *arg != "--json");
+    if json {
+        json::await_object();
+    }
 
     let wallet = match wallets.get(current_wallet) {
         Some(w) => w,
         None => {
-            println!("Current wallet '{}' not found.", current_wallet);
+            println!("{}", style::error(trf("current_wallet_missing", &[&*current_wallet])));
             return Ok(());
         }
     };
-    let public = wallet.to_public();
+    let public = wallet.public();
+    // What this wallet spent this session, what other wallets spent never limits it
+    let spent_inputs = used_session_inputs.of(public);
 
     match cmd {
         "help" => {
-            println!("Available commands:");
-            println!("  balance                    - Show wallet balance");
-            println!("  available                  - List available UTXOs");
-            println!("  history                    - Show transaction history");
-            println!("  tx-info <txid>             - Show transaction details");
-            println!("  merge-available            - Merge all available into one utxo.");
-            println!("  send <addr> <amt>...       - Send SNAP to addresses");
-            println!("  wallet <subcmd> [<wallet>] - Wallet management commands");
-            println!("    subcommands:");
-            println!(
-                "      delete [<wallet>]      - Delete the specified wallet (default: current)"
-            );
-            println!(
-                "      private [<wallet>]     - Show private key of the wallet (default: current)"
-            );
-            println!(
-                "      public [<wallet>]      - Show public key of the wallet (default: current)"
-            );
-            println!(
-                "      switch [<wallet>]      - Switch to the specified wallet (default: current)"
-            );
-
-            println!("  change-pin                 - Change wallet PIN");
-            println!("  help                       - Show this help message");
-            println!("  clear                      - Clears output history");
-            println!("  exit, quit                 - Exit the wallet");
+            let line = |indent: usize, usage: &str, key: &'static str| {
+                let prefix = format!(
+                    "{:indent$}{:<width$}- ",
+                    "",
+                    usage,
+                    indent = indent,
+                    width = 29 - indent
+                );
+                print_wrapped(&prefix, tr(key));
+            };
+            if let Some(name) = args.first() {
+                print_command_help(name);
+                return Ok(());
+            }
+            println!("{}", tr("help.header"));
+            for info in commands::COMMANDS {
+                line(2, info.usage, info.summary);
+                if !info.subcommands.is_empty() {
+                    println!("    {}", tr("help.subcommands"));
+                }
+                for sub in info.subcommands {
+                    line(6, sub.usage, sub.summary);
+                }
+            }
+            line(2, "<command> --nano", "help.nano");
+            line(2, "<command> --full", "help.full");
+            line(2, "<command> --json", "help.json");
+            println!("{}", tr("help.more"));
+            if !config.aliases.is_empty() {
+                println!("{}", tr("help.aliases"));
+                for (name, expansion) in &config.aliases {
+                    println!("  {:<27}= {}", name, expansion);
+                }
+            }
         }
 
         "balance" => {
-            let balance = to_snap(client.get_balance(public).await?);
-            println!("Balance: {} SNAP", balance);
+            // Any wallet by name, external signer ones too, without switching to it
+            let (name, public) = match args.as_slice() {
+                [] => (current_wallet.as_str(), public),
+                [name] => match wallets.get(*name) {
+                    Some(wallet) => (*name, wallet.public()),
+                    None => return Err(anyhow::Error::msg(trf("wallet_not_found", &[name]))),
+                },
+                _ => {
+                    println!("{}", tr("balance.usage"));
+                    return Ok(());
+                }
+            };
+            let spent_inputs = used_session_inputs.of(public);
+            if json {
+                let breakdown = spin(
+                    tr("balance.fetching"),
+                    spendable::fetch(client, public, spent_inputs),
+                )
+                .await?;
+                return json::print(&json::BalanceOutput {
+                    wallet: name.to_string(),
+                    address: public.dump_base36(),
+                    balance: breakdown.balance.into(),
+                    spendable: breakdown.spendable().into(),
+                    session_spent: breakdown.session_spent.into(),
+                    unavailable: breakdown.unavailable().into(),
+                    pending_incoming: breakdown.pending_incoming.into(),
+                });
+            }
+            let lines = spin(
+                tr("balance.fetching"),
+                balance_lines(client, public, spent_inputs),
+            )
+            .await?;
+            if name != current_wallet.as_str() {
+                println!("{}", trf("balance.of_wallet", &[&name]));
+            }
+            for line in lines {
+                println!("{}", line);
+            }
         }
 
         "available" => {
-            let utxos = client.get_available_transaction_outputs(public).await?;
-            let mut utxo_map: HashMap<Hash, Vec<(usize, TransactionOutput)>> = HashMap::new();
-            for (tx_hash, tx_output, index) in utxos {
-                utxo_map
-                    .entry(tx_hash)
-                    .or_default()
-                    .push((index, tx_output));
-            }
-
-            println!("Available UTXOs:");
-            for (tx_hash, outputs) in utxo_map {
-                println!("  Transaction: {}", tx_hash.dump_base36());
-                for (index, tx_output) in outputs {
-                    println!(
-                        "    - Output Index: {}, Amount: {}",
-                        index,
-                        to_snap(tx_output.amount)
-                    );
+            if let ["export", path] = args[..] {
+                // Rows go to stdout for `-`, so the count goes to stderr
+                let _stdout = (path == "-").then(console::reserve_until_dropped);
+                let utxos = spin(
+                    tr("available.fetching"),
+                    available_utxos(client, public, spent_inputs, "txid"),
+                )
+                .await?;
+                let csv = export::utxo_csv(&utxos);
+                if path == "-" {
+                    print!("{}", csv);
+                } else {
+                    export::write_atomic(Path::new(path), csv.as_bytes())?;
                 }
+                say!("{}", trf("available.exported", &[&utxos.len()]));
+                return Ok(());
             }
-        }
-
-        "history" => {
-            let history = client.get_transactions_of_address(public).await?;
-            println!("Transaction History ({} items):", history.len());
-            for tx_id in history {
-                println!("  - {}", tx_id.dump_base36());
+            let Some(sort) = parse_available_args(&args) else {
+                say!("{}", tr("available.usage"));
+                return Ok(());
+            };
+            if json {
+                let utxos = spin(
+                    tr("available.fetching"),
+                    available_utxos(client, public, spent_inputs, sort),
+                )
+                .await?;
+                let total: u64 = utxos.iter().map(|utxo| utxo.amount.nano).sum();
+                let balance = client.get_balance(public).await?;
+                return json::print(&json::UtxoList {
+                    address: public.dump_base36(),
+                    total: total.into(),
+                    unavailable: balance.saturating_sub(total).into(),
+                    utxos,
+                });
             }
+            let lines = spin(
+                tr("available.fetching"),
+                available_lines(client, public, spent_inputs, sort),
+            )
+            .await?;
+            let mut out = Output::new();
+            for line in lines {
+                out.line(line);
+            }
+            out.finish();
         }
 
-        "tx-info" => {
-            if args.len() != 1 {
-                println!("Usage: tx-info <TXID>");
+        "estimate" => {
+            if args.is_empty() || args.len() > 2 {
+                println!("{}", tr("estimate.usage"));
                 return Ok(());
             }
-            if let Some(tx_id) = TransactionId::new_from_base36(args[0]) {
-                match client.get_transaction(&tx_id).await? {
-                    Some(tx) => {
-                        println!("Transaction Details: {}", tx_id.dump_base36());
-                        println!("{:#?}", tx);
-                    }
-                    None => println!("Transaction not found: {}", args[0]),
+            let total = match amount::parse_amount(args[0]) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    println!("{}", style::error(e.message(args[0])));
+                    return Ok(());
+                }
+            };
+            let recipients = match args.get(1).map(|n| n.parse::<usize>()) {
+                None => 1,
+                Some(Ok(n)) if n > 0 => n,
+                _ => {
+                    let message = trf("estimate.invalid_recipients", &[&args[1]]);
+                    println!("{}", style::error(message));
+                    return Ok(());
+                }
+            };
+
+            // Mirror build_transaction's greedy selection, without building or signing anything
+            let mut available = node::available_outputs(client, public).await?;
+            available.retain(|(tx_id, _, index)| {
+                !spent_inputs
+                    .iter()
+                    .any(|input| input.transaction_id == *tx_id && input.output_index == *index)
+            });
+            let mut input_count = 0;
+            let mut funds = 0u64;
+            for (_, output, _) in &available {
+                if funds >= total {
+                    break;
                 }
+                funds += output.amount;
+                input_count += 1;
+            }
+            if funds < total {
+                println!(
+                    "{}",
+                    trf(
+                        "estimate.insufficient",
+                        &[&amount::display(funds), &amount::display(total)]
+                    )
+                );
+                return Ok(());
+            }
+            let output_count = recipients + if funds > total { 1 } else { 0 };
+            let difficulty = client.get_live_transaction_difficulty().await?;
+
+            println!("{}", tr("estimate.header"));
+            println!("{}", trf("estimate.inputs", &[&input_count]));
+            let outputs = if output_count > recipients {
+                trf("estimate.outputs_change", &[&output_count, &recipients])
             } else {
-                println!("Invalid TX ID: {}", args[0]);
+                trf("estimate.outputs", &[&output_count, &recipients])
+            };
+            println!("{}", outputs);
+            let size = estimate_transaction_size(input_count, output_count);
+            println!("{}", trf("estimate.size", &[&size]));
+            let hashes = format!("{:.0}", expected_pow_hashes(&difficulty, Some(0.1)));
+            println!("{}", trf("estimate.pow", &[&hashes]));
+            println!("{}", tr("estimate.fee"));
+            if input_count + output_count > MAX_TRANSACTION_IO {
+                let message = trf("estimate.too_large", &[&MAX_TRANSACTION_IO]);
+                println!("{}", style::warning(message));
             }
         }
 
-        "send" => {
-            if args.len() % 2 != 0 || args.len() < 2 {
-                println!("Usage: send <receiver> <amount> [...more pairs]");
-                return Ok(());
+        "history" => {
+            let limit = match args.as_slice() {
+                [] => None,
+                ["--limit", n] => match n.parse::<usize>() {
+                    Ok(n) => Some(n),
+                    Err(_) => {
+                        say!("{}", style::error(trf("invalid_limit", &[n])));
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    say!("{}", tr("history.usage"));
+                    return Ok(());
+                }
+            };
+
+            let history = spin(
+                tr("history.fetching"),
+                client.get_transactions_of_address(public),
+            )
+            .await?;
+            let shown = limit.unwrap_or(history.len()).min(history.len());
+            if json {
+                let mut transactions = Vec::new();
+                for tx_id in history.iter().take(shown) {
+                    let lookup = spin(tr("history.fetching"), client.get_transaction(tx_id));
+                    let tx = lookup.await?;
+                    let effect = tx.as_ref().map(|tx| TxEffect::of(tx, public));
+                    transactions.push(json::HistoryEntry {
+                        tx_id: tx_id.dump_base36(),
+                        timestamp: tx.map(|tx| tx.timestamp),
+                        received: effect.map_or(0, |effect| effect.received).into(),
+                        sent: effect.map_or(0, |effect| effect.sent).into(),
+                        note: notes.get(&tx_id.dump_base36()).map(|note| note.text.clone()),
+                    });
+                }
+                return json::print(&json::HistoryOutput {
+                    address: public.dump_base36(),
+                    transactions,
+                });
+            }
+            // Too long for the terminal: collect everything for the pager instead of streaming
+            let paged = pager::would_page(shown + 2);
+            let mut out = Output::new();
+            if shown > 1 {
+                println!("{}", tr("ctrl_c_stops"));
+            }
+            if shown < history.len() {
+                out.line(trf("history.header_partial", &[&history.len(), &shown]));
+            } else {
+                out.line(trf("history.header", &[&history.len()]));
             }
 
-            let mut payments = Vec::new();
-            let mut iter = args.iter();
-            while let Some(receiver) = iter.next() {
-                if let Some(amount_str) = iter.next() {
-                    match amount_str.parse::<f64>() {
-                        Ok(amount) => {
-                            if let Some(receiver) = Public::new_from_base36(receiver) {
-                                payments.push((receiver, to_nano(amount)));
+            // Each entry needs its own lookup, so rows are printed as they arrive, with column
+            // widths fixed up front: ids all have the same length, amounts get a generous column
+            let columns = [
+                Column::shrinking(tr("column.tx_id")),
+                Column::right(tr("column.amount")),
+                Column::right(tr("column.age")),
+                Column::left(tr("column.note")),
+            ];
+            let sample = history
+                .first()
+                .map(|tx_id| vec![tx_id.dump_base36(), " ".repeat(20), "999d".to_string()])
+                .unwrap_or_default();
+            let widths = table::column_widths(&columns, &[sample], table::terminal_width());
+            if shown > 0 {
+                out.line(table::format_header(&columns, &widths));
+            }
+            if !paged {
+                out.finish();
+                out = Output::new();
+            }
+            let now = clock::now()?;
+            let spinner = Spinner::start("");
+            let stream = async {
+                for (done, tx_id) in history.iter().take(shown).enumerate() {
+                    spinner.set_label(trf("history.fetching_count", &[&done, &shown]));
+                    let (amount, age) = match client.get_transaction(tx_id).await? {
+                        Some(tx) => {
+                            let effect = TxEffect::of(&tx, public);
+                            let amount = if effect.is_outgoing() {
+                                style::outgoing(format!("-{}", format_amount(effect.sent)))
                             } else {
-                                println!("Invalid public address: {}", receiver);
-                            }
-                        }
-                        Err(_) => {
-                            println!("Invalid amount: {}", amount_str);
-                            return Ok(());
+                                style::incoming(format!("+{}", format_amount(effect.received)))
+                            };
+                            (amount, table::format_age(now.saturating_sub(tx.timestamp)))
                         }
+                        None => (tr("history.not_found").to_string(), "?".to_string()),
+                    };
+                    let note = notes
+                        .get(&tx_id.dump_base36())
+                        .map(|note| note.text.clone())
+                        .unwrap_or_default();
+                    let row = [tx_id.dump_base36(), amount, age, note];
+                    let row = table::format_row(&columns, &widths, &row);
+                    if paged {
+                        out.line(row);
+                    } else {
+                        spinner.println(row);
                     }
                 }
+                Ok::<(), anyhow::Error>(())
+            };
+            let _shield = interrupt::handled();
+            let stopped = tokio::select! {
+                result = stream => {
+                    result?;
+                    false
+                }
+                _ = tokio::signal::ctrl_c() => true,
+            };
+            drop(spinner);
+            if stopped {
+                println!("{}", tr("stopped"));
             }
+            out.finish();
+        }
 
-            let transaction =
-                build_transaction(client, *wallet, payments, &used_session_inputs).await;
-            if let Err(ref e) = transaction {
-                println!("Failed to create transaction: {}", e);
+        "report" => {
+            let usage = tr("report.usage");
+            let Some(year) = args.first().and_then(|year| year.parse::<i64>().ok()) else {
+                println!("{}", usage);
                 return Ok(());
+            };
+            let (mut out_path, mut wallet_name) = (None, current_wallet.as_str());
+            let mut rest = args[1..].iter();
+            while let Some(&flag) = rest.next() {
+                match (flag, rest.next()) {
+                    ("--out", Some(&path)) => out_path = Some(path),
+                    ("--wallet", Some(&name)) => wallet_name = name,
+                    _ => {
+                        println!("{}", usage);
+                        return Ok(());
+                    }
+                }
             }
-
-            let mut transaction = transaction.unwrap();
-            println!("Computing Proof of Work...");
-            transaction.compute_pow(&client.get_live_transaction_difficulty().await?, Some(0.1f64))?;
-            let tx_id = transaction.transaction_id.unwrap();
-            println!("Created transaction: {}", tx_id.dump_base36());
-
-            if pin != read_pin("Enter 6-digit PIN to confirm: ")? {
-                println!("PIN incorrect!");
+            let Some(out_path) = out_path else {
+                println!("{}", usage);
                 return Ok(());
-            }
-
-            println!("Submitting transaction...");
+            };
+            let Some(wallet) = wallets.get(wallet_name) else {
+                println!("{}", style::error(trf("wallet_not_found", &[&wallet_name])));
+                return Ok(());
+            };
+            let address = wallet.public();
 
-            let used_inputs = transaction.inputs.clone();
-            let status = client.submit_transaction(transaction).await?;
-            println!("Transaction submission status: {:?}", status);
+            // Every year is fetched, as the running balance is replayed from the first
+            // transaction
+            let ids = spin(
+                tr("history.fetching"),
+                client.get_transactions_of_address(address),
+            )
+            .await?;
+            let spinner = Spinner::start("");
+            let fetch = async {
+                let mut history = Vec::with_capacity(ids.len());
+                for (done, tx_id) in ids.iter().enumerate() {
+                    let label = trf("report.fetching", &[&done, &ids.len()]);
+                    spinner.set_label(label);
+                    history.push((tx_id.dump_base36(), client.get_transaction(tx_id).await?));
+                }
+                Ok::<_, anyhow::Error>(history)
+            };
+            let _shield = interrupt::handled();
+            let history = tokio::select! {
+                history = fetch => Some(history?),
+                _ = tokio::signal::ctrl_c() => None,
+            };
+            drop(spinner);
+            let Some(history) = history else {
+                println!("{}", tr("report.stopped"));
+                return Ok(());
+            };
 
-            println!("Validating submission...");
-            if client
-                .get_mempool()
-                .await?
-                .iter()
-                .any(|tx| tx.transaction_id == Some(tx_id))
-            {
-                println!("Transaction successfully submitted.");
-                used_session_inputs.extend_from_slice(&used_inputs);
-                println!("Saved spent UTXOs to session.");
-            } else {
-                println!("Transaction failed to submit.");
+            let report = report::build(&history, address, year, book, notes);
+            if report.missing_timestamps > 0 {
+                let message = trf("report.missing_timestamps", &[&report.missing_timestamps]);
+                println!("{}", style::warning(message));
+            }
+            if report.unknown_inputs > 0 {
+                let message = trf("report.unknown_inputs", &[&report.unknown_inputs]);
+                println!("{}", style::warning(message));
             }
+            export::write_atomic(Path::new(out_path), report::csv(&report).as_bytes())?;
+            let count = report.rows.len();
+            let written = trf("report.written", &[&count, &year, &wallet_name, &out_path]);
+            println!("{}", written);
         }
 
-        // ---------------- Wallet management ----------------
-        "wallet" => {
-            if args.is_empty() {
-                println!("Usage: wallet <delete|private|public|switch> [wallet_name]");
+        "snapshot" => {
+            if !args.is_empty() {
+                println!("{}", tr("snapshot.usage"));
                 return Ok(());
             }
-
-            let subcmd = args[0];
-            let name = if args.len() > 1 {
-                args[1]
-            } else {
-                current_wallet.as_str()
+            let seal = match (config.encrypt_snapshots, wallet) {
+                (false, _) => None,
+                (true, WalletKey::Local(private)) => Some(SealKey::of(private)),
+                (true, WalletKey::External(_)) => {
+                    println!("{}", style::error(tr("snapshot.external_signer")));
+                    return Ok(());
+                }
             };
+            let balance = spin(tr("balance.fetching"), client.get_balance(public)).await?;
+            let taken = Snapshot {
+                timestamp: clock::now()?,
+                balance,
+            };
+            let line = snapshot::line(taken, public, seal.as_ref())
+                .ok_or_else(|| anyhow::Error::msg(tr("snapshot.encrypt_failed")))?;
+            snapshot::append(&crate::snapshots_path()?, &line)?;
+            let key = match seal {
+                Some(_) => "snapshot.recorded_encrypted",
+                None => "snapshot.recorded",
+            };
+            say!(
+                "{}",
+                trf(key, &[&amount::display(balance), &current_wallet])
+            );
+        }
 
-            match subcmd {
-                "delete" => {
-                    if !wallets.contains_key(name) {
-                        println!("Wallet '{}' not found.", name);
+        "balance-history" => {
+            let limit = match args.as_slice() {
+                [] => None,
+                ["--limit", n] => match n.parse::<usize>() {
+                    Ok(n) => Some(n),
+                    Err(_) => {
+                        say!("{}", style::error(trf("invalid_limit", &[n])));
                         return Ok(());
                     }
-                    let confirm =
-                        read_pin(&format!("Enter PIN to confirm deletion of '{}': ", name))?;
-                    if confirm != pin {
-                        println!("Incorrect PIN. Wallet not deleted.");
-                        return Ok(());
+                },
+                _ => {
+                    say!("{}", tr("balance_history.usage"));
+                    return Ok(());
+                }
+            };
+            let seal = match wallet {
+                WalletKey::Local(private) => Some(SealKey::of(private)),
+                WalletKey::External(_) => None,
+            };
+            let log = match fs::read_to_string(crate::snapshots_path()?) {
+                Ok(log) => log,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e.into()),
+            };
+            let snapshots = snapshot::series(&log, public, seal.as_ref());
+            if snapshots.is_empty() {
+                println!("{}", trf("balance_history.none", &[&current_wallet]));
+                return Ok(());
+            }
+            let mut out = Output::new();
+            for line in balance_history_lines(&snapshots, limit.unwrap_or(snapshots.len())) {
+                out.line(line);
+            }
+            out.finish();
+        }
+
+        "mempool" => {
+            let Some(mine) = parse_mempool_args(&args) else {
+                println!("{}", tr("mempool.usage"));
+                return Ok(());
+            };
+            let lines = spin(tr("mempool.fetching"), mempool_lines(client, public, mine)).await?;
+            let mut out = Output::new();
+            for line in lines {
+                out.line(line);
+            }
+            out.finish();
+        }
+
+        "watch" => {
+            let Some((watched, interval)) = watch::parse_args(&args) else {
+                println!("{}", tr("watch.usage"));
+                return Ok(());
+            };
+            let (name, watched_args) = (watched[0], &watched[1..]);
+            if !watch::WATCHABLE.contains(&name) {
+                let message = trf("watch.not_watchable", &[&watch::WATCHABLE.join(", ")]);
+                println!("{}", style::error(message));
+                return Ok(());
+            }
+            // Check the arguments once, before the screen is taken over
+            let sort = parse_available_args(watched_args);
+            let mine = parse_mempool_args(watched_args);
+            let valid = match name {
+                "available" => sort.is_some(),
+                "mempool" => mine.is_some(),
+                _ => watched_args.is_empty(),
+            };
+            if !valid {
+                println!("{}", style::error(trf("watch.invalid_args", &[&name])));
+                return Ok(());
+            }
+
+            // One Ctrl+C listener for the whole loop, so a press during a fetch isn't missed
+            let _shield = interrupt::handled();
+            let stop = tokio::signal::ctrl_c();
+            tokio::pin!(stop);
+            let mut screen = watch::Screen::new()?;
+            loop {
+                let fetch = async {
+                    match name {
+                        "available" => {
+                            let sort = sort.unwrap_or("amount");
+                            available_lines(client, public, spent_inputs, sort).await
+                        }
+                        "mempool" => mempool_lines(client, public, mine.unwrap_or(false)).await,
+                        _ => balance_lines(client, public, spent_inputs).await,
+                    }
+                };
+                let lines = tokio::select! {
+                    lines = fetch => lines,
+                    _ = &mut stop => break,
+                };
+                let lines = lines.unwrap_or_else(|e| {
+                    vec![style::error(trf("command_error", &[&e]))]
+                });
+                let time = watch::clock_time(clock::local_now()?);
+                let header = trf("watch.header", &[&interval, &watched.join(" "), &time]);
+                screen.draw(&header, lines)?;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                    _ = &mut stop => break,
+                }
+            }
+        }
+
+        "vanity" => {
+            let [prefix] = args.as_slice() else {
+                println!("{}", tr("vanity.usage"));
+                return Ok(());
+            };
+            let prefix = match vanity::check_prefix(prefix) {
+                Ok(prefix) => prefix,
+                Err(reason) => {
+                    println!("{}", style::error(trf("vanity.invalid_prefix", &[&reason])));
+                    return Ok(());
+                }
+            };
+            // Checked before searching, a key found only to be refused would be lost
+            if !room_for_wallet(wallets, config.max_wallets) {
+                return Ok(());
+            }
+            let expected = vanity::expected_attempts(prefix.len());
+            println!("{}", trf("vanity.expected", &[&format!("{:.0}", expected)]));
+            if prefix.len() >= vanity::SLOW_PREFIX_LEN {
+                println!("{}", style::warning(tr("vanity.slow")));
+                if !confirm(tr("vanity.confirm_start"), false) {
+                    println!("{}", style::warning(tr("cancelled")));
+                    return Ok(());
+                }
+            }
+
+            let _shield = interrupt::handled();
+            let search = vanity::Search::start(&prefix);
+            let spinner = Spinner::start(tr("vanity.searching"));
+            let started = std::time::Instant::now();
+            let found = loop {
+                if let Some(private) = search.result() {
+                    break Some(private);
+                }
+                let attempts = search.attempts();
+                let elapsed = started.elapsed().as_secs_f64();
+                let rate = attempts as f64 / elapsed.max(0.001);
+                let remaining = ((expected - attempts as f64).max(0.0) / rate.max(1.0)) as u64;
+                let (rate, left) = (format!("{:.0}", rate), table::format_age(remaining));
+                spinner.set_label(trf("vanity.progress", &[&attempts, &rate, &left]));
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(200)) => {}
+                    _ = tokio::signal::ctrl_c() => break None,
+                }
+            };
+            drop(spinner);
+            let attempts = search.attempts();
+            drop(search);
+            let Some(private) = found else {
+                println!("{}", trf("vanity.stopped", &[&attempts]));
+                return Ok(());
+            };
+
+            let address = private.to_public().dump_base36();
+            println!("{}", trf("vanity.found", &[&attempts, &address]));
+            if !confirm(tr("vanity.confirm_save"), true) {
+                println!("{}", tr("vanity.not_saved"));
+                return Ok(());
+            }
+            let name = loop {
+                let name = read_input(tr("vanity.name_prompt"))?;
+                if let Err(reason) = sanitize::check_name(&name) {
+                    println!("{}", style::error(trf("invalid_name_retry", &[&reason])));
+                } else if wallets.contains_key(&name) {
+                    println!("{}", style::error(trf("wallet_exists_retry", &[&name])));
+                } else {
+                    break name;
+                }
+            };
+            wallets.insert(name.clone(), WalletKey::Local(private));
+            persist(wallets, book, notes, backups, keys);
+            println!("{}", trf("wallet_created", &[&name]));
+            println!("{}", style::danger(tr("backup.save_key")));
+            println!("{}", trf("backup.key", &[&private.dump_base36()]));
+            println!("{}", style::danger(tr("backup.loss_warning")));
+        }
+
+        "sign" | "prove-ownership" => {
+            if args.is_empty() {
+                println!("{}", tr("sign.usage"));
+                return Ok(());
+            }
+            // Words are rejoined with single spaces, verify does the same
+            let message = args.join(" ");
+            let WalletKey::Local(private) = wallet else {
+                println!("{}", style::error(tr("sign.external_signer")));
+                return Ok(());
+            };
+            if !confirm_pin(tr("prompt.pin_send"), keys)? {
+                println!("{}", style::error(tr("sign.denied")));
+                return Err(ErrorCode::WrongPin.reported());
+            }
+            let signature = sign_message(private, &message);
+            let (address, signature) = (public.dump_base36(), signature.dump_base36());
+            println!("{}", trf("sign.address", &[&address]));
+            println!("{}", trf("sign.message", &[&message]));
+            println!("{}", trf("sign.signature", &[&signature]));
+            let check = trf("sign.check_with", &[&address, &message, &signature]);
+            println!("{}", style::dim(check));
+        }
+
+        "verify" => {
+            let [address, message @ .., signature] = args.as_slice() else {
+                println!("{}", tr("verify.usage"));
+                return Ok(());
+            };
+            if message.is_empty() {
+                println!("{}", tr("verify.usage"));
+                return Ok(());
+            }
+            let address = match sanitize::parse_public(address) {
+                Ok(public) => public,
+                Err(reason) => {
+                    let address = format!("{} ({})", address, reason);
+                    let message = trf("invalid_address", &[&address]);
+                    return Err(ErrorCode::InvalidAddress.error(message));
+                }
+            };
+            let Some(signature) = Signature::new_from_base36(signature) else {
+                println!("{}", style::error(tr("verify.invalid_signature")));
+                return Ok(());
+            };
+            if verify_message(&address, &message.join(" "), &signature) {
+                println!("{}", style::incoming(tr("verify.valid")));
+            } else {
+                println!("{}", style::error(tr("verify.not_valid")));
+            }
+        }
+
+        "file-info" => crate::print_file_info()?,
+
+        "config" => match args.as_slice() {
+            ["show"] => crate::dump_config(startup_settings, config)?,
+            _ => println!("{}", tr("config.usage")),
+        },
+
+        "tx-info" => {
+            if args.len() != 1 {
+                say!("{}", tr("tx_info.usage"));
+                return Ok(());
+            }
+            if let Some(tx_id) = TransactionId::new_from_base36(args[0]) {
+                match client.get_transaction(&tx_id).await? {
+                    Some(tx) if json => {
+                        let note = notes.get(&tx_id.dump_base36()).map(|note| note.text.clone());
+                        let fee = fee_rate(client, &tx).await?;
+                        return json::print(&json::TxInfo::new(tx_id.dump_base36(), &tx, fee, note));
+                    }
+                    Some(tx) => {
+                        let details = style::dim(tx_id.dump_base36());
+                        println!("{}", trf("tx_info.header", &[&details]));
+                        match config.tx_link(&tx_id.dump_base36()) {
+                            Some(link) => println!("{}", trf("explorer", &[&link])),
+                            None => explorer_hint(),
+                        }
+                        if let Some(note) = notes.get(&tx_id.dump_base36()) {
+                            println!("{}", trf("tx_info.note", &[&note.text]));
+                        }
+                        println!("{}", trf("tx_info.timestamp", &[&tx.timestamp]));
+                        println!("{}", trf("tx_info.nonce", &[&tx.nonce]));
+                        println!("{}", trf("tx_info.inputs", &[&tx.inputs.len()]));
+                        for input in &tx.inputs {
+                            let spent = style::dim(input.transaction_id.dump_base36());
+                            let owner = input.output_owner.dump_base36();
+                            let line = trf("tx_info.input", &[&spent, &input.output_index, &owner]);
+                            println!("  {}", line);
+                        }
+                        println!("{}", trf("tx_info.outputs", &[&tx.outputs.len()]));
+                        for output in &tx.outputs {
+                            let amount = amount::display(output.amount);
+                            let receiver = output.receiver.dump_base36();
+                            println!("  {}", trf("tx_info.output", &[&amount, &receiver]));
+                        }
+                        if tx.inputs.is_empty() {
+                            println!("{}", trf("tx_info.fee_reward", &[&transaction_size(&tx)]));
+                        } else {
+                            match fee_rate(client, &tx).await? {
+                                Some(rate) => {
+                                    println!("{}", trf("tx_info.fee", &[&rate.describe()]))
+                                }
+                                None => println!("{}", tr("tx_info.fee_unknown")),
+                            }
+                        }
+                    }
+                    None => say!("{}", style::error(trf("tx_info.not_found", &[&args[0]]))),
+                }
+            } else {
+                say!("{}", style::error(trf("tx_info.invalid_id", &[&args[0]])));
+            }
+        }
+
+        "tx-note" => {
+            let Some(tx_id) = args.first().and_then(|id| TransactionId::new_from_base36(id)) else {
+                println!("{}", tr("tx_note.usage"));
+                return Ok(());
+            };
+            let tx_id = tx_id.dump_base36();
+            if args.len() == 1 {
+                match notes.remove(&tx_id) {
+                    Some(_) => {
+                        persist(wallets, book, notes, backups, keys);
+                        println!("{}", trf("tx_note.removed", &[&style::dim(&tx_id)]));
+                    }
+                    None => println!("{}", trf("tx_note.none", &[&style::dim(&tx_id)])),
+                }
+                return Ok(());
+            }
+            let note = TxNote {
+                text: args[1..].join(" "),
+                created: clock::now()?,
+            };
+            notes.insert(tx_id.clone(), note);
+            persist(wallets, book, notes, backups, keys);
+            println!("{}", trf("tx_note.saved", &[&style::dim(&tx_id)]));
+        }
+
+        "prune" => {
+            let max_age_days = match args.as_slice() {
+                [] => config.note_max_age_days,
+                ["--older-than", days] => match days.parse::<u64>() {
+                    Ok(days) => days,
+                    Err(_) => {
+                        println!("{}", style::error(trf("invalid_days", &[days])));
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    println!("{}", tr("prune.usage"));
+                    return Ok(());
+                }
+            };
+            if notes.is_empty() {
+                println!("{}", tr("prune.no_notes"));
+                return Ok(());
+            }
+
+            // Notes may belong to any wallet, and to sends still waiting in the mempool
+            let mut known = HashSet::new();
+            for wallet in wallets.values() {
+                for tx_id in client.get_transactions_of_address(wallet.public()).await? {
+                    known.insert(tx_id.dump_base36());
+                }
+            }
+            for tx in client.get_mempool().await? {
+                if let Some(tx_id) = tx.transaction_id {
+                    known.insert(tx_id.dump_base36());
+                }
+            }
+            let max_age = (max_age_days > 0).then_some(max_age_days * 24 * 60 * 60);
+            let candidates = prune_candidates(notes, &known, clock::now()?, max_age);
+            if candidates.is_empty() {
+                println!("{}", tr("prune.nothing"));
+                return Ok(());
+            }
+
+            println!("{}", tr("prune.candidates"));
+            for (tx_id, reason) in &candidates {
+                let text = &notes[tx_id].text;
+                println!("  - {} ({}): {}", style::dim(tx_id), reason.describe(), text);
+            }
+            if !confirm(&trf("prune.confirm", &[&candidates.len()]), false) {
+                println!("{}", style::warning(tr("cancelled")));
+                return Ok(());
+            }
+            for (tx_id, _) in &candidates {
+                notes.remove(tx_id);
+            }
+            persist(wallets, book, notes, backups, keys);
+            println!("{}", trf("prune.done", &[&candidates.len()]));
+        }
+
+        "send" => {
+            // Snap Coin transactions only carry inputs and outputs, there is nowhere to put data
+            if args.contains(&"--data") {
+                say!("{}", style::error(tr("send.data_unsupported")));
+                return Ok(());
+            }
+            // --no-refresh skips checking the inputs again before submitting, for tight loops
+            let refresh = !args.contains(&"--no-refresh");
+            args.retain(|arg| *arg != "--no-refresh");
+            // --expect-tx <txid> sends that transaction or nothing, for RPC `confirm_send`
+            let expected = match args.iter().position(|arg| *arg == "--expect-tx") {
+                Some(at) => {
+                    let id = args.get(at + 1).and_then(|id| TransactionId::new_from_base36(id));
+                    let Some(id) = id else {
+                        say!("{}", tr("send.usage"));
+                        return Ok(());
+                    };
+                    args.drain(at..at + 2);
+                    Some(id)
+                }
+                None => None,
+            };
+            let check_expected = |tx_id: TransactionId| match expected {
+                Some(expected) if expected != tx_id => {
+                    let (built, expected) = (tx_id.dump_base36(), expected.dump_base36());
+                    Err(ErrorCode::Cancelled.error(trf("send.unexpected_tx", &[&built, &expected])))
+                }
+                _ => Ok(()),
+            };
+            let wizard_args;
+            if args.is_empty() {
+                let Some(answers) = send_wizard(client, book, public, spent_inputs).await?
+                else {
+                    say!("{}", style::warning(tr("cancelled")));
+                    return Ok(());
+                };
+                wizard_args = answers;
+                args = wizard_args.iter().map(String::as_str).collect();
+            }
+            // Labels are only kept as the local note of the transaction, never broadcast
+            let labels = match split_labels(&args) {
+                Ok((pairs, labels)) => {
+                    args = pairs;
+                    labels
+                }
+                Err(reason) => {
+                    say!("{}", style::error(trf("send.invalid_label", &[&reason])));
+                    return Ok(());
+                }
+            };
+            if !args.len().is_multiple_of(2) || args.len() < 2 {
+                say!("{}", tr("send.usage"));
+                return Ok(());
+            }
+
+            // Percentages are of what can be sent now, only looked up when one is given
+            let spendable = match args.iter().skip(1).step_by(2).any(|a| amount::is_percentage(a)) {
+                true => spendable::fetch(client, public, spent_inputs).await?.spendable(),
+                false => 0,
+            };
+
+            let Payments {
+                payments,
+                recipients,
+                shares,
+                labels: recipient_labels,
+            } = match check_payments(&args, labels, book, spendable) {
+                Ok(checked) => checked,
+                Err(problems) => {
+                    for problem in &problems {
+                        say!("{}", style::error(exit_code::describe(problem)));
+                    }
+                    say!("{}", tr("send.nothing_sent"));
+                    return Err(ErrorCode::of(&problems[0]).reported());
+                }
+            };
+
+            // Inputs are picked from a listing fetched from the node for this build, never from
+            // an earlier command's
+            let transaction =
+                build_transaction(client, wallet.signer(), payments.clone(), spent_inputs)
+                    .await;
+            if let Err(ref e) = transaction {
+                say!("{}", style::error(trf("send.build_failed", &[&exit_code::describe(e)])));
+                // Explain a balance that can't be spent instead of leaving only the build error
+                let breakdown = spendable::fetch(client, public, spent_inputs).await?;
+                if breakdown.differs() {
+                    say!("{}", trf("balance", &[&amount::display(breakdown.balance)]));
+                    for line in spendable_lines(&breakdown) {
+                        say!("{}", line);
+                    }
+                }
+                return Err(ErrorCode::of(e).reported());
+            }
+
+            let (mut transaction, mut difficulty) =
+                finish_pow(client, pow_cache, transaction.unwrap()).await?;
+            let mut tx_id = transaction.transaction_id.unwrap();
+            say!("{}", trf("send.created", &[&style::dim(tx_id.dump_base36())]));
+            check_expected(tx_id)?;
+
+            print_send_summary(book, &recipients, &payments, &shares, &recipient_labels);
+            let mut rate = fee_rate(client, &transaction).await?;
+            if let Some(rate) = &rate {
+                say!("{}", trf("send.fee", &[&rate.describe()]));
+            }
+
+            if !confirm(tr("send.confirm"), true) {
+                say!("{}", style::warning(tr("cancelled")));
+                return Ok(());
+            }
+            if !confirm_pin(tr("prompt.pin_send"), keys)? {
+                say!("{}", style::error(tr("pin.incorrect")));
+                return Err(ErrorCode::WrongPin.reported());
+            }
+
+            // Another instance of the wallet may have spent an input since the build, which the
+            // node would refuse. The same payments are built again from what is unspent now,
+            // which changes the inputs, change and fee, so they are confirmed again
+            if refresh {
+                let inputs = &transaction.inputs;
+                let check = node::missing_inputs(client, public, inputs);
+                let missing = spin(tr("send.checking_inputs"), check).await?;
+                if missing > 0 {
+                    say!("{}", style::warning(trf("send.inputs_spent", &[&missing])));
+                    pow_cache.remove(&transaction);
+                    let rebuilt =
+                        build_transaction(client, wallet.signer(), payments.clone(), spent_inputs)
+                            .await?;
+                    (transaction, difficulty) = finish_pow(client, pow_cache, rebuilt).await?;
+                    tx_id = transaction.transaction_id.unwrap();
+                    say!("{}", trf("send.created", &[&style::dim(tx_id.dump_base36())]));
+                    check_expected(tx_id)?;
+                    print_send_summary(book, &recipients, &payments, &shares, &recipient_labels);
+                    rate = fee_rate(client, &transaction).await?;
+                    if let Some(rate) = &rate {
+                        say!("{}", trf("send.fee", &[&rate.describe()]));
+                    }
+                    if !confirm(tr("send.confirm_rebuilt"), true) {
+                        say!("{}", style::warning(tr("cancelled")));
+                        return Ok(());
+                    }
+                }
+            }
+
+            // The network may have got harder while the PoW was computed or the user decided
+            let live_difficulty = client.get_live_transaction_difficulty().await?;
+            if difficulty_increased(&difficulty, &live_difficulty) {
+                say!("{}", style::warning(tr("send.difficulty_increased")));
+                if !confirm(tr("send.recompute_pow"), true) {
+                    say!("{}", style::warning(tr("cancelled")));
+                    return Ok(());
+                }
+                say!("{}", tr("send.computing_pow"));
+                difficulty = live_difficulty;
+                transaction.compute_pow(&difficulty, Some(0.1f64))?;
+                pow_cache.insert(transaction.clone(), difficulty);
+                tx_id = transaction.transaction_id.unwrap();
+                say!("{}", trf("send.created", &[&style::dim(tx_id.dump_base36())]));
+                check_expected(tx_id)?;
+            }
+
+            say!("{}", tr("send.submitting"));
+
+            // Once submitted, the inputs must be recorded as spent whatever Ctrl+C says
+            let _shield = interrupt::critical();
+            let used_inputs = transaction.inputs.clone();
+            // Kept in the cache until the node has seen it, a network error means a free retry
+            let submitted = transaction.clone();
+            let submit = client.submit_transaction(transaction);
+            let status = match spin(tr("send.submitting_short"), submit).await {
+                Ok(status) => status,
+                Err(e) => {
+                    if config.bell_enabled() {
+                        style::bell();
+                    }
+                    return Err(e.into());
+                }
+            };
+            say!("{}", trf("send.status", &[&format!("{:?}", status)]));
+
+            say!("{}", tr("send.validating"));
+            let accepted = spin(tr("send.checking_mempool"), client.get_mempool())
+                .await?
+                .iter()
+                .any(|tx| tx.transaction_id == Some(tx_id));
+            if accepted {
+                pow_cache.remove(&submitted);
+                say!("{}", tr("send.submitted"));
+                match config.tx_link(&tx_id.dump_base36()) {
+                    Some(link) => say!("{}", trf("explorer", &[&link])),
+                    None => explorer_hint(),
+                }
+                used_session_inputs.add(public, &used_inputs);
+                say!("{}", tr("send.saved_inputs"));
+                if let Some(text) = send_note(&recipients, &recipient_labels) {
+                    say!("{}", trf("send.label_saved", &[&text]));
+                    let note = TxNote {
+                        text,
+                        created: clock::now()?,
+                    };
+                    notes.insert(tx_id.dump_base36(), note);
+                    persist(wallets, book, notes, backups, keys);
+                }
+                for recipient in recipients.iter().filter(|r| !book.contains_key(**r)) {
+                    recent_addresses.retain(|address| address != recipient);
+                    recent_addresses.insert(0, recipient.to_string());
+                }
+                recent_addresses.truncate(MAX_RECENT_ADDRESSES);
+                // Posted once confirmed, never for a transaction that is only submitted
+                if let Some(endpoint) = config.webhook() {
+                    let sent = webhook::Sent {
+                        tx_id,
+                        wallet: current_wallet.clone(),
+                        payments: json::Payment::list(&recipients, &payments),
+                    };
+                    webhook::track(endpoint, sent);
+                }
+            } else {
+                // The node answered but refused it, the same work would only be refused again
+                pow_cache.remove(&submitted);
+                let code = ErrorCode::Rejected;
+                let failed = trf("error_code.with_code", &[&code, &tr("send.failed")]);
+                say!("{}", style::error(failed));
+            }
+            if config.bell_enabled() {
+                style::bell();
+            }
+            if json {
+                json::print(&json::SendResult {
+                    tx_id: tx_id.dump_base36(),
+                    accepted,
+                    payments: json::Payment::list(&recipients, &payments),
+                    fee: rate.map(|rate| rate.fee.into()),
+                })?;
+            }
+            if !accepted {
+                return Err(ErrorCode::Rejected.reported());
+            }
+        }
+
+        "status" => {
+            // Meant to be pasted into bug reports: nothing here is secret
+            println!("{}", trf("status.version", &[&env!("CARGO_PKG_VERSION")]));
+            let address = style::dim(public.dump_base36());
+            println!("{}", trf("status.wallet", &[&current_wallet, &address]));
+            let signing = match wallet {
+                WalletKey::Local(_) => tr("status.local_key"),
+                WalletKey::External(_) => tr("status.external_signer"),
+            };
+            println!("{}", trf("status.signing", &[&signing]));
+            match crate::wallet_path() {
+                Ok(path) => println!("{}", trf("status.file", &[&path.display()])),
+                Err(e) => println!("{}", trf("status.file_unknown", &[&e])),
+            }
+            let unsaved = match (has_unsaved_changes(), config.autosave) {
+                (true, _) => style::warning(tr("status.unsaved_yes")),
+                (false, true) => tr("status.unsaved_autosave_on").to_string(),
+                (false, false) => tr("status.unsaved_autosave_off").to_string(),
+            };
+            println!("{}", trf("status.unsaved", &[&unsaved]));
+            // Each wallet's apart, as they only ever limit what that wallet can spend
+            let session_spent: Vec<String> = wallets
+                .iter()
+                .map(|(name, key)| (name, used_session_inputs.of(key.public()).len()))
+                .filter(|(_, count)| *count > 0)
+                .map(|(name, count)| trf("status.spent_in", &[&count, name]))
+                .collect();
+            match session_spent.is_empty() {
+                true => println!("{}", tr("status.session_spent_none")),
+                false => println!(
+                    "{}",
+                    trf("status.session_spent", &[&session_spent.join(", ")])
+                ),
+            }
+            // The wallet has no outbox, spend limit or idle lock: sends are submitted as they
+            // are made, and the session only locks on `suspend`. Said so rather than left out
+            println!("{}", tr("status.outbox"));
+            println!("{}", tr("status.spend_limit"));
+            match config.pin_timeout {
+                0 => println!("{}", tr("status.auto_lock_off")),
+                seconds => println!("{}", trf("status.auto_lock_timeout", &[&seconds])),
+            }
+
+            // One mempool call shows whether the node answers, what is pending and network time
+            let started = Instant::now();
+            let mempool = match tokio::time::timeout(STATUS_TIMEOUT, client.get_mempool()).await {
+                Ok(Ok(mempool)) => mempool,
+                Ok(Err(e)) => {
+                    let health = style::error(trf("status.node_unreachable", &[&e]));
+                    println!("{}", trf("status.node", &[&node, &health]));
+                    return Ok(());
+                }
+                Err(_) => {
+                    let health = trf("status.node_timeout", &[&STATUS_TIMEOUT.as_secs()]);
+                    println!("{}", trf("status.node", &[&node, &style::error(health)]));
+                    return Ok(());
+                }
+            };
+            let millis = started.elapsed().as_millis();
+            println!("{}", trf("status.node_answered", &[&node, &millis]));
+            let pending = mempool
+                .iter()
+                .filter(|tx| {
+                    let effect = TxEffect::of(tx, public);
+                    effect.received > 0 || effect.is_outgoing()
+                })
+                .count();
+            println!("{}", trf("status.pending", &[&pending]));
+            // Checked again, the mempool may have filled up since connecting
+            let timestamps: Vec<u64> = mempool.iter().map(|tx| tx.timestamp).collect();
+            if let Some(skew) = clock::estimate_skew(clock::local_now()?, &timestamps) {
+                *clock_skew = Some(skew);
+                clock::set_correction(*clock_skew);
+            }
+            match *clock_skew {
+                Some(skew) if clock::is_significant(skew) => {
+                    let key = if skew > 0 {
+                        "status.clock_ahead"
+                    } else {
+                        "status.clock_behind"
+                    };
+                    let skew = style::warning(trf(key, &[&skew.unsigned_abs()]));
+                    println!("{}", trf("status.clock", &[&skew]));
+                }
+                Some(skew) => {
+                    println!("{}", trf("status.clock_in_sync", &[&format!("{:+}", skew)]))
+                }
+                None => println!("{}", tr("status.clock_unknown")),
+            }
+        }
+
+        "diagnose" => {
+            let path = crate::wallet_path()?;
+            let (findings, skew) =
+                diagnose::run(client, *node, public, spent_inputs, &path, keys).await;
+            if skew.is_some() {
+                *clock_skew = skew;
+                clock::set_correction(*clock_skew);
+            }
+            for finding in &findings {
+                println!("{} {}: {}", finding.status.tag(), finding.check, finding.detail);
+                if let Some(hint) = finding.hint {
+                    println!("       {}", style::dim(hint));
+                }
+            }
+            let count = |status| findings.iter().filter(|f| f.status == status).count();
+            let failed = count(diagnose::Status::Fail);
+            let (passed, warned) = (count(diagnose::Status::Pass), count(diagnose::Status::Warn));
+            let skipped = count(diagnose::Status::Skip);
+            println!(
+                "{}",
+                trf("diagnose.summary", &[&passed, &warned, &failed, &skipped])
+            );
+            if failed > 0 {
+                return Err(ErrorCode::Failed.reported());
+            }
+        }
+
+        "log" => match args.as_slice() {
+            ["path"] => {
+                let path = oplog::path().ok_or_else(|| anyhow::Error::msg(tr("error.home_dir")))?;
+                println!("{}", path.display());
+                if !oplog::enabled() {
+                    let hint = tr("log.off");
+                    println!("{}", style::dim(hint));
+                }
+            }
+            _ => println!("{}", tr("log.usage")),
+        },
+
+        "session" => match args.first() {
+            None => {
+                let header = trf("session.header", &[&current_wallet, &spent_inputs.len()]);
+                println!("{}", header);
+                for input in spent_inputs {
+                    let tx_id = style::dim(input.transaction_id.dump_base36());
+                    println!(
+                        "  - {}",
+                        trf("session.input", &[&tx_id, &input.output_index])
+                    );
+                }
+            }
+            Some(&"reset") => {
+                // Only this wallet's unless --all, the others' transactions may still be pending
+                let all = args.get(1) == Some(&"--all");
+                let tracked = match all {
+                    true => used_session_inputs.len(),
+                    false => spent_inputs.len(),
+                };
+                if tracked == 0 {
+                    println!("{}", tr("session.none"));
+                    return Ok(());
+                }
+                println!("{}", style::warning(tr("session.reset_warning")));
+                println!("{}", style::warning(tr("session.reset_risk")));
+                if !confirm(tr("session.reset_confirm"), false) {
+                    println!("{}", tr("session.not_cleared"));
+                    return Ok(());
+                }
+                let cleared = match all {
+                    true => used_session_inputs.clear_all(),
+                    false => used_session_inputs.clear(public),
+                };
+                println!("{}", trf("session.cleared", &[&cleared]));
+            }
+            Some(subcmd) => println!("{}", style::error(trf("session.unknown", &[subcmd]))),
+        },
+
+        "contact" => match args.first() {
+            Some(&"add") => {
+                if args.len() != 3 {
+                    println!("{}", tr("contact.usage_add"));
+                    return Ok(());
+                }
+                if let Err(reason) = sanitize::check_name(args[1]) {
+                    println!("{}", style::error(trf("contact.invalid_alias", &[&reason])));
+                    return Ok(());
+                }
+                if book.contains_key(args[1]) {
+                    println!("{}", style::error(trf("contact.exists", &[&args[1]])));
+                    return Ok(());
+                }
+                if Public::new_from_base36(args[1]).is_some() {
+                    println!("{}", style::error(tr("contact.alias_is_address")));
+                    return Ok(());
+                }
+                let public = match sanitize::parse_public(args[2]) {
+                    Ok(public) => public,
+                    Err(reason) => {
+                        let address = format!("{} ({})", args[2], reason);
+                        let message = trf("invalid_address", &[&address]);
+                        return Err(ErrorCode::InvalidAddress.error(message));
+                    }
+                };
+                book.insert(
+                    args[1].to_string(),
+                    Contact {
+                        public,
+                        note: String::new(),
+                    },
+                );
+                persist(wallets, book, notes, backups, keys);
+                println!("{}", trf("contact.added", &[&args[1]]));
+            }
+            Some(&"remove") => {
+                if args.len() != 2 {
+                    println!("{}", tr("contact.usage_remove"));
+                    return Ok(());
+                }
+                if book.remove(args[1]).is_none() {
+                    println!("{}", style::error(trf("contact.not_found", &[&args[1]])));
+                    return Ok(());
+                }
+                persist(wallets, book, notes, backups, keys);
+                println!("{}", trf("contact.removed", &[&args[1]]));
+            }
+            Some(&"rename") => {
+                if args.len() != 3 {
+                    println!("{}", tr("contact.usage_rename"));
+                    return Ok(());
+                }
+                let (old, new) = (args[1], args[2]);
+                if let Err(reason) = sanitize::check_name(new) {
+                    println!("{}", style::error(trf("contact.invalid_alias", &[&reason])));
+                    return Ok(());
+                }
+                if book.contains_key(new) {
+                    println!("{}", style::error(trf("contact.exists", &[&new])));
+                    return Ok(());
+                }
+                if Public::new_from_base36(new).is_some() {
+                    println!("{}", style::error(tr("contact.alias_is_address")));
+                    return Ok(());
+                }
+                let Some(contact) = book.remove(old) else {
+                    println!("{}", style::error(trf("contact.not_found", &[&old])));
+                    return Ok(());
+                };
+                book.insert(new.to_string(), contact);
+                persist(wallets, book, notes, backups, keys);
+                println!("{}", trf("contact.renamed", &[&old, &new]));
+            }
+            Some(&"note") => {
+                if args.len() < 2 {
+                    println!("{}", tr("contact.usage_note"));
+                    return Ok(());
+                }
+                let Some(contact) = book.get_mut(args[1]) else {
+                    println!("{}", style::error(trf("contact.not_found", &[&args[1]])));
+                    return Ok(());
+                };
+                contact.note = args[2..].join(" ");
+                let cleared = contact.note.is_empty();
+                persist(wallets, book, notes, backups, keys);
+                if cleared {
+                    println!("{}", trf("contact.note_cleared", &[&args[1]]));
+                } else {
+                    println!("{}", trf("contact.note_updated", &[&args[1]]));
+                }
+            }
+            Some(&"list") | None => {
+                let mut out = Output::new();
+                out.line(trf("contact.header", &[&book.len()]));
+                for (alias, contact) in book.iter() {
+                    if contact.note.is_empty() {
+                        out.line(format!("  - {}: {}", alias, contact.public.dump_base36()));
+                    } else {
+                        out.line(format!(
+                            "  - {}: {} ({})",
+                            alias,
+                            contact.public.dump_base36(),
+                            contact.note
+                        ));
+                    }
+                }
+                out.finish();
+            }
+            Some(subcmd) => println!("{}", style::error(trf("contact.unknown", &[subcmd]))),
+        },
+
+        "alias" => match args.first() {
+            Some(&"add") => {
+                if args.len() < 3 {
+                    println!("{}", tr("alias.usage_add"));
+                    return Ok(());
+                }
+                let name = args[1];
+                if commands::find(name).is_some() || SHORT_COMMANDS.contains(&name) {
+                    println!("{}", style::error(trf("alias.builtin", &[&name])));
+                    return Ok(());
+                }
+                let expansion = args[2..].join(" ");
+                let expansion = expansion.trim_matches('"').trim().to_string();
+                let mut updated = config.clone();
+                updated.aliases.insert(name.to_string(), expansion);
+                if let Err(e) = updated.expand_alias(name) {
+                    println!("{}", style::error(trf("alias.not_added", &[&e])));
+                    return Ok(());
+                }
+                updated.save()?;
+                *config = updated;
+                println!("{}", trf("alias.added", &[&name]));
+            }
+            Some(&"remove") => {
+                if args.len() != 2 {
+                    println!("{}", tr("alias.usage_remove"));
+                    return Ok(());
+                }
+                if config.aliases.remove(args[1]).is_none() {
+                    println!("{}", style::error(trf("alias.not_found", &[&args[1]])));
+                    return Ok(());
+                }
+                config.save()?;
+                println!("{}", trf("alias.removed", &[&args[1]]));
+            }
+            Some(&"list") | None => {
+                println!("{}", trf("alias.header", &[&config.aliases.len()]));
+                for (name, expansion) in &config.aliases {
+                    println!("  - {} = {}", name, expansion);
+                }
+            }
+            Some(subcmd) => println!("{}", style::error(trf("alias.unknown", &[subcmd]))),
+        },
+
+        // ---------------- Wallet management ----------------
+        "wallet" => {
+            if args.is_empty() {
+                println!("{}", tr("wallet.usage"));
+                return Ok(());
+            }
+
+            let subcmd = args[0];
+            let name = if args.len() > 1 {
+                args[1]
+            } else {
+                current_wallet.as_str()
+            };
+
+            match subcmd {
+                "list" => {
+                    let balances = match &args[1..] {
+                        [] => false,
+                        ["--balances"] => true,
+                        _ => {
+                            println!("{}", tr("wallet.usage_list"));
+                            return Ok(());
+                        }
+                    };
+                    let names: Vec<&String> = wallets.keys().collect();
+
+                    let mut columns = vec![
+                        Column::left(tr("column.wallet")),
+                        Column::shrinking(tr("column.address")),
+                        Column::left(tr("column.type")),
+                    ];
+                    if balances {
+                        columns.push(Column::right(tr("column.balance")));
+                    }
+                    let spinner = Spinner::start(tr("wallet.fetching_balances"));
+                    let mut rows = Vec::new();
+                    for name in names {
+                        let wallet = &wallets[name];
+                        let marker = if *name == *current_wallet { "*" } else { " " };
+                        let kind = match wallet {
+                            WalletKey::Local(_) => tr("wallet.kind_local"),
+                            WalletKey::External(_) => tr("wallet.kind_external"),
+                        };
+                        let mut row = vec![
+                            format!("{} {}", marker, name),
+                            wallet.public().dump_base36(),
+                            kind.to_string(),
+                        ];
+                        if balances {
+                            let balance = client.get_balance(wallet.public()).await?;
+                            row.push(format_amount(balance));
+                        }
+                        rows.push(row);
+                    }
+                    drop(spinner);
+                    let mut out = Output::new();
+                    for line in table::render(&columns, &rows) {
+                        out.line(line);
+                    }
+                    out.finish();
+                }
+
+                "delete" => {
+                    if !wallets.contains_key(name) {
+                        println!("{}", style::error(trf("wallet_not_found", &[&name])));
+                        return Ok(());
+                    }
+                    if !confirm(&trf("wallet.confirm_delete", &[&name]), false) {
+                        println!("{}", style::warning(tr("cancelled")));
+                        return Ok(());
+                    }
+                    if !confirm_pin(&trf("prompt.pin_delete", &[&name]), keys)? {
+                        println!("{}", style::error(tr("wallet.not_deleted")));
+                        return Err(ErrorCode::WrongPin.reported());
                     }
                     wallets.remove(name);
-                    persist(wallets, pin);
-                    println!("Wallet '{}' deleted.", name);
+                    backups.remove(name);
+                    persist(wallets, book, notes, backups, keys);
+                    println!("{}", trf("wallet.deleted", &[&name]));
 
                     if current_wallet == name {
                         if let Some(first) = wallets.keys().next() {
                             *current_wallet = first.clone();
-                            println!("Switched to wallet '{}'.", current_wallet);
+                            println!("{}", trf("wallet.switched", &[&*current_wallet]));
                         } else {
-                            return Err(anyhow::Error::msg("No wallets remaining."));
+                            return Err(
+                                FatalError(tr("wallet.none_remaining").to_string()).into()
+                            );
+                        }
+                    }
+                }
+
+                "private" => {
+                    let mut name = current_wallet.as_str();
+                    let (mut screen, mut qr_only, mut checksummed) = (false, false, false);
+                    for (i, &arg) in args[1..].iter().enumerate() {
+                        match arg {
+                            "--screen" => screen = true,
+                            "--qr" => qr_only = true,
+                            "--checksummed" => checksummed = true,
+                            _ if i == 0 => name = arg,
+                            _ => {
+                                println!("{}", tr("wallet.usage_private"));
+                                return Ok(());
+                            }
                         }
                     }
+                    let wallet = match wallets.get(name) {
+                        Some(w) => w,
+                        None => {
+                            println!("{}", style::error(trf("wallet_not_found", &[&name])));
+                            return Ok(());
+                        }
+                    };
+                    if !confirm_pin(&trf("prompt.pin_private", &[&name]), keys)? {
+                        println!("{}", style::error(tr("wallet.private_denied")));
+                        return Err(ErrorCode::WrongPin.reported());
+                    }
+                    // The checksum makes a mistyped character fail on import, for writing down
+                    let export = |private: &Private| match checksummed {
+                        true => sanitize::dump_checksummed(private),
+                        false => Zeroizing::new(private.dump_base36()),
+                    };
+                    match wallet {
+                        WalletKey::Local(private) if screen || qr_only => {
+                            let key = export(private);
+                            let title = trf("wallet.private_key", &[&name, ""]);
+                            let text = match qr_only {
+                                true => qr::render(&key)?,
+                                false => Zeroizing::new(format!("{}{}", title, *key)),
+                            };
+                            let mut lines = vec![];
+                            if qr_only {
+                                lines.push(title.trim_end());
+                            }
+                            lines.extend(text.lines());
+                            show_secret(&lines)?;
+                        }
+                        WalletKey::Local(private) => {
+                            let shown = trf("wallet.private_key", &[&name, &*export(private)]);
+                            println!("{}", style::danger(shown));
+                            println!("{}", style::danger(tr("backup.theft_warning")));
+                        }
+                        WalletKey::External(external) => println!(
+                            "{}",
+                            trf("wallet.external_no_key", &[&name, &external.command])
+                        ),
+                    }
+                }
+
+                "confirm-backup" => {
+                    let Some(wallet) = wallets.get(name) else {
+                        println!("{}", style::error(trf("wallet_not_found", &[&name])));
+                        return Ok(());
+                    };
+                    let WalletKey::Local(private) = wallet else {
+                        println!("{}", trf("backup.no_key", &[&name]));
+                        return Ok(());
+                    };
+                    // Asked for a random part of the key, which only a copy of it can answer
+                    let key = Zeroizing::new(private.dump_base36());
+                    let start = backup::challenge_start(key.len());
+                    let from = start + 1;
+                    let to = start + backup::CHALLENGE_LEN;
+                    let answer = Zeroizing::new(read_input(&trf(
+                        "backup.challenge",
+                        &[&from, &to, &name],
+                    ))?);
+                    if !backup::check_answer(&key, start, &answer) {
+                        println!("{}", style::error(tr("backup.mismatch")));
+                        return Ok(());
+                    }
+                    backups.insert(name.to_string(), clock::now()?);
+                    persist(wallets, book, notes, backups, keys);
+                    println!("{}", trf("backup.confirmed", &[&name]));
+                }
+
+                "public" => {
+                    let wallet = match wallets.get(name) {
+                        Some(w) => w,
+                        None => {
+                            println!("{}", style::error(trf("wallet_not_found", &[&name])));
+                            return Ok(());
+                        }
+                    };
+                    let address = wallet.public().dump_base36();
+                    println!("{}", trf("wallet.public_key", &[&name, &address]));
+                    if let Some(link) = config.address_link(&address) {
+                        println!("{}", trf("explorer", &[&link]));
+                    }
+                }
+
+                "add-external" => {
+                    if args.len() < 4 {
+                        println!("{}", tr("wallet.usage_add_external"));
+                        return Ok(());
+                    }
+                    if let Err(reason) = sanitize::check_name(name) {
+                        println!("{}", style::error(trf("wallet.invalid_name", &[&reason])));
+                        return Ok(());
+                    }
+                    if wallets.contains_key(name) {
+                        println!("{}", style::error(trf("wallet.exists", &[&name])));
+                        return Ok(());
+                    }
+                    if !room_for_wallet(wallets, config.max_wallets) {
+                        return Ok(());
+                    }
+                    let public = match sanitize::parse_public(args[2]) {
+                        Ok(public) => public,
+                        Err(reason) => {
+                            let address = format!("{} ({})", args[2], reason);
+                            let message = trf("invalid_address", &[&address]);
+                            return Err(ErrorCode::InvalidAddress.error(message));
+                        }
+                    };
+                    wallets.insert(
+                        name.to_string(),
+                        WalletKey::External(ExternalSigner {
+                            public,
+                            command: args[3..].join(" "),
+                        }),
+                    );
+                    persist(wallets, book, notes, backups, keys);
+                    println!("{}", trf("wallet.external_added", &[&name]));
                 }
 
-                "private" => {
-                    let wallet = match wallets.get(name) {
-                        Some(w) => w,
-                        None => {
-                            println!("Wallet '{}' not found.", name);
+                "import-qr" => {
+                    let Some(path) = args.get(1) else {
+                        println!("{}", tr("wallet.usage_import_qr"));
+                        return Ok(());
+                    };
+                    if !room_for_wallet(wallets, config.max_wallets) {
+                        return Ok(());
+                    }
+                    let payload = qr::decode_file(Path::new(path))?;
+                    let private = match qr::parse_payload(&payload) {
+                        Ok(private) => private,
+                        Err(reason) => {
+                            let message = trf("wallet.invalid_qr_key", &[&reason]);
+                            println!("{}", style::error(message));
                             return Ok(());
                         }
                     };
-                    let confirm =
-                        read_pin(&format!("Enter PIN to view private key of '{}': ", name))?;
-                    if confirm != pin {
-                        println!("Incorrect PIN. Cannot show private key.");
+                    let public = private.to_public();
+                    let existing = wallets.iter().find(|(_, key)| key.public() == public);
+                    if let Some((existing, _)) = existing {
+                        let message = trf("wallet.key_exists", &[existing]);
+                        println!("{}", style::error(message));
+                        return Ok(());
+                    }
+                    println!("{}", trf("wallet.qr_address", &[&public.dump_base36()]));
+
+                    let new_name = match args.get(2) {
+                        Some(new_name) => new_name.to_string(),
+                        None => read_input(tr("prompt.new_wallet_name"))?,
+                    };
+                    if let Err(reason) = sanitize::check_name(&new_name) {
+                        println!("{}", style::error(trf("wallet.invalid_name", &[&reason])));
+                        return Ok(());
+                    }
+                    if wallets.contains_key(&new_name) {
+                        let message = trf("wallet.exists", &[&new_name]);
+                        println!("{}", style::error(message));
+                        return Ok(());
+                    }
+                    if !confirm(&trf("wallet.confirm_import_qr", &[&new_name]), false) {
+                        println!("{}", style::warning(tr("cancelled")));
                         return Ok(());
                     }
-                    println!("Private key of '{}': {}", name, wallet.dump_base36());
+                    wallets.insert(new_name.clone(), WalletKey::Local(private));
+                    persist(wallets, book, notes, backups, keys);
+                    println!("{}", trf("wallet_created", &[&new_name]));
                 }
 
-                "public" => {
-                    let wallet = match wallets.get(name) {
-                        Some(w) => w,
-                        None => {
-                            println!("Wallet '{}' not found.", name);
+                "import-json" => {
+                    let (path, dry_run) = match &args[1..] {
+                        [path] => (*path, false),
+                        [path, "--dry-run"] | ["--dry-run", path] => (*path, true),
+                        _ => {
+                            println!("{}", tr("wallet.usage_import_json"));
                             return Ok(());
                         }
                     };
-                    println!(
-                        "Public key of '{}': {}",
-                        name,
-                        wallet.to_public().dump_base36()
-                    );
+                    let text = fs::read_to_string(path)
+                        .map_err(|e| anyhow::Error::msg(trf("read_failed", &[&path, &e])))?;
+                    let text = Zeroizing::new(text);
+                    let entries = match keystore::plan(&text, wallets, config.max_wallets) {
+                        Ok(entries) => entries,
+                        Err(reason) => {
+                            let message = trf("wallet.not_keystore", &[&reason]);
+                            println!("{}", style::error(message));
+                            return Ok(());
+                        }
+                    };
+                    let mut new_wallets = Vec::new();
+                    let (mut duplicates, mut invalid) = (0, 0);
+                    for entry in entries {
+                        let label = match &entry.name {
+                            Some(name) => format!("#{} '{}'", entry.number, name),
+                            None => format!("#{}", entry.number),
+                        };
+                        match entry.outcome {
+                            keystore::Outcome::Import(private) => {
+                                let address = private.to_public().dump_base36();
+                                let action = match dry_run {
+                                    true => tr("wallet.would_import"),
+                                    false => tr("wallet.to_import"),
+                                };
+                                println!("{}: {} {}", label, action, style::dim(address));
+                                new_wallets.push((entry.name.unwrap_or_default(), private));
+                            }
+                            keystore::Outcome::Duplicate(existing) => {
+                                duplicates += 1;
+                                let message = trf("wallet.import_duplicate", &[&existing]);
+                                println!("{}: {}", label, style::warning(message));
+                            }
+                            keystore::Outcome::Invalid(reason) => {
+                                invalid += 1;
+                                let message = trf("wallet.import_invalid", &[&reason]);
+                                println!("{}: {}", label, style::error(message));
+                            }
+                        }
+                    }
+                    let found = new_wallets.len();
+                    let summary = trf("wallet.import_summary", &[&found, &duplicates, &invalid]);
+                    println!("{}", summary);
+                    if dry_run || new_wallets.is_empty() {
+                        return Ok(());
+                    }
+                    let question = trf("wallet.confirm_import_json", &[&new_wallets.len()]);
+                    if !confirm(&question, false) {
+                        println!("{}", style::warning(tr("cancelled")));
+                        return Ok(());
+                    }
+                    let count = new_wallets.len();
+                    for (new_name, private) in new_wallets {
+                        wallets.insert(new_name, WalletKey::Local(private));
+                    }
+                    // Once for the whole file, not once per wallet
+                    persist(wallets, book, notes, backups, keys);
+                    println!("{}", trf("wallet.imported", &[&count]));
                 }
 
                 "switch" => {
                     if !wallets.contains_key(name) {
-                        println!("Wallet '{}' not found.", name);
+                        println!("{}", style::error(trf("wallet_not_found", &[&name])));
                         return Ok(());
                     }
                     save_last_login(name.to_string())?;
+                    // Nothing fetched for the previous wallet is kept: every read command asks
+                    // the node about the current wallet, and session-spent inputs only ever
+                    // match the outputs of the wallet that spent them
                     *current_wallet = name.to_string();
-                    println!("Switched to wallet '{}'.", current_wallet);
+                    println!("{}", trf("wallet.switched", &[&*current_wallet]));
+                }
+
+                "merge" => {
+                    let (Some(&source_name), Some(&target_name)) = (args.get(1), args.get(2)) else {
+                        println!("{}", tr("wallet.usage_merge"));
+                        return Ok(());
+                    };
+                    for name in [source_name, target_name] {
+                        if !wallets.contains_key(name) {
+                            println!("{}", style::error(trf("wallet_not_found", &[&name])));
+                            return Ok(());
+                        }
+                    }
+                    if source_name == target_name {
+                        println!("{}", style::error(tr("wallet.merge_into_itself")));
+                        return Ok(());
+                    }
+                    let source = &wallets[source_name];
+                    let target = wallets[target_name].public();
+
+                    let spent = used_session_inputs.of(source.public());
+                    let available = spendable::outputs(client, source.public(), spent).await?;
+                    let total = available.iter().fold(0, |acc, output| acc + output.1.amount);
+                    if total == 0 {
+                        println!("{}", trf("wallet.merge_empty", &[&source_name]));
+                        return Ok(());
+                    }
+
+                    // One input slot per transaction is kept free, like merge-available
+                    let parts = available.chunks(MAX_TRANSACTION_IO - 1).count();
+                    let total = style::outgoing(amount::display(total));
+                    let address = style::dim(target.dump_base36());
+                    let moving = trf(
+                        "wallet.merge_summary",
+                        &[&total, &source_name, &target_name, &address, &parts],
+                    );
+                    println!("{}", moving);
+                    if !confirm(tr("wallet.merge_confirm"), true) {
+                        println!("{}", style::warning(tr("cancelled")));
+                        return Ok(());
+                    }
+                    if !confirm_pin(tr("prompt.pin_send"), keys)? {
+                        println!("{}", style::error(tr("pin.incorrect")));
+                        return Err(ErrorCode::WrongPin.reported());
+                    }
+
+                    for part in available.chunks(MAX_TRANSACTION_IO - 1) {
+                        let amount = part.iter().fold(0, |acc, output| acc + output.1.amount);
+                        let mut tx = build_transaction(
+                            client,
+                            source.signer(),
+                            vec![(target, amount)],
+                            used_session_inputs.of(source.public()),
+                        )
+                        .await?;
+                        println!("{}", tr("send.computing_pow"));
+                        let difficulty = client.get_live_transaction_difficulty().await?;
+                        tx.compute_pow(&difficulty, Some(0.1))?;
+                        let tx_id = style::dim(tx.transaction_id.unwrap().dump_base36());
+                        println!("{}", trf("wallet.merge_built", &[&tx_id]));
+                        let used_inputs = tx.inputs.clone();
+                        let _shield = interrupt::critical();
+                        client.submit_transaction(tx).await??;
+                        used_session_inputs.add(source.public(), &used_inputs);
+                    }
+                    println!("{}", trf("wallet.merged", &[&source_name, &target_name]));
+
+                    if !confirm(&trf("wallet.confirm_delete", &[&source_name]), false) {
+                        return Ok(());
+                    }
+                    wallets.remove(source_name);
+                    backups.remove(source_name);
+                    persist(wallets, book, notes, backups, keys);
+                    println!("{}", trf("wallet.deleted", &[&source_name]));
+                    if current_wallet == source_name {
+                        save_last_login(target_name.to_string())?;
+                        *current_wallet = target_name.to_string();
+                        println!("{}", trf("wallet.switched", &[&*current_wallet]));
+                    }
                 }
 
-                _ => println!("Unknown wallet subcommand: {}", subcmd),
+                _ => println!("{}", style::error(trf("wallet.unknown", &[&subcmd]))),
+            }
+        }
+
+        "save" => {
+            if save(wallets, book, notes, backups, keys) {
+                println!("{}", tr("save.done"));
+            }
+        }
+
+        "compact" => {
+            if !args.is_empty() {
+                println!("{}", tr("compact.usage"));
+                return Ok(());
+            }
+            let path = crate::wallet_path()?;
+            let Ok(data) = fs::read(&path) else {
+                println!("{}", tr("compact.no_file"));
+                return Ok(());
+            };
+            let version = match encryption::read_file_header(&data) {
+                FileHeader::Versioned { version, .. } => trf("compact.version", &[&version]),
+                _ => tr("compact.legacy").to_string(),
+            };
+            let plan = compact::plan(wallets, backups);
+            let dir = crate::backups_dir()?;
+            // One more backup is made before compacting, and counts towards the retention
+            let old = compact::old_backups(&dir, config.backup_retention - 1)?;
+
+            let target = encryption::format_version();
+            println!("{}", trf("compact.rewrite", &[&version, &target]));
+            if plan.is_empty() {
+                println!("  - {}", tr("compact.nothing"));
+            }
+            for (removed, kept) in &plan.duplicates {
+                println!("  - {}", trf("compact.duplicate", &[removed, kept]));
+            }
+            for name in &plan.stale_confirmations {
+                println!("  - {}", trf("compact.stale_backup", &[name]));
+            }
+            if !old.is_empty() {
+                let retention = config.backup_retention;
+                println!(
+                    "  - {}",
+                    trf("compact.old_backups", &[&old.len(), &retention])
+                );
+            }
+            println!("{}", trf("compact.backed_up", &[&dir.display()]));
+            if !confirm(tr("compact.confirm"), false) {
+                println!("{}", style::warning(tr("cancelled")));
+                return Ok(());
+            }
+
+            // Nothing is touched unless the backup is safely written
+            let backup = compact::back_up(&path, &dir, clock::now()?)?;
+            compact::apply(&plan, wallets, backups);
+            let replaced = plan.duplicates.iter().find(|(removed, _)| *removed == *current_wallet);
+            if let Some((_, kept)) = replaced {
+                save_last_login(kept.clone())?;
+                *current_wallet = kept.clone();
+                println!("{}", trf("wallet.switched", &[&*current_wallet]));
             }
+            if !save(wallets, book, notes, backups, keys) {
+                UNSAVED.store(true, Ordering::Relaxed);
+                println!("{}", tr("compact.unchanged"));
+                return Ok(());
+            }
+            let mut freed = 0;
+            for old in old.iter().filter(|old| **old != backup) {
+                freed += compact::file_size(old);
+                fs::remove_file(old)?;
+            }
+            let (before, after) = (data.len() as u64, compact::file_size(&path));
+            println!("{}", trf("compact.size", &[&after, &before]));
+            let reclaimed = (before + freed).saturating_sub(after);
+            let backup = backup.display();
+            println!(
+                "{}",
+                trf("compact.reclaimed", &[&reclaimed, &backup, &before])
+            );
         }
 
         "change-pin" => {
-            let confirm = read_pin("Enter current PIN: ")?;
-            if confirm != pin {
-                println!("Incorrect PIN. Cannot change pin.");
+            if !confirm_pin(tr("prompt.pin_current"), keys)? {
+                println!("{}", style::error(tr("change_pin.denied")));
+                return Err(ErrorCode::WrongPin.reported());
+            }
+            let new = read_pin(tr("prompt.pin_new"))?;
+            if !accept_pin(&new) {
+                println!("{}", style::warning(tr("change_pin.kept")));
                 return Ok(());
             }
-            let new = read_pin("Create a new 6-digit wallet PIN: ")?;
-            if new != read_pin("Confirm new 6-digit PIN: ")? {
-                println!("PINs do not match. Cannot change pin.");
+            if new != read_pin(tr("prompt.pin_new_confirm"))? {
+                println!("{}", style::error(tr("change_pin.mismatch")));
+            } else {
+                let Some(new_keys) = SessionKeys::derive(new, *keys.kdf_params()) else {
+                    println!("{}", style::error(tr("change_pin.derive_failed")));
+                    return Ok(());
+                };
+                // The file is rewritten for the new PIN right away, autosave or not, and only
+                // once it opens with it does the session carry on with the new keys
+                let path = crate::wallet_path()?;
+                let rewritten = rewrite_for_pin(
+                    &path,
+                    wallets,
+                    book,
+                    notes,
+                    backups,
+                    &new_keys,
+                    export::write_atomic,
+                );
+                if let Err(e) = rewritten {
+                    println!("{}", style::error(e));
+                    match fs::read(&path).is_ok_and(|data| keys.opens(&data)) {
+                        true => println!("{}", style::error(tr("change_pin.not_saved"))),
+                        false => println!("{}", style::error(tr("change_pin.file_lost"))),
+                    }
+                    return Ok(());
+                }
+                UNSAVED.store(false, Ordering::Relaxed);
+                *keys = new_keys;
+                println!("{}", tr("change_pin.done"));
+            }
+        }
+
+        "set" => match args.first() {
+            Some(&"kdf") => {
+                let kdf_params = keys.kdf_params();
+                let mut new_params = *kdf_params;
+                let mut options = args[1..].iter();
+                while let Some(option) = options.next() {
+                    let Some(value) = options.next() else {
+                        println!("{}", style::error(trf("kdf.missing_value", &[option])));
+                        return Ok(());
+                    };
+                    let parsed = match *option {
+                        "--memory" => parse_memory_size(value).map(|v| new_params.memory_kib = v),
+                        "--iterations" => value.parse().ok().map(|v| new_params.iterations = v),
+                        "--parallelism" => value.parse().ok().map(|v| new_params.parallelism = v),
+                        _ => {
+                            println!("{}", style::error(trf("kdf.unknown_option", &[option])));
+                            return Ok(());
+                        }
+                    };
+                    if parsed.is_none() {
+                        let message = trf("kdf.invalid_value", &[option, value]);
+                        println!("{}", style::error(message));
+                        return Ok(());
+                    }
+                }
+
+                if new_params == *kdf_params {
+                    let current = trf(
+                        "kdf.current",
+                        &[
+                            &kdf_params.algorithm.name(),
+                            &kdf_params.memory_kib,
+                            &kdf_params.iterations,
+                            &kdf_params.parallelism,
+                        ],
+                    );
+                    println!("{}", current);
+                    return Ok(());
+                }
+                if let Err(e) = new_params.validate() {
+                    println!("{}", style::error(trf("kdf.invalid", &[&e])));
+                    return Ok(());
+                }
+                if new_params.exceeds_sanity_ceiling() {
+                    println!("{}", style::warning(tr("kdf.above_ceiling")));
+                }
+
+                let confirm = read_pin(tr("prompt.pin_kdf"))?;
+                if !keys.verify(confirm.as_str()) {
+                    println!("{}", style::error(tr("kdf.denied")));
+                    return Err(ErrorCode::WrongPin.reported());
+                }
+                let Some(new_keys) = SessionKeys::derive(confirm, new_params) else {
+                    println!("{}", style::error(tr("kdf.derive_failed")));
+                    return Ok(());
+                };
+                // Like a new PIN, the session only takes the new keys once the file opens with them
+                let path = crate::wallet_path()?;
+                let rewritten = rewrite_for_pin(
+                    &path,
+                    wallets,
+                    book,
+                    notes,
+                    backups,
+                    &new_keys,
+                    export::write_atomic,
+                );
+                if let Err(e) = rewritten {
+                    println!("{}", style::error(e));
+                    match fs::read(&path).is_ok_and(|data| keys.opens(&data)) {
+                        true => println!("{}", style::error(tr("kdf.not_saved"))),
+                        false => println!("{}", style::error(tr("kdf.file_lost"))),
+                    }
+                    return Ok(());
+                }
+                UNSAVED.store(false, Ordering::Relaxed);
+                *keys = new_keys;
+                println!("{}", tr("kdf.done"));
+            }
+            Some(
+                &setting @ ("prompt-balance" | "bell" | "quiet" | "auto-login" | "pager"
+                | "autocorrect" | "autosave" | "encrypt-snapshots" | "log-file"),
+            ) => {
+                let Some(enabled) = args.get(1).and_then(|value| parse_switch(value)) else {
+                    println!("{}", trf("set.usage_switch", &[&setting]));
+                    return Ok(());
+                };
+                let (field, label) = match setting {
+                    "prompt-balance" => (&mut config.prompt_balance, "set.prompt_balance"),
+                    "bell" => (&mut config.bell, "set.bell"),
+                    "auto-login" => (&mut config.auto_login_default, "set.auto_login"),
+                    "pager" => (&mut config.pager, "set.pager"),
+                    "autocorrect" => (&mut config.autocorrect, "set.autocorrect"),
+                    "autosave" => (&mut config.autosave, "set.autosave"),
+                    "encrypt-snapshots" => (&mut config.encrypt_snapshots, "set.encrypt_snapshots"),
+                    "log-file" => (&mut config.log_file, "set.log_file"),
+                    _ => (&mut config.quiet, "set.quiet"),
+                };
+                *field = enabled;
+                config.save()?;
+                pager::set_enabled(config.pager);
+                notify::set_mode(config.notify, config.quiet);
+                set_autosave(config.autosave);
+                // Only when it is the setting changed, so a --log-file session keeps logging
+                if setting == "log-file" {
+                    oplog::set_enabled(enabled);
+                }
+                let state = if enabled {
+                    "set.enabled"
+                } else {
+                    "set.disabled"
+                };
+                println!("{}", trf(state, &[&tr(label)]));
+                // Changes kept back while autosave was off are written once it is on again
+                if config.autosave
+                    && has_unsaved_changes()
+                    && save(wallets, book, notes, backups, keys)
+                {
+                    println!("{}", tr("save.done"));
+                }
+            }
+            Some(&"pin-timeout") => {
+                let Some(seconds) = args.get(1).and_then(|value| value.parse().ok()) else {
+                    println!("{}", tr("set.usage_pin_timeout"));
+                    return Ok(());
+                };
+                config.pin_timeout = seconds;
+                config.save()?;
+                crate::input::set_pin_timeout(seconds);
+                if seconds == 0 {
+                    println!("{}", tr("set.pin_timeout_off"));
+                } else {
+                    println!("{}", trf("set.pin_timeout", &[&seconds]));
+                }
+            }
+            Some(&"thousands-separator") => {
+                let Some(grouping) = args.get(1).and_then(|value| Grouping::parse(value)) else {
+                    println!(
+                        "{}",
+                        trf("set.usage_separator", &[&Grouping::NAMES.join("|")])
+                    );
+                    return Ok(());
+                };
+                config.thousands_separator = grouping;
+                config.save()?;
+                crate::amount::set_grouping(grouping);
+                let example = amount::display(amount::parse_amount("1234567.89").unwrap_or(0));
+                println!("{}", trf("set.separator", &[&example]));
+            }
+            Some(&"unit") => {
+                let Some(unit) = args.get(1).and_then(|value| Unit::parse(value)) else {
+                    println!("{}", tr("set.usage_unit"));
+                    return Ok(());
+                };
+                config.unit = unit;
+                config.save()?;
+                amount::set_unit(unit);
+                println!("{}", trf("set.unit", &[&unit.symbol()]));
+            }
+            Some(&"lang") => {
+                let lang = match args.get(1) {
+                    Some(&"auto") => None,
+                    Some(code) => match Lang::from_code(code) {
+                        Some(lang) => Some(lang),
+                        None => {
+                            println!("{}", tr("set.usage_lang"));
+                            return Ok(());
+                        }
+                    },
+                    None => {
+                        println!("{}", tr("set.usage_lang"));
+                        return Ok(());
+                    }
+                };
+                config.lang = lang;
+                config.save()?;
+                i18n::set(lang.unwrap_or_else(i18n::detect));
+                println!("{}", trf("lang.set", &[&i18n::code()]));
+            }
+            Some(&"donation-prompt") => {
+                let Some(prompt) = args.get(1).and_then(|value| DonationPrompt::parse(value))
+                else {
+                    let names = DonationPrompt::NAMES.join("|");
+                    println!("{}", trf("set.usage_donation_prompt", &[&names]));
+                    return Ok(());
+                };
+                config.donation_prompt = prompt;
+                config.save()?;
+                match prompt {
+                    DonationPrompt::Always => println!("{}", tr("set.donation_always")),
+                    DonationPrompt::Weekly => println!("{}", tr("set.donation_weekly")),
+                    DonationPrompt::Off => println!("{}", tr("set.donation_off")),
+                }
+            }
+            Some(&"notify") => {
+                let Some(mode) = args.get(1).and_then(|value| NotifyMode::parse(value)) else {
+                    println!(
+                        "{}",
+                        trf("set.usage_notify", &[&NotifyMode::NAMES.join("|")])
+                    );
+                    return Ok(());
+                };
+                config.notify = mode;
+                config.save()?;
+                notify::set_mode(mode, config.quiet);
+                match mode {
+                    NotifyMode::Off => println!("{}", tr("set.notify_off")),
+                    _ => println!("{}", trf("set.notify_on", &[&mode.name()])),
+                }
+            }
+            Some(&"low-balance") => {
+                let threshold = match args.get(1) {
+                    Some(value) if value.eq_ignore_ascii_case("off") => None,
+                    Some(value) => match amount::parse_amount(value) {
+                        Ok(threshold) if threshold > 0 => Some(threshold),
+                        _ => {
+                            println!("{}", tr("set.usage_low_balance"));
+                            return Ok(());
+                        }
+                    },
+                    None => {
+                        println!("{}", tr("set.usage_low_balance"));
+                        return Ok(());
+                    }
+                };
+                config.low_balance = threshold;
+                config.save()?;
+                notify::set_low_balance(threshold);
+                match threshold {
+                    Some(threshold) => {
+                        let threshold = amount::display(threshold);
+                        println!("{}", trf("set.low_balance", &[&threshold]));
+                        if config.notify == NotifyMode::Off {
+                            let hint = tr("set.low_balance_notify_off");
+                            println!("{}", style::warning(hint));
+                        }
+                    }
+                    None => println!("{}", tr("set.low_balance_off")),
+                }
+            }
+            Some(&"note-max-age") => {
+                let Some(days) = args.get(1).and_then(|value| value.parse().ok()) else {
+                    println!("{}", tr("set.usage_note_max_age"));
+                    return Ok(());
+                };
+                config.note_max_age_days = days;
+                config.save()?;
+                if days == 0 {
+                    println!("{}", tr("set.note_max_age_off"));
+                } else {
+                    println!("{}", trf("set.note_max_age", &[&days]));
+                }
+            }
+            _ => {
+                // The settings `help set` and completion offer, from the same table
+                let settings = commands::find("set").map_or(&[][..], |info| info.subcommands);
+                for (i, setting) in settings.iter().enumerate() {
+                    let lead = if i == 0 { tr("help.usage") } else { "" };
+                    println!("{:<6} set {}", lead, setting.usage);
+                }
+            }
+        },
+
+        "open" => {
+            let [target] = args[..] else {
+                println!("{}", tr("open.usage"));
+                return Ok(());
+            };
+            // A transaction id and an address look alike, so whatever the node knows as a
+            // transaction is one
+            let known_tx = match TransactionId::new_from_base36(target) {
+                Some(tx_id) => client.get_transaction(&tx_id).await?.is_some(),
+                None => false,
+            };
+            let link = if known_tx {
+                config.tx_link(target)
             } else {
-                persist(&wallets, &new);
-                println!("Changed PIN.");
-                exit(0);
+                match resolve_recipient(book, target) {
+                    Ok(public) => config.address_link(&public.dump_base36()),
+                    Err(reason) => {
+                        let message = trf("open.unknown_target", &[&reason]);
+                        println!("{}", style::error(message));
+                        return Ok(());
+                    }
+                }
+            };
+            let Some(link) = link else {
+                println!("{}", style::warning(explorer::SETUP_HINT));
+                return Ok(());
+            };
+            println!("{}", trf("open.opening", &[&link]));
+            explorer::open(&link)?;
+        }
+
+        "install-uri-handler" => {
+            if !args.is_empty() {
+                println!("{}", tr("uri.usage_install"));
+                return Ok(());
+            }
+            match uri::install()? {
+                Installed::At(location) => {
+                    println!("{}", trf("uri.installed", &[&uri::SCHEME, &location]));
+                    println!("{}", tr("uri.installed_note"));
+                }
+                Installed::Instructions(steps) => println!("{}", steps),
+            }
+        }
+
+        "webhook" => {
+            if args.as_slice() != ["test"] {
+                println!("{}", tr("webhook.usage"));
+                return Ok(());
+            }
+            let Some(endpoint) = config.webhook() else {
+                let message = tr("webhook.not_set");
+                println!("{}", style::error(message));
+                return Ok(());
+            };
+            // Shaped like a real one, paying 1 SNAP to the current wallet
+            let amount = snap_coin::to_nano(1.0);
+            let payload = webhook::Payload {
+                event: "test",
+                tx_id: Hash::new_from_buf([0; 32]).dump_base36(),
+                wallet: current_wallet.clone(),
+                timestamp: clock::now()?,
+                payments: json::Payment::list(&[current_wallet.as_str()], &[(public, amount)]),
+                total: amount.into(),
+            };
+            match spin(tr("webhook.posting"), webhook::deliver(&endpoint, &payload)).await {
+                Ok(()) => println!("{}", trf("webhook.accepted", &[&endpoint.url])),
+                Err(e) => println!("{}", style::error(trf("webhook.failed", &[&e]))),
             }
         }
 
         "merge-available" => {
-            let confirm = read_pin("Enter current PIN: ")?;
-            if confirm != pin {
-                println!("Incorrect PIN.");
+            // The same outputs the builds pick from, so the total is what gets merged
+            let available = spendable::outputs(client, public, spent_inputs).await?;
+            if available.is_empty() {
+                say!("{}", trf("merge.nothing", &[&current_wallet]));
                 return Ok(());
             }
+            let total = available.iter().fold(0, |acc, output| acc + output.1.amount);
 
-            let available = client
-                .get_available_transaction_outputs(wallet.to_public())
-                .await?;
-            let mut part_count = 0;
+            // Every part is built before asking, each leaving out the inputs of the ones before
+            let mut ignored = spent_inputs.to_vec();
+            let mut built = Vec::new();
             for part in available.chunks(MAX_TRANSACTION_IO - 1) {
-                let amount = part.iter().fold(0, |acc, part| part.1.amount + acc);
-                let mut tx = build_transaction(
-                    client,
-                    *wallet,
-                    vec![(wallet.to_public(), amount)],
-                    &used_session_inputs,
-                )
-                .await?;
-                println!("Computing Proof Of Work for transaction");
-                tx.compute_pow(&client.get_transaction_difficulty().await?, None)?;
-                println!(
-                    "Built transaction: {}",
-                    tx.transaction_id.unwrap().dump_base36()
-                );
+                let amount = part.iter().fold(0, |acc, output| acc + output.1.amount);
+                let tx =
+                    build_transaction(client, wallet.signer(), vec![(public, amount)], &ignored)
+                        .await?;
+                ignored.extend_from_slice(&tx.inputs);
+                let (tx, difficulty) = finish_pow(client, pow_cache, tx).await?;
+                let tx_id = tx.transaction_id.unwrap();
+                say!("{}", trf("send.created", &[&style::dim(tx_id.dump_base36())]));
+                built.push((tx, difficulty));
+            }
 
-                println!("Submitting transaction...");
+            let total = style::outgoing(amount::display(total));
+            say!(
+                "{}",
+                trf("merge.summary", &[&available.len(), &total, &built.len()])
+            );
+            if !confirm(tr("merge.confirm"), true) {
+                say!("{}", style::warning(tr("cancelled")));
+                return Ok(());
+            }
+            if !confirm_pin(tr("prompt.pin_send"), keys)? {
+                say!("{}", style::error(tr("pin.incorrect")));
+                return Err(ErrorCode::WrongPin.reported());
+            }
 
+            let parts = built.len();
+            for (mut tx, difficulty) in built {
+                // The network may have got harder while the PoW was computed or the user decided
+                let live_difficulty = client.get_live_transaction_difficulty().await?;
+                if difficulty_increased(&difficulty, &live_difficulty) {
+                    say!("{}", style::warning(tr("send.difficulty_increased")));
+                    say!("{}", tr("send.computing_pow"));
+                    tx.compute_pow(&live_difficulty, Some(0.1f64))?;
+                    pow_cache.insert(tx.clone(), live_difficulty);
+                }
+                say!("{}", tr("send.submitting"));
                 let used_inputs = tx.inputs.clone();
-                client.submit_transaction(tx).await??;
-                println!("Submitted transaction");
-                used_session_inputs.extend_from_slice(&used_inputs);
-
-                part_count += 1;
+                let _shield = interrupt::critical();
+                // Kept in the cache until the node has seen it, like a send
+                let submitted = tx.clone();
+                let status = client.submit_transaction(tx).await?;
+                pow_cache.remove(&submitted);
+                status?;
+                used_session_inputs.add(public, &used_inputs);
             }
 
-            println!("Merged available utxos ({}) into {} utxos", available.len(), part_count);
+            say!("{}", trf("merge.done", &[&available.len(), &parts]));
         }
 
-        _ => println!(
-            "Unknown command: '{}'. Type 'help' for available commands.",
-            cmd
-        ),
+        _ => println!("{}", style::error(trf("unknown_command", &[&cmd]))),
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encryption::{KdfAlgorithm, KdfParams, decrypt_wallets, encrypt_wallets},
+        input::script_answers,
+        node::mock::MockNode,
+    };
+    use snap_coin::{crypto::keys::Private, to_nano};
+
+    #[tokio::test]
+    async fn test_balance_lines() {
+        let owner = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(owner, to_nano(2.0));
+        node.fund(owner, to_nano(3.0));
+        assert_eq!(balance_lines(&node, owner, &[]).await.unwrap().len(), 1);
+
+        // An output spent this session makes the spendable part differ, with its reason
+        let spent = [TransactionInput {
+            transaction_id: node.outputs[0].0,
+            output_index: 0,
+            signature: None,
+            output_owner: owner,
+        }];
+        assert_eq!(balance_lines(&node, owner, &spent).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_available_lines_show_unlisted_balance() {
+        let owner = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(owner, to_nano(2.0));
+        let lines = available_lines(&node, owner, &[], "amount").await.unwrap();
+        assert!(!lines.iter().any(|line| line.contains("no spendable outputs")));
+
+        // A reward the node doesn't offer yet is never listed, nor offered to a send
+        node.locked.push((owner, to_nano(3.0)));
+        let lines = available_lines(&node, owner, &[], "amount").await.unwrap();
+        assert_eq!(lines[0], "Available UTXOs (1):");
+        let unlisted = lines.last().unwrap();
+        assert!(unlisted.contains(&amount::display(to_nano(3.0))));
+        assert!(unlisted.contains("no spendable outputs"));
+    }
+
+    #[tokio::test]
+    async fn test_available_collapses_duplicate_outputs() {
+        let owner = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(owner, to_nano(1.0));
+        node.fund(owner, to_nano(1.0));
+        // The node lists the first output again
+        node.outputs.push(node.outputs[0].clone());
+
+        let utxos = available_utxos(&node, owner, &[], "amount").await.unwrap();
+        let outputs: Vec<(String, usize)> =
+            utxos.iter().map(|utxo| (utxo.tx_id.clone(), utxo.index)).collect();
+        let mut sorted = outputs.clone();
+        sorted.sort();
+        // Equal amounts fall back to the output order, whatever order the node gave
+        assert_eq!(outputs, sorted);
+        assert_eq!(outputs.len(), 2);
+        let lines = available_lines(&node, owner, &[], "amount").await.unwrap();
+        assert_eq!(lines[0], "Available UTXOs (2):");
+        let breakdown = spendable::fetch(&node, owner, &[]).await.unwrap();
+        assert_eq!(breakdown.available, to_nano(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_available_order_is_stable() {
+        let owner = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        for snap in [1.0, 3.0, 1.0, 2.0, 1.0] {
+            node.fund(owner, to_nano(snap));
+        }
+        let utxos = available_utxos(&node, owner, &[], "amount").await.unwrap();
+        let amounts: Vec<u64> = utxos.iter().map(|utxo| utxo.amount.nano).collect();
+        assert_eq!(amounts, [3.0, 2.0, 1.0, 1.0, 1.0].map(to_nano));
+        let ties: Vec<&String> = utxos[2..].iter().map(|utxo| &utxo.tx_id).collect();
+        assert!(ties.is_sorted(), "{:?}", ties);
+
+        // The node listing its outputs in another order changes nothing
+        node.outputs.reverse();
+        let again = available_utxos(&node, owner, &[], "amount").await.unwrap();
+        let ids = |utxos: &[json::Utxo]| -> Vec<String> {
+            utxos.iter().map(|utxo| utxo.tx_id.clone()).collect()
+        };
+        assert_eq!(ids(&again), ids(&utxos));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_sends_submit_nothing() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(1.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        let receiver = Private::new_random().to_public().dump_base36();
+
+        for command in [
+            format!("send {} 5", receiver),
+            format!("send {} lots", receiver),
+            format!("send {}", receiver),
+            format!("send {} 0", receiver),
+            format!("send {} -5", receiver),
+            format!("send {} nan", receiver),
+            format!("send {} inf", receiver),
+        ] {
+            // Only the unaffordable amount gets as far as building, and fails with its code
+            if let Err(e) = handle_command(&mut session, command).await {
+                assert_eq!(ErrorCode::of(&e), ErrorCode::InsufficientFunds);
+            }
+        }
+        assert!(submitted.lock().unwrap().is_empty());
+        assert!(session.used_session_inputs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mixed_recipients_send_nothing() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(100.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        let good = Private::new_random().to_public().dump_base36();
+        let other = Private::new_random().to_public().dump_base36();
+
+        for (command, code) in [
+            (format!("send BADADDR 5 {} 3", good), ErrorCode::InvalidAddress),
+            (format!("send {} 3 BADADDR 5", good), ErrorCode::InvalidAddress),
+            (format!("send {} 3 {} lots", good, other), ErrorCode::InvalidAmount),
+            (format!("send {} 3 {} 0 {} 2", good, other, other), ErrorCode::InvalidAmount),
+        ] {
+            let e = handle_command(&mut session, command).await.unwrap_err();
+            assert_eq!(ErrorCode::of(&e), code);
+        }
+        assert!(submitted.lock().unwrap().is_empty());
+        assert!(session.used_session_inputs.is_empty());
+
+        // Every problem is reported, not only the first
+        let args = ["BADADDR", "5", good.as_str(), "-1", other.as_str(), "2"];
+        let problems = check_payments(&args, vec![None; 3], &AddressBook::new(), 0)
+            .err()
+            .unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].to_string().contains("BADADDR"), "{}", problems[0]);
+        assert_eq!(ErrorCode::of(&problems[0]), ErrorCode::InvalidAddress);
+        assert!(problems[1].to_string().contains("-1"), "{}", problems[1]);
+        assert_eq!(ErrorCode::of(&problems[1]), ErrorCode::InvalidAmount);
+
+        let args = [good.as_str(), "5", other.as_str(), "2"];
+        let labels = vec![None, Some("gift".to_string())];
+        let checked = check_payments(&args, labels, &AddressBook::new(), 0).unwrap();
+        assert_eq!(checked.recipients, [good.as_str(), other.as_str()]);
+        assert_eq!(checked.payments[1].1, to_nano(2.0));
+        assert_eq!(checked.labels[1].as_deref(), Some("gift"));
+    }
+
+    /// A wallet from creation to history against the mock node, whose difficulty any PoW meets
+    #[tokio::test]
+    async fn test_create_receive_send_history() {
+        // Created: through the prompts of the first login, a taken name is asked for again and
+        // an empty key makes a new one. The wallet file then unlocks with the PIN only
+        let mut created = Session::mock(MockNode::default(), Private::new_random());
+        created.wallets.remove("main");
+        script_answers(&["savings", "main", ""]);
+        let (name, main) = crate::add_new_wallet(&mut created.wallets).unwrap();
+        assert_eq!(name, "main");
+        assert_eq!(created.wallets["main"].public(), main.to_public());
+        let file = encrypt_wallets(
+            &created.wallets,
+            &created.book,
+            &created.notes,
+            &created.backups,
+            &created.keys,
+        )
+        .unwrap();
+        assert!(decrypt_wallets(&file, "654321").is_none());
+        let (wallets, book, notes, backups, keys) = decrypt_wallets(&file, "123456").unwrap();
+        assert_eq!(wallets["main"].public(), main.to_public());
+
+        // Received: a payment shows in the balance
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session {
+            client: Box::new(node),
+            wallets,
+            book,
+            notes,
+            backups,
+            keys,
+            ..created
+        };
+        let lines = balance_lines(&*session.client, main.to_public(), &[]).await.unwrap();
+        assert_eq!(lines, vec![trf("balance", &[&amount::display(to_nano(10.0))])]);
+
+        // Sent: confirmed and authorized with the PIN like a user would
+        let savings = session.wallets["savings"].public();
+        script_answers(&["y", "123456"]);
+        let command = format!("send {} 4", savings.dump_base36());
+        handle_command(&mut session, command).await.unwrap();
+        let sent = submitted.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        let transaction = sent[0].clone();
+        let effect = TxEffect::of(&transaction, main.to_public());
+        assert_eq!((effect.sent, effect.received), (to_nano(4.0), to_nano(6.0)));
+        assert_eq!(TxEffect::of(&transaction, savings).received, to_nano(4.0));
+        // Its input isn't offered to another send until the node confirms it
+        let spent = session.used_session_inputs.of(main.to_public());
+        assert_eq!(spent.len(), 1);
+        let breakdown = spendable::fetch(&*session.client, main.to_public(), spent)
+            .await
+            .unwrap();
+        assert_eq!(breakdown.spendable(), 0);
+
+        // Confirmed: the node now has the transaction and its outputs
+        let tx_id = transaction.transaction_id.unwrap();
+        let mut confirmed = MockNode::default();
+        confirmed.outputs = transaction
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| (tx_id, output.clone(), index))
+            .collect();
+        confirmed.transactions = vec![transaction];
+        session.client = Box::new(confirmed);
+        session.used_session_inputs.clear(main.to_public());
+        let lines = balance_lines(&*session.client, main.to_public(), &[]).await.unwrap();
+        assert_eq!(lines, vec![trf("balance", &[&amount::display(to_nano(6.0))])]);
+
+        // History: both wallets list it
+        for public in [main.to_public(), savings] {
+            let history = session.client.get_transactions_of_address(public).await.unwrap();
+            assert_eq!(history, vec![tx_id]);
+        }
+        let capture = json::capture();
+        handle_command(&mut session, "history --json".to_string())
+            .await
+            .unwrap();
+        let history: serde_json::Value = serde_json::from_str(&capture.take().unwrap()).unwrap();
+        assert_eq!(history["address"], main.to_public().dump_base36());
+        let entries = history["transactions"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["tx_id"], tx_id.dump_base36());
+        assert_eq!(entries[0]["sent"]["nano"], to_nano(4.0));
+        assert_eq!(entries[0]["received"]["nano"], to_nano(6.0));
+
+        let command = format!("tx-info {} --json", tx_id.dump_base36());
+        handle_command(&mut session, command).await.unwrap();
+        let info: serde_json::Value = serde_json::from_str(&capture.take().unwrap()).unwrap();
+        drop(capture);
+        assert_eq!(info["tx_id"], tx_id.dump_base36());
+        let outputs = info["outputs"].as_array().unwrap();
+        let to_savings = outputs
+            .iter()
+            .find(|output| output["receiver"] == savings.dump_base36())
+            .unwrap();
+        assert_eq!(to_savings["amount"]["nano"], to_nano(4.0));
+    }
+
+    /// A wrong PIN fails the send with its code, so a script sees it in the exit code
+    #[tokio::test]
+    async fn test_send_wrong_pin() {
+        let main = Private::new_random();
+        let to = Private::new_random().to_public().dump_base36();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        script_answers(&["y", "000000"]);
+        let e = handle_command(&mut session, format!("send {} 4", to)).await.unwrap_err();
+        assert_eq!(ErrorCode::of(&e), ErrorCode::WrongPin);
+        assert!(exit_code::is_reported(&e));
+        assert!(submitted.lock().unwrap().is_empty());
+    }
+
+    /// Only outputs no send spent this session are merged, after the confirmation and the PIN
+    #[tokio::test]
+    async fn test_merge_available() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        node.fund(main.to_public(), to_nano(5.0));
+        node.fund(main.to_public(), to_nano(2.0));
+        let (second, third) = (node.outputs[1].0, node.outputs[2].0);
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        let to = Private::new_random().to_public().dump_base36();
+        script_answers(&["y", "123456"]);
+        handle_command(&mut session, format!("send {} 4", to)).await.unwrap();
+
+        script_answers(&["y", "000000"]);
+        let e = handle_command(&mut session, "merge-available".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(ErrorCode::of(&e), ErrorCode::WrongPin);
+        assert_eq!(submitted.lock().unwrap().len(), 1);
+
+        script_answers(&["y", "123456"]);
+        handle_command(&mut session, "merge-available".to_string())
+            .await
+            .unwrap();
+        let sent = submitted.lock().unwrap().clone();
+        assert_eq!(sent.len(), 2);
+        let merged = &sent[1];
+        let inputs: Vec<_> = merged.inputs.iter().map(|input| input.transaction_id).collect();
+        assert_eq!(inputs.len(), 2);
+        assert!(inputs.contains(&second) && inputs.contains(&third));
+        assert_eq!(merged.outputs.len(), 1);
+        assert_eq!(merged.outputs[0].receiver, main.to_public());
+        assert_eq!(merged.outputs[0].amount, to_nano(7.0));
+        assert_eq!(session.used_session_inputs.of(main.to_public()).len(), 3);
+    }
+
+    /// The JSON result carries the fee, the difference between the spent output and what the
+    /// transaction pays, which RPC `confirm_send` hands back to its caller
+    #[tokio::test]
+    async fn test_send_json_fee() {
+        let main = Private::new_random();
+        let to = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        // The transaction that created the funded output, for the fee to be worked out
+        let (funding, output, _) = node.outputs[0].clone();
+        node.transactions.push(Transaction {
+            inputs: vec![],
+            outputs: vec![output],
+            transaction_id: Some(funding),
+            nonce: 0,
+            timestamp: 0,
+        });
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+
+        script_answers(&["y", "123456"]);
+        let capture = json::capture();
+        let command = format!("send {} 4 --json", to.dump_base36());
+        handle_command(&mut session, command).await.unwrap();
+        let printed: serde_json::Value = serde_json::from_str(&capture.take().unwrap()).unwrap();
+        drop(capture);
+
+        let sent = submitted.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        let paid: u64 = sent[0].outputs.iter().map(|output| output.amount).sum();
+        assert_eq!(printed["tx_id"], sent[0].transaction_id.unwrap().dump_base36());
+        assert_eq!(printed["accepted"], true);
+        assert_eq!(printed["fee"]["nano"], to_nano(10.0) - paid);
+    }
+
+    /// An input spent elsewhere between the build and the submission is caught by the fresh
+    /// listing, and the payment goes out from the output that is left once confirmed again
+    #[tokio::test]
+    async fn test_send_refreshes_inputs() {
+        let main = Private::new_random();
+        let to = Private::new_random().to_public().dump_base36();
+        let node = || {
+            let mut node = MockNode::default();
+            node.fund(main.to_public(), to_nano(10.0));
+            node.fund(main.to_public(), to_nano(5.0));
+            node.spent_elsewhere = vec![node.outputs[0].0];
+            node
+        };
+        let (first, second) = (node().outputs[0].0, node().outputs[1].0);
+
+        let refreshed = node();
+        let (submitted, listings) = (refreshed.submitted.clone(), refreshed.listings.clone());
+        let mut session = Session::mock(refreshed, main);
+        script_answers(&["y", "123456", "y"]);
+        handle_command(&mut session, format!("send {} 4", to)).await.unwrap();
+        // Built, checked against a listing without the first output, built again
+        assert_eq!(*listings.lock().unwrap(), 3);
+        let sent = submitted.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].inputs[0].transaction_id, second);
+
+        // The transaction built again is a different one, declining it sends nothing
+        let declined = node();
+        let (submitted, listings) = (declined.submitted.clone(), declined.listings.clone());
+        let mut session = Session::mock(declined, main);
+        script_answers(&["y", "123456", "n"]);
+        handle_command(&mut session, format!("send {} 4", to)).await.unwrap();
+        assert_eq!(*listings.lock().unwrap(), 3);
+        assert!(submitted.lock().unwrap().is_empty());
+
+        // Skipped, the transaction built first goes out as is for the node to judge
+        let skipped = node();
+        let (submitted, listings) = (skipped.submitted.clone(), skipped.listings.clone());
+        let mut session = Session::mock(skipped, main);
+        script_answers(&["y", "123456"]);
+        handle_command(&mut session, format!("send {} 4 --no-refresh", to))
+            .await
+            .unwrap();
+        assert_eq!(*listings.lock().unwrap(), 1);
+        assert_eq!(submitted.lock().unwrap()[0].inputs[0].transaction_id, first);
+    }
+
+    /// Sends from two wallets with switches in between: each wallet only ever consults and
+    /// clears its own spent inputs
+    #[tokio::test]
+    async fn test_session_inputs_per_wallet() {
+        let (main, savings) = (Private::new_random(), Private::new_random());
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        node.fund(main.to_public(), to_nano(5.0));
+        node.fund(savings.to_public(), to_nano(3.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        session
+            .wallets
+            .insert("savings".to_string(), WalletKey::Local(savings));
+        let (main, savings) = (main.to_public(), savings.to_public());
+        let to = Private::new_random().to_public().dump_base36();
+
+        script_answers(&["y", "123456"]);
+        handle_command(&mut session, format!("send {} 4", to)).await.unwrap();
+        handle_command(&mut session, "wallet switch savings".to_string())
+            .await
+            .unwrap();
+        // What main spent leaves every output of savings spendable
+        let spent = session.used_session_inputs.of(savings);
+        assert!(spent.is_empty());
+        let breakdown = spendable::fetch(&*session.client, savings, spent).await.unwrap();
+        assert_eq!(breakdown.spendable(), to_nano(3.0));
+        script_answers(&["y", "123456"]);
+        handle_command(&mut session, format!("send {} 1", to)).await.unwrap();
+        handle_command(&mut session, "wallet switch main".to_string())
+            .await
+            .unwrap();
+        assert_eq!(session.used_session_inputs.of(main).len(), 1);
+        assert_eq!(session.used_session_inputs.of(savings).len(), 1);
+
+        // Back on main, the next send takes the output the first one left alone
+        script_answers(&["y", "123456"]);
+        handle_command(&mut session, format!("send {} 2", to)).await.unwrap();
+        let sent = submitted.lock().unwrap().clone();
+        assert_eq!(sent.len(), 3);
+        let (first, third) = (&sent[0].inputs[0], &sent[2].inputs[0]);
+        assert!(
+            first.transaction_id != third.transaction_id
+                || first.output_index != third.output_index
+        );
+        assert_eq!(session.used_session_inputs.of(main).len(), 2);
+
+        // Resetting main's leaves what savings spent in place
+        script_answers(&["y"]);
+        handle_command(&mut session, "session reset".to_string())
+            .await
+            .unwrap();
+        assert!(session.used_session_inputs.of(main).is_empty());
+        assert_eq!(session.used_session_inputs.of(savings).len(), 1);
+        script_answers(&["y"]);
+        handle_command(&mut session, "session reset --all".to_string())
+            .await
+            .unwrap();
+        assert!(session.used_session_inputs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_balance_of_named_wallet() {
+        let main = Private::new_random();
+        let external = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        node.fund(external, to_nano(3.0));
+        let mut session = Session::mock(node, main);
+        let signer = ExternalSigner {
+            public: external,
+            command: "false".to_string(),
+        };
+        session
+            .wallets
+            .insert("cold".to_string(), WalletKey::External(signer));
+
+        let capture = json::capture();
+        handle_command(&mut session, "balance cold --json".to_string())
+            .await
+            .unwrap();
+        let printed: serde_json::Value = serde_json::from_str(&capture.take().unwrap()).unwrap();
+        drop(capture);
+        assert_eq!(printed["wallet"], "cold");
+        assert_eq!(printed["address"], external.dump_base36());
+        assert_eq!(printed["balance"]["nano"], to_nano(3.0));
+        // Still on the wallet it was on
+        assert_eq!(session.current_wallet, "main");
+
+        let e = handle_command(&mut session, "balance nosuch".to_string())
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("nosuch"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn test_switch_shows_new_wallet_balance() {
+        let (main, savings) = (Private::new_random(), Private::new_random());
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(10.0));
+        node.fund(savings.to_public(), to_nano(3.0));
+        let mut session = Session::mock(node, main);
+        session
+            .wallets
+            .insert("savings".to_string(), WalletKey::Local(savings));
+        // An input of the first wallet spent this session
+        let input = TransactionInput {
+            transaction_id: Hash::new_from_buf([1; 32]),
+            output_index: 0,
+            signature: None,
+            output_owner: main.to_public(),
+        };
+        session.used_session_inputs.add(main.to_public(), &[input]);
+
+        async fn current_balance(session: &Session) -> Vec<String> {
+            let public = session.current_public().unwrap();
+            balance_lines(&*session.client, public, session.used_session_inputs.of(public))
+                .await
+                .unwrap()
+        }
+        let display = |snap| vec![trf("balance", &[&amount::display(to_nano(snap))])];
+        assert_eq!(current_balance(&session).await, display(10.0));
+        handle_command(&mut session, "wallet switch savings".to_string())
+            .await
+            .unwrap();
+        assert_eq!(session.current_wallet, "savings");
+        assert_eq!(current_balance(&session).await, display(3.0));
+        handle_command(&mut session, "wallet switch main".to_string())
+            .await
+            .unwrap();
+        assert_eq!(current_balance(&session).await, display(10.0));
+    }
+
+    #[tokio::test]
+    async fn test_wallet_commands() {
+        let mut session = Session::mock(MockNode::default(), Private::new_random());
+        handle_command(&mut session, "wallet list --balances".to_string())
+            .await
+            .unwrap();
+        handle_command(&mut session, "wallet switch nope".to_string())
+            .await
+            .unwrap();
+        assert_eq!(session.current_wallet, "main");
+
+        // An unknown wallet is refused before any confirmation or PIN is asked for
+        handle_command(&mut session, "wallet delete nope".to_string())
+            .await
+            .unwrap();
+        assert_eq!(session.wallets.len(), 2);
+    }
+
+    #[test]
+    fn test_rewrite_for_pin() {
+        let params = KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let old_keys = SessionKeys::derive("123456", params).unwrap();
+        let new_keys = SessionKeys::derive("654321", params).unwrap();
+        let mut wallets = Wallets::new();
+        wallets.insert("main".to_string(), WalletKey::Local(Private::new_random()));
+        let (book, notes, backups) = (AddressBook::new(), TxNotes::new(), Backups::new());
+        let path = std::env::temp_dir().join(format!("snap-change-pin-{}", std::process::id()));
+        let old_file = encrypt_wallets(&wallets, &book, &notes, &backups, &old_keys).unwrap();
+        let rewrite = |write: &dyn Fn(&Path, &[u8]) -> Result<(), anyhow::Error>| {
+            fs::write(&path, &old_file).unwrap();
+            let result =
+                rewrite_for_pin(&path, &wallets, &book, &notes, &backups, &new_keys, write);
+            (result, fs::read(&path).unwrap())
+        };
+
+        // A write that fails leaves the file alone
+        let (result, data) = rewrite(&|_, _| Err(anyhow::Error::msg("disk full")));
+        assert_eq!(result.unwrap_err().to_string(), "disk full");
+        assert!(old_keys.opens(&data));
+
+        // A write that doesn't fail but leaves a damaged file is caught and undone
+        let (result, data) =
+            rewrite(&|path, bytes| Ok(fs::write(path, &bytes[..bytes.len() / 2])?));
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("doesn't open with the new PIN"), "{}", error);
+        assert_eq!(data, old_file);
+
+        let (result, data) = rewrite(&export::write_atomic);
+        assert!(result.is_ok());
+        assert!(new_keys.opens(&data) && !old_keys.opens(&data));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_suspend_and_resume() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(4.0));
+        let mut session = Session::mock(node, main);
+        let file = encrypt_wallets(
+            &session.wallets,
+            &session.book,
+            &session.notes,
+            &session.backups,
+            &session.keys,
+        )
+        .unwrap();
+
+        session.suspend();
+        assert!(session.wallets.is_empty());
+        assert!(!session.keys.verify("123456"));
+        let balance = session.client.get_balance(main.to_public()).await;
+        assert_eq!(ErrorCode::of(&balance.unwrap_err()), ErrorCode::NodeUnreachable);
+
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(4.0));
+        session.resume(decrypt_wallets(&file, "123456").unwrap(), Box::new(node));
+        assert!(session.keys.verify("123456"));
+        assert_eq!(session.current_public(), Some(main.to_public()));
+        let balance = session.client.get_balance(main.to_public()).await;
+        assert_eq!(balance.unwrap(), to_nano(4.0));
+    }
+}
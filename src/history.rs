@@ -0,0 +1,162 @@
+use anyhow::Error;
+use snap_coin::{core::transaction::Transaction, crypto::keys::Public};
+
+use crate::{amount, node::NodeApi, signing::transaction_size};
+
+/// What a transaction did to one address's balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxEffect {
+    /// Paid to the address, including change when it was the sender
+    pub received: u64,
+    /// Paid to other addresses, when the address was the sender
+    pub sent: u64,
+}
+
+impl TxEffect {
+    pub fn of(transaction: &Transaction, address: Public) -> Self {
+        let is_sender = transaction
+            .inputs
+            .iter()
+            .any(|input| input.output_owner == address);
+        let mut effect = TxEffect {
+            received: 0,
+            sent: 0,
+        };
+        for output in &transaction.outputs {
+            if output.receiver == address {
+                effect.received += output.amount;
+            } else if is_sender {
+                effect.sent += output.amount;
+            }
+        }
+        effect
+    }
+
+    /// Whether the address paid anyone else in this transaction
+    pub fn is_outgoing(&self) -> bool {
+        self.sent > 0
+    }
+}
+
+/// What a transaction paid for its inclusion, against its encoded size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRate {
+    /// Amount spent by the inputs beyond what the outputs pay
+    pub fee: u64,
+    /// Encoded size in bytes
+    pub size: usize,
+}
+
+impl FeeRate {
+    /// Nano paid per byte
+    pub fn per_byte(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.fee as f64 / self.size as f64
+    }
+
+    /// Fee, rate and size on one line, e.g. `0.5 SNAP (12.34 nano/byte, 4051 bytes)`
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({:.2} nano/byte, {} bytes)",
+            amount::display(self.fee),
+            self.per_byte(),
+            self.size
+        )
+    }
+}
+
+/// Fee and size of `transaction`. Inputs only reference outputs, so each is looked up in the
+/// transaction that created it. `None` when one of them can't be found
+pub async fn fee_rate(
+    client: &dyn NodeApi,
+    transaction: &Transaction,
+) -> Result<Option<FeeRate>, Error> {
+    let mut spent = 0u64;
+    for input in &transaction.inputs {
+        let Some(source) = client.get_transaction(&input.transaction_id).await? else {
+            return Ok(None);
+        };
+        let Some(output) = source.outputs.get(input.output_index) else {
+            return Ok(None);
+        };
+        spent += output.amount;
+    }
+    let paid: u64 = transaction.outputs.iter().map(|output| output.amount).sum();
+    Ok(Some(FeeRate {
+        fee: spent.saturating_sub(paid),
+        size: transaction_size(transaction),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::mock::MockNode;
+    use snap_coin::{
+        core::transaction::{TransactionInput, TransactionOutput},
+        crypto::{Hash, keys::Private},
+    };
+
+    fn transaction(sender: Public, outputs: &[(Public, u64)]) -> Transaction {
+        Transaction {
+            inputs: vec![TransactionInput {
+                transaction_id: Hash::new_from_buf([1; 32]),
+                output_index: 0,
+                signature: None,
+                output_owner: sender,
+            }],
+            outputs: outputs
+                .iter()
+                .map(|(receiver, amount)| TransactionOutput {
+                    amount: *amount,
+                    receiver: *receiver,
+                })
+                .collect(),
+            transaction_id: None,
+            nonce: 0,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_tx_effect() {
+        let me = Private::new_random().to_public();
+        let other = Private::new_random().to_public();
+
+        let incoming = transaction(other, &[(me, 5), (other, 3)]);
+        assert_eq!(
+            TxEffect::of(&incoming, me),
+            TxEffect {
+                received: 5,
+                sent: 0
+            }
+        );
+
+        // Change back to the sender counts as received, not sent
+        let outgoing = transaction(me, &[(other, 7), (me, 2)]);
+        let effect = TxEffect::of(&outgoing, me);
+        assert_eq!(effect, TxEffect { received: 2, sent: 7 });
+        assert!(effect.is_outgoing());
+    }
+
+    #[tokio::test]
+    async fn test_fee_rate() {
+        let me = Private::new_random().to_public();
+        let other = Private::new_random().to_public();
+        let mut funding = transaction(other, &[(me, 10)]);
+        funding.transaction_id = Some(Hash::new_from_buf([1; 32]));
+
+        let spending = transaction(me, &[(other, 7), (me, 2)]);
+        let mut node = MockNode::default();
+        assert_eq!(fee_rate(&node, &spending).await.unwrap(), None);
+
+        node.transactions.push(funding);
+        let rate = fee_rate(&node, &spending).await.unwrap().unwrap();
+        assert_eq!(rate.fee, 1);
+        assert_eq!(rate.size, transaction_size(&spending));
+        assert!(rate.per_byte() > 0.0);
+        assert_eq!(FeeRate { fee: 0, size: 0 }.per_byte(), 0.0);
+    }
+}
@@ -0,0 +1,1493 @@
+use std::{
+    env,
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// Languages with a message catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Es,
+}
+
+impl Lang {
+    /// Parse a language code like `es`, `es_ES` or `es_ES.UTF-8`
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Lang::En),
+            "es" => Some(Lang::Es),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Es => "es",
+        }
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => EN,
+            Lang::Es => ES,
+        }
+    }
+}
+
+/// The language in use, as a `Lang` discriminant
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+/// The language of the environment: `LC_ALL`, `LC_MESSAGES`, then `LANG`. Unknown or missing
+/// languages fall back to English
+pub fn detect() -> Lang {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|code| Lang::from_code(&code))
+        .unwrap_or(Lang::En)
+}
+
+/// Select the language for this run: the `--lang` flag, then the config file's `lang`, then
+/// the environment
+pub fn init(flag: Option<&str>, configured: Option<Lang>) {
+    let lang = match flag {
+        Some(code) => Lang::from_code(code).unwrap_or(Lang::En),
+        None => configured.unwrap_or_else(detect),
+    };
+    set(lang);
+}
+
+/// Switch the language of every message from now on
+pub fn set(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// Code of the language in use, e.g. `en`
+pub fn code() -> &'static str {
+    current().code()
+}
+
+fn current() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        code if code == Lang::Es as u8 => Lang::Es,
+        _ => Lang::En,
+    }
+}
+
+fn lookup(lang: Lang, key: &str) -> Option<&'static str> {
+    lang.catalog()
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, message)| *message)
+}
+
+/// Message for `key` in the current language, falling back to English, then to the key itself
+pub fn tr(key: &'static str) -> &'static str {
+    lookup(current(), key)
+        .or_else(|| lookup(Lang::En, key))
+        .unwrap_or(key)
+}
+
+/// Message for `key` with each `{}` replaced by the next argument
+/// Placeholders without a matching argument are left as they are
+pub fn trf(key: &'static str, args: &[&dyn Display]) -> String {
+    fill(tr(key), args)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(at) = rest.find("{}") {
+        out.push_str(&rest[..at]);
+        match args.next() {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str("{}"),
+        }
+        rest = &rest[at + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+const EN: &[(&str, &str)] = &[
+    ("banner", "--- Snap Coin Wallet ---"),
+    ("prompt.pin", "Enter 6-digit wallet PIN: "),
+    ("prompt.pin_confirm", "Confirm 6-digit wallet PIN: "),
+    ("prompt.pin_send", "Enter 6-digit PIN to confirm: "),
+    ("prompt.pin_current", "Enter current PIN: "),
+    ("prompt.pin_new", "Create a new 6-digit wallet PIN: "),
+    ("prompt.pin_new_confirm", "Confirm new 6-digit PIN: "),
+    ("wallet.confirm_delete", "Delete wallet '{}'? Without a backup of its key the coins are lost"),
+    ("prompt.pin_delete", "Enter PIN to confirm deletion of '{}': "),
+    ("prompt.pin_private", "Enter PIN to view private key of '{}': "),
+    ("prompt.login", "Enter wallet number or name to login: "),
+    ("prompt.new_wallet_name", "Enter a name for your new wallet: "),
+    (
+        "prompt.import_key",
+        "Enter a base36 private key to import (leave empty for random): ",
+    ),
+    ("prompt.menu_choice", "Choose option (1 or 2): "),
+    ("prompt.continue_loading", "Continue loading?"),
+    ("confirm.invalid", "Please answer y or n."),
+    ("menu.select", "1) Select existing wallet [default]"),
+    ("menu.create", "2) Create new wallet"),
+    ("error.invalid_choice", "Invalid choice, please enter 1 or 2."),
+    ("error.pins_mismatch", "PINs don't match"),
+    ("error.invalid_private_key", "Invalid base36 private key"),
+    ("error.home_dir", "Could not determine home directory"),
+    ("error.encrypt", "Failed to encrypt wallets"),
+    ("error.wallet_locked", "The wallet file is in use by another running wallet; close it first"),
+    ("error.derive_key", "Failed to derive wallet key"),
+    ("error.file_header", "Unrecognized wallet file header"),
+    ("error.load_aborted", "Aborted loading wallet file"),
+    ("error.decrypt", "Failed to decrypt wallets (wrong PIN?)"),
+    ("no_wallets", "No wallets found. Creating a new wallet."),
+    ("available_wallets", "Available wallets:"),
+    ("default_marker", " [default]"),
+    ("auto_login", "Logging in to '{}' (start with --choose to pick another wallet)"),
+    ("wallet_not_found_retry", "Wallet '{}' not found. Please try again."),
+    ("wallet_name_required", "There is no default wallet: enter a number from 1 to {} or a wallet name."),
+    ("wallet_exists_retry", "Wallet '{}' already exists. Please pick another name."),
+    ("wallet.limit_reached", "The wallet file already holds {} wallets, the most allowed. Delete one, or raise max_wallets in ~/.snap-coin-config."),
+    ("invalid_name_retry", "Invalid name: {}. Please try again."),
+    ("wallet_not_found", "Wallet '{}' not found."),
+    ("balance.of_wallet", "Wallet '{}':"),
+    ("wallet_created", "Wallet '{}' created successfully."),
+    (
+        "backup.save_key",
+        "Please make sure to save the wallet private key, in a SAFE, OFFLINE LOCATION!",
+    ),
+    ("backup.key", "Wallet private key (base 36): {}"),
+    (
+        "backup.loss_warning",
+        "!!! If you lose this key, you WILL lose your snap coins. There is NO way to recover them if lost !!!",
+    ),
+    (
+        "backup.theft_warning",
+        "!!! If anyone sees this key, they CAN and WILL steal your snap coins !!!",
+    ),
+    ("backup.reminder", "Wallet '{}' holds {} but its key was never confirmed as backed up. Write it down with `wallet private {} --screen`, then run `wallet confirm-backup {}`."),
+    ("backup.challenge", "Enter characters {} to {} of the private key of '{}': "),
+    ("backup.confirmed", "Backup of wallet '{}' confirmed."),
+    ("backup.mismatch", "That doesn't match the key. Backup not confirmed."),
+    ("uri.invalid", "Invalid payment link, nothing was sent: {}"),
+    ("uri.request", "Payment link to {}"),
+    ("uri.label", "Label given by the link: {}"),
+    ("uri.amount_prompt", "The link has no amount. Amount to send (empty to cancel): "),
+    (
+        "kdf.expensive_warning",
+        "WARNING: The wallet file requests unusually expensive key derivation ({} KiB memory, {} iterations, {} lanes).",
+    ),
+    (
+        "kdf.tamper_warning",
+        "A tampered wallet file can use this to exhaust memory or hang the wallet.",
+    ),
+    ("loaded_wallet", "Loaded wallet '{}' with public key: {}"),
+    ("donate", "Consider donating to the developer :) {}"),
+    ("connected", "Connected to node at {}"),
+    ("clock.skew_warning", "Warning: your clock seems to be {} seconds off from the network. Transaction timestamps will be corrected, but consider fixing the system clock."),
+    ("interrupt.hint", "Press Ctrl+D or type 'exit' to quit."),
+    ("interrupt.finishing", "Finishing the submission first, it can't be stopped halfway."),
+    ("command_error", "Command failed: {}"),
+    ("cancelled", "Cancelled."),
+    ("error_code.with_code", "[{}] {}"),
+    ("error_code.failed", "The command failed."),
+    ("error_code.usage", "Invalid arguments."),
+    ("error_code.wrong_pin", "Wrong PIN."),
+    ("error_code.node_unreachable", "The node can't be reached."),
+    ("error_code.insufficient_funds", "Insufficient funds."),
+    ("error_code.rejected", "The node didn't accept the transaction."),
+    ("error_code.wallet_locked", "The wallet file is in use."),
+    ("error_code.invalid_address", "Invalid address."),
+    ("error_code.invalid_amount", "Invalid amount."),
+    ("pin.timed_out", "PIN entry timed out."),
+    ("pin.digits_only", "(digits only, ? for help)"),
+    ("pin.key_help", "(6 digits, Backspace corrects, Esc cancels)"),
+    ("pin.no_enter", "(no Enter needed, it ends at 6 digits)"),
+    ("pin.weak", "This PIN is easy to guess: {}. The PIN is all that protects the wallet file."),
+    ("pin.weak_repeated", "one digit repeated"),
+    ("pin.weak_sequence", "consecutive digits"),
+    ("pin.weak_pattern", "a short group of digits repeated"),
+    ("pin.weak_common", "one of the most used PINs"),
+    ("pin.weak_confirm", "Use it anyway?"),
+    ("exiting_eof", "Exiting (Ctrl+D)"),
+    ("current_wallet_missing", "Current wallet '{}' not found."),
+    ("unknown_command", "Unknown command: '{}'. Type 'help' for available commands."),
+    ("help.header", "Available commands:"),
+    ("help.subcommands", "subcommands:"),
+    ("help.balance", "Show wallet balance, and what part of it can be sent now"),
+    ("help.available", "List available UTXOs, largest first"),
+    ("help.available_export", "Write the available UTXOs as CSV to a file, or to stdout for -"),
+    ("help.history", "Show transaction history"),
+    ("help.report", "Write a yearly CSV report of a wallet's transactions for accounting"),
+    ("help.snapshot", "Record the current balance in the local snapshot log"),
+    ("help.balance_history", "Show the recorded balance snapshots as a table and sparkline"),
+    ("help.tx_info", "Show transaction details"),
+    ("help.tx_note", "Attach a note to a transaction, or remove it"),
+    ("help.prune", "Remove notes of dropped or old transactions"),
+    ("help.estimate", "Estimate size and PoW cost of a payment"),
+    ("help.merge_available", "Merge all available into one utxo."),
+    ("help.send", "Send SNAP to addresses"),
+    ("help.session", "Show or clear inputs spent this session"),
+    ("help.status", "Summarize the session for bug reports: wallet, node health, pending and clock"),
+    ("help.diagnose", "Check the node, wallet file, pending transactions, clock and balance"),
+    ("help.log", "Show where the troubleshooting log is written"),
+    ("help.dashboard", "Full-screen view of balance, transactions, UTXOs and node"),
+    ("help.mempool", "List transactions waiting in the mempool, or only this wallet's"),
+    ("help.watch", "Re-run balance, available or mempool every few seconds"),
+    ("help.open", "Open a transaction or address in the block explorer"),
+    ("help.install_uri_handler", "Make snap: payment links open in this wallet"),
+    ("help.webhook", "Post a sample payload to the configured webhook"),
+    ("help.vanity", "Generate a wallet whose address starts with <prefix>"),
+    ("help.sign", "Sign a message to prove you control the current address"),
+    ("help.verify", "Check a message signature made with sign"),
+    ("help.file_info", "Show the wallet file format and KDF settings, without the PIN"),
+    ("help.config_show", "Show every setting in effect and where it came from"),
+    ("help.contact", "Address book commands"),
+    ("help.contact_add", "Save an address under an alias"),
+    ("help.contact_remove", "Remove a contact"),
+    ("help.contact_rename", "Rename a contact"),
+    ("help.contact_note", "Set or clear the note of a contact"),
+    ("help.contact_list", "List contacts"),
+    ("help.wallet", "Wallet management commands"),
+    ("help.wallet_list", "List all wallets, with their balances if asked"),
+    ("help.wallet_delete", "Delete the specified wallet (default: current)"),
+    ("help.wallet_private", "Show private key of the wallet (default: current), --screen keeps it out of the scrollback, --qr shows only a QR code, --checksummed adds a checksum"),
+    ("help.wallet_confirm_backup", "Confirm the key of the wallet (default: current) is backed up, by typing part of it"),
+    ("help.wallet_public", "Show public key of the wallet (default: current)"),
+    ("help.wallet_switch", "Switch to the specified wallet (default: current)"),
+    ("help.wallet_add_external", "Add a wallet signed by an external command"),
+    ("help.wallet_import_qr", "Import a private key from a QR code image"),
+    ("help.wallet_import_json", "Import the keys of a JSON keystore file, or only check them"),
+    ("help.wallet_merge", "Move all funds of one wallet into another"),
+    ("help.change_pin", "Change wallet PIN"),
+    ("help.set", "Change a setting"),
+    ("help.session_reset", "Forget the inputs this wallet spent this session, --all for every wallet"),
+    ("help.config", "Settings commands"),
+    ("help.more", "Type 'help <command>' for argument formats, flags and examples."),
+    ("help.usage", "Usage:"),
+    ("help.also", "Also:"),
+    ("help.examples", "Examples:"),
+    ("did_you_mean", "Did you mean '{}'?"),
+    ("unknown_command_suggest", "Unknown command '{}'. Did you mean '{}'?"),
+    ("unknown_subcommand_suggest", "Unknown wallet subcommand '{}'. Did you mean '{}'?"),
+    ("autocorrect_confirm", "Run '{}' instead?"),
+    ("help.set_kdf", "Show or change PIN key derivation settings"),
+    ("help.set_prompt_balance", "Show the balance in the prompt"),
+    ("help.set_bell", "Ring the bell when a send completes"),
+    ("help.set_quiet", "Silence all sounds"),
+    ("help.set_auto_login", "Log in to the last used wallet without the menu"),
+    ("help.set_pager", "Page output too long for the terminal through $PAGER"),
+    ("help.set_autocorrect", "Offer to run the command a one-letter typo meant"),
+    ("help.set_autosave", "Save changes as they are made, or only on save and exit"),
+    ("help.set_encrypt_snapshots", "Encrypt the balances snapshot records"),
+    ("help.set_log_file", "Write the troubleshooting log"),
+    ("help.save", "Write unsaved changes to the wallet file"),
+    ("help.compact", "Back up and rewrite the wallet file without redundant entries"),
+    ("save.done", "Wallets saved."),
+    ("save.unsaved", "There are changes that haven't been saved."),
+    ("save.confirm_exit", "Save them before exiting?"),
+    ("save.discarded", "Unsaved changes discarded."),
+    (
+        "piped.no_wallet",
+        "Piped commands run in the last wallet logged in to, and there is none: log in once \
+         without piping to create or pick it",
+    ),
+    ("piped.interactive_only", "{} needs a terminal, it can't be piped."),
+    ("piped.needs_assume_yes", "(no terminal to answer on: run with --assume-yes to answer yes)"),
+    ("help.set_thousands_separator", "Digit grouping: comma, period, space, apostrophe, none"),
+    ("help.set_unit", "Show amounts in SNAP or in nano"),
+    ("help.set_lang", "Show messages in a language, or follow the environment with auto"),
+    ("help.set_notify", "Announce incoming payments with a desktop notification or the bell"),
+    ("help.set_donation_prompt", "Show the donation line at every start, once a week, or never"),
+    ("help.set_low_balance", "Notify when the balance drops below an amount"),
+    ("help.nano", "Show the amounts of one command in nano"),
+    ("help.full", "Print ids and addresses in full, however narrow the terminal"),
+    ("help.json", "One JSON object for scripts: balance, available, history, tx-info, send"),
+    ("help.set_pin_timeout", "Give up on PIN prompts after this long"),
+    ("help.set_note_max_age", "Age at which prune removes notes"),
+    ("help.help", "Show this help message"),
+    ("help.clear", "Clear the screen"),
+    ("help.history_list", "List the command history, numbered for !<n>"),
+    ("help.clear_history", "Forget the command history, also on disk"),
+    ("clear.notice", "Note: clear now only clears the screen. Use clear-history to forget past commands."),
+    ("clear_history.confirm", "Forget every command in the history, also on disk?"),
+    ("clear_history.done", "Command history cleared."),
+    ("help.suspend", "Step away: forget the keys until the PIN is entered again"),
+    ("help.exit", "Exit the wallet"),
+    ("help.alias", "Command aliases"),
+    ("help.alias_add", "Run <cmd...> when <name> is typed"),
+    ("help.alias_remove", "Remove an alias"),
+    ("help.alias_list", "List aliases"),
+    ("help.aliases", "Your aliases:"),
+    ("balance", "Balance: {}"),
+    ("spendable", "Spendable now: {}"),
+    ("invalid_amount", "Invalid amount: {}"),
+    ("amount.too_precise", "Invalid amount {}: at most {} decimal places are allowed"),
+    ("amount.too_large", "Invalid amount {}: more than the largest amount there can be"),
+    ("send.amount_not_positive", "Amounts to send must be more than zero: {}"),
+    ("send.percent_out_of_range", "Percentages must be more than 0% and at most 100%: {}"),
+    ("send.share", "{} of the spendable {}"),
+    ("invalid_address", "Invalid public address: {}"),
+    ("send.usage", "Usage: send <receiver> <amount> [--label <text>] [...more pairs] [--no-refresh]"),
+    ("send.inputs_spent", "{} input(s) were spent elsewhere since the transaction was built, building it again from the current outputs."),
+    ("send.wizard_intro", "Enter the payment step by step. An empty answer goes back, Ctrl+C cancels."),
+    ("send.wizard_recipient", "Recipient (address or contact): "),
+    ("send.wizard_amount", "Amount to {} (up to {}): "),
+    ("send.wizard_exceeds", "That is more than the {} you can still send."),
+    ("send.wizard_another", "Add another recipient?"),
+    ("send.data_unsupported", "Not supported: Snap Coin transactions can't carry data, only inputs and outputs."),
+    ("send.build_failed", "Failed to create transaction: {}"),
+    ("send.computing_pow", "Computing Proof of Work..."),
+    ("send.pow_reused", "Reusing the Proof of Work from the previous attempt..."),
+    ("send.summary", "Paying:"),
+    ("send.fee", "  Fee: {}"),
+    ("send.confirm", "Send this transaction?"),
+    ("send.confirm_rebuilt", "Send the transaction built again?"),
+    ("send.difficulty_increased", "The network difficulty went up since the Proof of Work was computed, the node would likely refuse this transaction."),
+    ("send.recompute_pow", "Recompute the Proof of Work at the new difficulty? Otherwise the send is cancelled."),
+    ("send.created", "Created transaction: {}"),
+    ("send.submitting", "Submitting transaction..."),
+    ("send.status", "Transaction submission status: {}"),
+    ("send.validating", "Validating submission..."),
+    ("send.submitted", "Transaction successfully submitted."),
+    ("send.saved_inputs", "Saved spent UTXOs to session."),
+    ("send.label", "label: {}"),
+    ("send.invalid_label", "Invalid label: {}."),
+    ("send.nothing_sent", "Nothing was sent, to any recipient."),
+    (
+        "send.unexpected_tx",
+        "The transaction built is {} rather than the expected {}, nothing was sent.",
+    ),
+    ("send.label_saved", "Saved as the transaction's note (not broadcast): {}"),
+    ("send.failed", "Transaction failed to submit."),
+    ("pin.incorrect", "PIN incorrect!"),
+    ("wallet.deleted", "Wallet '{}' deleted."),
+    ("wallet.not_deleted", "Incorrect PIN. Wallet not deleted."),
+    ("wallet.switched", "Switched to wallet '{}'."),
+    ("wallet.none_remaining", "No wallets remaining."),
+    (
+        "suspend.not_saved",
+        "Unsaved changes couldn't be written, the session is not suspended.",
+    ),
+    ("suspend.done", "Suspended: the keys are forgotten and the node disconnected."),
+    ("suspend.prompt", "Press Enter to resume, Ctrl+C to quit. "),
+    ("suspend.no_node", "Can't reconnect to {}: {}"),
+    ("wallet.private_denied", "Incorrect PIN. Cannot show private key."),
+    ("wallet.private_key", "Private key of '{}': {}"),
+    ("wallet.external_no_key", "Wallet '{}' signs with an external signer, no private key is stored. Signer command: {}"),
+    ("backup.no_key", "Wallet '{}' has no private key to back up."),
+    ("lang.set", "Language set to {}."),
+    ("secret.close_hint", "Press any key to close, nothing shown here stays on the screen."),
+    ("wallet.public_key", "Public key of '{}': {}"),
+    ("change_pin.denied", "Incorrect PIN. Cannot change pin."),
+    ("change_pin.mismatch", "PINs do not match. Cannot change pin."),
+    ("change_pin.done", "Changed PIN. The wallet file is saved with it, carry on as before."),
+    (
+        "change_pin.not_saved",
+        "The wallet file couldn't be rewritten, the old PIN is still in effect.",
+    ),
+    (
+        "change_pin.file_lost",
+        "The wallet file on disk opens with neither PIN. Run `save` to write this session's \
+         wallets with the old PIN.",
+    ),
+    ("change_pin.kept", "PIN not changed."),
+    ("kdf.done", "Re-encrypted wallets with new KDF settings."),
+    (
+        "kdf.not_saved",
+        "The wallet file couldn't be rewritten, the old KDF settings are still in effect.",
+    ),
+    (
+        "kdf.file_lost",
+        "The wallet file on disk opens with neither the old nor the new KDF settings. Run \
+         `save` to write this session's wallets with the old ones.",
+    ),
+    ("save.failed", "Failed to save wallets: {}"),
+    ("save.no_path", "Could not determine wallet path: {}"),
+    ("save.not_encrypted", "Failed to encrypt wallets — wallets NOT saved!"),
+    ("read_failed", "Can't read {}: {}"),
+    ("rewrite.encrypt_failed", "Failed to encrypt the wallets with the new keys."),
+    ("rewrite.not_opening", "The rewritten wallet file doesn't open with the new PIN."),
+    ("rewrite.restore_failed", "Putting the previous one back failed too: {}"),
+    ("column.tx_id", "TX ID"),
+    ("column.index", "INDEX"),
+    ("column.amount", "AMOUNT"),
+    ("column.age", "AGE"),
+    ("column.flags", "FLAGS"),
+    ("column.inputs", "INPUTS"),
+    ("column.outputs", "OUTPUTS"),
+    ("column.time", "TIME (UTC)"),
+    ("column.balance", "BALANCE"),
+    ("column.change", "CHANGE"),
+    ("available.session_spent", "session-spent"),
+    ("available.header", "Available UTXOs ({}):"),
+    ("balance_history.showing", "Showing the last {} of {} snapshots."),
+    ("balance_history.trend", "Trend: {}"),
+    ("mempool.none_mine", "No transactions of this wallet are in the mempool."),
+    ("mempool.empty", "The mempool is empty."),
+    ("mempool.yours", "yours"),
+    ("mempool.header", "Mempool ({} transactions):"),
+    ("send.contact_note", "note: {}"),
+    ("json.unsupported", "{} doesn't take --json"),
+    ("balance.usage", "Usage: balance [wallet]"),
+    ("balance.fetching", "Fetching balance..."),
+    ("available.fetching", "Fetching available outputs..."),
+    ("available.exported", "Exported {} available outputs."),
+    ("available.usage", "Usage: available [--sort age|amount|txid] | available export <path|->"),
+    ("estimate.usage", "Usage: estimate <total amount> [num_recipients]"),
+    ("estimate.invalid_recipients", "Invalid number of recipients: {}"),
+    ("estimate.insufficient", "Insufficient funds: {} spendable, {} requested."),
+    ("estimate.header", "Estimate (not a quote, the network may change before you send):"),
+    ("estimate.inputs", "  Inputs selected:   ~{}"),
+    ("estimate.outputs", "  Outputs:           ~{} ({} recipients)"),
+    ("estimate.outputs_change", "  Outputs:           ~{} ({} recipients + change)"),
+    ("estimate.size", "  Transaction size:  ~{} bytes"),
+    ("estimate.pow", "  PoW cost:          ~{} hashes at current live difficulty"),
+    ("estimate.fee", "  Fee:               none, the sender pays in PoW instead"),
+    (
+        "estimate.too_large",
+        "  Warning: exceeds the {} input/output limit, the send would have to be split.",
+    ),
+    ("invalid_limit", "Invalid limit: {}"),
+    ("history.usage", "Usage: history [--limit <n>]"),
+    ("history.fetching", "Fetching history..."),
+    ("ctrl_c_stops", "(Ctrl+C to stop)"),
+    ("history.header_partial", "Transaction History ({} items, showing {}):"),
+    ("history.header", "Transaction History ({} items):"),
+    ("column.note", "NOTE"),
+    ("history.fetching_count", "Fetching history... {}/{} transactions"),
+    ("history.not_found", "(not found)"),
+    ("stopped", "Stopped."),
+    ("report.usage", "Usage: report <year> --out <path> [--wallet <name>]"),
+    ("report.fetching", "Fetching transactions... {}/{}"),
+    ("report.stopped", "Stopped, no report written."),
+    (
+        "report.missing_timestamps",
+        "{} transactions have no timestamp and are left out, so the running balance may be off.",
+    ),
+    (
+        "report.unknown_inputs",
+        "{} sends spent outputs missing from the history, so their fees and the running balance \
+         may be off.",
+    ),
+    ("report.written", "Wrote {} transactions of {} in wallet '{}' to {}."),
+    ("snapshot.usage", "Usage: snapshot"),
+    (
+        "snapshot.external_signer",
+        "Snapshots of a wallet with an external signer can't be encrypted, its key never reaches \
+         the wallet. `set encrypt-snapshots off` records them in plain text.",
+    ),
+    ("snapshot.encrypt_failed", "Failed to encrypt the snapshot."),
+    ("snapshot.recorded", "Recorded a balance of {} for wallet '{}'."),
+    ("snapshot.recorded_encrypted", "Recorded a balance of {} for wallet '{}', encrypted."),
+    ("balance_history.usage", "Usage: balance-history [--limit <n>]"),
+    ("balance_history.none", "No snapshots of wallet '{}' yet, `snapshot` records one."),
+    ("mempool.usage", "Usage: mempool [--mine]"),
+    ("mempool.fetching", "Fetching the mempool..."),
+    ("watch.usage", "Usage: watch <command> [interval_seconds]"),
+    ("watch.not_watchable", "Only read-only commands can be watched: {}"),
+    ("watch.invalid_args", "Invalid arguments for {}"),
+    ("watch.header", "Every {}s: {}    {} UTC    (Ctrl+C to stop)"),
+    ("vanity.usage", "Usage: vanity <prefix>"),
+    ("vanity.invalid_prefix", "Invalid prefix: {}."),
+    ("vanity.expected", "Expect about {} keys to be tried."),
+    ("vanity.slow", "Long prefixes can take hours or days. Ctrl+C stops the search."),
+    ("vanity.confirm_start", "Start searching?"),
+    ("vanity.searching", "Searching..."),
+    ("vanity.progress", "Searching... {} keys tried, {} keys/s, about {} left on average"),
+    ("vanity.stopped", "Stopped after {} keys."),
+    ("vanity.found", "Found after {} keys: {}"),
+    ("vanity.confirm_save", "Save it as a new wallet?"),
+    ("vanity.not_saved", "Not saved; the key is gone once you leave this prompt."),
+    ("vanity.name_prompt", "Name for the new wallet: "),
+    ("sign.usage", "Usage: sign <message>"),
+    ("sign.external_signer", "Wallets with an external signer can't sign messages."),
+    ("sign.denied", "Incorrect PIN."),
+    ("sign.address", "Address:   {}"),
+    ("sign.message", "Message:   {}"),
+    ("sign.signature", "Signature: {}"),
+    ("sign.check_with", "Check with: verify {} {} {}"),
+    ("verify.usage", "Usage: verify <address> <message> <signature>"),
+    ("verify.invalid_signature", "Invalid signature."),
+    ("verify.valid", "Valid: the message was signed by this address."),
+    ("verify.not_valid", "NOT valid for this address and message."),
+    ("config.usage", "Usage: config show"),
+    ("tx_info.usage", "Usage: tx-info <TXID>"),
+    ("tx_info.header", "Transaction Details: {}"),
+    ("explorer", "Explorer: {}"),
+    ("tx_info.note", "Note: {}"),
+    ("tx_info.timestamp", "Timestamp: {}"),
+    ("tx_info.nonce", "Nonce: {}"),
+    ("tx_info.inputs", "Inputs ({}):"),
+    ("tx_info.input", "{}:{} from {}"),
+    ("tx_info.outputs", "Outputs ({}):"),
+    ("tx_info.output", "{} to {}"),
+    ("tx_info.fee_reward", "Fee: none, a block reward ({} bytes)"),
+    ("tx_info.fee", "Fee: {}"),
+    ("tx_info.fee_unknown", "Fee: unknown, a spent output wasn't found"),
+    ("tx_info.not_found", "Transaction not found: {}"),
+    ("tx_info.invalid_id", "Invalid TX ID: {}"),
+    ("tx_note.usage", "Usage: tx-note <TXID> [<text...>]"),
+    ("tx_note.removed", "Removed the note on {}."),
+    ("tx_note.none", "Transaction {} has no note."),
+    ("tx_note.saved", "Saved the note on {}."),
+    ("invalid_days", "Invalid number of days: {}"),
+    ("prune.usage", "Usage: prune [--older-than <days>]"),
+    ("prune.no_notes", "There are no transaction notes."),
+    ("prune.nothing", "Nothing to prune."),
+    ("prune.candidates", "These notes would be removed:"),
+    ("prune.confirm", "Remove {} note(s)?"),
+    ("prune.done", "Removed {} note(s)."),
+    ("send.checking_inputs", "Checking the inputs are still unspent..."),
+    ("send.checking_mempool", "Checking the mempool..."),
+    ("status.version", "Version: {}"),
+    ("status.wallet", "Wallet: {} ({})"),
+    ("status.local_key", "local key"),
+    ("status.external_signer", "external signer"),
+    ("status.signing", "Signing: {}"),
+    ("status.file", "Wallet file: {}"),
+    ("status.file_unknown", "Wallet file: unknown ({})"),
+    ("status.unsaved_yes", "yes, `save` writes them"),
+    ("status.unsaved_autosave_on", "none (autosave on)"),
+    ("status.unsaved_autosave_off", "none (autosave off)"),
+    ("status.unsaved", "Unsaved changes: {}"),
+    ("status.spent_in", "{} in {}"),
+    ("status.session_spent_none", "Session-spent inputs: none"),
+    ("status.session_spent", "Session-spent inputs: {}"),
+    ("status.outbox", "Outbox: none, sends are submitted right away"),
+    ("status.spend_limit", "Spend limit: none"),
+    ("status.auto_lock_off", "Auto-lock: off, PIN prompts wait forever"),
+    ("status.auto_lock_timeout", "Auto-lock: off, PIN prompts give up after {} s"),
+    ("status.node_unreachable", "unreachable: {}"),
+    ("status.node", "Node: {} ({})"),
+    ("status.node_timeout", "no answer within {} s"),
+    ("status.node_answered", "Node: {} (answered in {} ms)"),
+    ("status.pending", "Pending transactions: {}"),
+    ("status.clock_ahead", "{} seconds ahead of the network, transaction timestamps are corrected"),
+    ("status.clock_behind", "{} seconds behind the network, transaction timestamps are corrected"),
+    ("status.clock", "Clock: {}"),
+    ("status.clock_in_sync", "Clock: in sync with the network ({} s)"),
+    ("status.clock_unknown", "Clock: unknown, too few mempool transactions to compare with"),
+    ("diagnose.summary", "{} passed, {} warnings, {} failed, {} skipped"),
+    ("log.off", "Logging is off, start with --log-file or run `set log-file on`."),
+    ("log.usage", "Usage: log path"),
+    ("send.submitting_short", "Submitting..."),
+    (
+        "session.header",
+        "Inputs spent this session by '{}' ({} items, excluded from its new sends):",
+    ),
+    ("session.input", "{} (output {})"),
+    ("session.none", "No inputs tracked this session."),
+    (
+        "session.reset_warning",
+        "Warning: only reset if the node restarted or a sent transaction was dropped.",
+    ),
+    (
+        "session.reset_risk",
+        "If those transactions are still pending, new sends may try to double-spend their inputs \
+         and get rejected.",
+    ),
+    ("session.reset_confirm", "Clear the session inputs?"),
+    ("session.not_cleared", "Session inputs not cleared."),
+    ("session.cleared", "Cleared {} session inputs."),
+    ("session.unknown", "Unknown session subcommand: {}"),
+    ("contact.usage_add", "Usage: contact add <alias> <address>"),
+    ("contact.invalid_alias", "Invalid alias: {}."),
+    ("contact.exists", "Contact '{}' already exists."),
+    ("contact.alias_is_address", "A contact alias can't itself be an address."),
+    ("contact.added", "Added contact '{}'."),
+    ("contact.usage_remove", "Usage: contact remove <alias>"),
+    ("contact.not_found", "Contact '{}' not found."),
+    ("contact.removed", "Removed contact '{}'."),
+    ("contact.usage_rename", "Usage: contact rename <old> <new>"),
+    ("contact.renamed", "Renamed contact '{}' to '{}'."),
+    ("contact.usage_note", "Usage: contact note <alias> [text...]"),
+    ("contact.note_cleared", "Cleared note of '{}'."),
+    ("contact.note_updated", "Updated note of '{}'."),
+    ("contact.header", "Contacts ({} items):"),
+    ("contact.unknown", "Unknown contact subcommand: {}"),
+    ("alias.usage_add", "Usage: alias add <name> <command...>"),
+    ("alias.builtin", "'{}' is a built-in command and can't be an alias."),
+    ("alias.not_added", "Alias not added: {}"),
+    ("alias.added", "Added alias '{}'."),
+    ("alias.usage_remove", "Usage: alias remove <name>"),
+    ("alias.not_found", "Alias '{}' not found."),
+    ("alias.removed", "Removed alias '{}'."),
+    ("alias.header", "Aliases ({} items):"),
+    ("alias.unknown", "Unknown alias subcommand: {}"),
+    (
+        "wallet.usage",
+        "Usage: wallet <list|delete|private|confirm-backup|public|switch|add-external|import-qr|\
+         import-json|merge> [wallet_name]",
+    ),
+    ("wallet.usage_list", "Usage: wallet list [--balances]"),
+    ("column.wallet", "WALLET"),
+    ("column.address", "ADDRESS"),
+    ("column.type", "TYPE"),
+    ("wallet.fetching_balances", "Fetching balances..."),
+    ("wallet.kind_local", "local"),
+    ("wallet.kind_external", "external"),
+    ("wallet.usage_private", "Usage: wallet private [<wallet>] [--screen] [--qr] [--checksummed]"),
+    ("wallet.usage_add_external", "Usage: wallet add-external <wallet> <public_key> <command...>"),
+    ("wallet.invalid_name", "Invalid wallet name: {}."),
+    ("wallet.exists", "Wallet '{}' already exists."),
+    ("wallet.external_added", "Added external signer wallet '{}'."),
+    ("wallet.usage_import_qr", "Usage: wallet import-qr <image> [<wallet>]"),
+    ("wallet.invalid_qr_key", "Invalid base36 private key: {}"),
+    ("wallet.key_exists", "This key is already wallet '{}'."),
+    ("wallet.qr_address", "The code holds the key of address {}"),
+    ("wallet.confirm_import_qr", "Import it as wallet '{}'?"),
+    ("wallet.usage_import_json", "Usage: wallet import-json <path> [--dry-run]"),
+    ("wallet.not_keystore", "Not a keystore file: {}."),
+    ("wallet.would_import", "would import"),
+    ("wallet.to_import", "to import"),
+    ("wallet.import_duplicate", "skipped, already wallet '{}'"),
+    ("wallet.import_invalid", "invalid, {}"),
+    ("wallet.import_summary", "{} to import, {} duplicates skipped, {} invalid."),
+    ("wallet.confirm_import_json", "Import {} wallets?"),
+    ("wallet.imported", "Imported {} wallets."),
+    ("wallet.usage_merge", "Usage: wallet merge <source> <destination>"),
+    ("wallet.merge_into_itself", "Cannot merge a wallet into itself."),
+    ("wallet.merge_empty", "Wallet '{}' has nothing to send."),
+    ("wallet.merge_summary", "Moving {} from '{}' to '{}' ({}) in {} transaction(s)."),
+    ("wallet.merge_confirm", "Merge these wallets?"),
+    ("wallet.merge_built", "Built transaction: {}"),
+    ("wallet.merged", "Merged '{}' into '{}'."),
+    ("wallet.unknown", "Unknown wallet subcommand: {}"),
+    ("compact.usage", "Usage: compact"),
+    ("compact.no_file", "There is no wallet file to compact yet, run save first."),
+    ("compact.version", "version {}"),
+    ("compact.legacy", "the legacy format"),
+    ("compact.rewrite", "The wallet file, now in {}, will be rewritten in format version {}."),
+    ("compact.nothing", "No duplicate wallets or stale entries to remove"),
+    ("compact.duplicate", "Remove wallet '{}', wallet '{}' holds its key"),
+    ("compact.stale_backup", "Drop the backup confirmation of deleted wallet '{}'"),
+    ("compact.old_backups", "Remove {} old backups, keeping the newest {}"),
+    ("compact.backed_up", "The current file is backed up to {} first."),
+    ("compact.confirm", "Compact the wallet file?"),
+    ("compact.unchanged", "The wallet file is unchanged, old backups were kept."),
+    ("compact.size", "Wallet file: {} bytes, was {}."),
+    ("compact.reclaimed", "Reclaimed {} bytes. The backup in {} takes {}."),
+    ("change_pin.derive_failed", "Failed to derive key for the new PIN. Cannot change pin."),
+    ("kdf.missing_value", "Missing value for {}"),
+    ("kdf.unknown_option", "Unknown option: {}"),
+    ("kdf.invalid_value", "Invalid value for {}: {}"),
+    ("kdf.current", "KDF: {}, memory: {} KiB, iterations: {}, parallelism: {}"),
+    ("kdf.invalid", "Invalid KDF parameters: {}"),
+    (
+        "kdf.above_ceiling",
+        "Warning: these parameters are above the sanity ceiling, every future load will ask for \
+         confirmation.",
+    ),
+    ("prompt.pin_kdf", "Enter current PIN to re-encrypt wallets: "),
+    ("kdf.denied", "Incorrect PIN. KDF settings not changed."),
+    ("kdf.derive_failed", "Failed to derive key with the new KDF settings."),
+    ("set.usage_switch", "Usage: set {} on|off"),
+    ("set.prompt_balance", "Prompt balance"),
+    ("set.bell", "Bell"),
+    ("set.auto_login", "Automatic login"),
+    ("set.pager", "Pager"),
+    ("set.autocorrect", "Autocorrect"),
+    ("set.autosave", "Autosave"),
+    ("set.encrypt_snapshots", "Snapshot encryption"),
+    ("set.log_file", "Operation log"),
+    ("set.quiet", "Quiet mode"),
+    ("set.enabled", "{} enabled."),
+    ("set.disabled", "{} disabled."),
+    ("set.usage_pin_timeout", "Usage: set pin-timeout <seconds>   (0 waits forever)"),
+    ("set.pin_timeout_off", "PIN prompts now wait forever."),
+    ("set.pin_timeout", "PIN prompts now time out after {} seconds."),
+    ("set.usage_separator", "Usage: set thousands-separator {}"),
+    ("set.separator", "Amounts now look like {}."),
+    ("set.usage_unit", "Usage: set unit snap|nano"),
+    ("set.unit", "Amounts are now shown in {}."),
+    ("set.usage_lang", "Usage: set lang en|es|auto"),
+    ("set.usage_donation_prompt", "Usage: set donation-prompt {}"),
+    ("set.donation_always", "The donation line shows at every start."),
+    ("set.donation_weekly", "The donation line shows once a week."),
+    ("set.donation_off", "The donation line is off. Thank you!"),
+    ("set.usage_notify", "Usage: set notify {}"),
+    ("set.notify_off", "Notifications are off."),
+    ("set.notify_on", "Notifications are on ({})."),
+    ("set.usage_low_balance", "Usage: set low-balance <amount>|off"),
+    ("set.low_balance", "You'll be notified when the balance drops below {}."),
+    ("set.low_balance_notify_off", "Notifications are off, turn them on with `set notify`."),
+    ("set.low_balance_off", "Low balance notifications are off."),
+    ("set.usage_note_max_age", "Usage: set note-max-age <days>   (0 keeps notes forever)"),
+    ("set.note_max_age_off", "prune now keeps notes regardless of age."),
+    ("set.note_max_age", "prune now removes notes older than {} days."),
+    ("open.usage", "Usage: open <txid|address|alias>"),
+    ("open.unknown_target", "Not a known transaction or an address: {}"),
+    ("open.opening", "Opening {}"),
+    ("uri.usage_install", "Usage: install-uri-handler"),
+    ("uri.installed", "{}: links now open in this wallet, see {}"),
+    ("uri.installed_note", "A clicked link still asks for confirmation and the PIN."),
+    ("webhook.usage", "Usage: webhook test"),
+    ("webhook.not_set", "No webhook is set, add webhook_url = <url> to ~/.snap-coin-config."),
+    ("webhook.posting", "Posting a test payload..."),
+    ("webhook.accepted", "{} accepted the test payload."),
+    ("webhook.failed", "The webhook failed: {}"),
+    ("merge.nothing", "Wallet '{}' has nothing to merge."),
+    ("merge.summary", "Merging {} outputs worth {} into {} transaction(s)."),
+    ("merge.confirm", "Merge these outputs?"),
+    ("merge.done", "Merged available utxos ({}) into {} utxos"),
+    ("rpc.pin_locked", "Too many wrong PINs, restart serve to confirm sends again"),
+    ("rpc.unknown_proposal", "No proposal {}, it was confirmed, dropped or replaced"),
+    ("rpc.confirm_locked", "Too many wrong PINs, confirm_send is locked."),
+    ("rpc.wrong_pin", "Wrong PIN, the proposal was dropped"),
+    ("rpc.other_wallet", "The proposal spends from wallet {}"),
+    ("rpc.send_cancelled", "The send was cancelled, create it again"),
+    ("rpc.token_file_unreadable", "Can't read the token file {}: {}"),
+    ("rpc.token_file_empty", "The token file {} is empty"),
+    ("rpc.invalid_listen", "Invalid listen address {}: {}"),
+    ("rpc.not_loopback", "{} isn't a loopback address, serve only listens locally"),
+    ("rpc.cant_listen", "Can't listen on {}: {}"),
+    ("rpc.token_in", "Clients authenticate with the token in {}."),
+    ("rpc.token_for_run", "Token for this run: {}"),
+    ("rpc.serving", "Serving wallet '{}' on http://{}, Ctrl+C stops."),
+    ("rpc.stopped", "Stopped serving."),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("banner", "--- Monedero Snap Coin ---"),
+    ("prompt.pin", "Introduce el PIN de 6 dígitos del monedero: "),
+    ("prompt.pin_confirm", "Confirma el PIN de 6 dígitos del monedero: "),
+    ("prompt.pin_send", "Introduce el PIN de 6 dígitos para confirmar: "),
+    ("prompt.pin_current", "Introduce el PIN actual: "),
+    ("prompt.pin_new", "Crea un nuevo PIN de 6 dígitos: "),
+    ("prompt.pin_new_confirm", "Confirma el nuevo PIN de 6 dígitos: "),
+    ("wallet.confirm_delete", "¿Borrar el monedero '{}'? Sin una copia de su clave, las monedas se pierden"),
+    ("prompt.pin_delete", "Introduce el PIN para confirmar el borrado de '{}': "),
+    ("prompt.pin_private", "Introduce el PIN para ver la clave privada de '{}': "),
+    ("prompt.login", "Número o nombre del monedero para iniciar sesión: "),
+    ("prompt.new_wallet_name", "Nombre para el nuevo monedero: "),
+    (
+        "prompt.import_key",
+        "Clave privada base36 a importar (vacío para generar una aleatoria): ",
+    ),
+    ("prompt.menu_choice", "Elige una opción (1 o 2): "),
+    ("prompt.continue_loading", "¿Continuar la carga?"),
+    ("confirm.invalid", "Responde y (sí) o n (no)."),
+    ("menu.select", "1) Seleccionar un monedero existente [por defecto]"),
+    ("menu.create", "2) Crear un monedero nuevo"),
+    ("error.invalid_choice", "Opción no válida, introduce 1 o 2."),
+    ("error.pins_mismatch", "Los PIN no coinciden"),
+    ("error.invalid_private_key", "Clave privada base36 no válida"),
+    ("error.home_dir", "No se pudo determinar el directorio personal"),
+    ("error.encrypt", "No se pudieron cifrar los monederos"),
+    ("error.wallet_locked", "Otro monedero en ejecución está usando el archivo del monedero; ciérralo primero"),
+    ("error.derive_key", "No se pudo derivar la clave del monedero"),
+    ("error.file_header", "Cabecera del archivo de monederos no reconocida"),
+    ("error.load_aborted", "Carga del archivo de monederos cancelada"),
+    ("error.decrypt", "No se pudieron descifrar los monederos (¿PIN incorrecto?)"),
+    ("no_wallets", "No hay monederos. Creando un monedero nuevo."),
+    ("available_wallets", "Monederos disponibles:"),
+    ("default_marker", " [por defecto]"),
+    ("auto_login", "Entrando a '{}' (inicia con --choose para elegir otro monedero)"),
+    ("wallet_not_found_retry", "No se encontró el monedero '{}'. Inténtalo de nuevo."),
+    ("wallet_name_required", "No hay monedero por defecto: introduce un número del 1 al {} o el nombre de un monedero."),
+    ("wallet_exists_retry", "El monedero '{}' ya existe. Elige otro nombre."),
+    ("wallet.limit_reached", "El archivo ya contiene {} monederos, el máximo permitido. Borra uno o sube max_wallets en ~/.snap-coin-config."),
+    ("invalid_name_retry", "Nombre no válido: {}. Inténtalo de nuevo."),
+    ("wallet_not_found", "No se encontró el monedero '{}'."),
+    ("balance.of_wallet", "Monedero '{}':"),
+    ("wallet_created", "Monedero '{}' creado correctamente."),
+    (
+        "backup.save_key",
+        "¡Guarda la clave privada del monedero en un LUGAR SEGURO y SIN CONEXIÓN!",
+    ),
+    ("backup.key", "Clave privada del monedero (base 36): {}"),
+    (
+        "backup.loss_warning",
+        "!!! Si pierdes esta clave, PERDERÁS tus snap coins. NO hay forma de recuperarlas !!!",
+    ),
+    (
+        "backup.theft_warning",
+        "!!! Cualquiera que vea esta clave PUEDE robar tus snap coins y LO HARÁ !!!",
+    ),
+    ("backup.reminder", "El monedero '{}' contiene {} pero nunca se confirmó la copia de seguridad de su clave. Anótala con `wallet private {} --screen` y después ejecuta `wallet confirm-backup {}`."),
+    ("backup.challenge", "Introduce los caracteres {} a {} de la clave privada de '{}': "),
+    ("backup.confirmed", "Copia de seguridad del monedero '{}' confirmada."),
+    ("backup.mismatch", "No coincide con la clave. Copia de seguridad no confirmada."),
+    ("uri.invalid", "Enlace de pago no válido, no se envió nada: {}"),
+    ("uri.request", "Enlace de pago a {}"),
+    ("uri.label", "Etiqueta indicada por el enlace: {}"),
+    ("uri.amount_prompt", "El enlace no indica importe. Importe a enviar (vacío para cancelar): "),
+    (
+        "kdf.expensive_warning",
+        "AVISO: El archivo de monederos pide una derivación de clave inusualmente costosa ({} KiB de memoria, {} iteraciones, {} carriles).",
+    ),
+    (
+        "kdf.tamper_warning",
+        "Un archivo manipulado puede usar esto para agotar la memoria o bloquear el monedero.",
+    ),
+    ("loaded_wallet", "Monedero '{}' cargado con clave pública: {}"),
+    ("donate", "Considera donar al desarrollador :) {}"),
+    ("connected", "Conectado al nodo en {}"),
+    ("clock.skew_warning", "Aviso: tu reloj parece desviado {} segundos respecto a la red. Las marcas de tiempo de las transacciones se corregirán, pero conviene ajustar el reloj del sistema."),
+    ("interrupt.hint", "Pulsa Ctrl+D o escribe 'exit' para salir."),
+    ("interrupt.finishing", "Terminando primero el envío, no se puede detener a medias."),
+    ("command_error", "El comando falló: {}"),
+    ("cancelled", "Cancelado."),
+    ("error_code.with_code", "[{}] {}"),
+    ("error_code.failed", "El comando falló."),
+    ("error_code.usage", "Argumentos no válidos."),
+    ("error_code.wrong_pin", "PIN incorrecto."),
+    ("error_code.node_unreachable", "No se puede contactar con el nodo."),
+    ("error_code.insufficient_funds", "Fondos insuficientes."),
+    ("error_code.rejected", "El nodo no aceptó la transacción."),
+    ("error_code.wallet_locked", "El archivo del monedero está en uso."),
+    ("error_code.invalid_address", "Dirección no válida."),
+    ("error_code.invalid_amount", "Cantidad no válida."),
+    ("pin.timed_out", "Se agotó el tiempo para introducir el PIN."),
+    ("pin.digits_only", "(solo dígitos, ? para ayuda)"),
+    ("pin.key_help", "(6 dígitos, Retroceso corrige, Esc cancela)"),
+    ("pin.no_enter", "(no hace falta Enter, termina a los 6 dígitos)"),
+    ("pin.weak", "Este PIN es fácil de adivinar: {}. El PIN es lo único que protege el archivo de monederos."),
+    ("pin.weak_repeated", "un mismo dígito repetido"),
+    ("pin.weak_sequence", "dígitos consecutivos"),
+    ("pin.weak_pattern", "un grupo corto de dígitos repetido"),
+    ("pin.weak_common", "uno de los PIN más usados"),
+    ("pin.weak_confirm", "¿Usarlo de todos modos?"),
+    ("exiting_eof", "Saliendo (Ctrl+D)"),
+    ("current_wallet_missing", "No se encontró el monedero actual '{}'."),
+    ("unknown_command", "Comando desconocido: '{}'. Escribe 'help' para ver los comandos."),
+    ("help.header", "Comandos disponibles:"),
+    ("help.subcommands", "subcomandos:"),
+    ("help.balance", "Mostrar el saldo del monedero y cuánto se puede enviar ya"),
+    ("help.available", "Listar los UTXO disponibles, los mayores primero"),
+    ("help.available_export", "Escribir los UTXO disponibles como CSV en un archivo, o en stdout con -"),
+    ("help.history", "Mostrar el historial de transacciones"),
+    ("help.report", "Escribir un informe CSV anual de las transacciones de un monedero para contabilidad"),
+    ("help.snapshot", "Registrar el saldo actual en el registro local de instantáneas"),
+    ("help.balance_history", "Mostrar las instantáneas de saldo registradas como tabla y gráfico"),
+    ("help.tx_info", "Mostrar los detalles de una transacción"),
+    ("help.tx_note", "Añadir una nota a una transacción, o quitarla"),
+    ("help.prune", "Borrar notas de transacciones descartadas o antiguas"),
+    ("help.estimate", "Estimar el tamaño y el coste de PoW de un pago"),
+    ("help.merge_available", "Unir todo lo disponible en un solo utxo."),
+    ("help.send", "Enviar SNAP a direcciones"),
+    ("help.session", "Mostrar o vaciar las entradas gastadas en esta sesión"),
+    ("help.status", "Resumir la sesión para informes de errores: monedero, nodo, pendientes y reloj"),
+    ("help.diagnose", "Comprobar el nodo, el archivo del monedero, los pendientes, el reloj y el saldo"),
+    ("help.log", "Mostrar dónde se escribe el registro de diagnóstico"),
+    ("help.dashboard", "Vista a pantalla completa de saldo, transacciones, UTXOs y nodo"),
+    ("help.mempool", "Listar las transacciones pendientes en el mempool, o solo las propias"),
+    ("help.watch", "Repetir balance, available o mempool cada pocos segundos"),
+    ("help.open", "Abrir una transacción o dirección en el explorador de bloques"),
+    ("help.install_uri_handler", "Hacer que los enlaces de pago snap: se abran en este monedero"),
+    ("help.webhook", "Enviar un ejemplo de notificación al webhook configurado"),
+    ("help.vanity", "Generar un monedero cuya dirección empiece por <prefix>"),
+    ("help.sign", "Firmar un mensaje para demostrar que controlas la dirección actual"),
+    ("help.verify", "Comprobar la firma de un mensaje hecha con sign"),
+    ("help.file_info", "Mostrar el formato y el KDF del archivo de monederos, sin el PIN"),
+    ("help.config_show", "Mostrar cada ajuste en uso y de dónde viene"),
+    ("help.contact", "Comandos de la libreta de direcciones"),
+    ("help.contact_add", "Guardar una dirección con un alias"),
+    ("help.contact_remove", "Eliminar un contacto"),
+    ("help.contact_rename", "Renombrar un contacto"),
+    ("help.contact_note", "Poner o borrar la nota de un contacto"),
+    ("help.contact_list", "Listar los contactos"),
+    ("help.wallet", "Comandos de gestión de monederos"),
+    ("help.wallet_list", "Listar todos los monederos, con sus saldos si se pide"),
+    ("help.wallet_delete", "Borrar el monedero indicado (por defecto: el actual)"),
+    ("help.wallet_private", "Mostrar la clave privada del monedero (por defecto: el actual), --screen la mantiene fuera del historial de la terminal, --qr muestra solo un código QR, --checksummed añade una suma de control"),
+    ("help.wallet_confirm_backup", "Confirmar que la clave del monedero (por defecto: el actual) tiene copia de seguridad, escribiendo parte de ella"),
+    ("help.wallet_public", "Mostrar la clave pública del monedero (por defecto: el actual)"),
+    ("help.wallet_switch", "Cambiar al monedero indicado (por defecto: el actual)"),
+    ("help.wallet_add_external", "Añadir un monedero firmado por un comando externo"),
+    ("help.wallet_import_qr", "Importar una clave privada desde la imagen de un código QR"),
+    ("help.wallet_import_json", "Importar las claves de un archivo JSON, o solo comprobarlas"),
+    ("help.wallet_merge", "Mover todos los fondos de un monedero a otro"),
+    ("help.change_pin", "Cambiar el PIN del monedero"),
+    ("help.set", "Cambiar un ajuste"),
+    ("help.session_reset", "Olvidar las entradas que este monedero gastó en esta sesión, --all para todos"),
+    ("help.config", "Comandos de ajustes"),
+    ("help.more", "Escribe 'help <comando>' para ver formatos, opciones y ejemplos."),
+    ("help.usage", "Uso:"),
+    ("help.also", "También:"),
+    ("help.examples", "Ejemplos:"),
+    ("did_you_mean", "¿Quisiste decir '{}'?"),
+    ("unknown_command_suggest", "Comando desconocido '{}'. ¿Quisiste decir '{}'?"),
+    ("unknown_subcommand_suggest", "Subcomando de wallet desconocido '{}'. ¿Quisiste decir '{}'?"),
+    ("autocorrect_confirm", "¿Ejecutar '{}' en su lugar?"),
+    ("help.set_kdf", "Mostrar o cambiar la derivación de clave del PIN"),
+    ("help.set_prompt_balance", "Mostrar el saldo en el indicador"),
+    ("help.set_bell", "Sonar la campana al terminar un envío"),
+    ("help.set_quiet", "Silenciar todos los sonidos"),
+    ("help.set_auto_login", "Entrar al último monedero usado sin mostrar el menú"),
+    ("help.set_pager", "Paginar con $PAGER la salida que no cabe en la terminal"),
+    ("help.set_autocorrect", "Ofrecer ejecutar el comando al que apunta una errata de una letra"),
+    ("help.set_autosave", "Guardar los cambios al hacerlos, o solo con save y al salir"),
+    ("help.set_encrypt_snapshots", "Cifrar los saldos que registra snapshot"),
+    ("help.set_log_file", "Escribir el registro de diagnóstico"),
+    ("help.save", "Escribir los cambios sin guardar en el archivo de monederos"),
+    ("help.compact", "Copiar y reescribir el archivo de monederos sin entradas redundantes"),
+    ("save.done", "Monederos guardados."),
+    ("save.unsaved", "Hay cambios sin guardar."),
+    ("save.confirm_exit", "¿Guardarlos antes de salir?"),
+    ("save.discarded", "Cambios sin guardar descartados."),
+    (
+        "piped.no_wallet",
+        "Los comandos por tubería usan el último monedero abierto, y no hay ninguno: inicia \
+         sesión una vez sin tubería para crearlo o elegirlo",
+    ),
+    ("piped.interactive_only", "{} necesita una terminal, no se puede usar por tubería."),
+    ("piped.needs_assume_yes", "(no hay terminal para responder: usa --assume-yes para responder sí)"),
+    ("help.set_thousands_separator", "Separador de miles: comma, period, space, apostrophe, none"),
+    ("help.set_unit", "Mostrar las cantidades en SNAP o en nano"),
+    ("help.set_lang", "Mostrar los mensajes en un idioma, o seguir el entorno con auto"),
+    ("help.set_notify", "Avisar de los pagos recibidos con una notificación o la campana"),
+    ("help.set_donation_prompt", "Mostrar la línea de donación en cada inicio, una vez por semana o nunca"),
+    ("help.set_low_balance", "Avisar cuando el saldo baje de una cantidad"),
+    ("help.nano", "Mostrar en nano las cantidades de un comando"),
+    ("help.full", "Mostrar ids y direcciones completos, aunque la terminal sea estrecha"),
+    ("help.json", "Un objeto JSON para scripts: balance, available, history, tx-info, send"),
+    ("help.set_pin_timeout", "Abandonar las solicitudes de PIN tras este tiempo"),
+    ("help.set_note_max_age", "Antigüedad a la que prune borra las notas"),
+    ("help.help", "Mostrar esta ayuda"),
+    ("help.clear", "Limpiar la pantalla"),
+    ("help.history_list", "Listar el historial de comandos, numerado para !<n>"),
+    ("help.clear_history", "Olvidar el historial de comandos, también en disco"),
+    ("clear.notice", "Nota: clear ahora solo limpia la pantalla. Usa clear-history para olvidar los comandos anteriores."),
+    ("clear_history.confirm", "¿Olvidar todos los comandos del historial, también en disco?"),
+    ("clear_history.done", "Historial de comandos borrado."),
+    ("help.suspend", "Ausentarse: olvidar las claves hasta que se vuelva a introducir el PIN"),
+    ("help.exit", "Salir del monedero"),
+    ("help.alias", "Alias de comandos"),
+    ("help.alias_add", "Ejecutar <cmd...> al escribir <name>"),
+    ("help.alias_remove", "Eliminar un alias"),
+    ("help.alias_list", "Listar los alias"),
+    ("help.aliases", "Tus alias:"),
+    ("balance", "Saldo: {}"),
+    ("spendable", "Disponible ahora: {}"),
+    ("invalid_amount", "Cantidad no válida: {}"),
+    ("amount.too_precise", "Cantidad no válida {}: se admiten como mucho {} decimales"),
+    ("amount.too_large", "Cantidad no válida {}: supera la mayor cantidad posible"),
+    ("send.amount_not_positive", "Las cantidades a enviar deben ser mayores que cero: {}"),
+    ("send.percent_out_of_range", "Los porcentajes deben ser mayores que 0% y como mucho 100%: {}"),
+    ("send.share", "{} de los {} disponibles"),
+    ("invalid_address", "Dirección pública no válida: {}"),
+    ("send.usage", "Uso: send <destinatario> <cantidad> [--label <texto>] [...más pares] [--no-refresh]"),
+    ("send.inputs_spent", "{} entrada(s) se gastaron en otro sitio desde que se creó la transacción, se vuelve a crear con las salidas actuales."),
+    ("send.wizard_intro", "Introduce el pago paso a paso. Una respuesta vacía vuelve atrás, Ctrl+C cancela."),
+    ("send.wizard_recipient", "Destinatario (dirección o contacto): "),
+    ("send.wizard_amount", "Cantidad para {} (hasta {}): "),
+    ("send.wizard_exceeds", "Es más de los {} que aún puedes enviar."),
+    ("send.wizard_another", "¿Añadir otro destinatario?"),
+    ("send.data_unsupported", "No disponible: las transacciones de Snap Coin no pueden llevar datos, solo entradas y salidas."),
+    ("send.build_failed", "No se pudo crear la transacción: {}"),
+    ("send.computing_pow", "Calculando la prueba de trabajo..."),
+    ("send.pow_reused", "Reutilizando la prueba de trabajo del intento anterior..."),
+    ("send.summary", "Pagando a:"),
+    ("send.fee", "  Comisión: {}"),
+    ("send.confirm", "¿Enviar esta transacción?"),
+    ("send.confirm_rebuilt", "¿Enviar la transacción creada de nuevo?"),
+    ("send.difficulty_increased", "La dificultad de la red ha subido desde que se calculó la prueba de trabajo, el nodo probablemente rechazaría esta transacción."),
+    ("send.recompute_pow", "¿Recalcular la prueba de trabajo con la nueva dificultad? Si no, se cancela el envío."),
+    ("send.created", "Transacción creada: {}"),
+    ("send.submitting", "Enviando la transacción..."),
+    ("send.status", "Estado del envío de la transacción: {}"),
+    ("send.validating", "Validando el envío..."),
+    ("send.submitted", "Transacción enviada correctamente."),
+    ("send.saved_inputs", "UTXO gastados guardados en la sesión."),
+    ("send.label", "etiqueta: {}"),
+    ("send.invalid_label", "Etiqueta no válida: {}."),
+    ("send.nothing_sent", "No se envió nada, a ningún destinatario."),
+    ("send.unexpected_tx", "La transacción construida es {} y no la esperada {}, no se envió nada."),
+    ("send.label_saved", "Guardada como nota de la transacción (no se difunde): {}"),
+    ("send.failed", "No se pudo enviar la transacción."),
+    ("pin.incorrect", "¡PIN incorrecto!"),
+    ("wallet.deleted", "Monedero '{}' borrado."),
+    ("wallet.not_deleted", "PIN incorrecto. El monedero no se ha borrado."),
+    ("wallet.switched", "Cambiado al monedero '{}'."),
+    ("wallet.none_remaining", "No quedan monederos."),
+    (
+        "suspend.not_saved",
+        "No se pudieron guardar los cambios pendientes, la sesión no se suspende.",
+    ),
+    ("suspend.done", "Suspendido: las claves se olvidaron y el nodo se desconectó."),
+    ("suspend.prompt", "Pulsa Enter para continuar, Ctrl+C para salir. "),
+    ("suspend.no_node", "No se puede reconectar a {}: {}"),
+    ("wallet.private_denied", "PIN incorrecto. No se puede mostrar la clave privada."),
+    ("wallet.private_key", "Clave privada de '{}': {}"),
+    ("wallet.external_no_key", "El monedero '{}' firma con un firmante externo, no guarda ninguna clave privada. Comando del firmante: {}"),
+    ("backup.no_key", "El monedero '{}' no tiene clave privada de la que hacer copia."),
+    ("lang.set", "Idioma cambiado a {}."),
+    ("secret.close_hint", "Pulsa cualquier tecla para cerrar, nada de lo mostrado queda en pantalla."),
+    ("wallet.public_key", "Clave pública de '{}': {}"),
+    ("change_pin.denied", "PIN incorrecto. No se puede cambiar el PIN."),
+    ("change_pin.mismatch", "Los PIN no coinciden. No se puede cambiar el PIN."),
+    ("change_pin.done", "PIN cambiado. El archivo del monedero ya se guardó con él, puedes seguir como antes."),
+    ("change_pin.not_saved", "No se pudo reescribir el archivo del monedero, el PIN anterior sigue vigente."),
+    (
+        "change_pin.file_lost",
+        "El archivo del monedero en disco no se abre con ninguno de los dos PIN. Ejecuta `save` \
+         para escribir los monederos de esta sesión con el PIN anterior.",
+    ),
+    ("change_pin.kept", "PIN sin cambios."),
+    ("kdf.done", "Monederos cifrados de nuevo con los nuevos ajustes de KDF."),
+    ("kdf.not_saved", "No se pudo reescribir el archivo del monedero, los ajustes de KDF anteriores siguen vigentes."),
+    (
+        "kdf.file_lost",
+        "El archivo del monedero en disco no se abre ni con los ajustes de KDF anteriores ni \
+         con los nuevos. Ejecuta `save` para escribir los monederos de esta sesión con los \
+         anteriores.",
+    ),
+    ("save.failed", "No se pudieron guardar los monederos: {}"),
+    ("save.no_path", "No se pudo determinar la ruta del monedero: {}"),
+    (
+        "save.not_encrypted",
+        "No se pudieron cifrar los monederos: ¡los monederos NO se han guardado!",
+    ),
+    ("read_failed", "No se puede leer {}: {}"),
+    ("rewrite.encrypt_failed", "No se pudieron cifrar los monederos con las claves nuevas."),
+    ("rewrite.not_opening", "El archivo de monederos reescrito no se abre con el PIN nuevo."),
+    ("rewrite.restore_failed", "Tampoco se pudo restaurar el anterior: {}"),
+    ("column.tx_id", "ID TX"),
+    ("column.index", "ÍNDICE"),
+    ("column.amount", "CANTIDAD"),
+    ("column.age", "EDAD"),
+    ("column.flags", "MARCAS"),
+    ("column.inputs", "ENTRADAS"),
+    ("column.outputs", "SALIDAS"),
+    ("column.time", "HORA (UTC)"),
+    ("column.balance", "SALDO"),
+    ("column.change", "CAMBIO"),
+    ("available.session_spent", "gastada-en-sesión"),
+    ("available.header", "UTXO disponibles ({}):"),
+    ("balance_history.showing", "Se muestran las últimas {} de {} instantáneas."),
+    ("balance_history.trend", "Tendencia: {}"),
+    ("mempool.none_mine", "No hay transacciones de este monedero en la mempool."),
+    ("mempool.empty", "La mempool está vacía."),
+    ("mempool.yours", "tuya"),
+    ("mempool.header", "Mempool ({} transacciones):"),
+    ("send.contact_note", "nota: {}"),
+    ("json.unsupported", "{} no admite --json"),
+    ("balance.usage", "Uso: balance [monedero]"),
+    ("balance.fetching", "Consultando el saldo..."),
+    ("available.fetching", "Consultando las salidas disponibles..."),
+    ("available.exported", "Exportadas {} salidas disponibles."),
+    ("available.usage", "Uso: available [--sort age|amount|txid] | available export <ruta|->"),
+    ("estimate.usage", "Uso: estimate <cantidad total> [num_destinatarios]"),
+    ("estimate.invalid_recipients", "Número de destinatarios no válido: {}"),
+    ("estimate.insufficient", "Fondos insuficientes: {} gastables, {} solicitados."),
+    ("estimate.header", "Estimación (no es un presupuesto, la red puede cambiar antes de enviar):"),
+    ("estimate.inputs", "  Entradas elegidas: ~{}"),
+    ("estimate.outputs", "  Salidas:           ~{} ({} destinatarios)"),
+    ("estimate.outputs_change", "  Salidas:           ~{} ({} destinatarios + cambio)"),
+    ("estimate.size", "  Tamaño:            ~{} bytes"),
+    ("estimate.pow", "  Coste de PoW:      ~{} hashes con la dificultad actual"),
+    ("estimate.fee", "  Comisión:          ninguna, el remitente paga con PoW"),
+    (
+        "estimate.too_large",
+        "  Aviso: supera el límite de {} entradas/salidas, habría que dividir el envío.",
+    ),
+    ("invalid_limit", "Límite no válido: {}"),
+    ("history.usage", "Uso: history [--limit <n>]"),
+    ("history.fetching", "Consultando el historial..."),
+    ("ctrl_c_stops", "(Ctrl+C para parar)"),
+    ("history.header_partial", "Historial de transacciones ({} elementos, se muestran {}):"),
+    ("history.header", "Historial de transacciones ({} elementos):"),
+    ("column.note", "NOTA"),
+    ("history.fetching_count", "Consultando el historial... {}/{} transacciones"),
+    ("history.not_found", "(no encontrada)"),
+    ("stopped", "Detenido."),
+    ("report.usage", "Uso: report <año> --out <ruta> [--wallet <nombre>]"),
+    ("report.fetching", "Consultando transacciones... {}/{}"),
+    ("report.stopped", "Detenido, no se ha escrito ningún informe."),
+    (
+        "report.missing_timestamps",
+        "{} transacciones no tienen fecha y se omiten, así que el saldo acumulado puede no \
+         cuadrar.",
+    ),
+    (
+        "report.unknown_inputs",
+        "{} envíos gastaron salidas que faltan en el historial, así que sus comisiones y el saldo \
+         acumulado pueden no cuadrar.",
+    ),
+    ("report.written", "Escritas {} transacciones de {} del monedero '{}' en {}."),
+    ("snapshot.usage", "Uso: snapshot"),
+    (
+        "snapshot.external_signer",
+        "Las instantáneas de un monedero con firmante externo no se pueden cifrar, su clave nunca \
+         llega al monedero. `set encrypt-snapshots off` las guarda en texto plano.",
+    ),
+    ("snapshot.encrypt_failed", "No se pudo cifrar la instantánea."),
+    ("snapshot.recorded", "Registrado un saldo de {} para el monedero '{}'."),
+    ("snapshot.recorded_encrypted", "Registrado un saldo de {} para el monedero '{}', cifrado."),
+    ("balance_history.usage", "Uso: balance-history [--limit <n>]"),
+    (
+        "balance_history.none",
+        "Todavía no hay instantáneas del monedero '{}', `snapshot` registra una.",
+    ),
+    ("mempool.usage", "Uso: mempool [--mine]"),
+    ("mempool.fetching", "Consultando la mempool..."),
+    ("watch.usage", "Uso: watch <comando> [intervalo_segundos]"),
+    ("watch.not_watchable", "Solo se pueden vigilar comandos de lectura: {}"),
+    ("watch.invalid_args", "Argumentos no válidos para {}"),
+    ("watch.header", "Cada {}s: {}    {} UTC    (Ctrl+C para parar)"),
+    ("vanity.usage", "Uso: vanity <prefijo>"),
+    ("vanity.invalid_prefix", "Prefijo no válido: {}."),
+    ("vanity.expected", "Se probarán unas {} claves."),
+    ("vanity.slow", "Los prefijos largos pueden tardar horas o días. Ctrl+C detiene la búsqueda."),
+    ("vanity.confirm_start", "¿Empezar a buscar?"),
+    ("vanity.searching", "Buscando..."),
+    ("vanity.progress", "Buscando... {} claves probadas, {} claves/s, quedan unos {} de media"),
+    ("vanity.stopped", "Detenido tras {} claves."),
+    ("vanity.found", "Encontrada tras {} claves: {}"),
+    ("vanity.confirm_save", "¿Guardarla como monedero nuevo?"),
+    ("vanity.not_saved", "No se ha guardado; la clave se pierde al salir de esta pregunta."),
+    ("vanity.name_prompt", "Nombre del monedero nuevo: "),
+    ("sign.usage", "Uso: sign <mensaje>"),
+    ("sign.external_signer", "Los monederos con firmante externo no pueden firmar mensajes."),
+    ("sign.denied", "PIN incorrecto."),
+    ("sign.address", "Dirección: {}"),
+    ("sign.message", "Mensaje:   {}"),
+    ("sign.signature", "Firma:     {}"),
+    ("sign.check_with", "Compruébalo con: verify {} {} {}"),
+    ("verify.usage", "Uso: verify <dirección> <mensaje> <firma>"),
+    ("verify.invalid_signature", "Firma no válida."),
+    ("verify.valid", "Válida: el mensaje lo firmó esta dirección."),
+    ("verify.not_valid", "NO es válida para esta dirección y mensaje."),
+    ("config.usage", "Uso: config show"),
+    ("tx_info.usage", "Uso: tx-info <TXID>"),
+    ("tx_info.header", "Detalles de la transacción: {}"),
+    ("explorer", "Explorador: {}"),
+    ("tx_info.note", "Nota: {}"),
+    ("tx_info.timestamp", "Fecha: {}"),
+    ("tx_info.nonce", "Nonce: {}"),
+    ("tx_info.inputs", "Entradas ({}):"),
+    ("tx_info.input", "{}:{} de {}"),
+    ("tx_info.outputs", "Salidas ({}):"),
+    ("tx_info.output", "{} a {}"),
+    ("tx_info.fee_reward", "Comisión: ninguna, es una recompensa de bloque ({} bytes)"),
+    ("tx_info.fee", "Comisión: {}"),
+    ("tx_info.fee_unknown", "Comisión: desconocida, no se encontró una salida gastada"),
+    ("tx_info.not_found", "Transacción no encontrada: {}"),
+    ("tx_info.invalid_id", "ID de transacción no válido: {}"),
+    ("tx_note.usage", "Uso: tx-note <TXID> [<texto...>]"),
+    ("tx_note.removed", "Se ha quitado la nota de {}."),
+    ("tx_note.none", "La transacción {} no tiene nota."),
+    ("tx_note.saved", "Se ha guardado la nota de {}."),
+    ("invalid_days", "Número de días no válido: {}"),
+    ("prune.usage", "Uso: prune [--older-than <días>]"),
+    ("prune.no_notes", "No hay notas de transacciones."),
+    ("prune.nothing", "No hay nada que podar."),
+    ("prune.candidates", "Se quitarían estas notas:"),
+    ("prune.confirm", "¿Quitar {} nota(s)?"),
+    ("prune.done", "Se han quitado {} nota(s)."),
+    ("send.checking_inputs", "Comprobando que las entradas siguen sin gastar..."),
+    ("send.checking_mempool", "Comprobando la mempool..."),
+    ("status.version", "Versión: {}"),
+    ("status.wallet", "Monedero: {} ({})"),
+    ("status.local_key", "clave local"),
+    ("status.external_signer", "firmante externo"),
+    ("status.signing", "Firma: {}"),
+    ("status.file", "Archivo de monederos: {}"),
+    ("status.file_unknown", "Archivo de monederos: desconocido ({})"),
+    ("status.unsaved_yes", "sí, `save` los escribe"),
+    ("status.unsaved_autosave_on", "ninguno (autoguardado activado)"),
+    ("status.unsaved_autosave_off", "ninguno (autoguardado desactivado)"),
+    ("status.unsaved", "Cambios sin guardar: {}"),
+    ("status.spent_in", "{} en {}"),
+    ("status.session_spent_none", "Entradas gastadas en la sesión: ninguna"),
+    ("status.session_spent", "Entradas gastadas en la sesión: {}"),
+    ("status.outbox", "Bandeja de salida: ninguna, los envíos se mandan en el acto"),
+    ("status.spend_limit", "Límite de gasto: ninguno"),
+    ("status.auto_lock_off", "Bloqueo automático: no, las peticiones de PIN esperan sin límite"),
+    (
+        "status.auto_lock_timeout",
+        "Bloqueo automático: no, las peticiones de PIN se rinden tras {} s",
+    ),
+    ("status.node_unreachable", "inaccesible: {}"),
+    ("status.node", "Nodo: {} ({})"),
+    ("status.node_timeout", "sin respuesta en {} s"),
+    ("status.node_answered", "Nodo: {} (respondió en {} ms)"),
+    ("status.pending", "Transacciones pendientes: {}"),
+    (
+        "status.clock_ahead",
+        "{} segundos por delante de la red, las fechas de las transacciones se corrigen",
+    ),
+    (
+        "status.clock_behind",
+        "{} segundos por detrás de la red, las fechas de las transacciones se corrigen",
+    ),
+    ("status.clock", "Reloj: {}"),
+    ("status.clock_in_sync", "Reloj: sincronizado con la red ({} s)"),
+    (
+        "status.clock_unknown",
+        "Reloj: desconocido, hay muy pocas transacciones en la mempool para comparar",
+    ),
+    ("diagnose.summary", "{} correctas, {} avisos, {} fallidas, {} omitidas"),
+    (
+        "log.off",
+        "El registro está desactivado, arranca con --log-file o ejecuta `set log-file on`.",
+    ),
+    ("log.usage", "Uso: log path"),
+    ("send.submitting_short", "Enviando..."),
+    (
+        "session.header",
+        "Entradas gastadas en esta sesión por '{}' ({} elementos, excluidas de sus nuevos envíos):",
+    ),
+    ("session.input", "{} (salida {})"),
+    ("session.none", "No hay entradas registradas en esta sesión."),
+    (
+        "session.reset_warning",
+        "Aviso: reinicia solo si el nodo se reinició o se descartó una transacción enviada.",
+    ),
+    (
+        "session.reset_risk",
+        "Si esas transacciones siguen pendientes, los nuevos envíos pueden intentar gastar dos \
+         veces sus entradas y ser rechazados.",
+    ),
+    ("session.reset_confirm", "¿Borrar las entradas de la sesión?"),
+    ("session.not_cleared", "No se han borrado las entradas de la sesión."),
+    ("session.cleared", "Borradas {} entradas de la sesión."),
+    ("session.unknown", "Subcomando de session desconocido: {}"),
+    ("contact.usage_add", "Uso: contact add <alias> <dirección>"),
+    ("contact.invalid_alias", "Alias no válido: {}."),
+    ("contact.exists", "El contacto '{}' ya existe."),
+    ("contact.alias_is_address", "El alias de un contacto no puede ser una dirección."),
+    ("contact.added", "Contacto '{}' añadido."),
+    ("contact.usage_remove", "Uso: contact remove <alias>"),
+    ("contact.not_found", "Contacto '{}' no encontrado."),
+    ("contact.removed", "Contacto '{}' quitado."),
+    ("contact.usage_rename", "Uso: contact rename <antiguo> <nuevo>"),
+    ("contact.renamed", "Contacto '{}' renombrado a '{}'."),
+    ("contact.usage_note", "Uso: contact note <alias> [texto...]"),
+    ("contact.note_cleared", "Nota de '{}' borrada."),
+    ("contact.note_updated", "Nota de '{}' actualizada."),
+    ("contact.header", "Contactos ({} elementos):"),
+    ("contact.unknown", "Subcomando de contact desconocido: {}"),
+    ("alias.usage_add", "Uso: alias add <nombre> <comando...>"),
+    ("alias.builtin", "'{}' es un comando integrado y no puede ser un alias."),
+    ("alias.not_added", "Alias no añadido: {}"),
+    ("alias.added", "Alias '{}' añadido."),
+    ("alias.usage_remove", "Uso: alias remove <nombre>"),
+    ("alias.not_found", "Alias '{}' no encontrado."),
+    ("alias.removed", "Alias '{}' quitado."),
+    ("alias.header", "Alias ({} elementos):"),
+    ("alias.unknown", "Subcomando de alias desconocido: {}"),
+    (
+        "wallet.usage",
+        "Uso: wallet <list|delete|private|confirm-backup|public|switch|add-external|import-qr|\
+         import-json|merge> [nombre_monedero]",
+    ),
+    ("wallet.usage_list", "Uso: wallet list [--balances]"),
+    ("column.wallet", "MONEDERO"),
+    ("column.address", "DIRECCIÓN"),
+    ("column.type", "TIPO"),
+    ("wallet.fetching_balances", "Consultando los saldos..."),
+    ("wallet.kind_local", "local"),
+    ("wallet.kind_external", "externo"),
+    ("wallet.usage_private", "Uso: wallet private [<monedero>] [--screen] [--qr] [--checksummed]"),
+    (
+        "wallet.usage_add_external",
+        "Uso: wallet add-external <monedero> <clave_pública> <comando...>",
+    ),
+    ("wallet.invalid_name", "Nombre de monedero no válido: {}."),
+    ("wallet.exists", "El monedero '{}' ya existe."),
+    ("wallet.external_added", "Monedero con firmante externo '{}' añadido."),
+    ("wallet.usage_import_qr", "Uso: wallet import-qr <imagen> [<monedero>]"),
+    ("wallet.invalid_qr_key", "Clave privada en base36 no válida: {}"),
+    ("wallet.key_exists", "Esta clave ya es el monedero '{}'."),
+    ("wallet.qr_address", "El código contiene la clave de la dirección {}"),
+    ("wallet.confirm_import_qr", "¿Importarla como el monedero '{}'?"),
+    ("wallet.usage_import_json", "Uso: wallet import-json <ruta> [--dry-run]"),
+    ("wallet.not_keystore", "No es un archivo de almacén de claves: {}."),
+    ("wallet.would_import", "se importaría"),
+    ("wallet.to_import", "por importar"),
+    ("wallet.import_duplicate", "omitido, ya es el monedero '{}'"),
+    ("wallet.import_invalid", "no válido, {}"),
+    ("wallet.import_summary", "{} por importar, {} duplicados omitidos, {} no válidos."),
+    ("wallet.confirm_import_json", "¿Importar {} monederos?"),
+    ("wallet.imported", "Importados {} monederos."),
+    ("wallet.usage_merge", "Uso: wallet merge <origen> <destino>"),
+    ("wallet.merge_into_itself", "No se puede fusionar un monedero consigo mismo."),
+    ("wallet.merge_empty", "El monedero '{}' no tiene nada que enviar."),
+    ("wallet.merge_summary", "Moviendo {} de '{}' a '{}' ({}) en {} transacción(es)."),
+    ("wallet.merge_confirm", "¿Fusionar estos monederos?"),
+    ("wallet.merge_built", "Transacción construida: {}"),
+    ("wallet.merged", "'{}' fusionado en '{}'."),
+    ("wallet.unknown", "Subcomando de wallet desconocido: {}"),
+    ("compact.usage", "Uso: compact"),
+    ("compact.no_file", "Todavía no hay archivo de monederos que compactar, ejecuta save primero."),
+    ("compact.version", "la versión {}"),
+    ("compact.legacy", "el formato antiguo"),
+    (
+        "compact.rewrite",
+        "El archivo de monederos, ahora en {}, se reescribirá con la versión de formato {}.",
+    ),
+    ("compact.nothing", "No hay monederos duplicados ni entradas obsoletas que quitar"),
+    ("compact.duplicate", "Quitar el monedero '{}', el monedero '{}' tiene su clave"),
+    ("compact.stale_backup", "Descartar la confirmación de copia del monedero borrado '{}'"),
+    ("compact.old_backups", "Quitar {} copias antiguas, conservando las {} más recientes"),
+    ("compact.backed_up", "Antes se hace una copia del archivo actual en {}."),
+    ("compact.confirm", "¿Compactar el archivo de monederos?"),
+    (
+        "compact.unchanged",
+        "El archivo de monederos no ha cambiado, se han conservado las copias antiguas.",
+    ),
+    ("compact.size", "Archivo de monederos: {} bytes, antes {}."),
+    ("compact.reclaimed", "Recuperados {} bytes. La copia en {} ocupa {}."),
+    (
+        "change_pin.derive_failed",
+        "No se pudo derivar la clave del PIN nuevo. No se puede cambiar el PIN.",
+    ),
+    ("kdf.missing_value", "Falta el valor de {}"),
+    ("kdf.unknown_option", "Opción desconocida: {}"),
+    ("kdf.invalid_value", "Valor no válido para {}: {}"),
+    ("kdf.current", "KDF: {}, memoria: {} KiB, iteraciones: {}, paralelismo: {}"),
+    ("kdf.invalid", "Parámetros de KDF no válidos: {}"),
+    (
+        "kdf.above_ceiling",
+        "Aviso: estos parámetros superan el límite razonable, cada carga futura pedirá \
+         confirmación.",
+    ),
+    ("prompt.pin_kdf", "Introduce el PIN actual para volver a cifrar los monederos: "),
+    ("kdf.denied", "PIN incorrecto. Los ajustes de KDF no han cambiado."),
+    ("kdf.derive_failed", "No se pudo derivar la clave con los ajustes de KDF nuevos."),
+    ("set.usage_switch", "Uso: set {} on|off"),
+    ("set.prompt_balance", "Saldo en el prompt"),
+    ("set.bell", "Campana"),
+    ("set.auto_login", "Inicio de sesión automático"),
+    ("set.pager", "Paginador"),
+    ("set.autocorrect", "Autocorrección"),
+    ("set.autosave", "Autoguardado"),
+    ("set.encrypt_snapshots", "Cifrado de instantáneas"),
+    ("set.log_file", "Registro de operaciones"),
+    ("set.quiet", "Modo silencioso"),
+    ("set.enabled", "{}: activado."),
+    ("set.disabled", "{}: desactivado."),
+    ("set.usage_pin_timeout", "Uso: set pin-timeout <segundos>   (0 espera sin límite)"),
+    ("set.pin_timeout_off", "Las peticiones de PIN ahora esperan sin límite."),
+    ("set.pin_timeout", "Las peticiones de PIN ahora caducan a los {} segundos."),
+    ("set.usage_separator", "Uso: set thousands-separator {}"),
+    ("set.separator", "Las cantidades ahora se ven así: {}."),
+    ("set.usage_unit", "Uso: set unit snap|nano"),
+    ("set.unit", "Las cantidades ahora se muestran en {}."),
+    ("set.usage_lang", "Uso: set lang en|es|auto"),
+    ("set.usage_donation_prompt", "Uso: set donation-prompt {}"),
+    ("set.donation_always", "La línea de donación se muestra en cada inicio."),
+    ("set.donation_weekly", "La línea de donación se muestra una vez por semana."),
+    ("set.donation_off", "La línea de donación está desactivada. ¡Gracias!"),
+    ("set.usage_notify", "Uso: set notify {}"),
+    ("set.notify_off", "Las notificaciones están desactivadas."),
+    ("set.notify_on", "Las notificaciones están activadas ({})."),
+    ("set.usage_low_balance", "Uso: set low-balance <cantidad>|off"),
+    ("set.low_balance", "Recibirás un aviso cuando el saldo baje de {}."),
+    (
+        "set.low_balance_notify_off",
+        "Las notificaciones están desactivadas, actívalas con `set notify`.",
+    ),
+    ("set.low_balance_off", "Los avisos de saldo bajo están desactivados."),
+    (
+        "set.usage_note_max_age",
+        "Uso: set note-max-age <días>   (0 conserva las notas para siempre)",
+    ),
+    ("set.note_max_age_off", "prune ahora conserva las notas sea cual sea su antigüedad."),
+    ("set.note_max_age", "prune ahora quita las notas de más de {} días."),
+    ("open.usage", "Uso: open <txid|dirección|alias>"),
+    ("open.unknown_target", "No es una transacción conocida ni una dirección: {}"),
+    ("open.opening", "Abriendo {}"),
+    ("uri.usage_install", "Uso: install-uri-handler"),
+    ("uri.installed", "{}: los enlaces ahora se abren en este monedero, ver {}"),
+    ("uri.installed_note", "Un enlace pulsado sigue pidiendo confirmación y el PIN."),
+    ("webhook.usage", "Uso: webhook test"),
+    ("webhook.not_set", "No hay ningún webhook, añade webhook_url = <url> a ~/.snap-coin-config."),
+    ("webhook.posting", "Enviando una carga de prueba..."),
+    ("webhook.accepted", "{} aceptó la carga de prueba."),
+    ("webhook.failed", "El webhook falló: {}"),
+    ("merge.nothing", "El monedero '{}' no tiene nada que fusionar."),
+    ("merge.summary", "Fusionando {} salidas por valor de {} en {} transacción(es)."),
+    ("merge.confirm", "¿Fusionar estas salidas?"),
+    ("merge.done", "UTXO disponibles ({}) fusionadas en {} UTXO"),
+    ("rpc.pin_locked", "Demasiados PIN incorrectos, reinicia serve para volver a confirmar envíos"),
+    ("rpc.unknown_proposal", "No existe la propuesta {}, se confirmó, se descartó o se sustituyó"),
+    ("rpc.confirm_locked", "Demasiados PIN incorrectos, confirm_send está bloqueado."),
+    ("rpc.wrong_pin", "PIN incorrecto, la propuesta se ha descartado"),
+    ("rpc.other_wallet", "La propuesta gasta del monedero {}"),
+    ("rpc.send_cancelled", "El envío se canceló, créalo de nuevo"),
+    ("rpc.token_file_unreadable", "No se puede leer el archivo de token {}: {}"),
+    ("rpc.token_file_empty", "El archivo de token {} está vacío"),
+    ("rpc.invalid_listen", "Dirección de escucha no válida {}: {}"),
+    ("rpc.not_loopback", "{} no es una dirección de loopback, serve solo escucha en local"),
+    ("rpc.cant_listen", "No se puede escuchar en {}: {}"),
+    ("rpc.token_in", "Los clientes se autentican con el token de {}."),
+    ("rpc.token_for_run", "Token de esta ejecución: {}"),
+    ("rpc.serving", "Sirviendo el monedero '{}' en http://{}, Ctrl+C para."),
+    ("rpc.stopped", "Se ha dejado de servir."),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lang_from_code() {
+        assert_eq!(Lang::from_code("es_ES.UTF-8"), Some(Lang::Es));
+        assert_eq!(Lang::from_code("en-GB"), Some(Lang::En));
+        assert_eq!(Lang::from_code("C"), Some(Lang::En));
+        assert_eq!(Lang::from_code("xx"), None);
+    }
+
+    #[test]
+    fn test_catalogs_complete() {
+        for (key, _) in ES {
+            assert!(lookup(Lang::En, key).is_some(), "'{}' missing in English", key);
+        }
+        assert_eq!(lookup(Lang::Es, "no-such-key"), None);
+    }
+
+    /// A translation takes the same arguments, in the same order as far as `{}` can tell
+    #[test]
+    fn test_placeholders_match() {
+        for (key, message) in ES {
+            let english = lookup(Lang::En, key).unwrap();
+            assert_eq!(
+                message.matches("{}").count(),
+                english.matches("{}").count(),
+                "'{}' takes different arguments in Spanish",
+                key
+            );
+        }
+    }
+
+    /// The key loss and theft warnings must shout in every language
+    #[test]
+    fn test_warnings_keep_emphasis() {
+        let shouts = |message: &str| {
+            message.split_whitespace().any(|word| {
+                let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+                letters.len() >= 2 && letters.iter().all(|c| c.is_uppercase())
+            })
+        };
+        for key in ["backup.save_key", "backup.loss_warning", "backup.theft_warning"] {
+            let english = lookup(Lang::En, key).unwrap();
+            for lang in [Lang::En, Lang::Es] {
+                let message = lookup(lang, key).unwrap();
+                assert!(shouts(message), "'{}' lost its capitals in {:?}", key, lang);
+                let bangs = |message: &str| message.matches("!!!").count();
+                assert_eq!(bangs(message), bangs(english), "'{}' in {:?}", key, lang);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        assert_eq!(fill("a {} b {}", &[&1, &"x"]), "a 1 b x");
+        assert_eq!(fill("a {} b {}", &[&1]), "a 1 b {}");
+        assert_eq!(fill("none", &[&1]), "none");
+    }
+}
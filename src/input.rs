@@ -1,48 +1,489 @@
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    cursor::{Hide, MoveTo, RestorePosition, SavePosition, Show},
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    style::Print,
+    terminal::{
+        Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+        enable_raw_mode,
+    },
 };
-use std::io::{self, Write};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, IsTerminal, Write},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use rustyline::{Behavior, Config, DefaultEditor, error::ReadlineError};
+use zeroize::Zeroizing;
+
+use crate::{
+    console::{self, say},
+    encryption::SessionKeys,
+    i18n::{tr, trf},
+    style,
+};
+
+/// Seconds of inactivity after which a PIN prompt gives up, 0 waits forever
+static PIN_TIMEOUT: AtomicU64 = AtomicU64::new(60);
+/// The remaining time is shown during the last this many seconds
+const PIN_COUNTDOWN: Duration = Duration::from_secs(10);
+
+/// Answer every `confirm` question with yes, set by `--assume-yes`
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_assume_yes(assume_yes: bool) {
+    ASSUME_YES.store(assume_yes, Ordering::Relaxed);
+}
+
+/// Set when commands are piped on stdin: prompts can't read it, it holds the next commands
+static PIPED: AtomicBool = AtomicBool::new(false);
+/// Set when piped commands follow a login with `--pin-file`: the PIN was proven against the
+/// wallet file then, so `confirm_pin` passes without it. The PIN itself isn't kept
+static PIN_PROVEN: AtomicBool = AtomicBool::new(false);
+/// Set when a piped command asked a question nothing could answer, so it counts as failed
+static REFUSED: AtomicBool = AtomicBool::new(false);
+
+/// Answer prompts without stdin from now on. With `pin_proven`, the login took the PIN of
+/// `--pin-file`, which confirms commands from then on
+pub fn set_piped(pin_proven: bool) {
+    PIN_PROVEN.store(pin_proven, Ordering::Relaxed);
+    PIPED.store(true, Ordering::Relaxed);
+}
+
+/// Whether a prompt was refused since the last call, for lack of a terminal to answer it
+pub fn take_refused() -> bool {
+    REFUSED.swap(false, Ordering::Relaxed)
+}
+
+/// Set the PIN prompt timeout from the config
+pub fn set_pin_timeout(seconds: u64) {
+    PIN_TIMEOUT.store(seconds, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Answers `read_pin` and `read_input` give in tests, in order, instead of reading stdin
+    static SCRIPTED: std::cell::RefCell<std::collections::VecDeque<String>> =
+        Default::default();
+}
+
+/// Queue answers for the next prompts, so tests can drive commands that ask questions
+#[cfg(test)]
+pub fn script_answers(answers: &[&str]) {
+    SCRIPTED.with(|scripted| {
+        scripted
+            .borrow_mut()
+            .extend(answers.iter().map(|answer| answer.to_string()))
+    });
+}
+
+#[cfg(test)]
+fn scripted_answer() -> Option<String> {
+    SCRIPTED.with(|scripted| scripted.borrow_mut().pop_front())
+}
+
+/// Answers for the prompts of a command run for the RPC server, which has no terminal to ask
+/// on. `None` outside such a command
+static REMOTE: Mutex<Option<VecDeque<Zeroizing<String>>>> = Mutex::new(None);
 
-pub fn read_pin(prompt: &str) -> Result<String, std::io::Error> {
-    print!("{}", prompt);
-    io::stdout().flush()?; // show prompt immediately
+/// Answers prompts from a request until dropped
+pub struct RemoteAnswers;
+
+/// Answer the next prompts with `answers`, in order. Prompts after the last answer are
+/// cancelled, they never fall back to the terminal
+pub fn answer_remotely(answers: Vec<Zeroizing<String>>) -> RemoteAnswers {
+    *REMOTE.lock().unwrap() = Some(answers.into());
+    RemoteAnswers
+}
+
+impl Drop for RemoteAnswers {
+    fn drop(&mut self) {
+        *REMOTE.lock().unwrap() = None;
+    }
+}
+
+/// The next remote answer, `None` when prompts go to the terminal
+fn remote_answer() -> Option<Result<Zeroizing<String>, InputError>> {
+    let mut remote = REMOTE.lock().unwrap();
+    let answers = remote.as_mut()?;
+    Some(answers.pop_front().ok_or(InputError::Cancelled))
+}
+
+/// Why `read_pin` or `read_input` returned without an answer
+#[derive(Debug)]
+pub enum InputError {
+    /// The user pressed Esc or Ctrl+C, or closed the input
+    Cancelled,
+    /// Nothing was typed into a PIN prompt for the configured timeout
+    TimedOut,
+    /// Commands are piped and nothing can answer the prompt, with what would
+    NotInteractive(&'static str),
+    Io(io::Error),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Cancelled => f.write_str("Cancelled"),
+            InputError::TimedOut => f.write_str("PIN entry timed out"),
+            InputError::NotInteractive(hint) => {
+                write!(f, "Can't answer a prompt when commands are piped, {}", hint)
+            }
+            InputError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+/// Read a 6 digit PIN without echoing it. The returned buffer is wiped when dropped
+/// When stdin is not a terminal (piped input, automation) the PIN is read as a plain line,
+/// once commands are piped it can't be read at all: `confirm_pin` is what `--pin-file` answers
+/// Esc or Ctrl+C abandon the prompt with `InputError::Cancelled`, and a terminal left alone
+/// for the configured timeout gives `InputError::TimedOut`
+pub fn read_pin(prompt: &str) -> Result<Zeroizing<String>, InputError> {
+    #[cfg(test)]
+    if let Some(answer) = scripted_answer() {
+        return Ok(Zeroizing::new(answer));
+    }
+    if let Some(answer) = remote_answer() {
+        return answer;
+    }
+    if PIPED.load(Ordering::Relaxed) {
+        let hint = match PIN_PROVEN.load(Ordering::Relaxed) {
+            true => "--pin-file only confirms commands, this one needs the PIN typed",
+            false => "run with --pin-file <path>",
+        };
+        return Err(InputError::NotInteractive(hint));
+    }
+    let mut out = console::messages();
+    write!(out, "{}", prompt)?;
+    out.flush()?; // show prompt immediately
+
+    if !io::stdin().is_terminal() {
+        let mut line = Zeroizing::new(String::new());
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Err(InputError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stdin closed while reading PIN",
+            )));
+        }
+        say!();
+        return Ok(Zeroizing::new(line.trim().to_string()));
+    }
 
     enable_raw_mode()?; // start raw mode
-    let mut pin = String::new();
+    let pin = read_pin_keys(&mut out);
+    disable_raw_mode()?; // exit raw mode, also when reading failed or was cancelled
+    writeln!(out)?; // move to new line
+    pin
+}
+
+/// Ask for the PIN to confirm a command and check it against `keys`. Piped commands after a
+/// `--pin-file` login pass without asking, the login already checked the file's PIN
+pub fn confirm_pin(prompt: &str, keys: &SessionKeys) -> Result<bool, InputError> {
+    if PIPED.load(Ordering::Relaxed) && PIN_PROVEN.load(Ordering::Relaxed) {
+        return Ok(true);
+    }
+    Ok(keys.verify(read_pin(prompt)?))
+}
+
+fn read_pin_keys(out: &mut impl Write) -> Result<Zeroizing<String>, InputError> {
+    let mut pin = Zeroizing::new(String::with_capacity(6));
+    let timeout = match PIN_TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    };
+    let mut last_key = Instant::now();
 
     while pin.len() < 6 {
+        if let Some(timeout) = timeout {
+            let remaining = timeout.saturating_sub(last_key.elapsed());
+            if remaining.is_zero() {
+                return Err(InputError::TimedOut);
+            }
+            if remaining <= PIN_COUNTDOWN {
+                // Drawn after the asterisks, the cursor stays where the next one goes
+                let countdown = format!(" ({}s)", remaining.as_secs() + 1);
+                execute!(
+                    out,
+                    SavePosition,
+                    Clear(ClearType::UntilNewLine),
+                    Print(countdown),
+                    RestorePosition
+                )?;
+            }
+            // Wake up at least once a second to update the countdown
+            if !event::poll(remaining.min(Duration::from_secs(1)))? {
+                continue;
+            }
+        }
         if let Event::Key(key_event) = event::read()? {
             if key_event.kind != KeyEventKind::Press {
                 continue;
             }
+            last_key = Instant::now();
+            execute!(out, Clear(ClearType::UntilNewLine))?;
             match key_event.code {
+                KeyCode::Esc => return Err(InputError::Cancelled),
+                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err(InputError::Cancelled);
+                }
                 KeyCode::Char(c) if c.is_ascii_digit() => {
                     pin.push(c);
-                    print!("*");
-                    io::stdout().flush()?;
+                    write!(out, "*")?;
+                    out.flush()?;
                 }
-                KeyCode::Backspace => {
-                    if !pin.is_empty() {
+                KeyCode::Backspace
+                    if !pin.is_empty() => {
                         pin.pop();
-                        print!("\x08 \x08"); // remove last *
-                        io::stdout().flush()?;
+                        write!(out, "\x08 \x08")?; // remove last *
+                        out.flush()?;
+                    }
+                // Nothing is added, but a key that looks ignored shouldn't look like a freeze
+                code => {
+                    if let Some(hint) = ignored_key_hint(code, key_event.modifiers) {
+                        // Drawn after the asterisks like the countdown, the next key clears it
+                        let hint = format!(" {}", style::dim(tr(hint)));
+                        execute!(out, SavePosition, Print(hint), RestorePosition)?;
                     }
                 }
-                _ => {} // ignore everything else
             }
         }
     }
 
-    disable_raw_mode()?; // exit raw mode
-    println!(); // move to new line
     Ok(pin)
 }
 
-pub fn read_input(prompt: &str) -> String {
-    print!("{}", prompt);
-    io::stdout().flush().unwrap();
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf).unwrap();
-    buf.trim().to_string()
-}
\ No newline at end of file
+/// What to tell about a key a PIN prompt doesn't take, as a message key. Keys with Ctrl or Alt
+/// are shortcuts rather than typos, and Backspace on nothing needs no explanation
+fn ignored_key_hint(code: KeyCode, modifiers: KeyModifiers) -> Option<&'static str> {
+    if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+        return None;
+    }
+    match code {
+        KeyCode::Char('?') => Some("pin.key_help"),
+        KeyCode::Char(c) if !c.is_ascii_digit() => Some("pin.digits_only"),
+        KeyCode::Enter => Some("pin.no_enter"),
+        _ => None,
+    }
+}
+
+/// Read a line of free text with line editing. Uses its own editor, so answers never end up
+/// in the command history. Ctrl+C and Ctrl+D give `InputError::Cancelled`
+/// While stdout is reserved, editing happens on the terminal itself
+pub fn read_input(prompt: &str) -> Result<String, InputError> {
+    #[cfg(test)]
+    if let Some(answer) = scripted_answer() {
+        return Ok(answer);
+    }
+    if let Some(answer) = remote_answer() {
+        return answer.map(|answer| answer.trim().to_string());
+    }
+    if PIPED.load(Ordering::Relaxed) {
+        return Err(InputError::NotInteractive("give the answer as an argument"));
+    }
+    let behavior = match console::stdout_reserved() {
+        true => Behavior::PreferTerm,
+        false => Behavior::Stdio,
+    };
+    let config = Config::builder().behavior(behavior).build();
+    let mut editor =
+        DefaultEditor::with_config(config).map_err(|e| InputError::Io(io::Error::other(e)))?;
+    match editor.readline(prompt) {
+        Ok(line) => Ok(line.trim().to_string()),
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => Err(InputError::Cancelled),
+        Err(ReadlineError::Io(e)) => Err(InputError::Io(e)),
+        Err(e) => Err(InputError::Io(io::Error::other(e))),
+    }
+}
+
+/// Restores the terminal after `show_secret`, also when drawing failed halfway
+struct SecretScreen;
+
+impl Drop for SecretScreen {
+    fn drop(&mut self) {
+        // Wipe the alternate screen too, some terminals keep it around after leaving
+        execute!(io::stdout(), Clear(ClearType::All), Show, LeaveAlternateScreen).ok();
+        disable_raw_mode().ok();
+    }
+}
+
+/// Show secret lines on the alternate screen until any key is pressed, or the PIN timeout
+/// passes. Nothing shown there reaches the scrollback, unlike printed output
+pub fn show_secret(lines: &[&str]) -> Result<(), InputError> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(InputError::Io(io::Error::other(
+            "showing a secret on screen needs a terminal",
+        )));
+    }
+    enable_raw_mode()?;
+    let _screen = SecretScreen;
+    let mut out = io::stdout();
+    execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All), MoveTo(0, 0))?;
+    for line in lines {
+        // Raw mode doesn't return the carriage by itself
+        write!(out, "{}\r\n", line)?;
+    }
+    write!(out, "\r\n{}", tr("secret.close_hint"))?;
+    out.flush()?;
+
+    let timeout = match PIN_TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    };
+    let shown = Instant::now();
+    loop {
+        if let Some(timeout) = timeout {
+            let remaining = timeout.saturating_sub(shown.elapsed());
+            if remaining.is_zero() || !event::poll(remaining)? {
+                return Ok(());
+            }
+        }
+        if let Event::Key(key_event) = event::read()?
+            && key_event.kind == KeyEventKind::Press
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// PINs guessed first, beyond the repeated digits and runs `pin_weakness` finds by itself
+const COMMON_PINS: &[&str] = &[
+    "112233", "123321", "147258", "147852", "159753", "102030", "110011", "200000", "789456",
+    "852456",
+];
+
+/// Why a new PIN is easy to guess, as a message key, or `None` when nothing stands out
+pub fn pin_weakness(pin: &str) -> Option<&'static str> {
+    let digits: Vec<i8> = pin.bytes().map(|b| b.wrapping_sub(b'0') as i8).collect();
+    if digits.windows(2).all(|pair| pair[0] == pair[1]) {
+        return Some("pin.weak_repeated");
+    }
+    // Runs up or down the keypad digits, wrapping around like 890123
+    for step in [1, 9] {
+        if digits.windows(2).all(|pair| (pair[1] - pair[0]).rem_euclid(10) == step) {
+            return Some("pin.weak_sequence");
+        }
+    }
+    // A short group typed over and over, like 121212 or 123123
+    for len in [2, 3] {
+        if digits.len() > len && digits.chunks(len).all(|chunk| chunk == &digits[..len]) {
+            return Some("pin.weak_pattern");
+        }
+    }
+    if COMMON_PINS.contains(&pin) {
+        return Some("pin.weak_common");
+    }
+    None
+}
+
+/// Warn about a weak new PIN and ask whether to keep it. The wallet file is only as safe as
+/// its PIN, but the choice is left to the user
+pub fn accept_pin(pin: &str) -> bool {
+    let Some(weakness) = pin_weakness(pin) else {
+        return true;
+    };
+    say!("{}", style::warning(trf("pin.weak", &[&tr(weakness)])));
+    confirm(tr("pin.weak_confirm"), false)
+}
+
+/// Parse a yes/no answer, case-insensitive
+fn parse_yes_no(answer: &str) -> Option<bool> {
+    match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Ask a yes/no question. An empty answer picks `default`, anything unrecognized asks again,
+/// and Ctrl+C or EOF count as no
+pub fn confirm(prompt: &str, default: bool) -> bool {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        say!("{} {} y", prompt, hint);
+        return true;
+    }
+    // Answering from stdin would eat the next piped command
+    if PIPED.load(Ordering::Relaxed) {
+        say!("{} {}", prompt, style::error(tr("piped.needs_assume_yes")));
+        REFUSED.store(true, Ordering::Relaxed);
+        return false;
+    }
+    loop {
+        let Ok(answer) = read_input(&format!("{} {} ", prompt, hint)) else {
+            return false;
+        };
+        if answer.is_empty() {
+            return default;
+        }
+        match parse_yes_no(&answer) {
+            Some(answer) => return answer,
+            None => say!("{}", tr("confirm.invalid")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignored_key_hint() {
+        let hint = |code| ignored_key_hint(code, KeyModifiers::NONE);
+        assert_eq!(hint(KeyCode::Char('a')), Some("pin.digits_only"));
+        assert_eq!(hint(KeyCode::Char('?')), Some("pin.key_help"));
+        assert_eq!(hint(KeyCode::Enter), Some("pin.no_enter"));
+        assert_eq!(hint(KeyCode::Backspace), None);
+        assert_eq!(hint(KeyCode::Left), None);
+        // Shortcuts aren't typos, shifted letters are
+        let ctrl_v = ignored_key_hint(KeyCode::Char('v'), KeyModifiers::CONTROL);
+        assert_eq!(ctrl_v, None);
+        let shifted = ignored_key_hint(KeyCode::Char('A'), KeyModifiers::SHIFT);
+        assert_eq!(shifted, Some("pin.digits_only"));
+    }
+
+    #[test]
+    fn test_parse_yes_no() {
+        for answer in ["y", "Y", "yes", "YES", "Yes"] {
+            assert_eq!(parse_yes_no(answer), Some(true));
+        }
+        for answer in ["n", "N", "no", "NO"] {
+            assert_eq!(parse_yes_no(answer), Some(false));
+        }
+        for answer in ["", "ye", "nope", "1", "y es"] {
+            assert_eq!(parse_yes_no(answer), None);
+        }
+    }
+
+    #[test]
+    fn test_pin_weakness() {
+        for pin in ["000000", "999999"] {
+            assert_eq!(pin_weakness(pin), Some("pin.weak_repeated"), "{}", pin);
+        }
+        for pin in ["123456", "654321", "012345", "890123", "210987"] {
+            assert_eq!(pin_weakness(pin), Some("pin.weak_sequence"), "{}", pin);
+        }
+        for pin in ["121212", "909090", "123123", "475475"] {
+            assert_eq!(pin_weakness(pin), Some("pin.weak_pattern"), "{}", pin);
+        }
+        for pin in ["112233", "159753", "696969"] {
+            assert!(pin_weakness(pin).is_some(), "{}", pin);
+        }
+        for pin in ["583920", "402718", "135790", "122334"] {
+            assert_eq!(pin_weakness(pin), None, "{}", pin);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::{cell::Cell, thread::LocalKey};
+
+// Per thread: commands run on the thread polling the REPL, which also answers Ctrl+C, and
+// tests running commands side by side don't see each other's shields
+thread_local! {
+    /// Running steps that must not stop halfway
+    static CRITICAL: Cell<usize> = const { Cell::new(0) };
+    /// Running commands that stop on Ctrl+C by themselves
+    static HANDLED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// What Ctrl+C does to the running command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    /// Cancel it, by dropping it at the point it is waiting
+    Cancel,
+    /// Let it finish, it is in a step that can't be undone halfway
+    Finish,
+    /// Leave it to the command, which stops by itself
+    Handled,
+}
+
+/// Keeps Ctrl+C from cancelling the running command until dropped
+pub struct Shield {
+    counter: &'static LocalKey<Cell<usize>>,
+}
+
+impl Drop for Shield {
+    fn drop(&mut self) {
+        self.counter.with(|count| count.set(count.get() - 1));
+    }
+}
+
+fn shield(counter: &'static LocalKey<Cell<usize>>) -> Shield {
+    counter.with(|count| count.set(count.get() + 1));
+    Shield { counter }
+}
+
+/// For a step that must not stop halfway, like submitting a transaction and recording its
+/// inputs as spent
+pub fn critical() -> Shield {
+    shield(&CRITICAL)
+}
+
+/// For a command listening for Ctrl+C itself, e.g. to keep the output it has so far
+pub fn handled() -> Shield {
+    shield(&HANDLED)
+}
+
+/// What a Ctrl+C pressed now should do to the running command
+pub fn on_ctrl_c() -> Interrupt {
+    if CRITICAL.get() > 0 {
+        Interrupt::Finish
+    } else if HANDLED.get() > 0 {
+        Interrupt::Handled
+    } else {
+        Interrupt::Cancel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shields() {
+        assert_eq!(on_ctrl_c(), Interrupt::Cancel);
+        let handled = handled();
+        assert_eq!(on_ctrl_c(), Interrupt::Handled);
+        // A critical step inside a command handling Ctrl+C still finishes
+        let critical = critical();
+        assert_eq!(on_ctrl_c(), Interrupt::Finish);
+        drop(critical);
+        drop(handled);
+        assert_eq!(on_ctrl_c(), Interrupt::Cancel);
+    }
+}
@@ -0,0 +1,279 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Error;
+use serde::Serialize;
+use snap_coin::{core::transaction::Transaction, crypto::keys::Public};
+
+use crate::{amount, exit_code::ErrorCode, history::FeeRate, signing::transaction_size};
+
+/// Commands that take `--json`
+pub const COMMANDS: &[&str] = &["balance", "available", "history", "tx-info", "send"];
+
+/// An amount for scripts: the exact integer in nano, and the same in SNAP as text, so no
+/// consumer has to go through floats
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Amount {
+    pub nano: u64,
+    pub snap: String,
+}
+
+impl From<u64> for Amount {
+    fn from(nano: u64) -> Self {
+        Amount {
+            nano,
+            snap: amount::format_snap_plain(nano),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BalanceOutput {
+    pub wallet: String,
+    pub address: String,
+    pub balance: Amount,
+    /// What a send can use now
+    pub spendable: Amount,
+    pub session_spent: Amount,
+    /// Balance that no spendable output backs
+    pub unavailable: Amount,
+    pub pending_incoming: Amount,
+}
+
+#[derive(Serialize)]
+pub struct Utxo {
+    pub tx_id: String,
+    pub index: usize,
+    pub amount: Amount,
+    /// Timestamp of the creating transaction, `None` when the node doesn't have it
+    pub timestamp: Option<u64>,
+    pub session_spent: bool,
+}
+
+#[derive(Serialize)]
+pub struct UtxoList {
+    pub address: String,
+    pub total: Amount,
+    /// Balance that no listed output backs, which can't be spent yet
+    pub unavailable: Amount,
+    pub utxos: Vec<Utxo>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryEntry {
+    pub tx_id: String,
+    /// `None` when the node no longer has the transaction
+    pub timestamp: Option<u64>,
+    pub received: Amount,
+    pub sent: Amount,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryOutput {
+    pub address: String,
+    /// Oldest first, like the node lists them
+    pub transactions: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize)]
+pub struct TxInput {
+    pub tx_id: String,
+    pub index: usize,
+    pub owner: String,
+}
+
+#[derive(Serialize)]
+pub struct TxOutput {
+    pub receiver: String,
+    pub amount: Amount,
+}
+
+#[derive(Serialize)]
+pub struct TxInfo {
+    pub tx_id: String,
+    pub timestamp: u64,
+    pub nonce: u64,
+    pub size: usize,
+    pub inputs: Vec<TxInput>,
+    pub outputs: Vec<TxOutput>,
+    /// `None` when a spent output wasn't found, zero for block rewards
+    pub fee: Option<Amount>,
+    pub note: Option<String>,
+}
+
+impl TxInfo {
+    pub fn new(
+        tx_id: String,
+        tx: &Transaction,
+        fee: Option<FeeRate>,
+        note: Option<String>,
+    ) -> Self {
+        TxInfo {
+            tx_id,
+            timestamp: tx.timestamp,
+            nonce: tx.nonce,
+            size: transaction_size(tx),
+            inputs: tx
+                .inputs
+                .iter()
+                .map(|input| TxInput {
+                    tx_id: input.transaction_id.dump_base36(),
+                    index: input.output_index,
+                    owner: input.output_owner.dump_base36(),
+                })
+                .collect(),
+            outputs: tx
+                .outputs
+                .iter()
+                .map(|output| TxOutput {
+                    receiver: output.receiver.dump_base36(),
+                    amount: output.amount.into(),
+                })
+                .collect(),
+            fee: fee.map(|rate| rate.fee.into()),
+            note,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Payment {
+    /// As typed: an address or a contact
+    pub recipient: String,
+    pub address: String,
+    pub amount: Amount,
+}
+
+impl Payment {
+    /// The payments of a send, with the recipients as typed
+    pub fn list(recipients: &[&str], payments: &[(Public, u64)]) -> Vec<Payment> {
+        recipients
+            .iter()
+            .zip(payments)
+            .map(|(recipient, (public, amount))| Payment {
+                recipient: recipient.to_string(),
+                address: public.dump_base36(),
+                amount: (*amount).into(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct Wallet {
+    pub name: String,
+    pub address: String,
+    /// `local` for a stored key, `external` for an external signer
+    pub signer: &'static str,
+    pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct SendResult {
+    pub tx_id: String,
+    /// Whether the node took it into its mempool
+    pub accepted: bool,
+    pub payments: Vec<Payment>,
+    pub fee: Option<Amount>,
+}
+
+/// A failed `--json` command, printed instead of the object it would have printed
+#[derive(Serialize)]
+pub struct ErrorOutput {
+    pub error: ErrorDetail,
+}
+
+#[derive(Serialize)]
+pub struct ErrorDetail {
+    /// One of the stable `E_` codes of `ErrorCode`
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ErrorOutput {
+    pub fn new(e: &Error) -> Self {
+        ErrorOutput {
+            error: ErrorDetail {
+                code: ErrorCode::of(e).as_str(),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Set while a `--json` command runs and hasn't printed its object yet
+static AWAITED: AtomicBool = AtomicBool::new(false);
+
+/// Note that the running command owes one JSON object
+pub fn await_object() {
+    AWAITED.store(true, Ordering::Relaxed);
+}
+
+/// Whether the last command owed a JSON object it never printed, clearing it
+pub fn take_awaited() -> bool {
+    AWAITED.swap(false, Ordering::Relaxed)
+}
+
+/// What `print` wrote while a command runs for the RPC server, `None` outside such a command
+static CAPTURED: Mutex<Option<Option<String>>> = Mutex::new(None);
+
+/// Keeps `print` capturing until dropped
+pub struct Capture;
+
+/// Have `print` keep its object for the RPC server instead of writing it to stdout
+pub fn capture() -> Capture {
+    *CAPTURED.lock().unwrap() = Some(None);
+    Capture
+}
+
+impl Capture {
+    /// The object printed since `capture`, if any
+    pub fn take(&self) -> Option<String> {
+        CAPTURED.lock().unwrap().as_mut().and_then(Option::take)
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        *CAPTURED.lock().unwrap() = None;
+    }
+}
+
+/// Print one compact JSON object on stdout
+pub fn print(value: &impl Serialize) -> Result<(), Error> {
+    let json = serde_json::to_string(value)?;
+    AWAITED.store(false, Ordering::Relaxed);
+    match CAPTURED.lock().unwrap().as_mut() {
+        Some(captured) => *captured = Some(json),
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amount() {
+        let amount = Amount::from(123_456_789_012);
+        assert_eq!(amount.snap, "1234.56789012");
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, r#"{"nano":123456789012,"snap":"1234.56789012"}"#);
+        // Beyond what a float holds exactly
+        assert_eq!(Amount::from(u64::MAX).snap, "184467440737.09551615");
+    }
+
+    #[test]
+    fn test_error_output() {
+        let e = ErrorCode::WrongPin.error("Failed to decrypt");
+        let json = serde_json::to_string(&ErrorOutput::new(&e)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"error":{"code":"E_WRONG_PIN","message":"Failed to decrypt"}}"#
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use serde_json::Value;
+use snap_coin::crypto::keys::{Private, Public};
+use zeroize::Zeroizing;
+
+use crate::{sanitize, signing::Wallets};
+
+/// Fields an entry may hold its private key in, the encoding is told apart by the value
+const KEY_FIELDS: &[&str] = &["private_key", "private_key_base36", "private_key_hex"];
+
+/// What importing one entry of a keystore file comes to
+pub enum Outcome {
+    Import(Private),
+    /// The key is already a wallet, or an earlier entry of the same file
+    Duplicate(String),
+    Invalid(String),
+}
+
+/// One entry of a keystore file, by position since a name may be missing
+pub struct Entry {
+    /// Position in the file, from 1
+    pub number: usize,
+    pub name: Option<String>,
+    pub outcome: Outcome,
+}
+
+/// Parse a private key given as 64 hex digits, optionally after `0x`, or in base36. A 32 byte
+/// key is at most 50 base36 digits, so the length alone tells the two apart
+pub fn parse_key(text: &str) -> Result<Private, String> {
+    let cleaned = Zeroizing::new(sanitize::clean(text));
+    let hex = cleaned
+        .strip_prefix("0x")
+        .or_else(|| cleaned.strip_prefix("0X"))
+        .unwrap_or(&cleaned);
+    if hex.len() != 64 {
+        return sanitize::parse_private(&cleaned);
+    }
+    let mut buf = Zeroizing::new([0u8; 32]);
+    for (byte, pair) in buf.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = std::str::from_utf8(pair)
+            .ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(|| "64 characters but not hex".to_string())?;
+    }
+    Ok(Private::new_from_buf(&buf))
+}
+
+/// The key of one entry, from whichever key field it has
+fn entry_key(entry: &mut serde_json::Map<String, Value>) -> Result<Private, String> {
+    let mut found = KEY_FIELDS.iter().filter_map(|field| entry.remove(*field));
+    let key = match (found.next(), found.next()) {
+        (None, _) => return Err(format!("no key, expected one of {}", KEY_FIELDS.join(", "))),
+        (Some(_), Some(_)) => return Err("more than one key field".to_string()),
+        (Some(key), None) => key,
+    };
+    match key {
+        // Moved out rather than copied, so it is wiped once parsed
+        Value::String(key) => parse_key(&Zeroizing::new(key)),
+        _ => Err("the key isn't a string".to_string()),
+    }
+}
+
+/// Work out what importing every entry of a keystore file, a JSON array of objects with a
+/// `name` and a key, would do. Nothing is imported: entries become `Import` only if the key is
+/// valid, new and its name free, and while the wallets stay under `max_wallets`
+pub fn plan(text: &str, wallets: &Wallets, max_wallets: usize) -> Result<Vec<Entry>, String> {
+    let Value::Array(items) = serde_json::from_str(text).map_err(|e| e.to_string())? else {
+        return Err("expected a JSON array of keys".to_string());
+    };
+    let mut taken: Vec<(Public, String)> = wallets
+        .iter()
+        .map(|(name, key)| (key.public(), name.clone()))
+        .collect();
+    let mut names: Vec<String> = Vec::new();
+    let mut entries = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        let Value::Object(mut item) = item else {
+            entries.push(Entry {
+                number: index + 1,
+                name: None,
+                outcome: Outcome::Invalid("not an object".to_string()),
+            });
+            continue;
+        };
+        let name = match item.get("name") {
+            Some(Value::String(name)) => Some(name.trim().to_string()),
+            _ => None,
+        };
+        let key = entry_key(&mut item);
+        let duplicate = key.as_ref().ok().and_then(|private| {
+            let public = private.to_public();
+            let existing = taken.iter().find(|(taken, _)| *taken == public);
+            existing.map(|(_, name)| name.clone())
+        });
+        let outcome = match (&name, key, duplicate) {
+            (_, Err(reason), _) => Outcome::Invalid(reason),
+            (_, Ok(_), Some(existing)) => Outcome::Duplicate(existing),
+            (None, Ok(_), None) => Outcome::Invalid("no name".to_string()),
+            (Some(name), Ok(private), None) => match sanitize::check_name(name) {
+                Err(reason) => Outcome::Invalid(format!("invalid name: {}", reason)),
+                Ok(()) if wallets.contains_key(name) || names.contains(name) => {
+                    Outcome::Invalid(format!("wallet '{}' already exists", name))
+                }
+                Ok(()) if wallets.len() + names.len() >= max_wallets => {
+                    Outcome::Invalid("the wallet limit is reached".to_string())
+                }
+                Ok(()) => {
+                    taken.push((private.to_public(), name.clone()));
+                    names.push(name.clone());
+                    Outcome::Import(private)
+                }
+            },
+        };
+        entries.push(Entry {
+            number: index + 1,
+            name,
+            outcome,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::WalletKey;
+
+    fn hex(private: &Private) -> String {
+        private
+            .dump_buf()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// The outcome as text, `import <address>` for keys to import
+    fn describe(outcome: &Outcome) -> String {
+        match outcome {
+            Outcome::Import(private) => format!("import {}", private.to_public().dump_base36()),
+            Outcome::Duplicate(name) => format!("duplicate of {}", name),
+            Outcome::Invalid(reason) => format!("invalid: {}", reason),
+        }
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let private = Private::new_random();
+        let public = private.to_public();
+        let parse = |text: &str| parse_key(text).map(|key| key.to_public());
+        assert_eq!(parse(&private.dump_base36()), Ok(public));
+        assert_eq!(parse(&hex(&private)), Ok(public));
+        assert_eq!(
+            parse(&format!("0x{}", hex(&private).to_uppercase())),
+            Ok(public)
+        );
+        assert!(parse(&"g".repeat(64)).unwrap_err().contains("not hex"));
+        assert!(parse("abc").is_err());
+    }
+
+    #[test]
+    fn test_plan() {
+        let existing = Private::new_random();
+        let new = Private::new_random();
+        let other = Private::new_random();
+        let mut wallets = Wallets::new();
+        wallets.insert("main".to_string(), WalletKey::Local(existing.clone()));
+        let text = serde_json::json!([
+            {"name": "cold", "private_key_hex": hex(&new)},
+            {"name": "copy", "private_key_base36": new.dump_base36()},
+            {"name": "old", "private_key": existing.dump_base36()},
+            {"name": "main", "private_key": other.dump_base36()},
+            {"name": "bad", "private_key": "not-a-key"},
+            {"private_key": other.dump_base36()},
+            {"name": "both", "private_key": "a", "private_key_hex": "b"},
+            "cold",
+        ])
+        .to_string();
+
+        let entries = plan(&text, &wallets, 1000).unwrap();
+        let outcomes: Vec<String> = entries
+            .iter()
+            .map(|entry| describe(&entry.outcome))
+            .collect();
+        let import_new = format!("import {}", new.to_public().dump_base36());
+        assert_eq!(outcomes[0], import_new);
+        assert_eq!(outcomes[1], "duplicate of cold");
+        assert_eq!(outcomes[2], "duplicate of main");
+        assert_eq!(outcomes[3], "invalid: wallet 'main' already exists");
+        assert!(outcomes[4].contains("invalid character"), "{}", outcomes[4]);
+        assert_eq!(outcomes[5], "invalid: no name");
+        assert_eq!(outcomes[6], "invalid: more than one key field");
+        assert_eq!(outcomes[7], "invalid: not an object");
+        assert_eq!(entries[7].number, 8);
+
+        // Only one more wallet fits
+        let full = serde_json::json!([
+            {"name": "a", "private_key": new.dump_base36()},
+            {"name": "b", "private_key": other.dump_base36()},
+        ]);
+        let entries = plan(&full.to_string(), &wallets, 2).unwrap();
+        assert_eq!(describe(&entries[0].outcome), import_new);
+        assert_eq!(
+            describe(&entries[1].outcome),
+            "invalid: the wallet limit is reached"
+        );
+
+        assert!(plan("{}", &wallets, 1000).is_err());
+        assert!(plan("[", &wallets, 1000).is_err());
+    }
+}
@@ -1,70 +1,518 @@
 use std::{
-    collections::HashMap,
-    env::args,
-    fs::File,
-    io::{Read, Write},
-    path::PathBuf,
+    cell::RefCell,
+    env::{self, args},
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{BufRead, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::UNIX_EPOCH,
 };
 
 use anyhow::Error;
-use rustyline::Editor;
+use rustyline::{CompletionType, Config as EditorConfig, Editor};
 use rustyline::{error::ReadlineError, history::DefaultHistory};
+use zeroize::Zeroizing;
 use snap_coin::{
-    api::client::Client, core::transaction::TransactionInput, crypto::keys::Private,
-    economics::DEV_WALLET,
+    api::client::Client, crypto::keys::Private, economics::DEV_WALLET,
 };
 
+mod address_book;
+mod amount;
+mod backup;
+mod clock;
+mod commands;
+mod compact;
+mod completion;
+mod config;
+mod console;
+mod diagnose;
 mod encryption;
+mod exit_code;
+mod explorer;
+mod export;
 mod handle_command;
+mod history;
+mod i18n;
 mod input;
+mod interrupt;
+mod json;
+mod keystore;
+mod node;
+mod notes;
+mod notify;
+mod oplog;
+mod pager;
+mod prompt;
+mod qr;
+mod report;
+mod rpc;
+mod sanitize;
+mod session;
+mod signing;
+mod snapshot;
+mod spendable;
+mod spinner;
+mod style;
+mod table;
+mod tui;
+mod uri;
+mod vanity;
+mod watch;
+mod webhook;
 
 use crate::{
-    encryption::{decrypt_wallets, encrypt_wallets},
-    handle_command::handle_command,
-    input::{read_input, read_pin},
+    address_book::AddressBook,
+    backup::{self, Backups},
+    commands::{Chain, expand_history, split_chain},
+    completion::{CompletionData, SharedCompletionData, WalletHelper},
+    config::{Config, DonationPrompt, Setting, Source},
+    console::say,
+    encryption::{
+        FileHeader, KdfParams, SessionKeys, Unlocked, decrypt_wallets, encrypt_wallets,
+        format_version, read_file_header, read_kdf_params,
+    },
+    exit_code::{ErrorCode, ExitCode},
+    handle_command::{
+        FatalError, correct_typos, handle_command, has_unsaved_changes, print_command_error,
+        room_for_wallet, save, set_autosave,
+    },
+    i18n::{tr, trf},
+    input::{InputError, accept_pin, confirm, read_input, read_pin},
+    interrupt::Interrupt,
+    node::NodeApi,
+    notes::TxNotes,
+    notify::WalletWatcher,
+    prompt::PromptBalance,
+    session::{Session, SessionInputs},
+    signing::{PowCache, WalletKey, Wallets},
+    table::Column,
+    uri::PaymentRequest,
 };
 
+/// Node API address used when none is given
+const DEFAULT_NODE: &str = "127.0.0.1:3003";
+
+/// Command line options
+struct CliArgs {
+    /// Node API address
+    node: String,
+    /// Language of user-facing messages, `None` to detect from the environment
+    lang: Option<String>,
+    no_color: bool,
+    /// Answer yes to every confirmation question
+    assume_yes: bool,
+    /// Run the dashboard instead of the line-based REPL
+    tui: bool,
+    /// Show the wallet menu even when `auto_login_default` is on
+    choose: bool,
+    /// Print the wallet file header and exit, without asking for the PIN
+    file_info: bool,
+    /// Print the effective configuration and exit
+    dump_config: bool,
+    /// Print the active wallet's address alone on stdout once connected, with the startup
+    /// messages on stderr
+    print_address: bool,
+    /// A `snap:` payment link to pay, given by the OS when one is clicked
+    handle_uri: Option<String>,
+    /// Serve the wallet to local applications over JSON-RPC instead of running the REPL
+    serve: bool,
+    /// Address `serve` listens on
+    listen: Option<String>,
+    /// File holding the PIN, for logging in and running piped commands without a terminal
+    pin_file: Option<PathBuf>,
+    /// Write the troubleshooting log, whatever the config says
+    log_file: bool,
+    /// Leave out the donation line, whatever the config says
+    no_donation_prompt: bool,
+}
+
+impl CliArgs {
+    fn parse() -> Result<Self, Error> {
+        let mut cli = CliArgs {
+            node: DEFAULT_NODE.to_string(),
+            lang: None,
+            no_color: false,
+            assume_yes: false,
+            tui: false,
+            choose: false,
+            file_info: false,
+            dump_config: false,
+            print_address: false,
+            handle_uri: None,
+            serve: false,
+            listen: None,
+            pin_file: None,
+            log_file: false,
+            no_donation_prompt: false,
+        };
+        let mut args = args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--lang" => {
+                    cli.lang =
+                        Some(args.next().ok_or_else(|| Error::msg("Missing value for --lang"))?)
+                }
+                "--no-color" => cli.no_color = true,
+                "--assume-yes" | "-y" => cli.assume_yes = true,
+                "--tui" => cli.tui = true,
+                "--choose" => cli.choose = true,
+                "--file-info" => cli.file_info = true,
+                "--dump-config" => cli.dump_config = true,
+                "--print-address-on-start" => cli.print_address = true,
+                "--log-file" => cli.log_file = true,
+                "--no-donation-prompt" => cli.no_donation_prompt = true,
+                "--handle-uri" => {
+                    cli.handle_uri = Some(
+                        args.next().ok_or_else(|| Error::msg("Missing value for --handle-uri"))?,
+                    )
+                }
+                "--listen" => {
+                    cli.listen =
+                        Some(args.next().ok_or_else(|| Error::msg("Missing value for --listen"))?)
+                }
+                "--pin-file" => {
+                    let path = args.next();
+                    let path = path.ok_or_else(|| Error::msg("Missing value for --pin-file"))?;
+                    cli.pin_file = Some(PathBuf::from(path));
+                }
+                "serve" => cli.serve = true,
+                _ if arg.starts_with("--") => {
+                    return Err(Error::msg(format!("Unknown option: {}", arg)));
+                }
+                _ => cli.node = arg,
+            }
+        }
+        // A clicked link must never pay without the user answering for it
+        if cli.assume_yes && cli.handle_uri.is_some() {
+            return Err(Error::msg("--assume-yes can't be used with --handle-uri"));
+        }
+        if cli.serve && (cli.handle_uri.is_some() || cli.tui) {
+            return Err(Error::msg("serve can't be combined with --handle-uri or --tui"));
+        }
+        if cli.listen.is_some() && !cli.serve {
+            return Err(Error::msg("--listen only applies to serve"));
+        }
+        Ok(cli)
+    }
+
+    /// Settings decided by the command line and the environment, with where each came from.
+    /// Call after `i18n::init` and `style::init`
+    fn settings(&self, config: &Config) -> Result<Vec<Setting>, Error> {
+        let flag_or_default = |set: bool| if set { Source::Flag } else { Source::Default };
+        let lang_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .any(|var| env::var(var).is_ok_and(|value| !value.is_empty()));
+        let lang_source = match (&self.lang, config.lang, lang_env) {
+            (Some(_), _, _) => Source::Flag,
+            (None, Some(_), _) => Source::File,
+            (None, None, true) => Source::Environment,
+            (None, None, false) => Source::Default,
+        };
+        let color_source = if self.no_color {
+            Source::Flag
+        } else if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            Source::Environment
+        } else {
+            Source::Default
+        };
+        // Only whether it is set, never the secret itself
+        let webhook_secret =
+            env::var_os(webhook::SECRET_VAR).is_some_and(|value| !value.is_empty());
+        let webhook_secret_source = match webhook_secret {
+            true => Source::Environment,
+            false => Source::Default,
+        };
+        let switch = |on: bool| if on { "on" } else { "off" };
+        Ok(vec![
+            Setting::new("node", &self.node, flag_or_default(self.node != DEFAULT_NODE)),
+            Setting::new("lang", i18n::code(), lang_source),
+            Setting::new("color", switch(style::enabled()), color_source),
+            Setting::new("assume_yes", switch(self.assume_yes), flag_or_default(self.assume_yes)),
+            Setting::new(
+                "no_donation_prompt",
+                switch(self.no_donation_prompt),
+                flag_or_default(self.no_donation_prompt),
+            ),
+            // The path only, the PIN itself is never shown
+            Setting::new(
+                "pin_file",
+                self.pin_file.as_ref().map_or("none".into(), |path| path.display().to_string()),
+                flag_or_default(self.pin_file.is_some()),
+            ),
+            Setting::new("webhook_secret", switch(webhook_secret), webhook_secret_source),
+            Setting::new("wallet_file", wallet_path()?.display(), Source::Default),
+            Setting::new("config_file", config_path()?.display(), Source::Default),
+            Setting::new("history_file", history_path()?.display(), Source::Default),
+            Setting::new("snapshot_file", snapshots_path()?.display(), Source::Default),
+        ])
+    }
+}
+
+/// Print the effective configuration: command line and environment settings first, then the
+/// config file ones. The config never holds secrets, so nothing needs masking
+pub fn dump_config(startup_settings: &[Setting], config: &Config) -> Result<(), Error> {
+    let (_, file_keys) = Config::load_with_keys()?;
+    let columns = [
+        Column::left("SETTING"),
+        Column::left("VALUE"),
+        Column::left("SOURCE"),
+    ];
+    let rows: Vec<Vec<String>> = startup_settings
+        .iter()
+        .chain(&config.settings(&file_keys))
+        .map(|setting| {
+            vec![
+                setting.name.clone(),
+                setting.value.clone(),
+                setting.source.name().to_string(),
+            ]
+        })
+        .collect();
+    for line in table::render(&columns, &rows) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
 /// Returns wallet file path
 fn wallet_path() -> Result<PathBuf, Error> {
-    let home = dirs::home_dir().ok_or_else(|| Error::msg("Could not determine home directory"))?;
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
     Ok(home.join(".snap-coin-wallet"))
 }
 
+/// Returns the path of the file locked while a wallet runs, next to the wallet file
+fn wallet_lock_path() -> Result<PathBuf, Error> {
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
+    Ok(home.join(".snap-coin-wallet.lock"))
+}
+
+/// Lock the wallet file against other running wallets, so two of them can't overwrite each
+/// other's changes. The lock lasts until the returned file is dropped or the program exits
+fn lock_wallet_file(path: &Path) -> Result<File, Error> {
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => {
+            Err(ErrorCode::WalletLocked.error(tr("error.wallet_locked")))
+        }
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Returns the directory `compact` keeps wallet file backups in
+pub fn backups_dir() -> Result<PathBuf, Error> {
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
+    Ok(home.join(".snap-coin-backups"))
+}
+
+/// Read the PIN from the first line of a file, so nothing has to be typed
+fn read_pin_file(path: &Path) -> Result<Zeroizing<String>, Error> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        ErrorCode::Usage.error(format!("Can't read the PIN file {}: {}", path.display(), e))
+    })?;
+    let text = Zeroizing::new(text);
+    Ok(Zeroizing::new(text.lines().next().unwrap_or_default().trim().to_string()))
+}
+
 /// Returns history file path
 fn history_path() -> Result<PathBuf, Error> {
-    let home = dirs::home_dir().ok_or_else(|| Error::msg("Could not determine home directory"))?;
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
     Ok(home.join(".snap-coin-history"))
 }
 
+/// Returns the file `snapshot` records balances in
+pub fn snapshots_path() -> Result<PathBuf, Error> {
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
+    Ok(home.join(".snap-coin-balances"))
+}
+
+/// Number of entries in a history file, without its version header
+fn history_file_entries(path: &Path) -> usize {
+    fs::read_to_string(path)
+        .map(|text| text.lines().filter(|line| !line.starts_with("#V")).count())
+        .unwrap_or(0)
+}
+
+/// Returns config file path
+fn config_path() -> Result<PathBuf, Error> {
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
+    Ok(home.join(".snap-coin-config"))
+}
+
 /// Returns last login file path
 fn last_login_path() -> Result<PathBuf, Error> {
-    let home = dirs::home_dir().ok_or_else(|| Error::msg("Could not determine home directory"))?;
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
     Ok(home.join(".snap-coin-last-login"))
 }
 
-/// Save all wallets with PIN
-fn save_wallets(wallets: &HashMap<String, Private>, pin: &str) -> Result<(), Error> {
+/// Returns the file recording which one-time notices were shown
+fn notices_path() -> Result<PathBuf, Error> {
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
+    Ok(home.join(".snap-coin-notices"))
+}
+
+/// Returns the file recording when the donation line was last shown
+fn donation_shown_path() -> Result<PathBuf, Error> {
+    let home = dirs::home_dir().ok_or_else(|| Error::msg(tr("error.home_dir")))?;
+    Ok(home.join(".snap-coin-donation-shown"))
+}
+
+/// Whether the donation line is due under `mode`. A weekly line is recorded as shown
+fn donation_due(mode: DonationPrompt) -> bool {
+    if mode != DonationPrompt::Weekly {
+        return mode.due(None, 0);
+    }
+    let (Ok(path), Ok(now)) = (donation_shown_path(), clock::local_now()) else {
+        return false;
+    };
+    let last_shown = fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| text.trim().parse().ok());
+    if !mode.due(last_shown, now) {
+        return false;
+    }
+    fs::write(&path, now.to_string()).ok();
+    true
+}
+
+/// Whether the one-time notice `name` is yet to be shown, recording it as shown
+pub fn first_notice(name: &str) -> Result<bool, Error> {
+    // Tests never touch the real notices file
+    if cfg!(test) {
+        return Ok(false);
+    }
+    let path = notices_path()?;
+    let mut shown = String::new();
+    if path.exists() {
+        File::open(&path)?.read_to_string(&mut shown)?;
+    }
+    if shown.lines().any(|line| line == name) {
+        return Ok(false);
+    }
+    shown.push_str(name);
+    shown.push('\n');
+    File::create(path)?.write_all(shown.as_bytes())?;
+    Ok(true)
+}
+
+/// Save all wallets, the address book, transaction notes and confirmed backups with the
+/// session keys
+fn save_wallets(
+    wallets: &Wallets,
+    book: &AddressBook,
+    notes: &TxNotes,
+    backups: &Backups,
+    keys: &SessionKeys,
+) -> Result<(), Error> {
     let path = wallet_path()?;
+    let encrypted = encrypt_wallets(wallets, book, notes, backups, keys)
+        .ok_or_else(|| Error::msg(tr("error.encrypt")))?;
     let mut file = File::create(path)?;
-    let encrypted =
-        encrypt_wallets(wallets, pin).ok_or_else(|| Error::msg("Failed to encrypt wallets"))?;
     file.write_all(&encrypted)?;
     Ok(())
 }
 
-/// Load wallets using PIN
-fn load_wallets(pin: &str) -> Result<HashMap<String, Private>, Error> {
+/// Load wallets, the address book, transaction notes and confirmed backups using PIN, with the
+/// session keys needed to save them again
+/// Takes the PIN by value, so it is dropped as soon as the keys are derived
+fn load_wallets(pin: Zeroizing<String>) -> Result<Unlocked, Error> {
     let path = wallet_path()?;
     if !path.exists() {
-        return Ok(HashMap::new());
+        let keys = SessionKeys::derive(pin, KdfParams::default())
+            .ok_or_else(|| Error::msg(tr("error.derive_key")))?;
+        return Ok((Wallets::new(), AddressBook::new(), TxNotes::new(), Backups::new(), keys));
     }
     let mut file = File::open(path)?;
     let mut buf = Vec::new();
     file.read_to_end(&mut buf)?;
-    decrypt_wallets(&buf, pin).ok_or_else(|| Error::msg("Failed to decrypt wallets (wrong PIN?)"))
+
+    let kdf_params =
+        read_kdf_params(&buf).ok_or_else(|| Error::msg(tr("error.file_header")))?;
+    if kdf_params.exceeds_sanity_ceiling() {
+        say!(
+            "{}",
+            style::warning(trf(
+                "kdf.expensive_warning",
+                &[
+                    &kdf_params.memory_kib,
+                    &kdf_params.iterations,
+                    &kdf_params.parallelism
+                ]
+            ))
+        );
+        say!("{}", style::warning(tr("kdf.tamper_warning")));
+        if !confirm(tr("prompt.continue_loading"), false) {
+            return Err(Error::msg(tr("error.load_aborted")));
+        }
+    }
+
+    decrypt_wallets(&buf, pin).ok_or_else(|| ErrorCode::WrongPin.error(tr("error.decrypt")))
+}
+
+/// Print the unencrypted header fields of the wallet file. Reads no key material and never
+/// attempts decryption
+pub fn print_file_info() -> Result<(), Error> {
+    let path = wallet_path()?;
+    println!("Wallet file: {}", path.display());
+    if !path.exists() {
+        println!("  (does not exist yet)");
+        return Ok(());
+    }
+    let metadata = fs::metadata(&path)?;
+    println!("  Size:     {} bytes", metadata.len());
+    if let Ok(modified) = metadata.modified() {
+        let modified = modified.duration_since(UNIX_EPOCH)?.as_secs();
+        let age = clock::local_now()?.saturating_sub(modified);
+        println!("  Modified: {} (unix time, {} ago)", modified, table::format_age(age));
+    }
+
+    let mut data = Vec::new();
+    File::open(&path)?.read_to_end(&mut data)?;
+    match read_file_header(&data) {
+        FileHeader::Legacy => {
+            println!("  Format:   version 1, without header (legacy)");
+            println!("  KDF:      legacy PIN hash, no salt");
+            println!("  Upgraded to version {} on the next save.", format_version());
+        }
+        FileHeader::Versioned {
+            version,
+            params,
+            salt,
+        } => {
+            println!("  Format:   version {} (this wallet writes {})", version, format_version());
+            println!(
+                "  KDF:      {}, memory {} KiB, {} iteration(s), parallelism {}",
+                params.algorithm.name(),
+                params.memory_kib,
+                params.iterations,
+                params.parallelism
+            );
+            println!("  Salt:     {}", if salt { "present" } else { "missing (all zero)" });
+            if params.exceeds_sanity_ceiling() {
+                println!("{}", style::warning("  The KDF settings exceed the sanity limits."));
+            }
+        }
+        FileHeader::Unreadable { version } => {
+            match version {
+                Some(version) if version > format_version() => println!(
+                    "  Format:   version {}, newer than this wallet understands ({})",
+                    version,
+                    format_version()
+                ),
+                Some(version) => println!("  Format:   version {}, damaged header", version),
+                None => println!("  Format:   truncated header"),
+            }
+            println!("{}", style::error("  This wallet can't read the file."));
+        }
+    }
+    Ok(())
 }
 
 pub fn save_last_login(last_login: String) -> Result<(), Error> {
+    // Tests switch wallets without writing to the real home directory
+    if cfg!(test) {
+        return Ok(());
+    }
     let path = last_login_path()?;
     let mut file = File::create(path)?;
     file.write_all(last_login.as_bytes())?;
@@ -83,169 +531,787 @@ pub fn load_last_login() -> Result<String, Error> {
 }
 
 /// Select wallet from existing ones
-fn select_wallet(wallets: &HashMap<String, Private>) -> Result<String, Error> {
-    println!("Available wallets:");
+fn select_wallet(wallets: &Wallets) -> Result<String, Error> {
     let last_wallet = load_last_login()?;
-    for name in wallets.keys() {
-        println!(
-            "  - {}{}",
-            name,
-            if name == &last_wallet {
-                " [default]"
-            } else {
-                ""
-            }
-        );
+    choose_wallet(wallets, last_wallet.trim())
+}
+
+/// Ask for a wallet from a numbered list, by its number or its name. An empty answer picks
+/// `default` as long as that wallet still exists, or else the only wallet there is. Ctrl+C
+/// and the end of input come back as `InputError::Cancelled`
+fn choose_wallet(wallets: &Wallets, default: &str) -> Result<String, Error> {
+    let names: Vec<&String> = wallets.keys().collect();
+    let default = match names.as_slice() {
+        _ if wallets.contains_key(default) => Some(default),
+        [only] => Some(only.as_str()),
+        _ => None,
+    };
+    say!("{}", tr("available_wallets"));
+    for (number, name) in names.iter().enumerate() {
+        let marker = match default == Some(name.as_str()) {
+            true => tr("default_marker"),
+            false => "",
+        };
+        say!("  {}. {}{}", number + 1, name, marker);
     }
     loop {
-        let name = read_input("Enter wallet name to login: ");
-        if name == "" && last_wallet != "" {
-            return Ok(last_wallet);
+        let answer = read_input(tr("prompt.login"))?;
+        if answer.is_empty() {
+            match default {
+                Some(name) => return Ok(name.to_string()),
+                None => say!("{}", style::error(trf("wallet_name_required", &[&names.len()]))),
+            }
+            continue;
         }
-        if wallets.contains_key(&name) {
-            return Ok(name);
+        // A wallet named like a number is picked by its name first
+        if wallets.contains_key(&answer) {
+            return Ok(answer);
+        }
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=names.len()).contains(&number) => {
+                return Ok(names[number - 1].clone());
+            }
+            _ => say!("{}", style::error(trf("wallet_not_found_retry", &[&answer]))),
         }
-        println!("Wallet '{}' not found. Please try again.", name);
     }
 }
 
-/// Create new wallet, optionally import from base36 private key
-fn create_wallet(wallets: &mut HashMap<String, Private>, pin: &str) -> Result<String, Error> {
-    let name = read_input("Enter a name for your new wallet: ");
-    if wallets.contains_key(&name) {
-        return Err(Error::msg("Wallet already exists"));
-    }
+/// Create new wallet, optionally import from base36 private key, and save it right away
+fn create_wallet(
+    wallets: &mut Wallets,
+    book: &AddressBook,
+    notes: &TxNotes,
+    backups: &Backups,
+    keys: &SessionKeys,
+) -> Result<String, Error> {
+    let (name, wallet) = add_new_wallet(wallets)?;
+    save_wallets(wallets, book, notes, backups, keys)?;
+    say!("{}", trf("wallet_created", &[&name]));
+    say!();
+    say!("{}", style::danger(tr("backup.save_key")));
+    say!("{}", trf("backup.key", &[&wallet.dump_base36()]));
+    say!("{}", style::danger(tr("backup.loss_warning")));
+    say!("{}", style::danger(tr("backup.theft_warning")));
+    say!();
 
-    let key_input = read_input("Enter a base36 private key to import (leave empty for random): ");
-    let wallet = if key_input.is_empty() {
-        Private::new_random()
-    } else {
-        Private::new_from_base36(&key_input)
-            .ok_or_else(|| Error::msg("Invalid base36 private key"))?
+    Ok(name)
+}
+
+/// Ask for the name and key of a new wallet and add it to `wallets`, without saving
+/// Invalid or taken names and invalid keys are asked for again; an empty key means a new one
+pub fn add_new_wallet(wallets: &mut Wallets) -> Result<(String, Private), Error> {
+    let name = loop {
+        let name = read_input(tr("prompt.new_wallet_name"))?;
+        if let Err(reason) = sanitize::check_name(&name) {
+            say!("{}", style::error(trf("invalid_name_retry", &[&reason])));
+        } else if wallets.contains_key(&name) {
+            say!("{}", style::error(trf("wallet_exists_retry", &[&name])));
+        } else {
+            break name;
+        }
     };
 
-    wallets.insert(name.clone(), wallet);
-    save_wallets(wallets, pin)?;
-    println!("Wallet '{}' created successfully.", name);
-    println!();
-    println!("Please make sure to save the wallet private key, in a SAFE, OFFLINE LOCATION!");
-    println!("Wallet private key (base 36): {}", wallet.dump_base36());
-    println!(
-        "!!! If you loose this key, you can and will loose your snap coin's. There is NO way to recover them if lost !!!"
-    );
-    println!("!!! If anyone sees this key, they can and will still your snap coin's !!!");
-    println!();
+    let wallet = loop {
+        let key_input = read_input(tr("prompt.import_key"))?;
+        if key_input.is_empty() {
+            break Private::new_random();
+        }
+        match sanitize::parse_private(&key_input) {
+            Ok(private) => break private,
+            Err(reason) => say!(
+                "{}",
+                style::error(format!("{}: {}", tr("error.invalid_private_key"), reason))
+            ),
+        }
+    };
 
-    Ok(name)
+    wallets.insert(name.clone(), WalletKey::Local(wallet));
+    Ok((name, wallet))
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    println!("--- Snap Coin Wallet ---");
+/// The unlocked wallet file and the wallet picked to log in to
+struct Login {
+    wallets: Wallets,
+    book: AddressBook,
+    notes: TxNotes,
+    backups: Backups,
+    keys: SessionKeys,
+    current_wallet: String,
+}
 
+/// Ask for the PIN, or take the one of `--pin-file`, load the wallet file and pick the wallet
+/// to log in to. With `auto_login`, the last used wallet is picked without showing the menu.
+/// With `piped`, stdin holds commands rather than answers, so it is picked or login fails
+fn login(
+    auto_login: bool,
+    piped: bool,
+    pin_file: Option<Zeroizing<String>>,
+    max_wallets: usize,
+) -> Result<Login, Error> {
     // --- Read PIN ---
-    let pin = read_pin("Enter 6-digit wallet PIN: ")?;
+    // Without a wallet file the PIN typed is a new one, so it is checked for weakness
+    let new_file = !wallet_path()?.exists();
+    if piped && new_file {
+        return Err(ErrorCode::Usage.error(tr("piped.no_wallet")));
+    }
+    let pin = match pin_file {
+        Some(pin) if new_file && !accept_pin(&pin) => return Err(InputError::Cancelled.into()),
+        Some(pin) => pin,
+        None => loop {
+            let pin = read_pin(tr("prompt.pin"))?;
+            if !new_file || accept_pin(&pin) {
+                break pin;
+            }
+        },
+    };
 
     // --- Load wallets ---
-    let mut wallets = load_wallets(&pin)?;
+    let (mut wallets, book, notes, backups, keys) = load_wallets(pin)?;
 
     // --- Determine current wallet ---
-    let mut current_wallet = if wallets.is_empty() {
-        println!("No wallets found. Creating a new wallet.");
-        if read_pin("Confirm 6-digit wallet PIN: ")? != pin {
-            return Err(Error::msg("PINs don't match"));
+    let last_wallet = load_last_login()?;
+    let current_wallet = if (auto_login || piped) && wallets.contains_key(&last_wallet) {
+        say!("{}", trf("auto_login", &[&last_wallet]));
+        last_wallet
+    } else if piped {
+        return Err(ErrorCode::Usage.error(tr("piped.no_wallet")));
+    } else if wallets.is_empty() {
+        say!("{}", tr("no_wallets"));
+        if !keys.verify(read_pin(tr("prompt.pin_confirm"))?) {
+            return Err(ErrorCode::WrongPin.error(tr("error.pins_mismatch")));
         }
-        create_wallet(&mut wallets, &pin)?
+        create_wallet(&mut wallets, &book, &notes, &backups, &keys)?
     } else {
-        println!("1) Select existing wallet [default]");
-        println!("2) Create new wallet");
-        let choice = read_input("Choose option (1 or 2): ");
-        let choice = if choice.is_empty() {
-            "1"
-        } else {
-            choice.as_str()
+        say!("{}", tr("menu.select"));
+        say!("{}", tr("menu.create"));
+        // An empty answer picks the default, 1
+        loop {
+            match read_input(tr("prompt.menu_choice"))?.as_str() {
+                "" | "1" => break select_wallet(&wallets)?,
+                "2" if room_for_wallet(&wallets, max_wallets) => {
+                    break create_wallet(&mut wallets, &book, &notes, &backups, &keys)?;
+                }
+                "2" => {}
+                _ => say!("{}", style::error(tr("error.invalid_choice"))),
+            }
+        }
+    };
+
+    Ok(Login {
+        wallets,
+        book,
+        notes,
+        backups,
+        keys,
+        current_wallet,
+    })
+}
+
+/// Remind about every funded local wallet whose key was never confirmed as backed up, at each
+/// startup until it is
+async fn remind_backups(session: &Session) {
+    for name in backup::unconfirmed(&session.wallets, &session.backups) {
+        let public = session.wallets[name].public();
+        // A balance the node can't give now is asked for again at the next startup
+        let Ok(balance) = session.client.get_balance(public).await else {
+            continue;
+        };
+        if balance > 0 {
+            let held = amount::display(balance);
+            let message = trf("backup.reminder", &[&name, &held, &name, &name]);
+            say!("{}", style::warning(message));
+        }
+    }
+}
+
+/// Run a command. Ctrl+C cancels it by dropping it where it waits, unless it is in a step that
+/// can't stop halfway or handles Ctrl+C itself. `None` when it was cancelled
+async fn run_interruptible(session: &mut Session, command: String) -> Option<Result<(), Error>> {
+    let running = handle_command(session, command);
+    tokio::pin!(running);
+    loop {
+        tokio::select! {
+            result = &mut running => return Some(result),
+            _ = tokio::signal::ctrl_c() => match interrupt::on_ctrl_c() {
+                Interrupt::Cancel => return None,
+                Interrupt::Finish => println!("{}", style::warning(tr("interrupt.finishing"))),
+                Interrupt::Handled => {}
+            },
+        }
+    }
+}
+
+/// Offer to save changes autosave didn't write, before quitting
+fn save_on_exit(session: &Session) {
+    if !has_unsaved_changes() {
+        return;
+    }
+    println!("{}", style::warning(tr("save.unsaved")));
+    if confirm(tr("save.confirm_exit"), true) {
+        let saved = save(
+            &session.wallets,
+            &session.book,
+            &session.notes,
+            &session.backups,
+            &session.keys,
+        );
+        if saved {
+            println!("{}", tr("save.done"));
+        }
+    } else {
+        println!("{}", style::warning(tr("save.discarded")));
+    }
+}
+
+/// `suspend`: save, forget the keys and drop the node connection, then wait until the PIN
+/// unlocks the wallet file again and the node is back. Returns whether the session goes on,
+/// `false` when quitting from the suspended screen. The only error is a `FatalError`: a session
+/// still suspended can't run commands
+async fn suspend(session: &mut Session) -> Result<bool, Error> {
+    if has_unsaved_changes() {
+        let saved = save(
+            &session.wallets,
+            &session.book,
+            &session.notes,
+            &session.backups,
+            &session.keys,
+        );
+        if !saved {
+            say!("{}", style::error(tr("suspend.not_saved")));
+            return Ok(true);
+        }
+    }
+    session.suspend();
+    say!("{}", tr("suspend.done"));
+    loop {
+        // Ctrl+C, Esc or a closed input quit, everything is saved already
+        if read_input(tr("suspend.prompt")).is_err() {
+            return Ok(false);
+        }
+        // A cancelled or timed out PIN prompt goes back to waiting
+        let Ok(pin) = read_pin(tr("prompt.pin")) else {
+            continue;
+        };
+        let unlocked = match load_wallets(pin) {
+            Ok(unlocked) => unlocked,
+            Err(e) => {
+                say!("{}", style::error(e));
+                continue;
+            }
+        };
+        let connected = Client::connect(session.node).await;
+        oplog::connect(session.node, "resume", connected.as_ref().err());
+        let client = match connected {
+            Ok(client) => client,
+            Err(e) => {
+                let message = trf("suspend.no_node", &[&session.node, &e]);
+                say!("{}", style::error(message));
+                continue;
+            }
         };
+        session.resume(unlocked, Box::new(client));
+        say!("{}", trf("connected", &[&session.node]));
+        // The file may have been replaced meanwhile, such as restored from a backup
+        if !session.wallets.contains_key(&session.current_wallet) {
+            let Some(first) = session.wallets.keys().next() else {
+                return Err(FatalError(tr("wallet.none_remaining").to_string()).into());
+            };
+            session.current_wallet = first.clone();
+            say!("{}", trf("wallet.switched", &[&session.current_wallet]));
+        }
+        return Ok(true);
+    }
+}
+
+/// Run the commands piped on stdin, a line at a time, until it ends. Lines chain with `&&` and
+/// `;` like typed ones, but nothing goes to the command history. Every line runs even after a
+/// failure, which then decides the exit code: the first failed command's
+async fn run_piped(session: &mut Session) -> Result<(), Error> {
+    let mut first_failure: Option<ExitCode> = None;
+    let mut fatal: Option<Error> = None;
+    'lines: for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let mut failed = false;
+        for (chain, command) in split_chain(&line) {
+            if chain == Chain::OnSuccess && failed {
+                continue;
+            }
+            failed = true;
+            say!("{} > {}", session.current_wallet, command);
+            let Some(command) = correct_typos(&command, &session.config) else {
+                first_failure.get_or_insert(ExitCode::Failure);
+                continue;
+            };
+            match command.as_str() {
+                "exit" | "e" | "quit" | "q" => break 'lines,
+                // Nothing on screen to clear
+                "clear" | "cls" => {
+                    failed = false;
+                    continue;
+                }
+                "dashboard" | "history-list" | "clear-history" | "suspend" => {
+                    say!("{}", style::error(trf("piped.interactive_only", &[&command])));
+                    first_failure.get_or_insert(ExitCode::Failure);
+                    continue;
+                }
+                _ => {}
+            }
+            let result = run_interruptible(session, command).await;
+            let refused = input::take_refused();
+            match result {
+                Some(Ok(())) if !refused => failed = false,
+                Some(Ok(())) => {
+                    first_failure.get_or_insert(ExitCode::Failure);
+                }
+                Some(Err(e)) if e.downcast_ref::<FatalError>().is_some() => {
+                    fatal = Some(e);
+                    break 'lines;
+                }
+                Some(Err(e)) => {
+                    print_command_error(&e);
+                    first_failure.get_or_insert(ExitCode::of(&e));
+                }
+                // Ctrl+C stops the commands still to come as well
+                None => {
+                    say!("{}", style::warning(tr("cancelled")));
+                    first_failure.get_or_insert(ExitCode::Failure);
+                    break 'lines;
+                }
+            }
+        }
+    }
+    save_on_exit(session);
+    match (fatal, first_failure) {
+        (Some(e), _) => Err(e),
+        (None, Some(code)) => Err(code.reported()),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Pay a `snap:` link: show what it asks for, then run it as a `send`, which shows the summary
+/// and asks for confirmation and the PIN like any other
+async fn pay_request(session: &mut Session, request: PaymentRequest) -> Result<(), Error> {
+    let address = request.address.dump_base36();
+    say!("{}", trf("uri.request", &[&address]));
+    if let Some(label) = &request.label {
+        say!("{}", trf("uri.label", &[label]));
+    }
+    let amount = match request.amount {
+        Some(amount) => amount,
+        None => loop {
+            let answer = read_input(tr("uri.amount_prompt")).unwrap_or_default();
+            if answer.is_empty() {
+                say!("{}", style::warning(tr("cancelled")));
+                return Ok(());
+            }
+            match amount::parse_payment(&answer) {
+                Ok(amount) => break amount,
+                Err(e) => say!("{}", style::error(e.message(&answer))),
+            }
+        },
+    };
+    // An amount in nano goes through `send` exactly
+    let mut command = format!("send {} {}nano", address, amount);
+    if let Some(label) = &request.label {
+        // Kept as the note of the payment, quoted as `send --label` expects
+        command.push_str(&format!(" --label \"{}\"", label.replace('"', "'")));
+    }
+    match run_interruptible(session, command).await {
+        Some(result) => result,
+        None => {
+            say!("{}", style::warning(tr("cancelled")));
+            Ok(())
+        }
+    }
+}
 
-        match choice {
-            "1" => select_wallet(&wallets)?,
-            "2" => create_wallet(&mut wallets, &pin)?,
-            _ => return Err(Error::msg("Invalid choice")),
+/// Whether an error is the user backing out of a prompt, which at startup just ends the program
+fn startup_abort(e: &Error) -> bool {
+    match e.downcast_ref::<InputError>() {
+        Some(InputError::Cancelled) => true,
+        Some(InputError::TimedOut) => {
+            say!("{}", style::warning(tr("pin.timed_out")));
+            true
+        }
+        _ => false,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        if !exit_code::is_reported(&e) {
+            match ErrorCode::of(&e) {
+                ErrorCode::Failed => eprintln!("Error: {:?}", e),
+                code => eprintln!("Error: [{}] {:?}", code, e),
+            }
         }
+        oplog::exited(ExitCode::of(&e) as i32);
+        std::process::exit(ExitCode::of(&e) as i32);
+    }
+    oplog::exited(0);
+}
+
+async fn run() -> Result<(), Error> {
+    let cli = CliArgs::parse().map_err(|e| ErrorCode::Usage.error(e.to_string()))?;
+    let config = Config::load()?;
+    i18n::init(cli.lang.as_deref(), config.lang);
+    style::init(cli.no_color);
+    input::set_pin_timeout(config.pin_timeout);
+    amount::set_grouping(config.thousands_separator);
+    amount::set_unit(config.unit);
+    pager::set_enabled(config.pager);
+    notify::set_mode(config.notify, config.quiet);
+    notify::set_low_balance(config.low_balance);
+    set_autosave(config.autosave);
+    input::set_assume_yes(cli.assume_yes);
+    oplog::set_enabled(cli.log_file || config.log_file);
+    oplog::started();
+    console::reserve_stdout(cli.print_address);
+    say!("{}", tr("banner"));
+    if cli.file_info {
+        return print_file_info();
+    }
+    let startup_settings = cli.settings(&config)?;
+    if cli.dump_config {
+        return dump_config(&startup_settings, &config);
+    }
+    // A link that doesn't parse is refused before the PIN is even asked
+    let payment_request = match &cli.handle_uri {
+        Some(link) => Some(uri::parse(link).map_err(|reason| {
+            ErrorCode::Usage.error(trf("uri.invalid", &[&reason]))
+        })?),
+        None => None,
+    };
+
+    // Commands piped on stdin run without the REPL, stdin is then no place for answers
+    let piped = !std::io::stdin().is_terminal() && !cli.serve && cli.handle_uri.is_none();
+    if piped && cli.tui {
+        return Err(ErrorCode::Usage.error("--tui needs a terminal, not piped commands"));
+    }
+    let pin_file = cli.pin_file.as_deref().map(read_pin_file).transpose()?;
+    // Held until the program exits
+    let _wallet_lock = lock_wallet_file(&wallet_lock_path()?)?;
+
+    let auto_login = config.auto_login_default && !cli.choose;
+    // The PIN is dropped once it unlocked the wallet file, piped commands are confirmed by
+    // that alone
+    let pin_proven = pin_file.is_some();
+    let Login {
+        wallets,
+        book,
+        notes,
+        backups,
+        keys,
+        current_wallet,
+    } = match login(auto_login, piped, pin_file, config.max_wallets) {
+        Err(e) if startup_abort(&e) => return Ok(()),
+        result => result?,
     };
+    if piped {
+        input::set_piped(pin_proven);
+    }
 
     let wallet = wallets.get(&current_wallet).unwrap();
     save_last_login(current_wallet.clone())?;
-    println!(
-        "Loaded wallet '{}' with public key: {}",
-        current_wallet,
-        wallet.to_public().dump_base36()
-    );
-    println!(
-        "Consider donating to the developer :) {}",
-        DEV_WALLET.dump_base36()
+    say!(
+        "{}",
+        trf(
+            "loaded_wallet",
+            &[&current_wallet, &wallet.public().dump_base36()]
+        )
     );
+    // Scripts and programs reading the output never get it
+    let scripted = piped || cli.serve || cli.print_address;
+    if !scripted && !cli.no_donation_prompt && donation_due(config.donation_prompt) {
+        say!("{}", trf("donate", &[&DEV_WALLET.dump_base36()]));
+    }
 
     // --- Connect to node ---
-    let mut node_addr = "127.0.0.1:3003";
+    let node_addr = cli.node.as_str();
+    let node_socket = node_addr
+        .parse()
+        .map_err(|e| ErrorCode::Usage.error(format!("Invalid node address {}: {}", node_addr, e)))?;
+    let connected = Client::connect(node_socket).await;
+    oplog::connect(node_socket, "session", connected.as_ref().err());
+    let client = connected.map_err(|e| {
+        ErrorCode::NodeUnreachable.error(format!("Can't connect to {}: {}", node_addr, e))
+    })?;
+    say!("{}", trf("connected", &[&node_addr]));
 
-    let args = args().collect::<Vec<String>>();
-    if let Some(node) = args.get(1) {
-        node_addr = node;
+    let clock_skew = clock::detect_skew(&client).await.unwrap_or(None);
+    if let Some(skew) = clock_skew.filter(|skew| clock::is_significant(*skew)) {
+        say!("{}", style::warning(trf("clock.skew_warning", &[&skew])));
     }
+    clock::set_correction(clock_skew);
 
-    let client = Client::connect(node_addr.parse()?).await?;
-    println!("Connected to node at {}", node_addr);
+    // The address is the first and only line on stdout until here, for a wrapping process
+    if cli.print_address {
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "{}", wallet.public().dump_base36())?;
+        stdout.flush()?;
+        console::reserve_stdout(false);
+    }
+
+    let mut session = Session {
+        client: Box::new(client),
+        node: node_socket,
+        wallets,
+        book,
+        notes,
+        backups,
+        current_wallet,
+        keys,
+        used_session_inputs: SessionInputs::default(),
+        recent_addresses: vec![],
+        pow_cache: PowCache::default(),
+        clock_skew,
+        config,
+        startup_settings,
+    };
+    remind_backups(&session).await;
+
+    if let Some(request) = payment_request {
+        return pay_request(&mut session, request).await;
+    }
+    if cli.serve {
+        let listen = cli.listen.as_deref().unwrap_or(rpc::DEFAULT_LISTEN);
+        return rpc::serve(&mut session, listen).await;
+    }
+    if piped {
+        // Started for sends to be posted, the other watchers only matter at a prompt
+        webhook::spawn(node_socket);
+        return run_piped(&mut session).await;
+    }
+
+    if cli.tui && tui::run(&mut session).await? {
+        return Ok(());
+    }
+
+    let prompt_balance = PromptBalance::spawn(node_socket);
+    if session.config.prompt_balance {
+        prompt_balance.refresh(session.wallets[&session.current_wallet].public());
+    }
+    let incoming = WalletWatcher::spawn(node_socket);
+    webhook::spawn(node_socket);
+    incoming.watch(session.current_public(), &session.current_wallet);
 
     // --- Setup Rustyline ---
-    let mut rl = Editor::<(), DefaultHistory>::new()?;
+    let completion_data: SharedCompletionData = Rc::new(RefCell::new(CompletionData {
+        wallet_names: session.wallets.keys().cloned().collect(),
+        contact_aliases: session.book.keys().cloned().collect(),
+        recent_addresses: vec![],
+        command_aliases: session.config.aliases.keys().cloned().collect(),
+    }));
+    let editor_config = EditorConfig::builder()
+        .completion_type(CompletionType::List)
+        .max_history_size(session.config.history_size)?
+        .history_ignore_dups(true)?
+        .build();
+    let mut rl = Editor::<WalletHelper, DefaultHistory>::with_config(editor_config)?;
+    rl.set_helper(Some(WalletHelper::new(completion_data.clone())));
     let hist_path = history_path()?;
     if hist_path.exists() {
         rl.load_history(&hist_path).ok();
+        // Only the newest entries were loaded, writing them back trims the file
+        if history_file_entries(&hist_path) > session.config.history_size {
+            rl.save_history(&hist_path).ok();
+        }
     }
 
-    let mut used_session_inputs: Vec<TransactionInput> = vec![];
+    let mut fatal: Option<Error> = None;
 
-    loop {
-        let readline = rl.readline("snap coin wallet > ");
+    'repl: loop {
+        let current_public = session.current_public();
+        let prompt = match current_public {
+            Some(public) if session.config.prompt_balance => format!(
+                "{} ({}) > ",
+                session.current_wallet,
+                prompt_balance.display(public)
+            ),
+            _ => format!("{} > ", session.current_wallet),
+        };
+        let readline = rl.readline(&prompt);
         match readline {
             Ok(line) => {
-                let command = line.trim();
-                if command.is_empty() {
-                    continue;
+                // History references are expanded first and shown, so it is clear what runs
+                let entries: Vec<String> = rl.history().iter().cloned().collect();
+                let line = match expand_history(&line, &entries) {
+                    Ok(Some(expanded)) => {
+                        println!("{}", expanded);
+                        expanded
+                    }
+                    Ok(None) => line,
+                    Err(e) => {
+                        println!("{}", style::error(e));
+                        continue;
+                    }
+                };
+                let commands = split_chain(&line);
+                // A chained line is remembered as typed, single commands by the rules below
+                let chained = commands.len() > 1;
+                if chained {
+                    rl.add_history_entry(line.trim()).ok();
                 }
-                rl.add_history_entry(command)?;
+                // Whether the last command run failed, which skips the `&&` ones after it
+                let mut failed = false;
+                for (chain, command) in commands {
+                    if chain == Chain::OnSuccess && failed {
+                        continue;
+                    }
+                    failed = true;
+                    // Unknown commands aren't remembered, corrected ones are as corrected
+                    let Some(command) = correct_typos(&command, &session.config) else {
+                        continue;
+                    };
+                    let command = command.as_str();
+                    // Commands handled here can't fail, the others are remembered once they
+                    // succeed
+                    let handled_here = [
+                        "exit",
+                        "e",
+                        "quit",
+                        "q",
+                        "dashboard",
+                        "suspend",
+                        "clear",
+                        "cls",
+                        "history-list",
+                    ];
+                    if !chained && handled_here.contains(&command) {
+                        rl.add_history_entry(command).ok();
+                    }
 
-                if ["exit", "e", "quit", "q"].contains(&command) {
-                    break;
-                }
-                if command == "clear" || command == "cls" {
-                    rl.clear_history()?;
-                    rl.clear_screen()?;
-                    continue;
-                }
+                    if ["exit", "e", "quit", "q"].contains(&command) {
+                        break 'repl;
+                    }
+                    if command == "dashboard" {
+                        match tui::run(&mut session).await {
+                            Ok(true) => break 'repl,
+                            Ok(false) => failed = false,
+                            Err(e) if e.downcast_ref::<FatalError>().is_some() => {
+                                fatal = Some(e);
+                                break 'repl;
+                            }
+                            Err(e) => print_command_error(&e),
+                        }
+                        let new_public = session.current_public();
+                        if let Some(public) = new_public.filter(|_| session.config.prompt_balance) {
+                            prompt_balance.refresh(public);
+                        }
+                        incoming.watch(new_public, &session.current_wallet);
+                        continue;
+                    }
+                    if command == "suspend" {
+                        // Nothing to notify about while the keys are gone
+                        incoming.watch(None, &session.current_wallet);
+                        match suspend(&mut session).await {
+                            Ok(true) => failed = false,
+                            Ok(false) => break 'repl,
+                            Err(e) => {
+                                fatal = Some(e);
+                                break 'repl;
+                            }
+                        }
+                        let public = session.current_public();
+                        if let Some(public) = public.filter(|_| session.config.prompt_balance) {
+                            prompt_balance.refresh(public);
+                        }
+                        incoming.watch(public, &session.current_wallet);
+                        let mut data = completion_data.borrow_mut();
+                        data.wallet_names = session.wallets.keys().cloned().collect();
+                        data.contact_aliases = session.book.keys().cloned().collect();
+                        continue;
+                    }
+                    if command == "clear" || command == "cls" {
+                        rl.clear_screen().ok();
+                        // `clear` used to wipe the command history as well
+                        if first_notice("clear-keeps-history").unwrap_or(false) {
+                            println!("{}", style::warning(tr("clear.notice")));
+                        }
+                        failed = false;
+                        continue;
+                    }
+                    if command == "history-list" {
+                        let mut output = pager::Output::new();
+                        for (number, entry) in entries.iter().enumerate() {
+                            output.line(format!("{:>5}  {}", number + 1, entry));
+                        }
+                        output.finish();
+                        failed = false;
+                        continue;
+                    }
+                    if command == "clear-history" {
+                        if confirm(tr("clear_history.confirm"), false) {
+                            rl.clear_history().ok();
+                            match fs::remove_file(&hist_path) {
+                                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                                    println!("{}", style::error(trf("command_error", &[&e])))
+                                }
+                                _ => {
+                                    println!("{}", tr("clear_history.done"));
+                                    failed = false;
+                                }
+                            }
+                        } else {
+                            println!("{}", style::warning(tr("cancelled")));
+                        }
+                        continue;
+                    }
 
-                // Pass mutable references to handle_command
-                handle_command(
-                    &client,
-                    &mut wallets,
-                    &mut current_wallet,
-                    &pin,
-                    command.to_string(),
-                    &mut used_session_inputs,
-                )
-                .await?;
-            }
+                    let result = run_interruptible(&mut session, command.to_string()).await;
+                    let cancelled = result.is_none();
+                    match result {
+                        Some(Ok(())) => {
+                            failed = false;
+                            if !chained {
+                                rl.add_history_entry(command).ok();
+                            }
+                        }
+                        Some(Err(e)) if e.downcast_ref::<FatalError>().is_some() => {
+                            fatal = Some(e);
+                            break 'repl;
+                        }
+                        Some(Err(e)) => print_command_error(&e),
+                        None => println!("{}", style::warning(tr("cancelled"))),
+                    }
+                    // Refresh the prompt balance after anything that may have changed it
+                    let new_public = session.current_public();
+                    let expanded = session.config.expand_alias(command).unwrap_or_default();
+                    let changes_balance = matches!(
+                        expanded.split_whitespace().next(),
+                        Some("send" | "merge-available" | "balance" | "set")
+                    );
+                    match new_public {
+                        Some(public) if session.config.prompt_balance => {
+                            if changes_balance || new_public != current_public {
+                                prompt_balance.refresh(public);
+                            }
+                        }
+                        _ => prompt_balance.disable(),
+                    }
+                    incoming.watch(new_public, &session.current_wallet);
 
-            Err(ReadlineError::Interrupted) => {
-                println!("Interrupted (Ctrl+C)");
-                break;
+                    let mut data = completion_data.borrow_mut();
+                    data.wallet_names = session.wallets.keys().cloned().collect();
+                    data.contact_aliases = session.book.keys().cloned().collect();
+                    data.recent_addresses = session.recent_addresses.clone();
+                    data.command_aliases = session.config.aliases.keys().cloned().collect();
+                    // Ctrl+C stops the rest of the line too
+                    if cancelled {
+                        break;
+                    }
+                }
             }
+
+            // Only Ctrl+D or `exit` leave, a reflexive Ctrl+C shouldn't
+            Err(ReadlineError::Interrupted) => println!("{}", tr("interrupt.hint")),
             Err(ReadlineError::Eof) => {
-                println!("Exiting (Ctrl+D)");
+                println!("{}", tr("exiting_eof"));
                 break;
             }
             Err(err) => {
-                println!("Error: {:?}", err);
+                println!("{}", style::error(format!("Error: {:?}", err)));
                 break;
             }
         }
@@ -253,6 +1319,59 @@ async fn main() -> Result<(), Error> {
 
     // --- Save wallet history ---
     rl.save_history(&hist_path).ok();
+    save_on_exit(&session);
 
-    Ok(())
+    match fatal {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::script_answers;
+
+    fn wallets(names: &[&str]) -> Wallets {
+        names
+            .iter()
+            .map(|name| (name.to_string(), WalletKey::Local(Private::new_random())))
+            .collect()
+    }
+
+    #[test]
+    fn test_choose_wallet() {
+        let several = wallets(&["alice", "bob", "2"]);
+        // Listed by name, so "2" is first, then "alice" and "bob"
+        script_answers(&["3"]);
+        assert_eq!(choose_wallet(&several, "").unwrap(), "bob");
+        script_answers(&["alice"]);
+        assert_eq!(choose_wallet(&several, "").unwrap(), "alice");
+        // A name wins over a number
+        script_answers(&["2"]);
+        assert_eq!(choose_wallet(&several, "").unwrap(), "2");
+        script_answers(&[""]);
+        assert_eq!(choose_wallet(&several, "bob").unwrap(), "bob");
+        // Without a default an empty answer, or a number out of range, is asked again
+        script_answers(&["", "4", "0", "carol", "1"]);
+        assert_eq!(choose_wallet(&several, "gone").unwrap(), "2");
+
+        let single = wallets(&["alice"]);
+        script_answers(&[""]);
+        assert_eq!(choose_wallet(&single, "").unwrap(), "alice");
+        script_answers(&[""]);
+        assert_eq!(choose_wallet(&single, "gone").unwrap(), "alice");
+    }
+
+    #[test]
+    fn test_lock_wallet_file() {
+        let path = env::temp_dir().join(format!("snap-lock-{}", std::process::id()));
+        let lock = lock_wallet_file(&path).unwrap();
+        let second = lock_wallet_file(&path).unwrap_err();
+        assert_eq!(ExitCode::of(&second), ExitCode::WalletLocked);
+        drop(lock);
+        // Free again once the first wallet is gone
+        drop(lock_wallet_file(&path).unwrap());
+        fs::remove_file(&path).ok();
+    }
 }
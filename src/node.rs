@@ -0,0 +1,377 @@
+use std::{collections::HashSet, future::Future, pin::Pin, time::Instant};
+
+use anyhow::Error;
+use snap_coin::{
+    api::client::Client,
+    core::transaction::{Transaction, TransactionId, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+
+use crate::{exit_code::ErrorCode, oplog};
+
+/// A pending call to the node
+pub type NodeFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + 'a>>;
+
+/// The node calls the command layer makes. `Client` is the real implementation, tests use a
+/// mock with canned responses so commands can run without a network
+pub trait NodeApi {
+    fn get_balance(&self, address: Public) -> NodeFuture<'_, u64>;
+
+    /// Unspent outputs of `address`: creating transaction, output, index in that transaction
+    fn get_available_transaction_outputs(
+        &self,
+        address: Public,
+    ) -> NodeFuture<'_, Vec<(Hash, TransactionOutput, usize)>>;
+
+    /// Confirmed transactions involving `address`, oldest first
+    fn get_transactions_of_address(&self, address: Public) -> NodeFuture<'_, Vec<TransactionId>>;
+
+    fn get_transaction<'a>(&'a self, id: &'a TransactionId) -> NodeFuture<'a, Option<Transaction>>;
+
+    fn get_mempool(&self) -> NodeFuture<'_, Vec<Transaction>>;
+
+    fn get_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]>;
+
+    fn get_live_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]>;
+
+    /// Submit a finished transaction. The outer error is the call failing, the inner one the
+    /// node refusing the transaction
+    fn submit_transaction(&self, transaction: Transaction) -> NodeFuture<'_, Result<(), Error>>;
+}
+
+/// A call to the real node, timed for the operation log. A call that fails means the node
+/// can't be reached, such as after it dropped the connection
+fn timed<'a, T: 'a>(
+    method: &'static str,
+    call: impl Future<Output = Result<T, Error>> + 'a,
+) -> NodeFuture<'a, T> {
+    Box::pin(async move {
+        let started = Instant::now();
+        let result = call.await;
+        oplog::rpc(method, started.elapsed(), result.as_ref().err());
+        result.map_err(|e| ErrorCode::NodeUnreachable.error(e.to_string()))
+    })
+}
+
+impl NodeApi for Client {
+    fn get_balance(&self, address: Public) -> NodeFuture<'_, u64> {
+        timed("get_balance", live::get_balance(self, address))
+    }
+
+    fn get_available_transaction_outputs(
+        &self,
+        address: Public,
+    ) -> NodeFuture<'_, Vec<(Hash, TransactionOutput, usize)>> {
+        timed(
+            "get_available_transaction_outputs",
+            live::get_available_transaction_outputs(self, address),
+        )
+    }
+
+    fn get_transactions_of_address(&self, address: Public) -> NodeFuture<'_, Vec<TransactionId>> {
+        timed(
+            "get_transactions_of_address",
+            live::get_transactions_of_address(self, address),
+        )
+    }
+
+    fn get_transaction<'a>(&'a self, id: &'a TransactionId) -> NodeFuture<'a, Option<Transaction>> {
+        timed("get_transaction", live::get_transaction(self, id))
+    }
+
+    fn get_mempool(&self) -> NodeFuture<'_, Vec<Transaction>> {
+        timed("get_mempool", live::get_mempool(self))
+    }
+
+    fn get_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]> {
+        timed(
+            "get_transaction_difficulty",
+            live::get_transaction_difficulty(self),
+        )
+    }
+
+    fn get_live_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]> {
+        timed(
+            "get_live_transaction_difficulty",
+            live::get_live_transaction_difficulty(self),
+        )
+    }
+
+    fn submit_transaction(&self, transaction: Transaction) -> NodeFuture<'_, Result<(), Error>> {
+        timed(
+            "submit_transaction",
+            live::submit_transaction(self, transaction),
+        )
+    }
+}
+
+/// Stands in for the node while the session is suspended: there is no connection, so every
+/// call fails
+pub struct Disconnected;
+
+fn disconnected<'a, T: 'a>() -> NodeFuture<'a, T> {
+    Box::pin(async {
+        Err(ErrorCode::NodeUnreachable.error("Suspended, not connected to the node"))
+    })
+}
+
+impl NodeApi for Disconnected {
+    fn get_balance(&self, _: Public) -> NodeFuture<'_, u64> {
+        disconnected()
+    }
+
+    fn get_available_transaction_outputs(
+        &self,
+        _: Public,
+    ) -> NodeFuture<'_, Vec<(Hash, TransactionOutput, usize)>> {
+        disconnected()
+    }
+
+    fn get_transactions_of_address(&self, _: Public) -> NodeFuture<'_, Vec<TransactionId>> {
+        disconnected()
+    }
+
+    fn get_transaction<'a>(&'a self, _: &'a TransactionId) -> NodeFuture<'a, Option<Transaction>> {
+        disconnected()
+    }
+
+    fn get_mempool(&self) -> NodeFuture<'_, Vec<Transaction>> {
+        disconnected()
+    }
+
+    fn get_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]> {
+        disconnected()
+    }
+
+    fn get_live_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]> {
+        disconnected()
+    }
+
+    fn submit_transaction(&self, _: Transaction) -> NodeFuture<'_, Result<(), Error>> {
+        disconnected()
+    }
+}
+
+/// Unspent outputs of `address` in the order the node gives them, each output once. A node
+/// listing one twice would otherwise have it shown twice and spent twice in the same send
+pub async fn available_outputs(
+    client: &dyn NodeApi,
+    address: Public,
+) -> Result<Vec<(Hash, TransactionOutput, usize)>, Error> {
+    let mut outputs = client.get_available_transaction_outputs(address).await?;
+    let mut seen = HashSet::new();
+    outputs.retain(|(tx_id, _, index)| seen.insert((*tx_id, *index)));
+    Ok(outputs)
+}
+
+/// How many of `inputs` the node no longer lists among the unspent outputs of `owner`, from a
+/// listing fetched now. A transaction built earlier may have had one spent by another
+/// instance of the wallet since
+pub async fn missing_inputs(
+    client: &dyn NodeApi,
+    owner: Public,
+    inputs: &[TransactionInput],
+) -> Result<usize, Error> {
+    let available = available_outputs(client, owner).await?;
+    let listed = |input: &&TransactionInput| {
+        available
+            .iter()
+            .any(|(tx_id, _, index)| *tx_id == input.transaction_id && *index == input.output_index)
+    };
+    Ok(inputs.iter().filter(|input| !listed(input)).count())
+}
+
+/// Calls on the real client. `NodeApi` isn't in scope here, so each call resolves to the
+/// client's own method and not back to the trait
+mod live {
+    use anyhow::Error;
+    use snap_coin::{
+        api::client::Client,
+        blockchain_data_provider::BlockchainDataProvider,
+        core::transaction::{Transaction, TransactionId, TransactionOutput},
+        crypto::{Hash, keys::Public},
+    };
+
+    pub async fn get_balance(client: &Client, address: Public) -> Result<u64, Error> {
+        Ok(client.get_balance(address).await?)
+    }
+
+    pub async fn get_available_transaction_outputs(
+        client: &Client,
+        address: Public,
+    ) -> Result<Vec<(Hash, TransactionOutput, usize)>, Error> {
+        Ok(client.get_available_transaction_outputs(address).await?)
+    }
+
+    pub async fn get_transactions_of_address(
+        client: &Client,
+        address: Public,
+    ) -> Result<Vec<TransactionId>, Error> {
+        Ok(client.get_transactions_of_address(address).await?)
+    }
+
+    pub async fn get_transaction(
+        client: &Client,
+        id: &TransactionId,
+    ) -> Result<Option<Transaction>, Error> {
+        Ok(client.get_transaction(id).await?)
+    }
+
+    pub async fn get_mempool(client: &Client) -> Result<Vec<Transaction>, Error> {
+        Ok(client.get_mempool().await?)
+    }
+
+    pub async fn get_transaction_difficulty(client: &Client) -> Result<[u8; 32], Error> {
+        Ok(client.get_transaction_difficulty().await?)
+    }
+
+    pub async fn get_live_transaction_difficulty(client: &Client) -> Result<[u8; 32], Error> {
+        Ok(client.get_live_transaction_difficulty().await?)
+    }
+
+    pub async fn submit_transaction(
+        client: &Client,
+        transaction: Transaction,
+    ) -> Result<Result<(), Error>, Error> {
+        let status = client.submit_transaction(transaction).await?;
+        Ok(status.map_err(Error::from))
+    }
+}
+
+/// A node answering from memory, for tests
+#[cfg(test)]
+pub mod mock {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct MockNode {
+        /// Spendable outputs by owner
+        pub outputs: Vec<(Hash, TransactionOutput, usize)>,
+        /// Balance by owner that no spendable output backs, like an immature mining reward
+        pub locked: Vec<(Public, u64)>,
+        pub transactions: Vec<Transaction>,
+        pub mempool: Vec<Transaction>,
+        /// Whether submitted transactions are refused
+        pub refuse: bool,
+        /// Every transaction submitted, refused or not. Shared, so a test can still read it
+        /// once the node is boxed into a session
+        pub submitted: Arc<Mutex<Vec<Transaction>>>,
+        /// How many times outputs were listed, shared like `submitted`
+        pub listings: Arc<Mutex<usize>>,
+        /// Outputs, by creating transaction, that are no longer listed after the first listing,
+        /// as if another instance of the wallet spent them in between
+        pub spent_elsewhere: Vec<Hash>,
+    }
+
+    impl MockNode {
+        /// Give `owner` an unspent output of `amount`, created by a made up transaction
+        pub fn fund(&mut self, owner: Public, amount: u64) {
+            let tx_id = Hash::new(&(self.outputs.len() as u64).to_le_bytes());
+            let output = TransactionOutput {
+                amount,
+                receiver: owner,
+            };
+            self.outputs.push((tx_id, output, 0));
+        }
+
+        pub fn submitted(&self) -> Vec<Transaction> {
+            self.submitted.lock().unwrap().clone()
+        }
+    }
+
+    /// Easiest possible difficulty, any hash meets it
+    const EASY: [u8; 32] = [0xff; 32];
+
+    impl NodeApi for MockNode {
+        fn get_balance(&self, address: Public) -> NodeFuture<'_, u64> {
+            let spendable: u64 = self
+                .outputs
+                .iter()
+                .filter(|(_, output, _)| output.receiver == address)
+                .map(|(_, output, _)| output.amount)
+                .sum();
+            let locked: u64 = self
+                .locked
+                .iter()
+                .filter(|(owner, _)| *owner == address)
+                .map(|(_, amount)| amount)
+                .sum();
+            let balance = spendable + locked;
+            Box::pin(async move { Ok(balance) })
+        }
+
+        fn get_available_transaction_outputs(
+            &self,
+            address: Public,
+        ) -> NodeFuture<'_, Vec<(Hash, TransactionOutput, usize)>> {
+            let mut listings = self.listings.lock().unwrap();
+            *listings += 1;
+            let outputs = self
+                .outputs
+                .iter()
+                .filter(|(_, output, _)| output.receiver == address)
+                .filter(|(tx_id, _, _)| *listings == 1 || !self.spent_elsewhere.contains(tx_id))
+                .cloned()
+                .collect();
+            Box::pin(async move { Ok(outputs) })
+        }
+
+        fn get_transactions_of_address(
+            &self,
+            address: Public,
+        ) -> NodeFuture<'_, Vec<TransactionId>> {
+            let ids = self
+                .transactions
+                .iter()
+                .filter(|tx| {
+                    tx.outputs.iter().any(|output| output.receiver == address)
+                        || tx.inputs.iter().any(|input| input.output_owner == address)
+                })
+                .filter_map(|tx| tx.transaction_id)
+                .collect();
+            Box::pin(async move { Ok(ids) })
+        }
+
+        fn get_transaction<'a>(
+            &'a self,
+            id: &'a TransactionId,
+        ) -> NodeFuture<'a, Option<Transaction>> {
+            let found = self
+                .transactions
+                .iter()
+                .find(|tx| tx.transaction_id.as_ref() == Some(id))
+                .cloned();
+            Box::pin(async move { Ok(found) })
+        }
+
+        fn get_mempool(&self) -> NodeFuture<'_, Vec<Transaction>> {
+            let mut mempool = self.mempool.clone();
+            if !self.refuse {
+                mempool.extend(self.submitted());
+            }
+            Box::pin(async move { Ok(mempool) })
+        }
+
+        fn get_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]> {
+            Box::pin(async { Ok(EASY) })
+        }
+
+        fn get_live_transaction_difficulty(&self) -> NodeFuture<'_, [u8; 32]> {
+            Box::pin(async { Ok(EASY) })
+        }
+
+        fn submit_transaction(
+            &self,
+            transaction: Transaction,
+        ) -> NodeFuture<'_, Result<(), Error>> {
+            self.submitted.lock().unwrap().push(transaction);
+            let status = match self.refuse {
+                true => Err(Error::msg("refused")),
+                false => Ok(()),
+            };
+            Box::pin(async move { Ok(status) })
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashSet};
+
+/// A note the user attached to a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxNote {
+    pub text: String,
+    /// Unix time the note was written
+    pub created: u64,
+}
+
+/// Notes by base36 transaction id, stored encrypted next to the wallets
+pub type TxNotes = BTreeMap<String, TxNote>;
+
+/// Longest label `send --label` takes for one recipient
+pub const MAX_LABEL: usize = 100;
+
+/// Check a label typed for a recipient: control characters are dropped so the label can't
+/// rewrite the terminal when shown, and it must have something left and fit `MAX_LABEL`
+pub fn clean_label(text: &str) -> Result<String, String> {
+    let cleaned: String = text.chars().filter(|c| !c.is_control()).collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() {
+        return Err("the label is empty".to_string());
+    }
+    if cleaned.chars().count() > MAX_LABEL {
+        return Err(format!("the label is longer than {} characters", MAX_LABEL));
+    }
+    Ok(cleaned.to_string())
+}
+
+/// Take the `--label <text>` options out of the arguments of `send`. Each one labels the
+/// recipient and amount right before it. Commands are split at whitespace, so a label of
+/// several words is quoted and its words are joined again here. Gives the recipient and
+/// amount pairs and the label of each pair
+pub fn split_labels<'a>(args: &[&'a str]) -> Result<(Vec<&'a str>, Vec<Option<String>>), String> {
+    let mut pairs = Vec::new();
+    let mut labels: Vec<Option<String>> = Vec::new();
+    let mut words = args.iter();
+    while let Some(word) = words.next() {
+        if *word != "--label" {
+            pairs.push(*word);
+            if pairs.len().is_multiple_of(2) {
+                labels.push(None);
+            }
+            continue;
+        }
+        let Some(first) = words.next() else {
+            return Err("--label needs a text".to_string());
+        };
+        let mut text = first.to_string();
+        if let Some(quote) = first.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let closed = |text: &str| text.len() > 1 && text.ends_with(quote);
+            while !closed(&text) {
+                let Some(word) = words.next() else {
+                    return Err(format!("the label {} has no closing quote", first));
+                };
+                text.push(' ');
+                text.push_str(word);
+            }
+            text = text[1..text.len() - 1].to_string();
+        }
+        let label = match labels.last_mut() {
+            Some(label) if pairs.len().is_multiple_of(2) && label.is_none() => label,
+            Some(_) if pairs.len().is_multiple_of(2) => {
+                return Err("a recipient can have only one --label".to_string());
+            }
+            _ => return Err("--label goes after a recipient and its amount".to_string()),
+        };
+        *label = Some(clean_label(&text)?);
+    }
+    Ok((pairs, labels))
+}
+
+/// The note a send is stored with: the label alone for a single recipient, each label after
+/// its recipient otherwise. `None` when no recipient is labelled
+pub fn send_note(recipients: &[&str], labels: &[Option<String>]) -> Option<String> {
+    if recipients.len() == 1 {
+        return labels.first().cloned().flatten();
+    }
+    let labelled: Vec<String> = recipients
+        .iter()
+        .zip(labels)
+        .filter_map(|(recipient, label)| Some(format!("{}: {}", recipient, label.as_ref()?)))
+        .collect();
+    (!labelled.is_empty()).then(|| labelled.join("; "))
+}
+
+/// Why `prune` would remove a note
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PruneReason {
+    /// The transaction is in neither the history of any wallet nor the mempool
+    NotInHistory,
+    /// The note was written longer ago than the maximum age
+    TooOld,
+}
+
+impl PruneReason {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            PruneReason::NotInHistory => "transaction no longer in history",
+            PruneReason::TooOld => "older than the maximum age",
+        }
+    }
+}
+
+/// Notes that `prune` would remove, in transaction id order. `known` holds the transaction
+/// ids the node still knows about, `max_age` is in seconds
+pub fn prune_candidates(
+    notes: &TxNotes,
+    known: &HashSet<String>,
+    now: u64,
+    max_age: Option<u64>,
+) -> Vec<(String, PruneReason)> {
+    notes
+        .iter()
+        .filter_map(|(tx_id, note)| {
+            if !known.contains(tx_id) {
+                Some((tx_id.clone(), PruneReason::NotInHistory))
+            } else if max_age.is_some_and(|max_age| now.saturating_sub(note.created) > max_age) {
+                Some((tx_id.clone(), PruneReason::TooOld))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(created: u64) -> TxNote {
+        TxNote {
+            text: "rent".to_string(),
+            created,
+        }
+    }
+
+    #[test]
+    fn test_split_labels() {
+        let args = ["alice", "10", "--label", "rent", "bob", "5"];
+        let (pairs, labels) = split_labels(&args).unwrap();
+        assert_eq!(pairs, ["alice", "10", "bob", "5"]);
+        assert_eq!(labels, [Some("rent".to_string()), None]);
+
+        let args = [
+            "alice", "10", "--label", "\"june", "rent\"", "bob", "5", "--label", "'x'",
+        ];
+        let (_, labels) = split_labels(&args).unwrap();
+        assert_eq!(
+            labels,
+            [Some("june rent".to_string()), Some("x".to_string())]
+        );
+        let (_, labels) = split_labels(&["alice", "10", "--label", "a\u{1b}[2Jb"]).unwrap();
+        assert_eq!(labels, [Some("a[2Jb".to_string())]);
+
+        for invalid in [
+            &["--label", "rent", "alice", "10"][..],
+            &["alice", "--label", "rent", "10"],
+            &["alice", "10", "--label"],
+            &["alice", "10", "--label", "a", "--label", "b"],
+            &["alice", "10", "--label", "\"rent"],
+            &["alice", "10", "--label", "\""],
+            &["alice", "10", "--label", "\"\""],
+        ] {
+            assert!(split_labels(invalid).is_err(), "{:?}", invalid);
+        }
+        let long = "a".repeat(MAX_LABEL + 1);
+        assert!(split_labels(&["alice", "10", "--label", &long]).is_err());
+    }
+
+    #[test]
+    fn test_send_note() {
+        let rent = Some("rent".to_string());
+        assert_eq!(send_note(&["alice"], &[rent.clone()]), rent);
+        assert_eq!(send_note(&["alice"], &[None]), None);
+        assert_eq!(
+            send_note(&["alice", "bob"], &[rent, None]),
+            Some("alice: rent".to_string())
+        );
+        assert_eq!(send_note(&["alice", "bob"], &[None, None]), None);
+    }
+
+    #[test]
+    fn test_prune_candidates() {
+        let mut notes = TxNotes::new();
+        notes.insert("dropped".to_string(), note(900));
+        notes.insert("old".to_string(), note(100));
+        notes.insert("recent".to_string(), note(900));
+        let known: HashSet<String> = ["old", "recent"].iter().map(|id| id.to_string()).collect();
+
+        assert_eq!(
+            prune_candidates(&notes, &known, 1000, None),
+            vec![("dropped".to_string(), PruneReason::NotInHistory)]
+        );
+        assert_eq!(
+            prune_candidates(&notes, &known, 1000, Some(500)),
+            vec![
+                ("dropped".to_string(), PruneReason::NotInHistory),
+                ("old".to_string(), PruneReason::TooOld),
+            ]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::atomic::{AtomicU8, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use notify_rust::Notification;
+
+use snap_coin::{
+    api::client::Client, blockchain_data_provider::BlockchainDataProvider, crypto::keys::Public,
+};
+use tokio::sync::watch;
+
+use crate::{amount, history::TxEffect, oplog, style};
+
+/// How often the current wallet is checked for new payments and a low balance
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const TITLE: &str = "Snap Coin Wallet";
+
+/// How incoming payments are announced
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotifyMode {
+    Off,
+    Bell,
+    Desktop,
+    Both,
+}
+
+impl NotifyMode {
+    pub const NAMES: &[&str] = &["off", "bell", "desktop", "both"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(NotifyMode::Off),
+            "bell" => Some(NotifyMode::Bell),
+            "desktop" => Some(NotifyMode::Desktop),
+            "both" => Some(NotifyMode::Both),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(NotifyMode::Off as u8);
+/// Balance in nano below which to warn, 0 for never
+static LOW_BALANCE: AtomicU64 = AtomicU64::new(0);
+/// `quiet` silences the bell of notifications too
+static QUIET: AtomicU8 = AtomicU8::new(0);
+
+/// Set how payments are announced, from the `notify` and `quiet` settings
+pub fn set_mode(mode: NotifyMode, quiet: bool) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+    QUIET.store(quiet as u8, Ordering::Relaxed);
+}
+
+/// Warn when the balance drops below `threshold`, from the `low_balance` setting
+pub fn set_low_balance(threshold: Option<u64>) {
+    LOW_BALANCE.store(threshold.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn low_balance() -> Option<u64> {
+    Some(LOW_BALANCE.load(Ordering::Relaxed)).filter(|threshold| *threshold > 0)
+}
+
+fn mode() -> NotifyMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => NotifyMode::Bell,
+        2 => NotifyMode::Desktop,
+        3 => NotifyMode::Both,
+        _ => NotifyMode::Off,
+    }
+}
+
+/// Notification text. Names the wallet but never shows its address, which could be read
+/// over the user's shoulder
+pub fn message(wallet: &str, amount: &str) -> String {
+    format!("Received {} in wallet '{}'", amount, wallet)
+}
+
+/// Low balance notification text, like `message` without the address
+pub fn low_balance_message(wallet: &str, balance: &str, threshold: &str) -> String {
+    format!("Balance of wallet '{}' is down to {}, below {}", wallet, balance, threshold)
+}
+
+/// Whether the balance just went from at least `threshold` to below it. Staying low
+/// warns only once, and so does the first balance seen
+pub fn fell_below(previous: Option<u64>, balance: u64, threshold: u64) -> bool {
+    previous.is_some_and(|previous| previous >= threshold) && balance < threshold
+}
+
+/// Show a desktop notification on Linux, macOS or Windows. Nothing happens when it is
+/// clicked or dismissed. Fails when the notification service is missing, e.g. on a headless
+/// system
+fn desktop_notification(body: &str) -> Result<(), ()> {
+    Notification::new()
+        .summary(TITLE)
+        .body(body)
+        .show()
+        .map(|_| ())
+        .map_err(|_| ())
+}
+
+/// Announce `body` the way the user asked for. A desktop notification that can't be shown
+/// falls back to the bell without complaint
+fn announce(body: &str) {
+    let mode = mode();
+    let mut bell = mode == NotifyMode::Bell || mode == NotifyMode::Both;
+    if mode == NotifyMode::Desktop || mode == NotifyMode::Both {
+        bell |= desktop_notification(body).is_err();
+    }
+    if bell && QUIET.load(Ordering::Relaxed) == 0 {
+        style::bell();
+    }
+}
+
+/// What is known about the watched wallet from earlier polls
+#[derive(Default)]
+struct Known {
+    /// Transactions already seen, `None` until the first poll so existing history isn't
+    /// announced
+    transactions: Option<HashSet<String>>,
+    balance: Option<u64>,
+}
+
+impl Known {
+    /// Check for new payments and a balance that fell below the threshold, and announce them
+    async fn poll(&mut self, client: &Client, public: Public, name: &str) {
+        if let Ok(history) = client.get_transactions_of_address(public).await {
+            let first_poll = self.transactions.is_none();
+            let seen = self.transactions.get_or_insert_with(HashSet::new);
+            let mut received = 0;
+            for tx_id in history {
+                if !seen.insert(tx_id.dump_base36()) || first_poll {
+                    continue;
+                }
+                // Change and merges come back from the wallet's own inputs and aren't payments
+                if let Ok(Some(tx)) = client.get_transaction(&tx_id).await
+                    && tx.inputs.iter().all(|input| input.output_owner != public)
+                {
+                    received += TxEffect::of(&tx, public).received;
+                }
+            }
+            if received > 0 {
+                let body = message(name, &amount::display(received));
+                tokio::task::spawn_blocking(move || announce(&body));
+            }
+        }
+
+        if let Ok(balance) = client.get_balance(public).await {
+            if let Some(threshold) = low_balance()
+                && fell_below(self.balance, balance, threshold)
+            {
+                let body = low_balance_message(
+                    name,
+                    &amount::display(balance),
+                    &amount::display(threshold),
+                );
+                tokio::task::spawn_blocking(move || announce(&body));
+            }
+            self.balance = Some(balance);
+        }
+    }
+}
+
+/// Background poller for payments to the current wallet and for its balance running low,
+/// on its own node connection
+pub struct WalletWatcher {
+    wallet: watch::Sender<Option<(Public, String)>>,
+}
+
+impl WalletWatcher {
+    pub fn spawn(node: SocketAddr) -> Self {
+        let (wallet, mut watched) = watch::channel(None::<(Public, String)>);
+        tokio::spawn(async move {
+            let connected = Client::connect(node).await;
+            oplog::connect(node, "notifications", connected.as_ref().err());
+            let Ok(client) = connected else {
+                return;
+            };
+            let mut known = Known::default();
+            loop {
+                let current = watched.borrow_and_update().clone();
+                match current.filter(|_| mode() != NotifyMode::Off) {
+                    Some((public, name)) => known.poll(&client, public, &name).await,
+                    // What happens while notifications are off isn't announced later
+                    None => known = Known::default(),
+                }
+                tokio::select! {
+                    changed = watched.changed() => match changed {
+                        // A different wallet starts from scratch
+                        Ok(()) => known = Known::default(),
+                        Err(_) => break,
+                    },
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+        });
+        WalletWatcher { wallet }
+    }
+
+    /// Watch `public`, named `name`, for payments. Watching the same wallet again changes nothing
+    pub fn watch(&self, public: Option<Public>, name: &str) {
+        self.wallet.send_if_modified(|current| {
+            let wanted = public.map(|public| (public, name.to_string()));
+            if *current == wanted {
+                return false;
+            }
+            *current = wanted;
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_mode_names() {
+        for name in NotifyMode::NAMES {
+            assert_eq!(NotifyMode::parse(name).map(|mode| mode.name()), Some(*name));
+        }
+        assert_eq!(NotifyMode::parse("BOTH"), Some(NotifyMode::Both));
+        assert_eq!(NotifyMode::parse("loud"), None);
+    }
+
+    #[test]
+    fn test_message_hides_address() {
+        let message = message("savings", "1.5 SNAP");
+        assert_eq!(message, "Received 1.5 SNAP in wallet 'savings'");
+    }
+
+    #[test]
+    fn test_fell_below() {
+        assert!(fell_below(Some(100), 99, 100));
+        assert!(!fell_below(Some(100), 100, 100));
+        // Already low, or nothing to compare with yet
+        assert!(!fell_below(Some(50), 40, 100));
+        assert!(!fell_below(None, 40, 100));
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    env,
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{clock, report::Date};
+
+/// Size a log file grows to before it is rotated
+const MAX_SIZE: u64 = 1024 * 1024;
+/// Rotated files kept, `wallet.log.1` being the most recent
+const ROTATED: usize = 3;
+/// Letters and digits in a row from which a word is taken for an address, key or id
+const LONG_WORD: usize = 20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Held while writing, so lines of concurrent tasks don't interleave
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// Where the log lives: `$XDG_STATE_HOME/snap-coin-wallet/wallet.log`, by default under
+/// `~/.local/state`
+pub fn path() -> Option<PathBuf> {
+    let state = match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir()?.join(".local/state"),
+    };
+    Some(state.join("snap-coin-wallet").join("wallet.log"))
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Make free text, such as an error message, safe to log: every long word is replaced by
+/// `<redacted>`, as addresses, keys and transaction ids are, and every other word with a digit
+/// in it by `#`, taking amounts, PINs and timestamps along. Line breaks become ` | `
+pub fn sanitize(text: &str) -> String {
+    let mut clean = String::with_capacity(text.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, clean: &mut String| {
+        if word.chars().count() >= LONG_WORD {
+            clean.push_str("<redacted>");
+        } else if word.chars().any(|c| c.is_numeric()) {
+            clean.push('#');
+        } else {
+            clean.push_str(word);
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        flush(&mut word, &mut clean);
+        match c {
+            '\n' => clean.push_str(" | "),
+            c if c.is_control() => clean.push(' '),
+            c => clean.push(c),
+        }
+    }
+    flush(&mut word, &mut clean);
+    clean
+}
+
+/// UTC time to the second, `2025-03-01T14:05:09Z`
+fn format_time(timestamp: u64) -> String {
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        Date::of(timestamp),
+        timestamp % 86_400 / 3600,
+        timestamp % 3600 / 60,
+        timestamp % 60
+    )
+}
+
+/// Outcome of an operation: `ok`, or its sanitized error
+fn outcome(error: Option<impl Display>) -> String {
+    match error {
+        Some(e) => format!("error: {}", sanitize(&e.to_string())),
+        None => "ok".to_string(),
+    }
+}
+
+/// Move `wallet.log` to `wallet.log.1`, and so on, dropping the oldest
+fn rotate(path: &Path) {
+    let rotated = |n: usize| {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    for n in (1..ROTATED).rev() {
+        fs::rename(rotated(n), rotated(n + 1)).ok();
+    }
+    fs::rename(path, rotated(1)).ok();
+}
+
+/// The only way into the log. `line` must be built from fixed text, numbers the wallet
+/// measured and `sanitize`d text. A log that can't be written is not worth failing for
+fn write(line: &str) {
+    if !enabled() {
+        return;
+    }
+    let Some(path) = path() else {
+        return;
+    };
+    let _writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+    let time = clock::local_now().map(format_time).unwrap_or_default();
+    let line = format!("{} {}\n", time, line);
+    let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > MAX_SIZE {
+        rotate(&path);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).ok();
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        file.write_all(line.as_bytes()).ok();
+    }
+}
+
+pub fn started() {
+    write(&format!("start version {}", env!("CARGO_PKG_VERSION")));
+}
+
+pub fn exited(code: i32) {
+    write(&format!("exit code {}", code));
+}
+
+/// A command that ran, by name only: its arguments never reach the log. `name` must come
+/// from the command list, not from what was typed
+pub fn command(name: &'static str, elapsed: Duration, error: Option<impl Display>) {
+    write(&format!(
+        "command {} {}ms {}",
+        name,
+        elapsed.as_millis(),
+        outcome(error)
+    ));
+}
+
+/// A call to the node
+pub fn rpc(method: &'static str, elapsed: Duration, error: Option<impl Display>) {
+    write(&format!(
+        "rpc {} {}ms {}",
+        method,
+        elapsed.as_millis(),
+        outcome(error)
+    ));
+}
+
+/// A connection to the node being opened, for the session or a background task
+pub fn connect(node: SocketAddr, purpose: &'static str, error: Option<impl Display>) {
+    write(&format!("connect {} {} {}", node, purpose, outcome(error)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snap_coin::crypto::keys::Private;
+
+    #[test]
+    fn test_sanitize() {
+        let address = Private::new_random().to_public().dump_base36();
+        let message = format!(
+            "Insufficient funds: sending 1,500.25 SNAP to {} needs 3 more\nPIN 123456 rejected",
+            address
+        );
+        assert_eq!(
+            sanitize(&message),
+            "Insufficient funds: sending #,#.# SNAP to <redacted> needs # more | PIN # rejected"
+        );
+        // Long words go even without a digit
+        let letters = "abcdefghijklmnopqrstuvwxyz";
+        assert_eq!(sanitize(&format!("key {}!", letters)), "key <redacted>!");
+        assert_eq!(sanitize("refused\tby\x1bnode"), "refused by node");
+        assert_eq!(format_time(86_400 + 3723), "1970-01-02T01:02:03Z");
+    }
+}
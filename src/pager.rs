@@ -0,0 +1,80 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Used when `$PAGER` isn't set. `-R` keeps colors
+const DEFAULT_PAGER: &str = "less -R";
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn paging on or off, from the `pager` setting
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `lines` lines of output should go through the pager: paging is on, stdout is a
+/// terminal and the output wouldn't fit on it. Piped output is never paged
+pub fn would_page(lines: usize) -> bool {
+    if !ENABLED.load(Ordering::Relaxed) || !io::stdout().is_terminal() {
+        return false;
+    }
+    match crossterm::terminal::size() {
+        // Keep a line for the prompt
+        Ok((_, rows)) => lines >= rows as usize,
+        Err(_) => false,
+    }
+}
+
+/// Output of one command, collected so it can be paged once its length is known
+#[derive(Default)]
+pub struct Output {
+    lines: Vec<String>,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&mut self, line: impl Display) {
+        self.lines.push(line.to_string());
+    }
+
+    /// Show the output, through the pager if it is too long for the terminal
+    pub fn finish(self) {
+        if would_page(self.lines.len()) && page(&self.lines).is_ok() {
+            return;
+        }
+        for line in &self.lines {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Run `$PAGER` (or `less -R`) with `lines` on its standard input, and wait for it to quit
+fn page(lines: &[String]) -> io::Result<()> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        for line in lines {
+            // The user quitting the pager early closes the pipe, which is fine
+            if writeln!(stdin, "{}", line).is_err() {
+                break;
+            }
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
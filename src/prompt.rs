@@ -0,0 +1,75 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use snap_coin::{
+    api::client::Client, blockchain_data_provider::BlockchainDataProvider, crypto::keys::Public,
+};
+use tokio::sync::watch;
+
+use crate::{
+    amount::{self, format_amount_short},
+    oplog,
+};
+
+/// How often the prompt balance is refreshed when nothing triggers it sooner
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Balance shown in the prompt, fetched by a background task on its own node connection
+/// so drawing the prompt never waits on the network
+pub struct PromptBalance {
+    wallet: watch::Sender<Option<Public>>,
+    last: Arc<Mutex<Option<(Public, Option<u64>)>>>,
+}
+
+impl PromptBalance {
+    pub fn spawn(node: SocketAddr) -> Self {
+        let (wallet, mut watched) = watch::channel(None::<Public>);
+        let last = Arc::new(Mutex::new(None));
+        let shared = last.clone();
+        tokio::spawn(async move {
+            let connected = Client::connect(node).await;
+            oplog::connect(node, "prompt balance", connected.as_ref().err());
+            let client = connected.ok();
+            loop {
+                let current = *watched.borrow_and_update();
+                if let Some(public) = current {
+                    let balance = match &client {
+                        Some(client) => client.get_balance(public).await.ok(),
+                        None => None,
+                    };
+                    *shared.lock().unwrap() = Some((public, balance));
+                }
+                tokio::select! {
+                    changed = watched.changed() => if changed.is_err() {
+                        break;
+                    },
+                    _ = tokio::time::sleep(REFRESH_INTERVAL) => {}
+                }
+            }
+        });
+        PromptBalance { wallet, last }
+    }
+
+    /// Fetch the balance of `public` in the background, without waiting for the next interval
+    pub fn refresh(&self, public: Public) {
+        self.wallet.send_replace(Some(public));
+    }
+
+    /// Stop fetching balances
+    pub fn disable(&self) {
+        self.wallet.send_replace(None);
+    }
+
+    /// Last known balance of `public`, or `?` if it isn't known (yet)
+    pub fn display(&self, public: Public) -> String {
+        match *self.last.lock().unwrap() {
+            Some((known, Some(balance))) if known == public => {
+                format!("{} {}", format_amount_short(balance), amount::unit().symbol())
+            }
+            _ => "?".to_string(),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use anyhow::Error;
+use qrcode::{QrCode, render::unicode::Dense1x2};
+use snap_coin::crypto::keys::Private;
+use zeroize::Zeroizing;
+
+use crate::sanitize;
+
+/// Word counts of BIP39 seed phrases
+const MNEMONIC_LENGTHS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// Read the text of the one QR code in an image file
+pub fn decode_file(path: &Path) -> Result<Zeroizing<String>, Error> {
+    let image = image::open(path)
+        .map_err(|e| Error::msg(format!("Can't read image {}: {}", path.display(), e)))?
+        .to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32).0[0],
+    );
+    let grids = prepared.detect_grids();
+    match grids.as_slice() {
+        [] => Err(Error::msg("No QR code found in the image.")),
+        [grid] => {
+            let (_, content) = grid
+                .decode()
+                .map_err(|e| Error::msg(format!("The QR code can't be read: {}", e)))?;
+            Ok(Zeroizing::new(content))
+        }
+        _ => Err(Error::msg(format!(
+            "The image holds {} QR codes, crop it to the one with the key.",
+            grids.len()
+        ))),
+    }
+}
+
+/// Draw text as a QR code in half-block characters, two modules per character cell. The
+/// colors are swapped so the code reads light on dark, the usual terminal theme, and the
+/// quiet zone stays in so scanners find its edges
+pub fn render(text: &str) -> Result<Zeroizing<String>, Error> {
+    let code = QrCode::new(text.as_bytes())
+        .map_err(|e| Error::msg(format!("Can't make a QR code: {}", e)))?;
+    Ok(Zeroizing::new(
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build(),
+    ))
+}
+
+/// The private key held by a decoded QR code. Seed phrases are recognized only to say why
+/// they can't be imported: wallets store plain keys, and there is no derivation from a phrase
+pub fn parse_payload(text: &str) -> Result<Private, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let is_phrase = MNEMONIC_LENGTHS.contains(&words.len())
+        && words
+            .iter()
+            .all(|word| word.chars().all(|c| c.is_ascii_lowercase()));
+    if is_phrase {
+        return Err(format!(
+            "the code holds a {} word seed phrase, only base36 private keys can be imported",
+            words.len()
+        ));
+    }
+    sanitize::parse_private(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_payload() {
+        let private = Private::new_random();
+        let parsed = parse_payload(&format!(" {}\n", private.dump_base36())).unwrap();
+        assert_eq!(parsed.dump_base36(), private.dump_base36());
+
+        let phrase = ["abandon"; 11].join(" ") + " about";
+        assert!(parse_payload(&phrase).unwrap_err().contains("12 word seed phrase"));
+        assert!(parse_payload("hello").is_err());
+        assert!(parse_payload("").is_err());
+    }
+}
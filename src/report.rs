@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, HashMap};
+
+use snap_coin::{core::transaction::Transaction, crypto::keys::Public};
+
+use crate::{
+    address_book::AddressBook, amount, export::csv_field, history::TxEffect, notes::TxNotes,
+};
+
+/// Header of the transaction rows of `report`
+const ROWS_HEADER: &str = "date,tx_id,direction,amount,fee,counterparty,balance,label";
+/// Header of the monthly totals after the rows
+const SUMMARY_HEADER: &str = "month,received,sent,fees,net";
+
+/// A UTC calendar date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// The UTC date of a Unix timestamp in seconds
+    pub fn of(timestamp: u64) -> Self {
+        // Days to civil date, after Howard Hinnant's `civil_from_days`
+        let days = (timestamp / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = match shifted_month {
+            0..10 => shifted_month + 3,
+            _ => shifted_month - 9,
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Date { year, month, day }
+    }
+
+    pub fn month_name(&self) -> String {
+        format!("{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Which way value moved for the wallet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    In,
+    Out,
+    /// Sent by the wallet to itself only, e.g. merging outputs
+    Internal,
+}
+
+impl Direction {
+    fn name(&self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+            Direction::Internal => "internal",
+        }
+    }
+}
+
+/// One transaction of the report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub date: Date,
+    pub tx_id: String,
+    pub direction: Direction,
+    /// Received for `In`, paid to others for `Out`
+    pub amount: u64,
+    /// Paid by the wallet, so only ever on its own sends. `None` when a spent output isn't in
+    /// the history
+    pub fee: Option<u64>,
+    pub counterparty: String,
+    /// Balance after the transaction, replayed from the history alone
+    pub balance: i128,
+    pub label: Option<String>,
+}
+
+/// The report of one year, with what kept it from being complete
+#[derive(Debug, Default)]
+pub struct Report {
+    pub rows: Vec<Row>,
+    /// Transactions of any year the node had no timestamp for, left out of the balance
+    pub missing_timestamps: usize,
+    /// Sends of any year with an input created outside the history, whose fee and balance
+    /// change are unknown
+    pub unknown_inputs: usize,
+}
+
+/// Addresses as contacts when the address book has them
+fn counterparties(addresses: &[Public], book: &AddressBook) -> String {
+    let mut names: Vec<String> = addresses
+        .iter()
+        .map(|address| {
+            book.iter()
+                .find(|(_, contact)| contact.public == *address)
+                .map(|(alias, _)| alias.clone())
+                .unwrap_or_else(|| address.dump_base36())
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names.join(" ")
+}
+
+/// Walk the whole history of `address`, oldest first, and keep the rows of `year`. `history`
+/// holds each transaction with its base36 id, `None` when the node no longer has it. The
+/// balance starts at zero before the oldest transaction, so it never depends on the balance
+/// the node reports
+pub fn build(
+    history: &[(String, Option<Transaction>)],
+    address: Public,
+    year: i64,
+    book: &AddressBook,
+    notes: &TxNotes,
+) -> Report {
+    let mut report = Report::default();
+    let by_id: HashMap<_, _> = history
+        .iter()
+        .filter_map(|(_, tx)| tx.as_ref())
+        .filter_map(|tx| tx.transaction_id.map(|id| (id, tx)))
+        .collect();
+
+    let mut dated: Vec<(&String, &Transaction)> = vec![];
+    for (tx_id, tx) in history {
+        match tx {
+            Some(tx) if tx.timestamp > 0 => dated.push((tx_id, tx)),
+            _ => report.missing_timestamps += 1,
+        }
+    }
+    dated.sort_by_key(|(_, tx)| tx.timestamp);
+
+    let mut balance: i128 = 0;
+    for (tx_id, tx) in dated {
+        let effect = TxEffect::of(tx, address);
+        let own_inputs: Vec<_> = tx
+            .inputs
+            .iter()
+            .filter(|input| input.output_owner == address)
+            .collect();
+        // Inputs only reference outputs, which the wallet received earlier in its history
+        let spent: Option<u64> = own_inputs
+            .iter()
+            .map(|input| {
+                let source = by_id.get(&input.transaction_id)?;
+                Some(source.outputs.get(input.output_index)?.amount)
+            })
+            .sum();
+        let (direction, amount, fee, others) = if own_inputs.is_empty() {
+            let mut senders: Vec<Public> = tx.inputs.iter().map(|i| i.output_owner).collect();
+            senders.dedup();
+            (Direction::In, effect.received, None, senders)
+        } else {
+            let paid: u64 = tx.outputs.iter().map(|output| output.amount).sum();
+            let fee = spent.map(|spent| spent.saturating_sub(paid));
+            let receivers: Vec<Public> = tx
+                .outputs
+                .iter()
+                .map(|output| output.receiver)
+                .filter(|receiver| *receiver != address)
+                .collect();
+            let direction = match effect.is_outgoing() {
+                true => Direction::Out,
+                false => Direction::Internal,
+            };
+            (direction, effect.sent, fee, receivers)
+        };
+        balance += i128::from(effect.received);
+        match spent {
+            Some(spent) => balance -= i128::from(spent),
+            None => report.unknown_inputs += 1,
+        }
+
+        let date = Date::of(tx.timestamp);
+        if date.year != year {
+            continue;
+        }
+        let counterparty = match (direction, tx.inputs.is_empty()) {
+            (Direction::In, true) => "block reward".to_string(),
+            _ => counterparties(&others, book),
+        };
+        report.rows.push(Row {
+            date,
+            tx_id: tx_id.clone(),
+            direction,
+            amount,
+            fee,
+            counterparty,
+            balance,
+            label: notes.get(tx_id).map(|note| note.text.clone()),
+        });
+    }
+    report
+}
+
+/// An amount in SNAP that may be negative
+fn signed_snap(value: i128) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let magnitude = u64::try_from(value.unsigned_abs()).unwrap_or(u64::MAX);
+    format!("{}{}", sign, amount::format_snap_plain(magnitude))
+}
+
+/// The rows as CSV in SNAP, then a blank line and the totals of each month that has rows
+pub fn csv(report: &Report) -> String {
+    let mut out = format!("{}\n", ROWS_HEADER);
+    // received, sent, fees
+    let mut months: BTreeMap<String, (u64, u64, u64)> = BTreeMap::new();
+    for row in &report.rows {
+        let fields = [
+            row.date.to_string(),
+            row.tx_id.clone(),
+            row.direction.name().to_string(),
+            amount::format_snap_plain(row.amount),
+            row.fee.map(amount::format_snap_plain).unwrap_or_default(),
+            csv_field(&row.counterparty),
+            signed_snap(row.balance),
+            csv_field(row.label.as_deref().unwrap_or_default()),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+
+        let totals = months.entry(row.date.month_name()).or_default();
+        match row.direction {
+            Direction::In => totals.0 += row.amount,
+            Direction::Out => totals.1 += row.amount,
+            Direction::Internal => {}
+        }
+        totals.2 += row.fee.unwrap_or(0);
+    }
+
+    out.push('\n');
+    out.push_str(SUMMARY_HEADER);
+    out.push('\n');
+    for (month, (received, sent, fees)) in months {
+        let net = i128::from(received) - i128::from(sent) - i128::from(fees);
+        let fields = [
+            month,
+            amount::format_snap_plain(received),
+            amount::format_snap_plain(sent),
+            amount::format_snap_plain(fees),
+            signed_snap(net),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{address_book::Contact, notes::TxNote};
+    use snap_coin::{
+        core::transaction::{TransactionInput, TransactionOutput},
+        crypto::{Hash, keys::Private},
+    };
+
+    /// 2024-01-15 00:00:00 UTC
+    const JAN_15: u64 = 1_705_276_800;
+    const DAY: u64 = 86_400;
+
+    fn transaction(
+        id: u8,
+        timestamp: u64,
+        inputs: &[(u8, Public)],
+        outputs: &[(Public, u64)],
+    ) -> (String, Option<Transaction>) {
+        let tx_id = Hash::new_from_buf([id; 32]);
+        let tx = Transaction {
+            inputs: inputs
+                .iter()
+                .map(|(source, owner)| TransactionInput {
+                    transaction_id: Hash::new_from_buf([*source; 32]),
+                    output_index: 0,
+                    signature: None,
+                    output_owner: *owner,
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|(receiver, amount)| TransactionOutput {
+                    amount: *amount,
+                    receiver: *receiver,
+                })
+                .collect(),
+            transaction_id: Some(tx_id),
+            nonce: 0,
+            timestamp,
+        };
+        (tx_id.dump_base36(), Some(tx))
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(Date::of(0).to_string(), "1970-01-01");
+        assert_eq!(Date::of(JAN_15).to_string(), "2024-01-15");
+        // Leap day, and the last second of a year
+        assert_eq!(Date::of(1_709_164_800).to_string(), "2024-02-29");
+        assert_eq!(Date::of(1_735_689_599).to_string(), "2024-12-31");
+        assert_eq!(Date::of(1_735_689_600).month_name(), "2025-01");
+    }
+
+    #[test]
+    fn test_report() {
+        let me = Private::new_random().to_public();
+        let alice = Private::new_random().to_public();
+        let bob = Private::new_random().to_public();
+        let mut book = AddressBook::new();
+        let contact = Contact {
+            public: bob,
+            note: String::new(),
+        };
+        book.insert("bob".to_string(), contact);
+
+        let history = vec![
+            // Last year, only in the balance
+            transaction(1, JAN_15 - 365 * DAY, &[(9, alice)], &[(me, 50)]),
+            transaction(2, JAN_15, &[(8, alice)], &[(me, 100), (alice, 5)]),
+            // 60 to bob from the 100, 38 back as change, 2 of fee
+            transaction(3, JAN_15 + 40 * DAY, &[(2, me)], &[(bob, 60), (me, 38)]),
+            (Hash::new_from_buf([4; 32]).dump_base36(), None),
+        ];
+        let mut notes = TxNotes::new();
+        let note = TxNote {
+            text: "rent, March".to_string(),
+            created: 0,
+        };
+        notes.insert(history[2].0.clone(), note);
+        let report = build(&history, me, 2024, &book, &notes);
+        assert_eq!(report.missing_timestamps, 1);
+        assert_eq!(report.unknown_inputs, 0);
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.rows[0].balance, 150);
+        let send = &report.rows[1];
+        assert_eq!(
+            (send.direction, send.amount, send.fee, send.balance),
+            (Direction::Out, 60, Some(2), 88)
+        );
+        assert_eq!(send.counterparty, "bob");
+
+        let csv = csv(&report);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], ROWS_HEADER);
+        assert!(lines[1].starts_with("2024-01-15,"));
+        assert!(lines[1].contains(",in,0.00000100,,"));
+        assert!(lines[2].ends_with(",out,0.00000060,0.00000002,bob,0.00000088,\"rent, March\""));
+        assert_eq!(
+            &lines[3..],
+            &[
+                "",
+                SUMMARY_HEADER,
+                "2024-01,0.00000100,0.00000000,0.00000000,0.00000100",
+                "2024-02,0.00000000,0.00000060,0.00000002,-0.00000062",
+            ]
+        );
+    }
+}
@@ -0,0 +1,834 @@
+use std::{env, fs, net::SocketAddr, path::Path, time::Duration};
+
+use aes_gcm::aead::{OsRng, rand_core::RngCore};
+use anyhow::Error;
+use serde_json::{Value, json};
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use zeroize::Zeroizing;
+
+use crate::{
+    amount,
+    console::say,
+    exit_code::{self, ErrorCode},
+    handle_command::{check_payments, finish_pow, handle_command},
+    history::{TxEffect, fee_rate},
+    i18n::{tr, trf},
+    input, json,
+    session::Session,
+    signing::{WalletKey, build_transaction},
+    spendable, style,
+};
+
+/// Where `serve` listens when `--listen` isn't given
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8832";
+/// Environment variable holding a fixed token. The config file never holds it, only the path
+/// of a file that does, `rpc_token_file`
+pub const TOKEN_VAR: &str = "SNAP_RPC_TOKEN";
+/// Longest request head, up to the blank line
+const MAX_HEAD: usize = 16 * 1024;
+/// Longest request body
+const MAX_BODY: usize = 64 * 1024;
+/// Time a client gets to send its whole request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Sends proposed and not confirmed yet that are kept, the oldest go first
+const MAX_PROPOSALS: usize = 16;
+/// Wrong PINs in a row after which `confirm_send` is refused until `serve` is restarted
+const MAX_PIN_ATTEMPTS: u32 = 3;
+
+// JSON-RPC 2.0 error codes, and this server's own in the range the spec leaves to servers
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The command behind the method failed
+const COMMAND_FAILED: i64 = -32000;
+const WRONG_PIN: i64 = -32001;
+const UNKNOWN_PROPOSAL: i64 = -32002;
+/// Too many wrong PINs, `confirm_send` takes no more
+const PIN_LOCKED: i64 = -32003;
+
+#[derive(Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+    /// What the command printed before it failed, such as a refused send's result
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+/// The head of an HTTP request, as far as the server needs it
+#[derive(Debug, PartialEq)]
+struct Head {
+    method: String,
+    authorization: Option<String>,
+    content_length: usize,
+}
+
+/// Parse the request line and headers. Gives the status to answer with when they don't parse
+fn parse_head(head: &str) -> Result<Head, u16> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(_), Some(version)) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        return Err(400);
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(400);
+    }
+    let mut parsed = Head {
+        method: method.to_string(),
+        authorization: None,
+        content_length: 0,
+    };
+    for line in lines.filter(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':').ok_or(400u16)?;
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => parsed.content_length = value.parse().map_err(|_| 400u16)?,
+            "authorization" => parsed.authorization = Some(value.to_string()),
+            // Chunked bodies aren't read, better refused than misread
+            "transfer-encoding" => return Err(400),
+            _ => {}
+        }
+    }
+    Ok(parsed)
+}
+
+/// Whether an `Authorization` header carries the token, compared in constant time
+fn is_authorized(authorization: Option<&str>, token: &str) -> bool {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| given.trim().as_bytes().ct_eq(token.as_bytes()).into())
+}
+
+/// Read one request: its head, then its body. Gives the status to answer with on failure
+async fn read_request(stream: &mut TcpStream) -> Result<(Head, Vec<u8>), u16> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD {
+            return Err(431);
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Err(400),
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    };
+    let head = std::str::from_utf8(&buffer[..head_end]).map_err(|_| 400u16)?;
+    let head = parse_head(head)?;
+    if head.content_length > MAX_BODY {
+        return Err(413);
+    }
+    let mut body = buffer.split_off(head_end + 4);
+    while body.len() < head.content_length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Err(400),
+            Ok(read) => body.extend_from_slice(&chunk[..read]),
+        }
+    }
+    body.truncate(head.content_length);
+    Ok((head, body))
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: &str) -> Result<(), Error> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Request Header Fields Too Large",
+    };
+    let content_type = match status {
+        200 => "application/json",
+        _ => "text/plain",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// The id, method and params of a JSON-RPC call. Errors come with the id to answer them with
+fn parse_call(body: &[u8]) -> Result<(Value, String, Value), (Value, RpcError)> {
+    let call: Value = serde_json::from_slice(body)
+        .map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+    let id = call.get("id").cloned().unwrap_or(Value::Null);
+    let invalid = |message: &str| (id.clone(), RpcError::new(INVALID_REQUEST, message));
+    if !call.is_object() || call["jsonrpc"] != "2.0" {
+        return Err(invalid(
+            "Expected a JSON-RPC 2.0 call object, batches aren't supported",
+        ));
+    }
+    let Some(method) = call["method"].as_str() else {
+        return Err(invalid("Missing method"));
+    };
+    let params = match call.get("params") {
+        None => json!({}),
+        Some(params) if params.is_object() => params.clone(),
+        Some(_) => {
+            let error = RpcError::new(INVALID_PARAMS, "params must be an object");
+            return Err((id, error));
+        }
+    };
+    Ok((id, method.to_string(), params))
+}
+
+/// The response object for a call
+fn envelope(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => {
+            let mut body = json!({ "code": error.code, "message": error.message });
+            if let Some(data) = error.data {
+                body["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": body })
+        }
+    }
+}
+
+/// A send built and worked out by `create_send`, waiting for `confirm_send`
+struct Proposal {
+    id: String,
+    /// Wallet it spends from, the current one when it was created
+    wallet: String,
+    /// `send` arguments paying it: recipients and exact amounts in nano
+    args: Vec<String>,
+    /// Transaction `create_send` answered with, the only one `confirm_send` submits
+    tx_id: String,
+    /// What `create_send` answered, for `get_pending`
+    summary: Value,
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buffer);
+    buffer.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Run a command with `--json` the way the REPL does, its prompts answered by `answers`.
+/// Gives the object it printed, if any
+async fn run_json(
+    session: &mut Session,
+    command: String,
+    answers: Vec<Zeroizing<String>>,
+) -> Result<Option<Value>, RpcError> {
+    let capture = json::capture();
+    let _answers = input::answer_remotely(answers);
+    let result = handle_command(session, command).await;
+    let printed = capture
+        .take()
+        .and_then(|printed| serde_json::from_str(&printed).ok());
+    match result {
+        Ok(()) => Ok(printed),
+        Err(e) => Err(RpcError {
+            code: COMMAND_FAILED,
+            message: e.to_string(),
+            data: printed,
+        }),
+    }
+}
+
+/// The problems `check_payments` found in the payments of `create_send`, each with its code
+/// in `data` for clients to branch on
+fn invalid_payments(problems: &[Error]) -> RpcError {
+    let messages: Vec<String> = problems.iter().map(exit_code::describe).collect();
+    let errors: Vec<json::ErrorDetail> = problems
+        .iter()
+        .map(|e| json::ErrorOutput::new(e).error)
+        .collect();
+    RpcError {
+        code: INVALID_PARAMS,
+        message: messages.join("; "),
+        data: Some(json!({ "errors": errors })),
+    }
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params[name]
+        .as_str()
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("{} must be a string", name)))
+}
+
+struct Server {
+    token: Zeroizing<String>,
+    /// Oldest first
+    proposals: Vec<Proposal>,
+    /// Wrong PINs given to `confirm_send` since the last right one
+    failed_pins: u32,
+}
+
+impl Server {
+    async fn call(
+        &mut self,
+        session: &mut Session,
+        method: &str,
+        params: &Value,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "get_balance" => self.command(session, "balance --json".to_string()).await,
+            "get_history" => {
+                let command = match &params["limit"] {
+                    Value::Null => "history --json".to_string(),
+                    limit => match limit.as_u64() {
+                        Some(limit) => format!("history --json --limit {}", limit),
+                        None => {
+                            let message = "limit must be a non-negative integer";
+                            return Err(RpcError::new(INVALID_PARAMS, message));
+                        }
+                    },
+                };
+                self.command(session, command).await
+            }
+            "list_wallets" => Ok(list_wallets(session)),
+            "create_send" => self.create_send(session, params).await,
+            "confirm_send" => self.confirm_send(session, params).await,
+            "get_pending" => self.pending(session).await,
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method {}", method),
+            )),
+        }
+    }
+
+    /// A read-only command's JSON, nothing it asks is answered
+    async fn command(&self, session: &mut Session, command: String) -> Result<Value, RpcError> {
+        run_json(session, command, vec![])
+            .await?
+            .ok_or_else(|| RpcError::new(COMMAND_FAILED, "The command printed no result"))
+    }
+
+    /// Check the payments and build and work out their transaction, the way `send` does before
+    /// it asks for confirmation. Nothing is submitted
+    async fn create_send(
+        &mut self,
+        session: &mut Session,
+        params: &Value,
+    ) -> Result<Value, RpcError> {
+        let invalid = |message: String| RpcError::new(INVALID_PARAMS, message);
+        let failed = |e: Error| RpcError::new(COMMAND_FAILED, e.to_string());
+        let requested = params["payments"]
+            .as_array()
+            .filter(|payments| !payments.is_empty())
+            .ok_or_else(|| invalid("payments must be a non-empty list".to_string()))?;
+        let Some(wallet) = session.wallets.get(&session.current_wallet) else {
+            return Err(RpcError::new(
+                COMMAND_FAILED,
+                "The current wallet is missing",
+            ));
+        };
+        let public = wallet.public();
+
+        // The arguments `send` would get, recipient and amount in turn
+        let mut args = Vec::new();
+        for payment in requested {
+            args.push(string_param(payment, "recipient")?);
+            args.push(string_param(payment, "amount")?);
+        }
+        let spent_inputs = session.used_session_inputs.of(public);
+        // Percentages are of what can be sent now, only looked up when one is given
+        let spendable = match args
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .any(|amount| amount::is_percentage(amount))
+        {
+            true => spendable::fetch(&*session.client, public, spent_inputs)
+                .await
+                .map_err(failed)?
+                .spendable(),
+            false => 0,
+        };
+        // Checked the way `send` checks them, every problem at once with its code
+        let labels = vec![None; args.len() / 2];
+        let checked = match check_payments(&args, labels, &session.book, spendable) {
+            Ok(checked) => checked,
+            Err(problems) => return Err(invalid_payments(&problems)),
+        };
+        // Contacts by name, anything else by its clean address, so the arguments stay single
+        // words
+        let recipients: Vec<String> = checked
+            .recipients
+            .iter()
+            .zip(&checked.payments)
+            .map(
+                |(name, (address, _))| match session.book.contains_key(*name) {
+                    true => name.to_string(),
+                    false => address.dump_base36(),
+                },
+            )
+            .collect();
+        let payments = checked.payments;
+
+        let transaction = build_transaction(
+            &*session.client,
+            wallet.signer(),
+            payments.clone(),
+            spent_inputs,
+        )
+        .await
+        .map_err(failed)?;
+        let (transaction, _) = finish_pow(&*session.client, &mut session.pow_cache, transaction)
+            .await
+            .map_err(failed)?;
+        let fee = fee_rate(&*session.client, &transaction)
+            .await
+            .ok()
+            .flatten();
+
+        let id = random_hex(8);
+        let tx_id = transaction.transaction_id.unwrap().dump_base36();
+        let names: Vec<&str> = recipients.iter().map(String::as_str).collect();
+        let summary = json!({
+            "proposal_id": id,
+            "wallet": session.current_wallet,
+            "tx_id": tx_id,
+            "payments": json::Payment::list(&names, &payments),
+            "fee": fee.map(|rate| json::Amount::from(rate.fee)),
+        });
+        let args = recipients
+            .iter()
+            .zip(&payments)
+            .flat_map(|(recipient, (_, amount))| [recipient.clone(), format!("{}nano", amount)])
+            .collect();
+        if self.proposals.len() >= MAX_PROPOSALS {
+            self.proposals.remove(0);
+        }
+        self.proposals.push(Proposal {
+            id,
+            wallet: session.current_wallet.clone(),
+            args,
+            tx_id,
+            summary: summary.clone(),
+        });
+        Ok(summary)
+    }
+
+    /// Submit a proposal through `send` itself, answering its confirmation and PIN prompts.
+    /// `send` builds the transaction again, and refuses it unless it is the proposed one, with
+    /// the same inputs, fee and id. A wrong PIN drops the proposal, and after `MAX_PIN_ATTEMPTS`
+    /// of them in a row nothing is confirmed any more, so PINs can't be guessed by proposing
+    /// the send again and again
+    async fn confirm_send(
+        &mut self,
+        session: &mut Session,
+        params: &Value,
+    ) -> Result<Value, RpcError> {
+        if self.failed_pins >= MAX_PIN_ATTEMPTS {
+            return Err(RpcError::new(PIN_LOCKED, tr("rpc.pin_locked")));
+        }
+        let id = string_param(params, "proposal_id")?;
+        let pin = Zeroizing::new(string_param(params, "pin")?.to_string());
+        let Some(index) = self.proposals.iter().position(|proposal| proposal.id == id) else {
+            let message = trf("rpc.unknown_proposal", &[&id]);
+            return Err(RpcError::new(UNKNOWN_PROPOSAL, message));
+        };
+        let proposal = self.proposals.remove(index);
+        if !session.keys.verify(&*pin) {
+            self.failed_pins += 1;
+            if self.failed_pins >= MAX_PIN_ATTEMPTS {
+                say!("{}", style::warning(tr("rpc.confirm_locked")));
+            }
+            return Err(RpcError::new(WRONG_PIN, tr("rpc.wrong_pin")));
+        }
+        self.failed_pins = 0;
+        if proposal.wallet != session.current_wallet {
+            let message = trf("rpc.other_wallet", &[&proposal.wallet]);
+            return Err(RpcError::new(COMMAND_FAILED, message));
+        }
+        let args = proposal.args.join(" ");
+        let command = format!("send {} --expect-tx {} --json", args, proposal.tx_id);
+        let answers = vec![Zeroizing::new("y".to_string()), pin];
+        run_json(session, command, answers).await?.ok_or_else(|| {
+            // Only a question nobody could answer stops it, such as whether to redo the proof
+            // of work for a higher difficulty
+            RpcError::new(COMMAND_FAILED, tr("rpc.send_cancelled"))
+        })
+    }
+
+    /// Proposals waiting for confirmation, and the current wallet's transactions in the mempool
+    async fn pending(&self, session: &Session) -> Result<Value, RpcError> {
+        let public = session.current_public();
+        let mempool = session
+            .client
+            .get_mempool()
+            .await
+            .map_err(|e| RpcError::new(COMMAND_FAILED, e.to_string()))?;
+        let transactions: Vec<json::HistoryEntry> = mempool
+            .iter()
+            .filter_map(|tx| {
+                let effect = TxEffect::of(tx, public?);
+                let tx_id = tx.transaction_id?.dump_base36();
+                (effect.received > 0 || effect.is_outgoing()).then(|| json::HistoryEntry {
+                    note: session.notes.get(&tx_id).map(|note| note.text.clone()),
+                    tx_id,
+                    timestamp: Some(tx.timestamp),
+                    received: effect.received.into(),
+                    sent: effect.sent.into(),
+                })
+            })
+            .collect();
+        let proposals: Vec<&Value> = self.proposals.iter().map(|p| &p.summary).collect();
+        Ok(json!({ "proposals": proposals, "mempool": transactions }))
+    }
+
+    /// Answer one connection. Only loopback clients with the token get as far as a call
+    async fn connection(
+        &mut self,
+        session: &mut Session,
+        mut stream: TcpStream,
+        peer: SocketAddr,
+    ) -> Result<(), Error> {
+        if !peer.ip().is_loopback() {
+            return respond(&mut stream, 403, "Only local clients are served").await;
+        }
+        let (head, body) = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await
+        {
+            Ok(Ok(request)) => request,
+            Ok(Err(status)) => return respond(&mut stream, status, "Invalid request").await,
+            Err(_) => return Ok(()),
+        };
+        if head.method != "POST" {
+            return respond(&mut stream, 405, "Calls are POSTed").await;
+        }
+        if !is_authorized(head.authorization.as_deref(), &self.token) {
+            return respond(&mut stream, 401, "Missing or wrong token").await;
+        }
+        let response = match parse_call(&body) {
+            Ok((id, method, params)) => {
+                say!("{}", style::dim(format!("rpc: {}", method)));
+                envelope(id, self.call(session, &method, &params).await)
+            }
+            Err((id, error)) => envelope(id, Err(error)),
+        };
+        respond(&mut stream, 200, &response.to_string()).await
+    }
+}
+
+fn list_wallets(session: &Session) -> Value {
+    let mut wallets: Vec<json::Wallet> = session
+        .wallets
+        .iter()
+        .map(|(name, wallet)| json::Wallet {
+            name: name.clone(),
+            address: wallet.public().dump_base36(),
+            signer: match wallet {
+                WalletKey::Local(_) => "local",
+                WalletKey::External(_) => "external",
+            },
+            current: *name == session.current_wallet,
+        })
+        .collect();
+    wallets.sort_by(|a, b| a.name.cmp(&b.name));
+    json!({ "wallets": wallets })
+}
+
+/// The token on the first line of `path`, the file `rpc_token_file` names. A missing or empty
+/// one stops `serve` rather than letting it fall back to a token nobody was told
+fn read_token_file(path: &Path) -> Result<Zeroizing<String>, Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => Zeroizing::new(text),
+        Err(e) => {
+            let message = trf("rpc.token_file_unreadable", &[&path.display(), &e]);
+            return Err(Error::msg(message));
+        }
+    };
+    let token = text.lines().next().unwrap_or("").trim();
+    if token.is_empty() {
+        let message = trf("rpc.token_file_empty", &[&path.display()]);
+        return Err(ErrorCode::Usage.error(message));
+    }
+    Ok(Zeroizing::new(token.to_string()))
+}
+
+/// Serve the logged in session over JSON-RPC on a loopback address until Ctrl+C
+pub async fn serve(session: &mut Session, listen: &str) -> Result<(), Error> {
+    let address: SocketAddr = listen
+        .parse()
+        .map_err(|e| ErrorCode::Usage.error(trf("rpc.invalid_listen", &[&listen, &e])))?;
+    if !address.ip().is_loopback() {
+        let message = trf("rpc.not_loopback", &[&listen]);
+        return Err(ErrorCode::Usage.error(message));
+    }
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| Error::msg(trf("rpc.cant_listen", &[&address, &e])))?;
+    let token = match env::var(TOKEN_VAR).ok().filter(|token| !token.is_empty()) {
+        Some(token) => {
+            say!("{}", trf("rpc.token_in", &[&TOKEN_VAR]));
+            Zeroizing::new(token)
+        }
+        None => match &session.config.rpc_token_file {
+            Some(path) => {
+                let token = read_token_file(Path::new(path))?;
+                say!("{}", trf("rpc.token_in", &[path]));
+                token
+            }
+            None => {
+                let token = Zeroizing::new(random_hex(16));
+                say!("{}", trf("rpc.token_for_run", &[&style::dim(&*token)]));
+                token
+            }
+        },
+    };
+    let serving = trf("rpc.serving", &[&session.current_wallet, &address]);
+    say!("{}", serving);
+    let mut server = Server {
+        token,
+        proposals: vec![],
+        failed_pins: 0,
+    };
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = tokio::signal::ctrl_c() => break,
+        };
+        // A client that went away is no reason to stop serving the others
+        let result = match accepted {
+            Ok((stream, peer)) => server.connection(session, stream, peer).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            say!("{}", style::warning(format!("rpc: {}", e)));
+        }
+    }
+    say!("{}", tr("rpc.stopped"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::mock::MockNode;
+    use snap_coin::{core::transaction::TransactionInput, crypto::keys::Private, to_nano};
+
+    #[test]
+    fn test_parse_head() {
+        let head = "POST / HTTP/1.1\r\nHost: 127.0.0.1\r\ncontent-length: 42\r\n\
+                    Authorization: Bearer abc";
+        assert_eq!(
+            parse_head(head),
+            Ok(Head {
+                method: "POST".to_string(),
+                authorization: Some("Bearer abc".to_string()),
+                content_length: 42,
+            })
+        );
+        assert_eq!(parse_head("POST /"), Err(400));
+        assert_eq!(parse_head("POST / HTTP/1.1\r\nContent-Length: x"), Err(400));
+        let chunked = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked";
+        assert_eq!(parse_head(chunked), Err(400));
+
+        assert!(is_authorized(Some("Bearer abc"), "abc"));
+        assert!(!is_authorized(Some("Bearer abd"), "abc"));
+        assert!(!is_authorized(Some("abc"), "abc"));
+        assert!(!is_authorized(None, "abc"));
+    }
+
+    #[test]
+    fn test_read_token_file() {
+        let path = env::temp_dir().join(format!("snap-rpc-token-{}", std::process::id()));
+        fs::write(&path, "  s3cret \nsecond line\n").unwrap();
+        assert_eq!(*read_token_file(&path).unwrap(), "s3cret");
+        fs::write(&path, "\n").unwrap();
+        let e = read_token_file(&path).unwrap_err();
+        assert_eq!(ErrorCode::of(&e), ErrorCode::Usage);
+        fs::remove_file(&path).unwrap();
+        assert!(read_token_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_parse_call() {
+        let call = br#"{"jsonrpc":"2.0","id":7,"method":"get_balance"}"#;
+        assert_eq!(
+            parse_call(call).unwrap(),
+            (json!(7), "get_balance".to_string(), json!({}))
+        );
+        let (id, error) = parse_call(b"{").unwrap_err();
+        assert_eq!((id, error.code), (Value::Null, PARSE_ERROR));
+        let (id, error) = parse_call(br#"{"id":1,"method":"get_balance"}"#).unwrap_err();
+        assert_eq!((id, error.code), (json!(1), INVALID_REQUEST));
+        let positional = br#"{"jsonrpc":"2.0","id":2,"method":"get_history","params":[5]}"#;
+        assert_eq!(parse_call(positional).unwrap_err().1.code, INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_send_needs_confirmation_with_pin() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(5.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        let mut server = Server {
+            token: Zeroizing::new("token".to_string()),
+            proposals: vec![],
+            failed_pins: 0,
+        };
+        let receiver = Private::new_random().to_public().dump_base36();
+        let params = json!({ "payments": [{ "recipient": receiver, "amount": "2" }] });
+
+        let balance = server
+            .call(&mut session, "get_balance", &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(balance["balance"]["nano"], to_nano(5.0));
+
+        let proposal = server
+            .call(&mut session, "create_send", &params)
+            .await
+            .unwrap();
+        let id = proposal["proposal_id"].clone();
+        let pending = server
+            .call(&mut session, "get_pending", &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(pending["proposals"][0]["proposal_id"], id);
+
+        // A wrong PIN drops the proposal, nothing is sent
+        let wrong = json!({ "proposal_id": id, "pin": "000000" });
+        let error = server
+            .call(&mut session, "confirm_send", &wrong)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, WRONG_PIN);
+        let retried = json!({ "proposal_id": id, "pin": "123456" });
+        let error = server
+            .call(&mut session, "confirm_send", &retried)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, UNKNOWN_PROPOSAL);
+        assert!(submitted.lock().unwrap().is_empty());
+
+        let proposal = server
+            .call(&mut session, "create_send", &params)
+            .await
+            .unwrap();
+        let confirm = json!({ "proposal_id": proposal["proposal_id"], "pin": "123456" });
+        let sent = server
+            .call(&mut session, "confirm_send", &confirm)
+            .await
+            .unwrap();
+        assert_eq!(sent["accepted"], true);
+        assert_eq!(sent["tx_id"], proposal["tx_id"]);
+        assert_eq!(submitted.lock().unwrap().len(), 1);
+
+        // Every problem is reported at once, with the codes `send` gives them
+        let invalid = json!({ "payments": [
+            { "recipient": "nobody", "amount": "2" },
+            { "recipient": receiver, "amount": "0.000000001" },
+        ] });
+        let error = server
+            .call(&mut session, "create_send", &invalid)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+        let errors = &error.data.unwrap()["errors"];
+        assert_eq!(errors[0]["code"], "E_INVALID_ADDRESS");
+        assert_eq!(errors[1]["code"], "E_INVALID_AMOUNT");
+    }
+
+    /// A send that would go out with other inputs than proposed, and so another fee and id, is
+    /// refused rather than submitted
+    #[tokio::test]
+    async fn test_confirm_send_refuses_other_transaction() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(5.0));
+        node.fund(main.to_public(), to_nano(5.0));
+        let first = node.outputs[0].0;
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        let mut server = Server {
+            token: Zeroizing::new("token".to_string()),
+            proposals: vec![],
+            failed_pins: 0,
+        };
+        let receiver = Private::new_random().to_public().dump_base36();
+        let params = json!({ "payments": [{ "recipient": receiver, "amount": "2" }] });
+        let proposal = server
+            .call(&mut session, "create_send", &params)
+            .await
+            .unwrap();
+
+        // Spent meanwhile, as if by another send of this session
+        let spent = TransactionInput {
+            transaction_id: first,
+            output_index: 0,
+            signature: None,
+            output_owner: main.to_public(),
+        };
+        session.used_session_inputs.add(main.to_public(), &[spent]);
+        let confirm = json!({ "proposal_id": proposal["proposal_id"], "pin": "123456" });
+        let error = server
+            .call(&mut session, "confirm_send", &confirm)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, COMMAND_FAILED);
+        assert!(error.message.contains(proposal["tx_id"].as_str().unwrap()));
+        assert!(submitted.lock().unwrap().is_empty());
+    }
+
+    /// Proposing the send again doesn't buy more PIN guesses
+    #[tokio::test]
+    async fn test_confirm_send_locks_after_wrong_pins() {
+        let main = Private::new_random();
+        let mut node = MockNode::default();
+        node.fund(main.to_public(), to_nano(5.0));
+        let submitted = node.submitted.clone();
+        let mut session = Session::mock(node, main);
+        let mut server = Server {
+            token: Zeroizing::new("token".to_string()),
+            proposals: vec![],
+            failed_pins: 0,
+        };
+        let receiver = Private::new_random().to_public().dump_base36();
+        let params = json!({ "payments": [{ "recipient": receiver, "amount": "2" }] });
+
+        for pin in ["000000", "111111", "222222", "123456"] {
+            let proposal = server
+                .call(&mut session, "create_send", &params)
+                .await
+                .unwrap();
+            let confirm = json!({ "proposal_id": proposal["proposal_id"], "pin": pin });
+            let error = server
+                .call(&mut session, "confirm_send", &confirm)
+                .await
+                .unwrap_err();
+            let expected = if pin == "123456" {
+                PIN_LOCKED
+            } else {
+                WRONG_PIN
+            };
+            assert_eq!(error.code, expected);
+        }
+        assert!(submitted.lock().unwrap().is_empty());
+    }
+}
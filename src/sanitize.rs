@@ -0,0 +1,244 @@
+use std::ops::RangeInclusive;
+
+use snap_coin::crypto::{
+    Hash,
+    keys::{Private, Public},
+};
+use zeroize::Zeroizing;
+
+/// Characters that paste along invisibly from PDFs, chat apps and web pages
+const INVISIBLE: &[char] = &[
+    '\u{00AD}', // soft hyphen
+    '\u{200B}', // zero-width space
+    '\u{200C}', // zero-width non-joiner
+    '\u{200D}', // zero-width joiner
+    '\u{2060}', // word joiner
+    '\u{FEFF}', // byte order mark
+];
+
+/// Strip whitespace, line breaks of a wrapped paste included, and invisible characters
+pub fn clean(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| !c.is_whitespace() && !INVISIBLE.contains(c))
+        .collect()
+}
+
+/// Reject empty input and point at the first character that can't appear in base36
+fn check_characters(cleaned: &str) -> Result<(), String> {
+    if cleaned.is_empty() {
+        return Err("nothing was entered".to_string());
+    }
+    match cleaned.chars().enumerate().find(|(_, c)| !c.is_ascii_alphanumeric()) {
+        Some((index, c)) => Err(format!(
+            "invalid character '{}' (U+{:04X}) at position {}",
+            c.escape_default(),
+            c as u32,
+            index + 1
+        )),
+        None => Ok(()),
+    }
+}
+
+fn wrong_length(cleaned: &str) -> String {
+    format!("not a valid key ({} characters)", cleaned.chars().count())
+}
+
+/// Digits of a base36 address. A 32 byte key takes at most 50, and fewer than 40 only once in
+/// 10^15 keys, so a shorter address has lost part of itself
+const ADDRESS_DIGITS: RangeInclusive<usize> = 40..=50;
+
+/// Parse a pasted base36 address. Addresses are bare public keys without a checksum, so only
+/// a cut or padded address is caught here, not a mistyped character
+pub fn parse_public(input: &str) -> Result<Public, String> {
+    let cleaned = clean(input);
+    check_characters(&cleaned)?;
+    let digits = cleaned.chars().count();
+    if !ADDRESS_DIGITS.contains(&digits) {
+        return Err(format!(
+            "not an address ({} characters, addresses have {} to {}) — part of it may be missing",
+            digits,
+            ADDRESS_DIGITS.start(),
+            ADDRESS_DIGITS.end()
+        ));
+    }
+    let public = Public::new_from_base36(&cleaned).ok_or_else(|| wrong_length(&cleaned))?;
+    // Extra leading zeros and the like decode to a key, but not to the one that was copied
+    if !public.dump_base36().eq_ignore_ascii_case(&cleaned) {
+        return Err("not an address as the wallet writes it — possible typo".to_string());
+    }
+    Ok(public)
+}
+
+/// Characters of the checksum of an exported private key
+const CHECKSUM_LEN: usize = 4;
+/// Sets the checksum apart from the key, base36 keys vary in length so it can't go by position
+const CHECKSUM_SEPARATOR: char = '-';
+
+/// Checksum of a private key: the first 4 bytes of the hash of the key bytes, big endian,
+/// modulo 36^4 and written as 4 base36 digits, most significant first. A mistyped character
+/// goes unnoticed with a chance of about one in 1.7 million
+fn checksum(private: &Private) -> String {
+    let hash = Hash::new(private.dump_buf()).dump_buf();
+    let mut value = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]) % 36u32.pow(4);
+    let mut digits = [0u8; CHECKSUM_LEN];
+    for digit in digits.iter_mut().rev() {
+        *digit = char::from_digit(value % 36, 36).unwrap() as u8;
+        value /= 36;
+    }
+    String::from_utf8(digits.to_vec()).unwrap()
+}
+
+/// A private key for writing down: its base36 form, a `-` and its checksum, e.g.
+/// `<key>-3kq0`. Restoring it catches a mistyped character instead of yielding another wallet
+pub fn dump_checksummed(private: &Private) -> Zeroizing<String> {
+    let mut exported = Zeroizing::new(private.dump_base36());
+    exported.push(CHECKSUM_SEPARATOR);
+    exported.push_str(&checksum(private));
+    exported
+}
+
+/// Parse a pasted base36 private key, plain or with the checksum of `dump_checksummed`,
+/// which must then match
+pub fn parse_private(input: &str) -> Result<Private, String> {
+    let cleaned = Zeroizing::new(clean(input));
+    let checksummed = cleaned
+        .rsplit_once(CHECKSUM_SEPARATOR)
+        .filter(|(key, sum)| !key.contains(CHECKSUM_SEPARATOR) && sum.len() == CHECKSUM_LEN);
+    let Some((key, sum)) = checksummed else {
+        check_characters(&cleaned)?;
+        return Private::new_from_base36(&cleaned).ok_or_else(|| wrong_length(&cleaned));
+    };
+    check_characters(key)?;
+    check_characters(sum).map_err(|_| format!("invalid checksum '{}'", sum))?;
+    let private = Private::new_from_base36(key).ok_or_else(|| wrong_length(key))?;
+    if checksum(&private) != sum.to_ascii_lowercase() {
+        return Err("checksum mismatch — you likely mistyped a character".to_string());
+    }
+    Ok(private)
+}
+
+/// Check a wallet or contact name typed by the user. Names are referenced as single words
+/// in commands, so they must be non-empty and free of whitespace and invisible characters
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("the name can't be empty".to_string());
+    }
+    if name.chars().any(char::is_whitespace) {
+        return Err("the name can't contain spaces".to_string());
+    }
+    if name.chars().any(|c| INVISIBLE.contains(&c) || c.is_control()) {
+        return Err("the name contains invisible characters".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nasty_pastes() {
+        let public = Private::new_random().to_public();
+        let address = public.dump_base36();
+        let (head, tail) = address.split_at(address.len() / 2);
+        let pastes = [
+            format!("{}\n", address),
+            format!("  {}\r\n", address),
+            format!("\u{FEFF}{}", address),
+            format!("{}\u{200B}{}", head, tail),
+            format!("{}\n{}", head, tail),
+            format!("{}\r\n  {}\t", head, tail),
+            format!("{}\u{00A0}{}\u{2060}", head, tail),
+            format!("{}\u{00AD}{}", head, tail),
+        ];
+        for paste in pastes {
+            assert_eq!(parse_public(&paste), Ok(public), "paste {:?}", paste);
+        }
+    }
+
+    #[test]
+    fn test_invalid_character_position() {
+        let address = Private::new_random().to_public().dump_base36();
+        let broken = format!("{}-{}", &address[..5], &address[5..]);
+        let error = parse_public(&broken).unwrap_err();
+        assert!(error.contains("'-'") && error.contains("position 6"), "{}", error);
+
+        // Positions count from the cleaned text, invisible characters don't shift them
+        let broken = format!("\u{200B}{}é{}", &address[..3], &address[3..]);
+        assert!(parse_public(&broken).unwrap_err().contains("position 4"));
+
+        assert_eq!(parse_public(" \n"), Err("nothing was entered".to_string()));
+        assert_eq!(wrong_length("abc"), "not a valid key (3 characters)");
+    }
+
+    #[test]
+    fn test_address_shape() {
+        let public = Private::new_random().to_public();
+        let address = public.dump_base36();
+        let cut = parse_public(&address[..address.len() - 12]).unwrap_err();
+        assert!(cut.contains("part of it may be missing"), "{}", cut);
+
+        // Most addresses take all 50 digits, one that doesn't can take a leading zero
+        let short = std::iter::repeat_with(|| Private::new_random().to_public().dump_base36())
+            .find(|address| address.len() < *ADDRESS_DIGITS.end())
+            .unwrap();
+        let padded = parse_public(&format!("0{}", short)).unwrap_err();
+        assert!(padded.contains("possible typo"), "{}", padded);
+    }
+
+    #[test]
+    fn test_checksummed_private() {
+        let private = Private::new_random();
+        let public = private.to_public();
+        let exported = dump_checksummed(&private);
+        let (key, sum) = exported.rsplit_once('-').unwrap();
+        assert_eq!(key, private.dump_base36());
+        assert_eq!(sum.len(), CHECKSUM_LEN);
+        let parse = |text: &str| parse_private(text).map(|key| key.to_public());
+        assert_eq!(parse(&exported), Ok(public));
+        let typed = format!(" {}-{}\n", key, sum.to_uppercase());
+        assert_eq!(parse(&typed), Ok(public));
+        // The plain format keeps working
+        assert_eq!(parse(key), Ok(public));
+
+        let mismatch = "checksum mismatch — you likely mistyped a character";
+        let positions = [0, key.len() / 2, key.len() - 1, key.len() + 1, exported.len() - 1];
+        for position in positions {
+            let mut corrupted: Vec<char> = exported.chars().collect();
+            corrupted[position] = if corrupted[position] == 'a' { 'b' } else { 'a' };
+            let corrupted: String = corrupted.into_iter().collect();
+            // A changed key character may also make the key itself invalid
+            match parse(&corrupted) {
+                Err(reason) if position >= key.len() => assert_eq!(reason, mismatch),
+                Err(_) => {}
+                Ok(other) => panic!("{} parsed as {:?}", corrupted, other),
+            }
+        }
+        assert!(parse(&format!("{}-3k", key)).is_err());
+        let invalid = parse(&format!("{}-3k!0", key)).unwrap_err();
+        assert!(invalid.contains("checksum"), "{}", invalid);
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert_eq!(check_name("savings"), Ok(()));
+        assert_eq!(check_name("cold-storage_2"), Ok(()));
+        for empty in ["", " ", "\t", " \n "] {
+            assert_eq!(check_name(empty), Err("the name can't be empty".to_string()));
+        }
+        assert!(check_name("my wallet").unwrap_err().contains("spaces"));
+        assert!(check_name("\u{200B}").unwrap_err().contains("invisible"));
+        assert!(check_name("a\u{FEFF}b").unwrap_err().contains("invisible"));
+    }
+
+    #[test]
+    fn test_private_key_paste() {
+        let private = Private::new_random();
+        let pasted = format!("{}\u{200B}\n", private.dump_base36());
+        assert_eq!(
+            parse_private(&pasted).map(|key| key.to_public()),
+            Ok(private.to_public())
+        );
+    }
+}
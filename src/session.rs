@@ -0,0 +1,140 @@
+use std::{collections::BTreeMap, net::SocketAddr};
+
+use snap_coin::{core::transaction::TransactionInput, crypto::keys::Public};
+
+use crate::{
+    address_book::AddressBook,
+    backup::Backups,
+    config::{Config, Setting},
+    encryption::{SessionKeys, Unlocked},
+    node::{Disconnected, NodeApi},
+    notes::TxNotes,
+    signing::{PowCache, WalletKey, Wallets},
+};
+
+/// Inputs spent by transactions submitted this session, kept apart for each wallet that spent
+/// them: a wallet's sends and balance only ever consult its own, and forgetting one wallet's
+/// leaves the others' in place
+#[derive(Default)]
+pub struct SessionInputs {
+    /// By the base36 address of the spending wallet
+    by_wallet: BTreeMap<String, Vec<TransactionInput>>,
+}
+
+impl SessionInputs {
+    /// The inputs `wallet` spent this session
+    pub fn of(&self, wallet: Public) -> &[TransactionInput] {
+        self.by_wallet.get(&wallet.dump_base36()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Record the inputs of a transaction `wallet` submitted
+    pub fn add(&mut self, wallet: Public, inputs: &[TransactionInput]) {
+        self.by_wallet.entry(wallet.dump_base36()).or_default().extend_from_slice(inputs);
+    }
+
+    /// Forget the inputs `wallet` spent, giving how many there were
+    pub fn clear(&mut self, wallet: Public) -> usize {
+        self.by_wallet.remove(&wallet.dump_base36()).map_or(0, |inputs| inputs.len())
+    }
+
+    /// Forget the inputs of every wallet, giving how many there were
+    pub fn clear_all(&mut self) -> usize {
+        let cleared = self.len();
+        self.by_wallet.clear();
+        cleared
+    }
+
+    /// Inputs spent by all wallets together
+    pub fn len(&self) -> usize {
+        self.by_wallet.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Everything a logged in wallet session works with
+pub struct Session {
+    pub client: Box<dyn NodeApi>,
+    /// Address of the node `client` is connected to
+    pub node: SocketAddr,
+    pub wallets: Wallets,
+    pub book: AddressBook,
+    pub notes: TxNotes,
+    /// When each local wallet's key was confirmed as backed up
+    pub backups: Backups,
+    pub current_wallet: String,
+    pub keys: SessionKeys,
+    /// Inputs spent by transactions submitted this session, excluded from new transactions
+    pub used_session_inputs: SessionInputs,
+    /// Base36 addresses sent to this session, most recent first
+    pub recent_addresses: Vec<String>,
+    /// Finished transactions that may still need to be submitted
+    pub pow_cache: PowCache,
+    /// Seconds the local clock is ahead of the network, if it could be estimated at connect
+    pub clock_skew: Option<i64>,
+    pub config: Config,
+    /// Settings given on the command line or by the environment, for `config show`
+    pub startup_settings: Vec<Setting>,
+}
+
+impl Session {
+    /// Public key of the current wallet
+    pub fn current_public(&self) -> Option<Public> {
+        self.wallets.get(&self.current_wallet).map(|wallet| wallet.public())
+    }
+
+    /// Forget the wallets, their keys and everything else read from the wallet file, and drop
+    /// the connection to the node, for `suspend`. Save first: nothing can be saved until
+    /// `resume`
+    pub fn suspend(&mut self) {
+        self.wallets.clear();
+        self.book.clear();
+        self.notes.clear();
+        self.backups.clear();
+        self.keys.forget();
+        self.client = Box::new(Disconnected);
+    }
+
+    /// Carry on after `suspend` with the wallet file unlocked again and a new connection.
+    /// Spent inputs, finished transactions and settings were kept all along
+    pub fn resume(&mut self, unlocked: Unlocked, client: Box<dyn NodeApi>) {
+        (self.wallets, self.book, self.notes, self.backups, self.keys) = unlocked;
+        self.client = client;
+    }
+
+    /// A session on a mock node, logged in to `main` next to a `savings` wallet, with PIN
+    /// 123456 and the cheapest KDF
+    #[cfg(test)]
+    pub fn mock(node: crate::node::mock::MockNode, main: snap_coin::crypto::keys::Private) -> Self {
+        use crate::encryption::{KdfAlgorithm, KdfParams};
+        use snap_coin::crypto::keys::Private;
+
+        let mut wallets = Wallets::new();
+        wallets.insert("main".to_string(), WalletKey::Local(main));
+        wallets.insert("savings".to_string(), WalletKey::Local(Private::new_random()));
+        let params = KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        Session {
+            client: Box::new(node),
+            node: "127.0.0.1:3003".parse().unwrap(),
+            wallets,
+            book: AddressBook::new(),
+            notes: TxNotes::new(),
+            backups: Backups::new(),
+            current_wallet: "main".to_string(),
+            keys: SessionKeys::derive("123456", params).unwrap(),
+            used_session_inputs: SessionInputs::default(),
+            recent_addresses: vec![],
+            pow_cache: Default::default(),
+            clock_skew: None,
+            config: Config::default(),
+            startup_settings: vec![],
+        }
+    }
+}
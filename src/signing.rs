@@ -0,0 +1,464 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::Error;
+use snap_coin::{
+    core::transaction::{MAX_TRANSACTION_IO, Transaction, TransactionInput, TransactionOutput},
+    crypto::{
+        Hash, Signature,
+        keys::{Private, Public},
+    },
+};
+
+use crate::{
+    exit_code::ErrorCode,
+    node::{self, NodeApi},
+};
+
+/// Produces input signatures for transactions spending a wallet's coins
+pub trait Signer {
+    /// Public key whose outputs this signer can spend
+    fn public(&self) -> Public;
+
+    /// Sign a transactions input signing buffer
+    fn sign(&self, transaction: &Transaction, signing_buf: &[u8]) -> Result<Signature, Error>;
+}
+
+/// Default signer, signs with a locally stored private key
+impl Signer for Private {
+    fn public(&self) -> Public {
+        self.to_public()
+    }
+
+    fn sign(&self, _transaction: &Transaction, signing_buf: &[u8]) -> Result<Signature, Error> {
+        let mut private = *self;
+        Ok(Signature::new_signature(&mut private, signing_buf))
+    }
+}
+
+/// Delegates signing to an external program (e.g. an HSM bridge or air-gapped signer)
+/// The program is run through the shell and receives a JSON request on stdin:
+/// `{"public": <base36>, "transaction": <unsigned transaction>, "signing_buf": <hex>}`
+/// It must print the base36 signature of `signing_buf` on stdout
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalSigner {
+    pub public: Public,
+    pub command: String,
+}
+
+impl Signer for ExternalSigner {
+    fn public(&self) -> Public {
+        self.public
+    }
+
+    fn sign(&self, transaction: &Transaction, signing_buf: &[u8]) -> Result<Signature, Error> {
+        let request = serde_json::json!({
+            "public": self.public.dump_base36(),
+            "transaction": transaction,
+            "signing_buf": signing_buf.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        });
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::msg(format!("Failed to start external signer: {}", e)))?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::msg("Failed to open external signer stdin"))?
+            .write_all(request.to_string().as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::msg(format!(
+                "External signer exited with {}",
+                output.status
+            )));
+        }
+
+        let response = String::from_utf8_lossy(&output.stdout);
+        let signature = Signature::new_from_base36(response.trim())
+            .ok_or_else(|| Error::msg("External signer returned an invalid signature"))?;
+        if !signature
+            .validate_with_public(&self.public, signing_buf)
+            .unwrap_or(false)
+        {
+            return Err(Error::msg(
+                "External signer returned a signature that does not match the wallet public key",
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+/// The wallets by name. Kept sorted, so every listing of them comes out in the same order
+pub type Wallets = BTreeMap<String, WalletKey>;
+
+/// A stored wallet: either a local private key, or a reference to an external signer
+#[derive(Clone, PartialEq)]
+pub enum WalletKey {
+    Local(Private),
+    External(ExternalSigner),
+}
+
+impl WalletKey {
+    pub fn public(&self) -> Public {
+        self.signer().public()
+    }
+
+    pub fn signer(&self) -> &dyn Signer {
+        match self {
+            WalletKey::Local(private) => private,
+            WalletKey::External(external) => external,
+        }
+    }
+}
+
+/// Prepended to every signed message. Transaction signing buffers are raw transaction bytes,
+/// which never start with this text, so a message signature can't be passed off as a
+/// signature of a transaction, or the other way around
+const MESSAGE_PREFIX: &str = "Snap Coin Signed Message:\n";
+
+/// Bytes actually signed for `message`: the prefix, the message length, then the message, so
+/// no message can be extended into another one with the same signature
+pub fn message_signing_buf(message: &str) -> Vec<u8> {
+    format!("{}{}\n{}", MESSAGE_PREFIX, message.len(), message).into_bytes()
+}
+
+/// Sign `message` with a local private key, to prove control of its address
+pub fn sign_message(private: &Private, message: &str) -> Signature {
+    let mut private = *private;
+    Signature::new_signature(&mut private, &message_signing_buf(message))
+}
+
+/// Whether `signature` is `address`'s signature of `message`
+pub fn verify_message(address: &Public, message: &str, signature: &Signature) -> bool {
+    signature
+        .validate_with_public(address, &message_signing_buf(message))
+        .unwrap_or(false)
+}
+
+/// Build a new transaction, sending from the signers address to each receiver. Takes biggest coins first.
+/// Same input selection as `snap_coin::build_transaction`, but signing goes through a `Signer`
+/// WARNING: this does not compute transaction pow!
+pub async fn build_transaction(
+    client: &dyn NodeApi,
+    signer: &dyn Signer,
+    mut receivers: Vec<(Public, u64)>,
+    ignore_inputs: &[TransactionInput],
+) -> Result<Transaction, Error> {
+    let sender = signer.public();
+    let target_balance = receivers
+        .iter()
+        .fold(0u64, |acc, receiver| acc + receiver.1);
+
+    let mut available_inputs = node::available_outputs(client, sender).await?;
+    available_inputs.retain(|(transaction, _, index)| {
+        !ignore_inputs
+            .iter()
+            .any(|i_input| i_input.output_index == *index && i_input.transaction_id == *transaction)
+    });
+
+    let mut used_inputs = vec![];
+    let mut current_funds = 0u64;
+    for (transaction, input, index) in available_inputs {
+        current_funds += input.amount;
+        used_inputs.push((transaction, input, index));
+        if current_funds >= target_balance {
+            break;
+        }
+    }
+
+    if target_balance > current_funds {
+        return Err(ErrorCode::InsufficientFunds.error("Insufficient funds to complete operation"));
+    }
+    if target_balance < current_funds {
+        receivers.push((sender, current_funds - target_balance));
+    }
+    if used_inputs.len() + receivers.len() > MAX_TRANSACTION_IO {
+        return Err(Error::msg(
+            "Too many inputs and outputs for one transaction. Consider splitting transaction in to more than one (smaller SNAP amount) or less receivers.",
+        ));
+    }
+
+    used_inputs.sort_by_key(|input| input.1.amount);
+
+    let mut transaction = Transaction {
+        inputs: used_inputs
+            .iter()
+            .map(|input| TransactionInput {
+                transaction_id: input.0,
+                output_index: input.2,
+                signature: None,
+                output_owner: sender,
+            })
+            .collect(),
+        outputs: receivers
+            .iter()
+            .map(|receiver| TransactionOutput {
+                amount: receiver.1,
+                receiver: receiver.0,
+            })
+            .collect(),
+        transaction_id: None,
+        nonce: 0,
+        timestamp: crate::clock::now()?,
+    };
+
+    // Every input is owned by the same key, so one signature covers all of them
+    let signing_buf = transaction.get_input_signing_buf()?;
+    let signature = signer.sign(&transaction, &signing_buf)?;
+    for input in &mut transaction.inputs {
+        input.signature = Some(signature);
+    }
+
+    Ok(transaction)
+}
+
+/// Transactions whose proof of work is done but that haven't made it into the mempool yet,
+/// so retrying the same payment reuses the work instead of redoing it
+#[derive(Default)]
+pub struct PowCache {
+    /// Finished transactions with the difficulty their proof of work was computed for
+    transactions: HashMap<String, (Transaction, [u8; 32])>,
+}
+
+impl PowCache {
+    /// Identifies what a transaction spends and pays, ignoring the timestamp, nonce and
+    /// signatures that are recomputed every time it is built
+    fn content_key(transaction: &Transaction) -> String {
+        let inputs = transaction.inputs.iter().map(|input| {
+            format!(
+                "{}:{}:{}",
+                input.transaction_id.dump_base36(),
+                input.output_index,
+                input.output_owner.dump_base36()
+            )
+        });
+        let outputs = transaction
+            .outputs
+            .iter()
+            .map(|output| format!("{}:{}", output.receiver.dump_base36(), output.amount));
+        inputs.chain(["|".to_string()]).chain(outputs).collect::<Vec<_>>().join(",")
+    }
+
+    /// A finished transaction with the same inputs and outputs and the difficulty it was
+    /// computed for, if one was computed before
+    pub fn get(&self, transaction: &Transaction) -> Option<(Transaction, [u8; 32])> {
+        self.transactions.get(&Self::content_key(transaction)).cloned()
+    }
+
+    /// Remember a transaction after its proof of work was computed for `difficulty`
+    pub fn insert(&mut self, transaction: Transaction, difficulty: [u8; 32]) {
+        self.transactions
+            .insert(Self::content_key(&transaction), (transaction, difficulty));
+    }
+
+    /// Forget a transaction once it was accepted, or when the node refused it
+    pub fn remove(&mut self, transaction: &Transaction) {
+        self.transactions.remove(&Self::content_key(transaction));
+    }
+}
+
+/// Encoded size in bytes of a complete transaction with the given number of inputs and outputs
+pub fn estimate_transaction_size(input_count: usize, output_count: usize) -> usize {
+    let placeholder = Private::new_from_buf(&[1u8; 32]).to_public();
+    let transaction = Transaction {
+        inputs: vec![
+            TransactionInput {
+                transaction_id: Hash::new_from_buf([0xff; 32]),
+                output_index: MAX_TRANSACTION_IO,
+                signature: Some(Signature::new_from_buf(&[0xff; 64])),
+                output_owner: placeholder,
+            };
+            input_count
+        ],
+        outputs: vec![
+            TransactionOutput {
+                amount: u64::MAX / 2,
+                receiver: placeholder,
+            };
+            output_count
+        ],
+        transaction_id: None,
+        nonce: u64::MAX,
+        timestamp: u64::MAX,
+    };
+    transaction_size(&transaction)
+}
+
+/// Encoded size in bytes of a transaction
+pub fn transaction_size(transaction: &Transaction) -> usize {
+    // The hashing buffer is the full transaction minus its 32 byte id
+    transaction
+        .get_tx_hashing_buf()
+        .map(|buf| buf.len() + 32)
+        .unwrap_or(0)
+}
+
+/// Whether the difficulty went up from `used` to `now`. Difficulties are big-endian targets a
+/// hash must stay below, so a harder one is a smaller number
+pub fn difficulty_increased(used: &[u8; 32], now: &[u8; 32]) -> bool {
+    now < used
+}
+
+/// Expected number of hashes to find a PoW below a difficulty target, optionally tightened by a margin
+pub fn expected_pow_hashes(difficulty: &[u8; 32], difficulty_margin: Option<f64>) -> f64 {
+    let target = difficulty
+        .iter()
+        .fold(0f64, |acc, byte| acc * 256.0 + *byte as f64)
+        * (1.0 - difficulty_margin.unwrap_or(0.0));
+    if target <= 0.0 {
+        return f64::INFINITY;
+    }
+    2f64.powi(256) / target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_transaction(owner: Public) -> Transaction {
+        Transaction {
+            inputs: vec![],
+            outputs: vec![TransactionOutput {
+                amount: 100,
+                receiver: owner,
+            }],
+            transaction_id: None,
+            nonce: 0,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_external_signer_matches_local() {
+        let private = Private::new_random();
+        let transaction = test_transaction(private.to_public());
+        let signing_buf = transaction.get_input_signing_buf().unwrap();
+        let local = private.sign(&transaction, &signing_buf).unwrap();
+
+        // A stand-in signer that consumes the request and answers with a known signature
+        let external = ExternalSigner {
+            public: private.to_public(),
+            command: format!("cat > /dev/null; echo {}", local.dump_base36()),
+        };
+        let signature = external.sign(&transaction, &signing_buf).unwrap();
+        assert_eq!(signature.dump_buf(), local.dump_buf());
+    }
+
+    #[test]
+    fn test_external_signer_rejects_wrong_key() {
+        let private = Private::new_random();
+        let transaction = test_transaction(private.to_public());
+        let signing_buf = transaction.get_input_signing_buf().unwrap();
+        let other = Private::new_random().sign(&transaction, &signing_buf).unwrap();
+
+        let external = ExternalSigner {
+            public: private.to_public(),
+            command: format!("cat > /dev/null; echo {}", other.dump_base36()),
+        };
+        assert!(external.sign(&transaction, &signing_buf).is_err());
+
+        let failing = ExternalSigner {
+            public: private.to_public(),
+            command: "cat > /dev/null; exit 1".to_string(),
+        };
+        assert!(failing.sign(&transaction, &signing_buf).is_err());
+    }
+
+    #[test]
+    fn test_pow_cache_matches_content_only() {
+        let owner = Private::new_random().to_public();
+        let mut cache = PowCache::default();
+        let mut computed = test_transaction(owner);
+        computed.nonce = 42;
+        computed.timestamp = 1000;
+        cache.insert(computed, [7; 32]);
+
+        // Rebuilt later, the same payment gets the finished transaction back
+        let rebuilt = test_transaction(owner);
+        let found = cache.get(&rebuilt).map(|(tx, difficulty)| (tx.nonce, difficulty));
+        assert_eq!(found, Some((42, [7; 32])));
+
+        let mut changed = test_transaction(owner);
+        changed.outputs[0].amount = 99;
+        assert!(cache.get(&changed).is_none());
+
+        cache.remove(&rebuilt);
+        assert!(cache.get(&rebuilt).is_none());
+    }
+
+    #[test]
+    fn test_estimates() {
+        assert!(estimate_transaction_size(2, 2) > estimate_transaction_size(1, 2));
+        assert!(estimate_transaction_size(1, 3) > estimate_transaction_size(1, 2));
+
+        let mut easy = [0u8; 32];
+        easy[0] = 0x80; // half of all hashes are valid
+        assert!((expected_pow_hashes(&easy, None) - 2.0).abs() < 1e-9);
+        assert!((expected_pow_hashes(&easy, Some(0.5)) - 4.0).abs() < 1e-9);
+        assert!(expected_pow_hashes(&[0u8; 32], None).is_infinite());
+
+        let mut harder = easy;
+        harder[0] = 0x40;
+        assert!(difficulty_increased(&easy, &harder));
+        assert!(!difficulty_increased(&harder, &easy));
+        assert!(!difficulty_increased(&easy, &easy));
+        // A lower byte only counts when the higher ones are equal
+        let mut easier = harder;
+        easier[31] = 0xff;
+        assert!(!difficulty_increased(&harder, &easier));
+    }
+
+    #[test]
+    fn test_message_signatures() {
+        let private = Private::new_random();
+        let signature = sign_message(&private, "I own this address");
+        assert!(verify_message(&private.to_public(), "I own this address", &signature));
+        assert!(!verify_message(&private.to_public(), "I own this address!", &signature));
+        let other = Private::new_random().to_public();
+        assert!(!verify_message(&other, "I own this address", &signature));
+
+        // A message signature is not a signature of the raw message bytes
+        let buf = "I own this address".as_bytes();
+        assert!(!signature.validate_with_public(&private.to_public(), buf).unwrap_or(false));
+    }
+
+    #[tokio::test]
+    async fn test_build_transaction_selects_inputs() {
+        use crate::node::mock::MockNode;
+
+        let sender = Private::new_random();
+        let receiver = Private::new_random().to_public();
+        let mut node = MockNode::default();
+        node.fund(sender.to_public(), 50);
+        node.fund(sender.to_public(), 30);
+
+        let tx = build_transaction(&node, &sender, vec![(receiver, 60)], &[])
+            .await
+            .unwrap();
+        assert_eq!(tx.inputs.len(), 2);
+        let paid: Vec<(Public, u64)> = tx
+            .outputs
+            .iter()
+            .map(|output| (output.receiver, output.amount))
+            .collect();
+        assert_eq!(paid, vec![(receiver, 60), (sender.to_public(), 20)]);
+
+        // Inputs already spent this session are never selected again
+        let spent = [TransactionInput {
+            transaction_id: node.outputs[0].0,
+            output_index: 0,
+            signature: None,
+            output_owner: sender.to_public(),
+        }];
+        let result = build_transaction(&node, &sender, vec![(receiver, 60)], &spent).await;
+        assert!(result.is_err());
+    }
+}
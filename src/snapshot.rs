@@ -0,0 +1,249 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload, rand_core::RngCore};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Error;
+use snap_coin::crypto::{
+    Hash,
+    keys::{Private, Public},
+};
+use zeroize::Zeroizing;
+
+use crate::{export, report::Date};
+
+/// Lines the snapshot log keeps across all wallets, the oldest dropped first
+pub const MAX_SNAPSHOTS: usize = 5000;
+const NONCE_LEN: usize = 12;
+/// Levels of the sparkline, lowest first
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// A balance recorded at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    pub timestamp: u64,
+    pub balance: u64,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Key the sealed snapshots of one wallet are encrypted with. It comes from the wallet's
+/// private key rather than the PIN, so changing the PIN doesn't lose the series. The tag finds
+/// the wallet's lines without giving its address away
+pub struct SealKey {
+    key: Zeroizing<[u8; 32]>,
+    tag: String,
+}
+
+impl SealKey {
+    pub fn of(private: &Private) -> Self {
+        let mut buf = Zeroizing::new(b"snap-coin-wallet-snapshots-".to_vec());
+        buf.extend_from_slice(private.dump_buf());
+        let key = Zeroizing::new(Hash::new(&buf).dump_buf());
+        let mut buf = Zeroizing::new(b"snap-coin-wallet-snapshot-tag-".to_vec());
+        buf.extend_from_slice(&*key);
+        let tag = hex(&Hash::new(&buf).dump_buf()[..8]);
+        SealKey { key, tag }
+    }
+
+    /// The line's timestamp and tag are authenticated, so a sealed balance can't be moved
+    fn aad(&self, timestamp: u64) -> String {
+        format!("{} {}", timestamp, self.tag)
+    }
+
+    fn seal(&self, timestamp: u64, balance: u64) -> Option<String> {
+        let cipher = Aes256Gcm::new_from_slice(&*self.key).ok()?;
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let aad = self.aad(timestamp);
+        let payload = Payload {
+            msg: &balance.to_be_bytes(),
+            aad: aad.as_bytes(),
+        };
+        let sealed = cipher.encrypt(Nonce::from_slice(&nonce), payload).ok()?;
+        Some(format!("{}{}", hex(&nonce), hex(&sealed)))
+    }
+
+    fn open(&self, timestamp: u64, sealed: &str) -> Option<u64> {
+        let data = unhex(sealed)?;
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+        let cipher = Aes256Gcm::new_from_slice(&*self.key).ok()?;
+        let aad = self.aad(timestamp);
+        let payload = Payload {
+            msg: &data[NONCE_LEN..],
+            aad: aad.as_bytes(),
+        };
+        let balance = cipher
+            .decrypt(Nonce::from_slice(&data[..NONCE_LEN]), payload)
+            .ok()?;
+        Some(u64::from_be_bytes(balance.try_into().ok()?))
+    }
+}
+
+/// The log line of a snapshot: `<timestamp> plain <address> <nano>`, or with a key
+/// `<timestamp> sealed <tag> <encrypted balance>`
+pub fn line(snapshot: Snapshot, address: Public, seal: Option<&SealKey>) -> Option<String> {
+    let Snapshot { timestamp, balance } = snapshot;
+    match seal {
+        Some(seal) => Some(format!(
+            "{} sealed {} {}",
+            timestamp,
+            seal.tag,
+            seal.seal(timestamp, balance)?
+        )),
+        None => Some(format!(
+            "{} plain {} {}",
+            timestamp,
+            address.dump_base36(),
+            balance
+        )),
+    }
+}
+
+/// The snapshots of one wallet in a log, oldest first: its plain lines, and the sealed ones
+/// when its key is given. Lines of other wallets and damaged lines are skipped
+pub fn series(log: &str, address: Public, seal: Option<&SealKey>) -> Vec<Snapshot> {
+    let address = address.dump_base36();
+    let mut snapshots: Vec<Snapshot> = log
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let timestamp = fields.next()?.parse().ok()?;
+            let balance = match (fields.next()?, fields.next()?, fields.next()?, seal) {
+                ("plain", owner, balance, _) if owner == address => balance.parse().ok()?,
+                ("sealed", tag, sealed, Some(seal)) if tag == seal.tag => {
+                    seal.open(timestamp, sealed)?
+                }
+                _ => return None,
+            };
+            Some(Snapshot { timestamp, balance })
+        })
+        .collect();
+    // Appended in order unless the clock was changed
+    snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+    snapshots
+}
+
+/// Append a line to the log. Once it holds `MAX_SNAPSHOTS` lines it is rewritten without the
+/// oldest, otherwise nothing already written is touched
+pub fn append(path: &Path, line: &str) -> Result<(), Error> {
+    let log = match fs::read_to_string(path) {
+        Ok(log) => log,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::msg(format!("Can't read {}: {}", path.display(), e))),
+    };
+    let count = log.lines().count();
+    if count < MAX_SNAPSHOTS {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        // A line cut short by a crash is finished off, not joined to the new one
+        if !log.is_empty() && !log.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+        file.write_all(format!("{}\n", line).as_bytes())?;
+        return Ok(());
+    }
+    let mut kept: Vec<&str> = log.lines().skip(count + 1 - MAX_SNAPSHOTS).collect();
+    kept.push(line);
+    export::write_atomic(path, format!("{}\n", kept.join("\n")).as_bytes())
+}
+
+/// A UTC time to the minute, `2025-03-01 14:05`
+pub fn format_time(timestamp: u64) -> String {
+    format!(
+        "{} {:02}:{:02}",
+        Date::of(timestamp),
+        timestamp % 86_400 / 3600,
+        timestamp % 3600 / 60
+    )
+}
+
+/// One character per value, from the lowest to the highest of them. A flat series is drawn
+/// at the bottom
+pub fn sparkline(values: &[u64]) -> String {
+    let (Some(&min), Some(&max)) = (values.iter().min(), values.iter().max()) else {
+        return String::new();
+    };
+    let top = (SPARKS.len() - 1) as u128;
+    values
+        .iter()
+        .map(|&value| match max - min {
+            0 => SPARKS[0],
+            range => SPARKS[((value - min) as u128 * top / range as u128) as usize],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_series() {
+        let private = Private::new_random();
+        let address = private.to_public();
+        let other = Private::new_random();
+        let seal = SealKey::of(&private);
+        let at = |timestamp, balance| Snapshot { timestamp, balance };
+        let lines = [
+            line(at(300, 7), address, Some(&seal)).unwrap(),
+            line(at(100, 5), address, None).unwrap(),
+            line(at(200, 9), other.to_public(), None).unwrap(),
+            line(at(400, 1), other.to_public(), Some(&SealKey::of(&other))).unwrap(),
+            "garbage".to_string(),
+        ];
+        let log = lines.join("\n");
+        assert!(!lines[0].contains(&address.dump_base36()));
+        assert_eq!(
+            series(&log, address, Some(&seal)),
+            vec![at(100, 5), at(300, 7)]
+        );
+        // Sealed lines stay unreadable without the key
+        assert_eq!(series(&log, address, None), vec![at(100, 5)]);
+
+        // A sealed balance moved to another time doesn't open
+        let moved = lines[0].replacen("300", "301", 1);
+        assert_eq!(series(&moved, address, Some(&seal)), vec![]);
+    }
+
+    #[test]
+    fn test_append_bounded() {
+        let path = std::env::temp_dir().join(format!("snap-snapshots-{}", std::process::id()));
+        fs::write(&path, "0 a").unwrap();
+        append(&path, "1 b").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0 a\n1 b\n");
+
+        fs::write(&path, "0 first\n".repeat(MAX_SNAPSHOTS - 1) + "1 partial").unwrap();
+        append(&path, "2 new").unwrap();
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), MAX_SNAPSHOTS);
+        assert!(log.ends_with("1 partial\n2 new\n"));
+        assert!(log.starts_with("0 first\n"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[5, 5]), "▁▁");
+        assert_eq!(sparkline(&[0, 7, 14, 3]), "▁▄█▂");
+        assert_eq!(sparkline(&[u64::MAX, 0]), "█▁");
+        assert_eq!(format_time(86_400 + 3600 + 120), "1970-01-02 01:02");
+    }
+}
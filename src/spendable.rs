@@ -0,0 +1,152 @@
+use anyhow::Error;
+use snap_coin::{
+    core::transaction::{TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+
+use crate::{
+    amount,
+    node::{self, NodeApi},
+};
+
+/// How a wallet's balance splits into what a send can use right now and what it can't.
+/// The node answers balance and spendable outputs separately, so the two can disagree
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Breakdown {
+    /// Balance as reported by the node
+    pub balance: u64,
+    /// Sum of the outputs the node offers as spendable
+    pub available: u64,
+    /// Part of `available` already spent by sends this session, not yet confirmed
+    pub session_spent: u64,
+    /// Paid to the wallet by transactions still in the mempool
+    pub pending_incoming: u64,
+}
+
+impl Breakdown {
+    /// What a send can use now
+    pub fn spendable(&self) -> u64 {
+        self.available.saturating_sub(self.session_spent)
+    }
+
+    /// Balance that no spendable output backs
+    pub fn unavailable(&self) -> u64 {
+        self.balance.saturating_sub(self.available)
+    }
+
+    /// Whether part of the balance can't be sent right now
+    pub fn differs(&self) -> bool {
+        self.spendable() != self.balance
+    }
+
+    /// One line per reason the spendable amount differs from the balance
+    pub fn reasons(&self) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if self.session_spent > 0 {
+            reasons.push(format!(
+                "{} is in outputs spent by sends this session, waiting to be confirmed",
+                amount::display(self.session_spent)
+            ));
+        }
+        if self.unavailable() > 0 {
+            reasons.push(unavailable_reason(self.unavailable()));
+        }
+        if self.pending_incoming > 0 {
+            reasons.push(format!(
+                "{} is on its way in transactions still in the mempool",
+                amount::display(self.pending_incoming)
+            ));
+        }
+        reasons
+    }
+}
+
+/// Why `unavailable` of the balance can't be sent. Outputs carry no lock height or time, and
+/// the node simply leaves out the ones that can't be spent, so the cause can only be guessed
+pub fn unavailable_reason(unavailable: u64) -> String {
+    format!(
+        "{} has no spendable outputs yet: immature mining rewards, locked outputs or outputs \
+         spent by a pending transaction (the node doesn't say which)",
+        amount::display(unavailable)
+    )
+}
+
+/// Whether the output `index` of `tx_id` is among `used_session_inputs`
+fn spent_by(used_session_inputs: &[TransactionInput], tx_id: Hash, index: usize) -> bool {
+    used_session_inputs
+        .iter()
+        .any(|input| input.transaction_id == tx_id && input.output_index == index)
+}
+
+/// The outputs a send can use now: those the node offers, less the ones spent this session.
+/// Their sum is the `spendable` part of a breakdown fetched at the same time
+pub async fn outputs(
+    client: &dyn NodeApi,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+) -> Result<Vec<(Hash, TransactionOutput, usize)>, Error> {
+    let mut outputs = node::available_outputs(client, public).await?;
+    outputs.retain(|(tx_id, _, index)| !spent_by(used_session_inputs, *tx_id, *index));
+    Ok(outputs)
+}
+
+/// Query the balance, the spendable outputs and the mempool, and correlate them
+pub async fn fetch(
+    client: &dyn NodeApi,
+    public: Public,
+    used_session_inputs: &[TransactionInput],
+) -> Result<Breakdown, Error> {
+    let balance = client.get_balance(public).await?;
+    let mut breakdown = Breakdown {
+        balance,
+        ..Default::default()
+    };
+    for (tx_id, output, index) in node::available_outputs(client, public).await? {
+        breakdown.available += output.amount;
+        if spent_by(used_session_inputs, tx_id, index) {
+            breakdown.session_spent += output.amount;
+        }
+    }
+    for tx in client.get_mempool().await? {
+        breakdown.pending_incoming += tx
+            .outputs
+            .iter()
+            .filter(|output| output.receiver == public)
+            .map(|output| output.amount)
+            .sum::<u64>();
+    }
+    Ok(breakdown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown() {
+        let settled = Breakdown {
+            balance: 500,
+            available: 500,
+            ..Default::default()
+        };
+        assert!(!settled.differs());
+        assert!(settled.reasons().is_empty());
+
+        // Balance, but nothing the node offers to spend
+        let immature = Breakdown {
+            balance: 500,
+            ..Default::default()
+        };
+        assert_eq!((immature.spendable(), immature.unavailable()), (0, 500));
+        assert_eq!(immature.reasons().len(), 1);
+
+        let mixed = Breakdown {
+            balance: 500,
+            available: 300,
+            session_spent: 100,
+            pending_incoming: 50,
+        };
+        assert_eq!((mixed.spendable(), mixed.unavailable()), (200, 200));
+        assert_eq!(mixed.reasons().len(), 3);
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    io::{self, IsTerminal, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crossterm::{
+    execute,
+    terminal::{Clear, ClearType},
+};
+
+/// Operations finishing sooner than this never show a spinner
+const SHOW_AFTER: Duration = Duration::from_millis(300);
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+const FRAMES: &[char] = &['|', '/', '-', '\\'];
+
+struct State {
+    label: String,
+    /// The spinner line is currently on screen
+    drawn: bool,
+    stopped: bool,
+}
+
+impl State {
+    fn clear(&mut self) {
+        if self.drawn {
+            execute!(io::stderr(), Clear(ClearType::CurrentLine)).ok();
+            eprint!("\r");
+            self.drawn = false;
+        }
+    }
+}
+
+/// An animated progress line on stderr, cleared when dropped
+/// Does nothing unless both stdout and stderr are terminals, so piped output stays clean
+pub struct Spinner {
+    // The drawing task holds the lock while drawing, so output never lands mid-frame
+    state: Arc<Mutex<State>>,
+}
+
+impl Spinner {
+    pub fn start(label: impl Into<String>) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            label: label.into(),
+            drawn: false,
+            stopped: false,
+        }));
+        if io::stdout().is_terminal() && io::stderr().is_terminal() {
+            let state = state.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(SHOW_AFTER).await;
+                for frame in FRAMES.iter().cycle() {
+                    {
+                        let mut state = state.lock().unwrap();
+                        if state.stopped {
+                            break;
+                        }
+                        let mut stderr = io::stderr();
+                        execute!(stderr, Clear(ClearType::CurrentLine)).ok();
+                        write!(stderr, "\r{} {}", frame, state.label).ok();
+                        stderr.flush().ok();
+                        state.drawn = true;
+                    }
+                    tokio::time::sleep(FRAME_INTERVAL).await;
+                }
+            });
+        }
+        Spinner { state }
+    }
+
+    /// Change the label, e.g. to update a progress count
+    pub fn set_label(&self, label: impl Into<String>) {
+        self.state.lock().unwrap().label = label.into();
+    }
+
+    /// Print a line of output in place of the spinner, which comes back on its next frame
+    pub fn println(&self, line: impl Display) {
+        let mut state = self.state.lock().unwrap();
+        state.clear();
+        println!("{}", line);
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.stopped = true;
+        state.clear();
+    }
+}
+
+/// Await `future` with a spinner showing `label` if it takes a while
+pub async fn spin<T>(label: &str, future: impl Future<Output = T>) -> T {
+    let _spinner = Spinner::start(label);
+    future.await
+}
@@ -0,0 +1,72 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crossterm::style::{Color, Stylize};
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Enable colors unless `--no-color` was given, `NO_COLOR` is set, or stdout isn't a terminal
+pub fn init(no_color_flag: bool) {
+    let no_color_env = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    COLOR.store(
+        !no_color_flag && !no_color_env && io::stdout().is_terminal(),
+        Ordering::Relaxed,
+    );
+}
+
+/// Whether output is colored
+pub fn enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+fn paint(text: impl Display, color: Color, bold: bool) -> String {
+    if !COLOR.load(Ordering::Relaxed) {
+        return text.to_string();
+    }
+    let styled = text.to_string().with(color);
+    if bold {
+        styled.bold().to_string()
+    } else {
+        styled.to_string()
+    }
+}
+
+/// Failures and rejected input
+pub fn error(text: impl Display) -> String {
+    paint(text, Color::Red, false)
+}
+
+/// Things that worked, but deserve attention
+pub fn warning(text: impl Display) -> String {
+    paint(text, Color::Yellow, false)
+}
+
+/// Key backup and key exposure warnings
+pub fn danger(text: impl Display) -> String {
+    paint(text, Color::Red, true)
+}
+
+/// Amounts received
+pub fn incoming(text: impl Display) -> String {
+    paint(text, Color::Green, false)
+}
+
+/// Amounts sent
+pub fn outgoing(text: impl Display) -> String {
+    paint(text, Color::Red, false)
+}
+
+/// Transaction ids and other long identifiers
+pub fn dim(text: impl Display) -> String {
+    paint(text, Color::DarkGrey, false)
+}
+
+/// Ring the terminal bell, terminals that have it turned off may flash instead
+pub fn bell() {
+    print!("\x07");
+    io::stdout().flush().ok();
+}
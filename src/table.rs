@@ -0,0 +1,324 @@
+use std::{
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Space between columns
+const GAP: &str = "  ";
+/// A shortened column never gets narrower than this
+const MIN_SHRUNK_WIDTH: usize = 9;
+/// Width assumed for a terminal whose size can't be read
+const FALLBACK_WIDTH: usize = 80;
+
+/// Whether values are printed in full, as when output is piped
+static FULL: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+pub struct Column {
+    pub header: &'static str,
+    pub align: Align,
+    /// Shortened with an ellipsis when the table is wider than the terminal.
+    /// Its cells must be plain text, without colors
+    pub shrink: bool,
+}
+
+impl Column {
+    pub fn left(header: &'static str) -> Self {
+        Column {
+            header,
+            align: Align::Left,
+            shrink: false,
+        }
+    }
+
+    pub fn right(header: &'static str) -> Self {
+        Column {
+            header,
+            align: Align::Right,
+            shrink: false,
+        }
+    }
+
+    /// A left aligned column that gives up width first, for long ids
+    pub fn shrinking(header: &'static str) -> Self {
+        Column {
+            header,
+            align: Align::Left,
+            shrink: true,
+        }
+    }
+}
+
+/// Prints values untruncated until dropped, for a single command's `--full`
+pub struct FullOverride {
+    previous: bool,
+}
+
+pub fn show_full() -> FullOverride {
+    FullOverride {
+        previous: FULL.swap(true, Ordering::Relaxed),
+    }
+}
+
+impl Drop for FullOverride {
+    fn drop(&mut self) {
+        FULL.store(self.previous, Ordering::Relaxed);
+    }
+}
+
+/// Width of the terminal output is fitted to, or `None` when output is piped or asked for in
+/// full
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() || FULL.load(Ordering::Relaxed) {
+        return None;
+    }
+    let width = crossterm::terminal::size().ok().map(|(columns, _)| columns as usize);
+    Some(width.filter(|width| *width > 0).unwrap_or(FALLBACK_WIDTH))
+}
+
+/// Break text into lines of at most `width` characters at spaces. Words longer than a line
+/// are left whole
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Number of characters a cell takes on screen, not counting color escape sequences
+pub fn visible_width(cell: &str) -> usize {
+    let mut width = 0;
+    let mut chars = cell.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequence: ESC [ parameters final-byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Shorten text to `max` characters by replacing its middle with an ellipsis,
+/// keeping both ends of an id recognizable
+pub fn ellipsize(text: &str, max: usize) -> String {
+    let len = text.chars().count();
+    if len <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let keep = max - 1;
+    let head: String = text.chars().take(keep.div_ceil(2)).collect();
+    let tail: String = text.chars().skip(len - keep / 2).collect();
+    format!("{}…{}", head, tail)
+}
+
+/// Column widths fitting the headers and `rows`, with shrinking columns narrowed so the
+/// table fits `max_width` when possible
+pub fn column_widths(
+    columns: &[Column],
+    rows: &[Vec<String>],
+    max_width: Option<usize>,
+) -> Vec<usize> {
+    let mut widths: Vec<usize> = columns.iter().map(|column| column.header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(visible_width(cell));
+        }
+    }
+    if let Some(max_width) = max_width {
+        let total = widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+        let mut excess = total.saturating_sub(max_width);
+        for (width, column) in widths.iter_mut().zip(columns) {
+            if excess == 0 {
+                break;
+            }
+            if column.shrink && *width > MIN_SHRUNK_WIDTH {
+                let cut = excess.min(*width - MIN_SHRUNK_WIDTH);
+                *width -= cut;
+                excess -= cut;
+            }
+        }
+    }
+    widths
+}
+
+/// Render one row with the given widths
+pub fn format_row(columns: &[Column], widths: &[usize], cells: &[String]) -> String {
+    let mut line = String::new();
+    for (i, ((column, width), cell)) in columns.iter().zip(widths).zip(cells).enumerate() {
+        let cell = if column.shrink {
+            ellipsize(cell, *width)
+        } else {
+            cell.clone()
+        };
+        let padding = " ".repeat(width.saturating_sub(visible_width(&cell)));
+        if i > 0 {
+            line.push_str(GAP);
+        }
+        match column.align {
+            Align::Left => {
+                line.push_str(&cell);
+                // No trailing spaces after the last column
+                if i + 1 < columns.len() {
+                    line.push_str(&padding);
+                }
+            }
+            Align::Right => {
+                line.push_str(&padding);
+                line.push_str(&cell);
+            }
+        }
+    }
+    line
+}
+
+pub fn format_header(columns: &[Column], widths: &[usize]) -> String {
+    let headers: Vec<String> = columns.iter().map(|column| column.header.to_string()).collect();
+    format_row(columns, widths, &headers)
+}
+
+/// Render a complete table, header first. A table that doesn't fit even with its shrinking
+/// columns shortened is stacked instead
+pub fn render(columns: &[Column], rows: &[Vec<String>]) -> Vec<String> {
+    render_width(columns, rows, terminal_width())
+}
+
+fn render_width(columns: &[Column], rows: &[Vec<String>], max_width: Option<usize>) -> Vec<String> {
+    let widths = column_widths(columns, rows, max_width);
+    let total = widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+    if let Some(max_width) = max_width.filter(|max_width| total > *max_width) {
+        return render_stacked(columns, rows, max_width);
+    }
+    let mut lines = vec![format_header(columns, &widths)];
+    lines.extend(rows.iter().map(|row| format_row(columns, &widths, row)));
+    lines
+}
+
+/// One `HEADER  value` line per cell, rows separated by blank lines, for terminals too
+/// narrow for the columns side by side
+fn render_stacked(columns: &[Column], rows: &[Vec<String>], max_width: usize) -> Vec<String> {
+    let label_width = columns.iter().map(|column| column.header.len()).max().unwrap_or(0);
+    let value_width = max_width.saturating_sub(label_width + GAP.len()).max(MIN_SHRUNK_WIDTH);
+    let mut lines = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        for (column, cell) in columns.iter().zip(row) {
+            let cell = if column.shrink {
+                ellipsize(cell, value_width)
+            } else {
+                cell.clone()
+            };
+            let line = format!("{:<width$}{}{}", column.header, GAP, cell, width = label_width);
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    lines
+}
+
+/// Short human readable age, e.g. `45s`, `12m`, `5h`, `3d`
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ellipsize() {
+        assert_eq!(ellipsize("abcdefghij", 10), "abcdefghij");
+        assert_eq!(ellipsize("abcdefghij", 5), "ab…ij");
+        assert_eq!(ellipsize("abcdefghij", 6), "abc…ij");
+        assert_eq!(ellipsize("abcdefghij", 1), "…");
+    }
+
+    #[test]
+    fn test_alignment_ignores_colors() {
+        let columns = [Column::shrinking("ID"), Column::right("AMOUNT")];
+        let rows = vec![
+            vec!["aaaaaaaaaaaa".to_string(), "\x1b[32m1.5\x1b[39m".to_string()],
+            vec!["bb".to_string(), "12.25".to_string()],
+        ];
+        let widths = column_widths(&columns, &rows, None);
+        assert_eq!(widths, vec![12, 6]);
+        assert_eq!(format_row(&columns, &widths, &rows[1]), "bb             12.25");
+        assert_eq!(
+            visible_width(&format_row(&columns, &widths, &rows[0])),
+            "aaaaaaaaaaaa     1.5".len()
+        );
+
+        // Too narrow a terminal shortens the id column only
+        let widths = column_widths(&columns, &rows, Some(18));
+        assert_eq!(widths, vec![10, 6]);
+        assert_eq!(visible_width(&format_row(&columns, &widths, &rows[0])), 18);
+    }
+
+    #[test]
+    fn test_stacked_when_too_narrow() {
+        let columns = [Column::shrinking("ID"), Column::left("NOTE")];
+        let rows = vec![
+            vec!["aaaaaaaaaaaaaaaa".to_string(), "rent for the flat".to_string()],
+            vec!["bbbbbbbbbbbbbbbb".to_string(), String::new()],
+        ];
+        assert_eq!(render_width(&columns, &rows, Some(40)).len(), 3);
+        assert_eq!(render_width(&columns, &rows, None).len(), 3);
+        assert_eq!(
+            render_width(&columns, &rows, Some(24)),
+            vec![
+                "ID    aaaaaaaaaaaaaaaa",
+                "NOTE  rent for the flat",
+                "",
+                "ID    bbbbbbbbbbbbbbbb",
+                "NOTE",
+            ]
+        );
+        assert_eq!(render_width(&columns, &rows, Some(16))[0], "ID    aaaaa…aaaa");
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("one two three", 7), vec!["one two", "three"]);
+        assert_eq!(wrap("one two three", 80), vec!["one two three"]);
+        assert_eq!(wrap("a verylongword b", 4), vec!["a", "verylongword", "b"]);
+        assert_eq!(wrap("", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(59), "59s");
+        assert_eq!(format_age(60), "1m");
+        assert_eq!(format_age(7200), "2h");
+        assert_eq!(format_age(3 * 86400 + 5), "3d");
+    }
+}
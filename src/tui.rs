@@ -0,0 +1,494 @@
+use std::{
+    io::{self, Stdout, Write},
+    panic, pin,
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{Block, Paragraph},
+};
+
+use crate::{
+    amount,
+    handle_command::{
+        FatalError, available_lines, balance_lines, handle_command, mempool_lines,
+        print_command_error,
+    },
+    history::TxEffect,
+    save_last_login,
+    session::Session,
+};
+
+/// How often the dashboard refetches its data while idle
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of transactions in the recent transactions pane
+const RECENT_TRANSACTIONS: usize = 5;
+/// How often keys are checked for while data is being fetched
+const KEY_POLL: Duration = Duration::from_millis(50);
+
+/// Data shown in the dashboard panes. The panes reuse the line output of the matching REPL
+/// commands, so the two can't disagree
+#[derive(Default)]
+struct Dashboard {
+    /// As printed by `balance`
+    balance: Vec<String>,
+    /// Base36 transaction id and its effect on the wallet, newest first
+    recent: Vec<(String, TxEffect)>,
+    /// As printed by `mempool --mine`
+    pending: Vec<String>,
+    /// As printed by `available`
+    utxos: Vec<String>,
+    mempool_total: Option<usize>,
+    error: Option<String>,
+}
+
+async fn fetch(session: &Session) -> Dashboard {
+    let mut dashboard = Dashboard::default();
+    let Some(public) = session.current_public() else {
+        return dashboard;
+    };
+    let client = &*session.client;
+    let used_session_inputs = session.used_session_inputs.of(public);
+
+    match balance_lines(client, public, used_session_inputs).await {
+        Ok(lines) => dashboard.balance = lines,
+        Err(e) => dashboard.error = Some(e.to_string()),
+    }
+    if let Ok(history) = client.get_transactions_of_address(public).await {
+        for tx_id in history.iter().rev().take(RECENT_TRANSACTIONS) {
+            if let Ok(Some(tx)) = client.get_transaction(tx_id).await {
+                dashboard
+                    .recent
+                    .push((tx_id.dump_base36(), TxEffect::of(&tx, public)));
+            }
+        }
+    }
+    if let Ok(lines) = mempool_lines(client, public, true).await {
+        dashboard.pending = lines;
+    }
+    if let Ok(lines) = available_lines(client, public, used_session_inputs, "amount").await {
+        dashboard.utxos = lines;
+    }
+    if let Ok(mempool) = client.get_mempool().await {
+        dashboard.mempool_total = Some(mempool.len());
+    }
+    dashboard
+}
+
+/// Switches to the alternate screen in raw mode, and back when dropped
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn restore_terminal() {
+    execute!(io::stdout(), cursor::Show, LeaveAlternateScreen).ok();
+    terminal::disable_raw_mode().ok();
+}
+
+/// What keys do at the moment
+#[derive(Debug, PartialEq)]
+enum Mode {
+    /// Single key shortcuts
+    Keys,
+    /// Typing a command after `:`
+    Command(String),
+    /// Send form, typing the recipient
+    SendTo(String),
+    /// Send form, typing the amount
+    SendAmount { to: String, amount: String },
+}
+
+impl Mode {
+    /// Text of the bottom line
+    fn footer(&self) -> String {
+        match self {
+            Mode::Keys => " s send   w next wallet   r refresh   : command   q back".to_string(),
+            Mode::Command(input) => format!(":{}", input),
+            Mode::SendTo(input) => format!(" Send to (address or contact, Esc cancels): {}", input),
+            Mode::SendAmount { to, amount } => format!(" Amount to send to {}: {}", to, amount),
+        }
+    }
+
+    fn input(&mut self) -> Option<&mut String> {
+        match self {
+            Mode::Keys => None,
+            Mode::Command(input) | Mode::SendTo(input) => Some(input),
+            Mode::SendAmount { amount, .. } => Some(amount),
+        }
+    }
+}
+
+/// What a key asks for beyond editing the footer, which needs the session
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// Leave the dashboard, `true` to quit the wallet as well
+    Leave(bool),
+    NextWallet,
+    Refresh,
+    /// Run a command on the normal screen
+    Run(String),
+}
+
+/// Apply a key to the footer, and give what it asks for when that is more than editing
+fn press(mode: &mut Mode, key: KeyEvent) -> Option<Action> {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Some(Action::Leave(false));
+    }
+    if let Mode::Keys = mode {
+        return match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::Leave(false)),
+            KeyCode::Char('s') => {
+                *mode = Mode::SendTo(String::new());
+                None
+            }
+            KeyCode::Char(':') => {
+                *mode = Mode::Command(String::new());
+                None
+            }
+            KeyCode::Char('w') => Some(Action::NextWallet),
+            KeyCode::Char('r') => Some(Action::Refresh),
+            _ => None,
+        };
+    }
+    match key.code {
+        KeyCode::Esc => *mode = Mode::Keys,
+        KeyCode::Backspace => {
+            if let Some(input) = mode.input() {
+                input.pop();
+            }
+        }
+        KeyCode::Char(c) => {
+            if let Some(input) = mode.input() {
+                input.push(c);
+            }
+        }
+        KeyCode::Enter => match std::mem::replace(mode, Mode::Keys) {
+            Mode::SendTo(to) if !to.trim().is_empty() => {
+                *mode = Mode::SendAmount {
+                    to: to.trim().to_string(),
+                    amount: String::new(),
+                };
+            }
+            Mode::SendAmount { to, amount } if !amount.trim().is_empty() => {
+                return Some(Action::Run(format!("send {} {}", to, amount.trim())));
+            }
+            Mode::Command(command) => {
+                let command = command.trim();
+                if ["exit", "e", "quit", "q"].contains(&command) {
+                    return Some(Action::Leave(true));
+                }
+                if !command.is_empty() && command != "dashboard" {
+                    return Some(Action::Run(command.to_string()));
+                }
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+    None
+}
+
+/// Drop color codes, which the panes would show as text
+fn plain(line: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// A bordered pane of lines. Lines too long for it are cut, and lines past its height left
+/// out
+fn pane(frame: &mut Frame, area: Rect, title: &str, lines: Vec<Line>) {
+    let block = Block::bordered().title(format!(" {} ", title));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn plain_lines(lines: &[String]) -> Vec<Line<'static>> {
+    lines.iter().map(|line| Line::from(plain(line))).collect()
+}
+
+/// Lay the panes out over the whole terminal. Every size works, small terminals show as
+/// much of each pane as fits
+fn draw(frame: &mut Frame, session: &Session, dashboard: &Dashboard, mode: &Mode, busy: bool) {
+    let [title, body, node, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [left, right] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(body);
+    let balance_rows = dashboard.balance.len().max(1) as u16 + 2;
+    let [balance, recent] =
+        Layout::vertical([Constraint::Length(balance_rows), Constraint::Min(0)]).areas(left);
+    let [pending, utxos] =
+        Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(right);
+
+    let heading = format!(" Snap Coin Wallet - {}", session.current_wallet);
+    frame.render_widget(Paragraph::new(heading), title);
+
+    let mut lines = plain_lines(&dashboard.balance);
+    if lines.is_empty() {
+        lines.push(Line::from("?"));
+    }
+    pane(frame, balance, "Balance", lines);
+
+    let mut lines: Vec<Line> = dashboard
+        .recent
+        .iter()
+        .map(|(tx_id, effect)| {
+            let amount = if effect.is_outgoing() {
+                format!("-{}", amount::display(effect.sent))
+            } else {
+                format!("+{}", amount::display(effect.received))
+            };
+            Line::from(format!("{} {}", amount, tx_id))
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from("(none)"));
+    }
+    pane(frame, recent, "Recent transactions", lines);
+    pane(frame, pending, "Pending", plain_lines(&dashboard.pending));
+    pane(frame, utxos, "Outputs", plain_lines(&dashboard.utxos));
+
+    let status = match (&dashboard.error, dashboard.mempool_total) {
+        (Some(error), _) => format!("error: {}", error),
+        (None, Some(total)) => format!("connected, {} transactions in the mempool", total),
+        (None, None) => "connected".to_string(),
+    };
+    let status = match busy {
+        true => format!("{}, refreshing...", status),
+        false => status,
+    };
+    let node_title = format!("Node {}", session.node);
+    pane(frame, node, &node_title, vec![Line::from(status)]);
+    frame.render_widget(Paragraph::new(mode.footer()), footer);
+}
+
+/// Wait for a key press without blocking the task, so a fetch keeps going meanwhile
+async fn next_key() -> io::Result<KeyEvent> {
+    loop {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                return Ok(key);
+            }
+        }
+        tokio::time::sleep(KEY_POLL).await;
+    }
+}
+
+fn wait_for_key() -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    loop {
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            break;
+        }
+    }
+    terminal::disable_raw_mode()
+}
+
+/// Run `command` through `handle_command` on the normal screen, then come back
+async fn run_command(
+    session: &mut Session,
+    guard: &mut Option<TerminalGuard>,
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    command: &str,
+) -> Result<(), Error> {
+    drop(guard.take());
+    if let Err(e) = handle_command(session, command.to_string()).await {
+        if e.downcast_ref::<FatalError>().is_some() {
+            return Err(e);
+        }
+        print_command_error(&e);
+    }
+    println!();
+    print!("Press any key to return to the dashboard");
+    io::stdout().flush()?;
+    wait_for_key()?;
+    *guard = Some(TerminalGuard::enter()?);
+    // The normal screen was written over, every cell has to be drawn again
+    terminal.clear()?;
+    Ok(())
+}
+
+/// Switch to the wallet after the current one, by name
+fn next_wallet(session: &mut Session) -> Result<(), Error> {
+    let names: Vec<&String> = session.wallets.keys().collect();
+    let next = names
+        .iter()
+        .position(|name| **name == session.current_wallet)
+        .map_or(0, |index| (index + 1) % names.len());
+    if let Some(name) = names.get(next) {
+        let name = name.to_string();
+        save_last_login(name.clone())?;
+        session.current_wallet = name;
+    }
+    Ok(())
+}
+
+/// Run the dashboard until the user leaves it. Returns whether they asked to quit the wallet
+/// rather than go back to the prompt
+pub async fn run(session: &mut Session) -> Result<bool, Error> {
+    // The dashboard can be opened many times, the hook only needs installing once
+    static PANIC_HOOK: Once = Once::new();
+    PANIC_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+    });
+
+    let mut guard = Some(TerminalGuard::enter()?);
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let mut mode = Mode::Keys;
+    let mut dashboard = Dashboard::default();
+
+    loop {
+        // Keys are read while the data is fetched. Only an action, which may change the
+        // session, stops the fetch, and a new one starts after it. Resizes need no handling,
+        // every draw fills the terminal at its current size
+        let action = {
+            let mut fetching = pin::pin!(fetch(session));
+            let mut fetched_at: Option<Instant> = None;
+            loop {
+                let busy = fetched_at.is_none();
+                terminal.draw(|frame| draw(frame, session, &dashboard, &mode, busy))?;
+                let idle = fetched_at.map_or(REFRESH_INTERVAL, |at| {
+                    REFRESH_INTERVAL.saturating_sub(at.elapsed())
+                });
+                tokio::select! {
+                    fetched = &mut fetching, if busy => {
+                        dashboard = fetched;
+                        fetched_at = Some(Instant::now());
+                    }
+                    key = next_key() => {
+                        if let Some(action) = press(&mut mode, key?) {
+                            break action;
+                        }
+                    }
+                    _ = tokio::time::sleep(idle), if !busy => break Action::Refresh,
+                }
+            }
+        };
+        match action {
+            Action::Leave(quit) => {
+                drop(guard);
+                return Ok(quit);
+            }
+            Action::NextWallet => next_wallet(session)?,
+            Action::Refresh => {}
+            Action::Run(command) => {
+                run_command(session, &mut guard, &mut terminal, &command).await?
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::mock::MockNode;
+    use ratatui::backend::TestBackend;
+    use snap_coin::crypto::keys::Private;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_send_form() {
+        let mut mode = Mode::Keys;
+        assert_eq!(press(&mut mode, key(KeyCode::Char('s'))), None);
+        for c in "alice".chars() {
+            press(&mut mode, key(KeyCode::Char(c)));
+        }
+        press(&mut mode, key(KeyCode::Enter));
+        for c in "1.5".chars() {
+            press(&mut mode, key(KeyCode::Char(c)));
+        }
+        let action = press(&mut mode, key(KeyCode::Enter));
+        assert_eq!(action, Some(Action::Run("send alice 1.5".to_string())));
+        assert_eq!(mode, Mode::Keys);
+
+        // Typing only edits the footer, `q` in it is a letter like any other
+        press(&mut mode, key(KeyCode::Char(':')));
+        assert_eq!(press(&mut mode, key(KeyCode::Char('q'))), None);
+        assert_eq!(
+            press(&mut mode, key(KeyCode::Enter)),
+            Some(Action::Leave(true))
+        );
+        assert_eq!(
+            press(&mut mode, key(KeyCode::Char('w'))),
+            Some(Action::NextWallet)
+        );
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(press(&mut mode, ctrl_c), Some(Action::Leave(false)));
+    }
+
+    /// However small the terminal, drawing cuts the panes down instead of panicking
+    #[test]
+    fn test_draw_small_terminals() {
+        let session = Session::mock(MockNode::default(), Private::new_random());
+        let dashboard = Dashboard {
+            balance: vec!["Balance: \x1b[1m1.00000000\x1b[0m SNAP".to_string()],
+            ..Default::default()
+        };
+        for (width, height) in [(80, 24), (20, 5), (1, 1), (0, 0)] {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal
+                .draw(|frame| draw(frame, &session, &dashboard, &Mode::Keys, true))
+                .unwrap();
+        }
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal
+            .draw(|frame| draw(frame, &session, &dashboard, &Mode::Keys, false))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        // Colors are dropped, not shown as text
+        assert!(screen.contains("Balance: 1.00000000 SNAP"));
+        assert!(!screen.contains('\x1b'));
+    }
+}
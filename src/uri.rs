@@ -0,0 +1,278 @@
+use std::env;
+
+use anyhow::Error;
+use snap_coin::crypto::keys::Public;
+
+use crate::{
+    amount::{self, PaymentError},
+    sanitize,
+};
+
+/// Scheme of payment links, as in `snap:<address>?amount=1.5&label=Rent`
+pub const SCHEME: &str = "snap";
+/// Longest label shown, a link is untrusted text from a web page
+const MAX_LABEL: usize = 100;
+/// Name of the desktop entry that routes the scheme to the wallet on Linux
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE: &str = "snap-coin-wallet-uri.desktop";
+
+/// A payment a link asks for. Nothing is sent without the usual confirmation and PIN
+#[derive(Debug, PartialEq)]
+pub struct PaymentRequest {
+    pub address: Public,
+    /// Amount in nano, asked for when the link has none
+    pub amount: Option<u64>,
+    /// What the link says the payment is for, without control characters
+    pub label: Option<String>,
+}
+
+/// Decode `%XX` escapes and `+` as a space. Invalid escapes and decoded text that isn't UTF-8
+/// are rejected rather than guessed at
+fn percent_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("invalid escape at position {}", i + 1))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| "escapes that aren't UTF-8".to_string())
+}
+
+/// Parse the amount of a link: SNAP as a plain decimal number, nothing else the `send`
+/// command would accept such as percentages or a `nano` suffix
+fn parse_amount(text: &str) -> Result<u64, String> {
+    let plain = !text.is_empty()
+        && text.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && text.matches('.').count() <= 1;
+    match plain.then(|| amount::parse_payment(text)) {
+        Some(Ok(amount)) => Ok(amount),
+        Some(Err(PaymentError::TooPrecise(decimals))) => Err(format!(
+            "amount '{}' has more than {} decimal places",
+            text, decimals
+        )),
+        _ => Err(format!("invalid amount '{}'", text)),
+    }
+}
+
+/// Parse a `snap:` link. Anything not understood makes the whole link invalid, so a link
+/// never pays other than it reads
+pub fn parse(uri: &str) -> Result<PaymentRequest, String> {
+    let uri = uri.trim();
+    let rest = uri
+        .split_once(':')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+        .map(|(_, rest)| rest)
+        .ok_or_else(|| format!("not a {}: link", SCHEME))?;
+    let rest = rest.strip_prefix("//").unwrap_or(rest);
+    let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let address = percent_decode(address)?;
+    // Pasted addresses may wrap or carry invisible characters, a link has no excuse to
+    if sanitize::clean(&address) != address {
+        return Err("whitespace or invisible characters in the address".to_string());
+    }
+    let address = sanitize::parse_public(&address)?;
+
+    let mut request = PaymentRequest {
+        address,
+        amount: None,
+        label: None,
+    };
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        let key = percent_decode(key)?;
+        let value = percent_decode(value)?;
+        match key.as_str() {
+            "amount" if request.amount.is_none() => request.amount = Some(parse_amount(&value)?),
+            "label" if request.label.is_none() => {
+                let label: String = value.chars().filter(|c| !c.is_control()).collect();
+                let label = label.trim();
+                if label.chars().count() > MAX_LABEL {
+                    return Err(format!("label longer than {} characters", MAX_LABEL));
+                }
+                request.label = (!label.is_empty()).then(|| label.to_string());
+            }
+            "amount" | "label" => return Err(format!("'{}' given twice", key)),
+            // Links may carry extra information, but not a requirement the wallet can't meet
+            _ if key.starts_with("req-") => return Err(format!("unsupported '{}'", key)),
+            _ => {}
+        }
+    }
+    Ok(request)
+}
+
+/// How the scheme was registered, to tell the user
+pub enum Installed {
+    /// The desktop file or registry key that now routes the scheme to the wallet
+    At(String),
+    /// Registration needs a step the wallet can't take itself, with what to do
+    Instructions(String),
+}
+
+/// Quote a path for the `Exec` key of a desktop entry. Quoting escapes come first, then the
+/// backslashes of both are escaped again as the file's string values are unescaped once
+#[cfg(any(target_os = "linux", test))]
+fn desktop_quote(exe: &str) -> String {
+    let mut quoted = String::new();
+    for c in exe.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => quoted.extend(['\\', c]),
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    format!("\"{}\"", quoted.replace('\\', "\\\\"))
+}
+
+/// The desktop entry that opens links in a terminal, where the PIN can be typed
+#[cfg(any(target_os = "linux", test))]
+fn desktop_entry(exe: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Snap Coin Wallet\n\
+         Exec={} --handle-uri %u\n\
+         Terminal=true\n\
+         NoDisplay=true\n\
+         MimeType=x-scheme-handler/{};\n",
+        desktop_quote(exe),
+        SCHEME
+    )
+}
+
+/// Run a registration tool, failing with its output when it does
+#[cfg(any(target_os = "linux", windows))]
+fn run(program: &str, args: &[&str]) -> Result<(), Error> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| Error::msg(format!("Can't run {}: {}", program, e)))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(Error::msg(format!("{} failed: {}", program, reason)))
+}
+
+/// Register this binary as the handler of `snap:` links for the current user
+#[cfg(target_os = "linux")]
+pub fn install() -> Result<Installed, Error> {
+    let exe = env::current_exe()?;
+    let data = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => dirs::home_dir()
+            .ok_or_else(|| Error::msg(crate::i18n::tr("error.home_dir")))?
+            .join(".local/share"),
+    };
+    let dir = data.join("applications");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(DESKTOP_FILE);
+    std::fs::write(&path, desktop_entry(&exe.to_string_lossy()))?;
+    let mime = format!("x-scheme-handler/{}", SCHEME);
+    run("xdg-mime", &["default", DESKTOP_FILE, &mime])?;
+    // Only some desktops keep this cache, a missing tool is fine
+    run("update-desktop-database", &[&dir.to_string_lossy()]).ok();
+    Ok(Installed::At(path.display().to_string()))
+}
+
+/// Register this binary as the handler of `snap:` links for the current user
+#[cfg(windows)]
+pub fn install() -> Result<Installed, Error> {
+    let exe = env::current_exe()?;
+    let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+    let command = format!("\"{}\" --handle-uri \"%1\"", exe.display());
+    run(
+        "reg",
+        &["add", &key, "/ve", "/d", "URL:Snap Coin payment", "/f"],
+    )?;
+    run("reg", &["add", &key, "/v", "URL Protocol", "/d", "", "/f"])?;
+    let open = format!(r"{}\shell\open\command", key);
+    run("reg", &["add", &open, "/ve", "/d", &command, "/f"])?;
+    Ok(Installed::At(key))
+}
+
+/// Register this binary as the handler of `snap:` links for the current user
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn install() -> Result<Installed, Error> {
+    // Launch Services only routes schemes to application bundles, whose Info.plist lists them
+    let exe = env::current_exe()?;
+    Ok(Installed::Instructions(format!(
+        "Schemes can only be registered for an application bundle. Wrap the wallet in one \
+         whose Info.plist lists '{}' under CFBundleURLTypes and runs `\"{}\" --handle-uri <link>` \
+         in a terminal, then open the bundle once so the system registers it.",
+        SCHEME,
+        exe.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snap_coin::crypto::keys::Private;
+
+    #[test]
+    fn test_parse() {
+        let address = Private::new_random().to_public();
+        let base36 = address.dump_base36();
+
+        let request = parse(&format!(
+            "snap:{}?amount=1.5&label=Rent%20%E2%82%AC",
+            base36
+        ));
+        assert_eq!(
+            request,
+            Ok(PaymentRequest {
+                address,
+                amount: Some(amount::parse_payment("1.5").unwrap()),
+                label: Some("Rent €".to_string()),
+            })
+        );
+        let request = parse(&format!("SNAP://{}?message=thanks", base36)).unwrap();
+        assert_eq!((request.amount, request.label), (None, None));
+        // Control characters can't rewrite what the terminal shows
+        let request = parse(&format!("snap:{}?label=a%1B[2Jb%0A", base36)).unwrap();
+        assert_eq!(request.label.as_deref(), Some("a[2Jb"));
+
+        for invalid in [
+            format!("bitcoin:{}", base36),
+            format!("snap:{}x", base36),
+            format!("snap:{}%20", base36),
+            format!("snap:{}?amount=50%25", base36),
+            format!("snap:{}?amount=1500nano", base36),
+            format!("snap:{}?amount=-1", base36),
+            format!("snap:{}?amount=0", base36),
+            format!("snap:{}?amount=1&amount=2", base36),
+            format!("snap:{}?amount=%ZZ", base36),
+            format!("snap:{}?req-fee=1", base36),
+            format!("snap:{}?label={}", base36, "a".repeat(MAX_LABEL + 1)),
+        ] {
+            assert!(parse(&invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_desktop_entry() {
+        let entry = desktop_entry("/opt/snap \"wallet\" 100%");
+        let exec = r#"Exec="/opt/snap \\"wallet\\" 100%%" --handle-uri %u"#;
+        assert!(entry.contains(&format!("{}\n", exec)), "{}", entry);
+        assert!(entry.contains("MimeType=x-scheme-handler/snap;\n"));
+        assert!(entry.contains("Terminal=true\n"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread,
+};
+
+use snap_coin::crypto::keys::Private;
+
+/// Prefixes at least this long ask for confirmation first
+pub const SLOW_PREFIX_LEN: usize = 5;
+
+/// Normalize a requested prefix to the lowercase base36 of addresses
+pub fn check_prefix(prefix: &str) -> Result<String, String> {
+    let prefix = prefix.trim().to_ascii_lowercase();
+    if prefix.is_empty() {
+        return Err("the prefix can't be empty".to_string());
+    }
+    match prefix.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        Some(c) => Err(format!("'{}' can't appear in an address", c)),
+        None => Ok(prefix),
+    }
+}
+
+/// Keys to try on average before one matches a prefix of `len` characters
+pub fn expected_attempts(len: usize) -> f64 {
+    36f64.powi(len as i32)
+}
+
+/// A search for a key whose address starts with a prefix, on every core.
+/// Dropping it stops the search
+pub struct Search {
+    stop: Arc<AtomicBool>,
+    attempts: Arc<AtomicU64>,
+    found: Arc<Mutex<Option<Private>>>,
+}
+
+impl Search {
+    pub fn start(prefix: &str) -> Self {
+        let search = Search {
+            stop: Arc::new(AtomicBool::new(false)),
+            attempts: Arc::new(AtomicU64::new(0)),
+            found: Arc::new(Mutex::new(None)),
+        };
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        for _ in 0..threads {
+            let prefix = prefix.to_string();
+            let (stop, attempts, found) = (
+                search.stop.clone(),
+                search.attempts.clone(),
+                search.found.clone(),
+            );
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let private = Private::new_random();
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                    if private.to_public().dump_base36().starts_with(&prefix) {
+                        found.lock().unwrap().get_or_insert(private);
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        search
+    }
+
+    /// Keys tried so far
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// The matching key, once one is found
+    pub fn result(&self) -> Option<Private> {
+        *self.found.lock().unwrap()
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_check_prefix() {
+        assert_eq!(check_prefix(" AbC1 "), Ok("abc1".to_string()));
+        assert!(check_prefix("").is_err());
+        assert!(check_prefix("ab-c").unwrap_err().contains("'-'"));
+        assert_eq!(expected_attempts(2), 1296.0);
+    }
+
+    #[test]
+    fn test_search_finds_prefix() {
+        // The first character of an address is the one most likely to be restricted, so
+        // search for one that occurs in a real address
+        let prefix = Private::new_random().to_public().dump_base36()[..1].to_string();
+        let search = Search::start(&prefix);
+        let private = loop {
+            if let Some(private) = search.result() {
+                break private;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(private.to_public().dump_base36().starts_with(&prefix));
+        assert!(search.attempts() >= 1);
+    }
+}
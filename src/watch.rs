@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::Print,
+    terminal::{Clear, ClearType},
+};
+
+/// Read-only commands `watch` may re-run
+pub const WATCHABLE: &[&str] = &["balance", "available", "mempool"];
+/// Seconds between refreshes when no interval is given
+pub const DEFAULT_INTERVAL: u64 = 5;
+
+/// Split `watch` arguments into the watched command and the refresh interval in seconds,
+/// which is the last argument when it is a number
+pub fn parse_args<'a>(args: &[&'a str]) -> Option<(Vec<&'a str>, u64)> {
+    let (interval, command) = match args.split_last() {
+        Some((last, rest)) if last.parse::<u64>().is_ok() => (last.parse().ok()?, rest),
+        _ => (DEFAULT_INTERVAL, args),
+    };
+    if command.is_empty() || interval == 0 {
+        return None;
+    }
+    Some((command.to_vec(), interval))
+}
+
+/// `HH:MM:SS` of a unix time, in UTC
+pub fn clock_time(unix: u64) -> String {
+    let seconds = unix % 86400;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// The watched output, redrawn in place from the top of the screen. Lines are overwritten
+/// rather than cleared first, and an unchanged body isn't redrawn, so nothing flickers
+#[derive(Default)]
+pub struct Screen {
+    body: Option<Vec<String>>,
+}
+
+impl Screen {
+    /// Start from a blank screen
+    pub fn new() -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        queue!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+        stdout.flush()?;
+        Ok(Self::default())
+    }
+
+    pub fn draw(&mut self, header: &str, body: Vec<String>) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        queue!(stdout, MoveTo(0, 0), Print(header), Clear(ClearType::UntilNewLine))?;
+        if self.body.as_ref() != Some(&body) {
+            queue!(stdout, MoveTo(0, 2))?;
+            for line in &body {
+                queue!(stdout, Print(line), Clear(ClearType::UntilNewLine), Print("\r\n"))?;
+            }
+            queue!(stdout, Clear(ClearType::FromCursorDown))?;
+            self.body = Some(body);
+        }
+        // Park the cursor below the output, where anything printed after watch ends goes
+        let height = self.body.as_ref().map_or(0, |body| body.len());
+        queue!(stdout, MoveTo(0, (height + 2) as u16))?;
+        stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&["balance"]), Some((vec!["balance"], DEFAULT_INTERVAL)));
+        assert_eq!(parse_args(&["balance", "10"]), Some((vec!["balance"], 10)));
+        assert_eq!(
+            parse_args(&["available", "--sort", "age", "2"]),
+            Some((vec!["available", "--sort", "age"], 2))
+        );
+        assert_eq!(parse_args(&["10"]), None);
+        assert_eq!(parse_args(&["balance", "0"]), None);
+        assert_eq!(parse_args(&[]), None);
+    }
+
+    #[test]
+    fn test_clock_time() {
+        assert_eq!(clock_time(0), "00:00:00");
+        assert_eq!(clock_time(86400 + 3600 * 13 + 60 * 5 + 9), "13:05:09");
+    }
+}
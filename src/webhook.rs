@@ -0,0 +1,229 @@
+use std::{env, net::SocketAddr, sync::OnceLock, time::Duration};
+
+use anyhow::Error;
+use serde::Serialize;
+use snap_coin::{
+    api::client::Client, blockchain_data_provider::BlockchainDataProvider,
+    core::transaction::TransactionId,
+};
+use tokio::sync::mpsc;
+
+use crate::{json, oplog, style};
+
+/// Environment variable holding the shared secret, which the config file must not hold
+pub const SECRET_VAR: &str = "SNAP_WEBHOOK_SECRET";
+/// How often submitted transactions are checked for confirmation
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Polls after which a transaction that never confirmed is given up on, about a day
+const MAX_POLLS: u32 = 2880;
+/// Attempts at delivering one payload
+const ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled before each next one
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Time one attempt gets, from connecting to the end of the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where payloads go, from the `webhook_url` and `webhook_secret_header` settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub url: String,
+    /// Header carrying the secret from `SNAP_WEBHOOK_SECRET`, sent only when both are set
+    pub secret_header: Option<String>,
+}
+
+/// Whether a URL can be posted to
+pub fn is_valid_url(url: &str) -> bool {
+    ["http://", "https://"]
+        .iter()
+        .any(|scheme| url.len() > scheme.len() && url.starts_with(scheme))
+}
+
+/// What the endpoint receives, as JSON
+#[derive(Serialize)]
+pub struct Payload {
+    /// `transaction_confirmed`, or `test` for `webhook test`
+    pub event: &'static str,
+    pub tx_id: String,
+    pub wallet: String,
+    /// Timestamp of the transaction
+    pub timestamp: u64,
+    pub payments: Vec<json::Payment>,
+    pub total: json::Amount,
+}
+
+/// A transaction the node accepted into its mempool, waiting to be confirmed
+pub struct Sent {
+    pub tx_id: TransactionId,
+    pub wallet: String,
+    pub payments: Vec<json::Payment>,
+}
+
+impl Sent {
+    fn payload(self, timestamp: u64) -> Payload {
+        Payload {
+            event: "transaction_confirmed",
+            tx_id: self.tx_id.dump_base36(),
+            wallet: self.wallet,
+            timestamp,
+            total: self
+                .payments
+                .iter()
+                .map(|payment| payment.amount.nano)
+                .sum::<u64>()
+                .into(),
+            payments: self.payments,
+        }
+    }
+}
+
+/// Queue of the confirmation poller, unset until it is spawned
+static TRACKER: OnceLock<mpsc::UnboundedSender<(Endpoint, Sent)>> = OnceLock::new();
+
+/// Start the poller that posts sent transactions to their endpoint once confirmed, on its
+/// own node connection
+pub fn spawn(node: SocketAddr) {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(Endpoint, Sent)>();
+    if TRACKER.set(sender).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        let connected = Client::connect(node).await;
+        oplog::connect(node, "webhook", connected.as_ref().err());
+        let Ok(client) = connected else {
+            return;
+        };
+        let mut pending: Vec<(Endpoint, Sent, u32)> = vec![];
+        loop {
+            tokio::select! {
+                received = receiver.recv() => match received {
+                    Some((endpoint, sent)) => pending.push((endpoint, sent, 0)),
+                    None => break,
+                },
+                _ = tokio::time::sleep(POLL_INTERVAL), if !pending.is_empty() => {}
+            }
+            let Ok(mempool) = client.get_mempool().await else {
+                continue;
+            };
+            let mut waiting = vec![];
+            for (endpoint, sent, polls) in pending.drain(..) {
+                // Leaving the mempool isn't enough, a dropped transaction leaves it too
+                let in_mempool = mempool
+                    .iter()
+                    .any(|tx| tx.transaction_id == Some(sent.tx_id));
+                let confirmed = match in_mempool {
+                    true => None,
+                    false => client.get_transaction(&sent.tx_id).await.ok().flatten(),
+                };
+                match confirmed {
+                    Some(tx) => {
+                        let payload = sent.payload(tx.timestamp);
+                        tokio::spawn(async move {
+                            if let Err(e) = deliver(&endpoint, &payload).await {
+                                let message =
+                                    format!("Webhook for {} failed: {}", payload.tx_id, e);
+                                eprintln!("{}", style::warning(message));
+                            }
+                        });
+                    }
+                    None if polls + 1 < MAX_POLLS => waiting.push((endpoint, sent, polls + 1)),
+                    None => {}
+                }
+            }
+            pending = waiting;
+        }
+    });
+}
+
+/// Post `sent` to `endpoint` once it is confirmed. Does nothing before `spawn`
+pub fn track(endpoint: Endpoint, sent: Sent) {
+    if let Some(tracker) = TRACKER.get() {
+        tracker.send((endpoint, sent)).ok();
+    }
+}
+
+/// Post a payload, retrying with a growing delay, and give the last error if every attempt
+/// failed
+pub async fn deliver(endpoint: &Endpoint, payload: &Payload) -> Result<(), Error> {
+    let body = serde_json::to_string(payload)?;
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let (endpoint, body) = (endpoint.clone(), body.clone());
+        match tokio::task::spawn_blocking(move || post(&endpoint, &body)).await? {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= ATTEMPTS => return Err(e),
+            Err(_) => {}
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// The headers of a POST, `secret` going into the secret header when there is one
+fn headers<'a>(endpoint: &'a Endpoint, secret: Option<&'a str>) -> Vec<(&'a str, &'a str)> {
+    let mut headers = vec![("Content-Type", "application/json")];
+    if let (Some(header), Some(secret)) = (&endpoint.secret_header, secret) {
+        headers.push((header.as_str(), secret));
+    }
+    headers
+}
+
+/// One POST, over http or https. A response with an error status is a failure too
+fn post(endpoint: &Endpoint, body: &str) -> Result<(), Error> {
+    let secret = env::var(SECRET_VAR)
+        .ok()
+        .filter(|secret| !secret.is_empty());
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+    let mut request = agent.post(&endpoint.url);
+    for (name, value) in headers(endpoint, secret.as_deref()) {
+        request = request.set(name, value);
+    }
+    match request.send_string(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, response)) => Err(Error::msg(format!(
+            "The endpoint answered {} {}",
+            status,
+            response.status_text()
+        ))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headers() {
+        let endpoint = Endpoint {
+            url: "https://example.com/hook".to_string(),
+            secret_header: Some("X-Secret".to_string()),
+        };
+        assert_eq!(
+            headers(&endpoint, Some("s3cret")),
+            [("Content-Type", "application/json"), ("X-Secret", "s3cret")]
+        );
+        // Without a secret there is no header to send it in
+        assert_eq!(headers(&endpoint, None).len(), 1);
+    }
+
+    /// Nothing listens on port 9 of loopback, the error says why the post failed
+    #[test]
+    fn test_post_unreachable() {
+        let endpoint = Endpoint {
+            url: "http://127.0.0.1:9/hook".to_string(),
+            secret_header: None,
+        };
+        assert!(post(&endpoint, "{}").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_url() {
+        assert!(is_valid_url("https://example.com/hook"));
+        assert!(is_valid_url("http://127.0.0.1:8080"));
+        assert!(!is_valid_url("https://"));
+        assert!(!is_valid_url("ftp://example.com"));
+        assert!(!is_valid_url("example.com"));
+    }
+}